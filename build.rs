@@ -4,6 +4,11 @@ use std::path::PathBuf;
 fn main() {
     let _out_dir = env::var("OUT_DIR").unwrap(); // Prefixed with an underscore to indicate it's intentionally unused
 
+    // WinDivert only exists on Windows; other targets capture through libc
+    if env::var("CARGO_CFG_TARGET_OS").as_deref() != Ok("windows") {
+        return;
+    }
+
     // Add src/windivert to the library search path
     println!("cargo:rustc-link-search=native=src/windivert");
     println!("cargo:rustc-link-lib=dylib=WinDivert");
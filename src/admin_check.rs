@@ -1,8 +1,13 @@
 use crate::logging;
+#[cfg(windows)]
 use winapi::um::processthreadsapi::GetCurrentProcess;
+#[cfg(windows)]
 use winapi::um::processthreadsapi::OpenProcessToken;
+#[cfg(windows)]
 use winapi::um::winnt::{TokenElevation, TOKEN_ELEVATION, TOKEN_QUERY};
+#[cfg(windows)]
 use winapi::um::handleapi::CloseHandle;
+#[cfg(windows)]
 use std::ptr::null_mut;
 
 pub fn init_module() -> Result<(), String> {
@@ -17,7 +22,7 @@ pub fn init_module() -> Result<(), String> {
     }
 }
 
-#[allow(dead_code)]
+#[cfg(windows)]
 pub fn is_admin_user() -> bool {
     unsafe {
         let mut token: winapi::um::winnt::HANDLE = null_mut();
@@ -41,4 +46,10 @@ pub fn is_admin_user() -> bool {
 
         result != 0 && elevation.TokenIsElevated != 0
     }
-}
\ No newline at end of file
+}
+
+// Raw sockets need root (or CAP_NET_RAW) on unix
+#[cfg(unix)]
+pub fn is_admin_user() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
@@ -0,0 +1,462 @@
+use crate::logging;
+use crate::packet_decoder::{Packet, TransportLayer};
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+pub fn init_module() -> Result<(), String> {
+    // Placeholder for actual initialization logic
+    let initialization_passed = true;
+
+    if initialization_passed {
+        logging::debug_info("app_dissector module is online");
+        Ok(())
+    } else {
+        Err("app_dissector module initialization failed".to_string())
+    }
+}
+
+const DNS_PORT: u16 = 53;
+const MDNS_PORT: u16 = 5353;
+
+// Guards against compression pointer loops in hostile DNS packets
+const MAX_DNS_NAME_JUMPS: usize = 16;
+// Caps the number of records we bother to decode from one message
+const MAX_DNS_RECORDS: usize = 64;
+
+const HTTP_METHODS: &[&str] = &["GET", "POST", "PUT", "DELETE", "HEAD", "OPTIONS", "PATCH", "CONNECT", "TRACE"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsQuestion {
+    pub name: String,
+    pub record_type: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DnsRecordData {
+    A(Ipv4Addr),
+    Aaaa(Ipv6Addr),
+    Name(String), // CNAME, NS, PTR
+    Other(usize), // Length of data we do not interpret
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsAnswer {
+    pub name: String,
+    pub record_type: u16,
+    pub ttl: u32,
+    pub data: DnsRecordData,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsMessage {
+    pub id: u16,
+    pub is_response: bool,
+    pub response_code: u8,
+    pub questions: Vec<DnsQuestion>,
+    pub answers: Vec<DnsAnswer>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppLayerInfo {
+    Dns(DnsMessage),
+    HttpRequest {
+        method: String,
+        path: String,
+        version: String,
+        host: Option<String>,
+    },
+    HttpResponse {
+        version: String,
+        status: u16,
+        reason: String,
+    },
+    TlsClientHello {
+        version: u16,
+        server_name: Option<String>,
+        alpn: Vec<String>,
+    },
+}
+
+impl AppLayerInfo {
+    // The domain this packet is about, if the protocol names one
+    pub fn domain(&self) -> Option<&str> {
+        match self {
+            AppLayerInfo::Dns(dns) => dns.questions.first().map(|q| q.name.as_str()),
+            AppLayerInfo::HttpRequest { host, .. } => host.as_deref(),
+            AppLayerInfo::TlsClientHello { server_name, .. } => server_name.as_deref(),
+            AppLayerInfo::HttpResponse { .. } => None,
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        match self {
+            AppLayerInfo::Dns(dns) => {
+                let name = self.domain().unwrap_or("?");
+                if dns.is_response {
+                    let answers: Vec<String> = dns
+                        .answers
+                        .iter()
+                        .map(|answer| match &answer.data {
+                            DnsRecordData::A(ip) => ip.to_string(),
+                            DnsRecordData::Aaaa(ip) => ip.to_string(),
+                            DnsRecordData::Name(name) => name.clone(),
+                            DnsRecordData::Other(len) => format!("type {} ({} bytes)", answer.record_type, len),
+                        })
+                        .collect();
+                    format!("DNS response {} -> [{}]", name, answers.join(", "))
+                } else {
+                    format!("DNS query {}", name)
+                }
+            }
+            AppLayerInfo::HttpRequest { method, path, host, .. } => {
+                format!("HTTP {} {}{}", method, host.as_deref().unwrap_or(""), path)
+            }
+            AppLayerInfo::HttpResponse { status, reason, .. } => format!("HTTP {} {}", status, reason),
+            AppLayerInfo::TlsClientHello { server_name, alpn, .. } => format!(
+                "TLS ClientHello sni={} alpn=[{}]",
+                server_name.as_deref().unwrap_or("-"),
+                alpn.join(",")
+            ),
+        }
+    }
+}
+
+// Looks at the transport payload of a decoded packet and identifies the
+// application protocol. Returns None for anything we don't dissect.
+pub fn dissect(packet: &Packet) -> Option<AppLayerInfo> {
+    let payload = packet.payload;
+    if payload.is_empty() {
+        return None;
+    }
+    match &packet.transport {
+        Some(TransportLayer::Udp(udp)) => {
+            if is_dns_port(udp.source_port) || is_dns_port(udp.destination_port) {
+                return parse_dns(payload).map(AppLayerInfo::Dns);
+            }
+            None
+        }
+        Some(TransportLayer::Tcp(tcp)) => {
+            if tcp.source_port == DNS_PORT || tcp.destination_port == DNS_PORT {
+                // DNS over TCP carries a two byte length prefix
+                return payload.get(2..).and_then(parse_dns).map(AppLayerInfo::Dns);
+            }
+            parse_tls_client_hello(payload).or_else(|| parse_http(payload))
+        }
+        _ => None,
+    }
+}
+
+fn is_dns_port(port: u16) -> bool {
+    port == DNS_PORT || port == MDNS_PORT
+}
+
+fn be16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*data.get(at)?, *data.get(at + 1)?]))
+}
+
+fn be32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes([*data.get(at)?, *data.get(at + 1)?, *data.get(at + 2)?, *data.get(at + 3)?]))
+}
+
+// Reads a possibly compressed name; returns it and the offset after it
+fn read_dns_name(message: &[u8], start: usize) -> Option<(String, usize)> {
+    let mut labels: Vec<String> = Vec::new();
+    let mut offset = start;
+    let mut end = None;
+    let mut jumps = 0;
+
+    loop {
+        let len = *message.get(offset)? as usize;
+        match len & 0xc0 {
+            0x00 => {
+                if len == 0 {
+                    let end = end.unwrap_or(offset + 1);
+                    return Some((labels.join("."), end));
+                }
+                let label = message.get(offset + 1..offset + 1 + len)?;
+                labels.push(String::from_utf8_lossy(label).into_owned());
+                offset += 1 + len;
+            }
+            0xc0 => {
+                jumps += 1;
+                if jumps > MAX_DNS_NAME_JUMPS {
+                    return None;
+                }
+                let pointer = (be16(message, offset)? & 0x3fff) as usize;
+                end.get_or_insert(offset + 2);
+                offset = pointer;
+            }
+            _ => return None,
+        }
+    }
+}
+
+pub(crate) fn parse_dns(message: &[u8]) -> Option<DnsMessage> {
+    let id = be16(message, 0)?;
+    let flags = be16(message, 2)?;
+    let question_count = be16(message, 4)? as usize;
+    let answer_count = be16(message, 6)? as usize;
+    // A real message with no questions and no answers is not worth reporting
+    if question_count == 0 && answer_count == 0 {
+        return None;
+    }
+
+    let mut offset = 12;
+    let mut questions = Vec::new();
+    for _ in 0..question_count.min(MAX_DNS_RECORDS) {
+        let (name, next) = read_dns_name(message, offset)?;
+        let record_type = be16(message, next)?;
+        be16(message, next + 2)?; // class
+        questions.push(DnsQuestion { name, record_type });
+        offset = next + 4;
+    }
+
+    let mut answers = Vec::new();
+    for _ in 0..answer_count.min(MAX_DNS_RECORDS) {
+        let (name, next) = match read_dns_name(message, offset) {
+            Some(parsed) => parsed,
+            None => break,
+        };
+        let (record_type, ttl, data_len) = match (be16(message, next), be32(message, next + 4), be16(message, next + 8)) {
+            (Some(record_type), Some(ttl), Some(data_len)) => (record_type, ttl, data_len as usize),
+            _ => break,
+        };
+        let data_start = next + 10;
+        let rdata = match message.get(data_start..data_start + data_len) {
+            Some(rdata) => rdata,
+            None => break,
+        };
+        let data = match (record_type, rdata.len()) {
+            (1, 4) => DnsRecordData::A(Ipv4Addr::new(rdata[0], rdata[1], rdata[2], rdata[3])),
+            (28, 16) => {
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(rdata);
+                DnsRecordData::Aaaa(Ipv6Addr::from(octets))
+            }
+            (2, _) | (5, _) | (12, _) => match read_dns_name(message, data_start) {
+                Some((target, _)) => DnsRecordData::Name(target),
+                None => DnsRecordData::Other(rdata.len()),
+            },
+            _ => DnsRecordData::Other(rdata.len()),
+        };
+        answers.push(DnsAnswer { name, record_type, ttl, data });
+        offset = data_start + data_len;
+    }
+
+    Some(DnsMessage {
+        id,
+        is_response: flags & 0x8000 != 0,
+        response_code: (flags & 0x000f) as u8,
+        questions,
+        answers,
+    })
+}
+
+pub(crate) fn parse_http(payload: &[u8]) -> Option<AppLayerInfo> {
+    // Only the head of the message matters; it must be text
+    let head_end = payload.windows(4).position(|w| w == b"\r\n\r\n").unwrap_or(payload.len());
+    let head = std::str::from_utf8(&payload[..head_end]).ok()?;
+    let mut lines = head.split("\r\n");
+    let first = lines.next()?;
+    let mut parts = first.splitn(3, ' ');
+    let (a, b, c) = (parts.next()?, parts.next()?, parts.next().unwrap_or(""));
+
+    if a.starts_with("HTTP/1.") {
+        return Some(AppLayerInfo::HttpResponse {
+            version: a.to_string(),
+            status: b.parse().ok()?,
+            reason: c.to_string(),
+        });
+    }
+    if !HTTP_METHODS.contains(&a) || !c.starts_with("HTTP/1.") {
+        return None;
+    }
+
+    let host = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("host"))
+        .map(|(_, value)| value.trim().to_string());
+    Some(AppLayerInfo::HttpRequest {
+        method: a.to_string(),
+        path: b.to_string(),
+        version: c.to_string(),
+        host,
+    })
+}
+
+// Handles a ClientHello that starts at the beginning of the segment
+pub(crate) fn parse_tls_client_hello(payload: &[u8]) -> Option<AppLayerInfo> {
+    // Record header: handshake (22), version 3.x, length
+    if *payload.first()? != 22 || *payload.get(1)? != 3 {
+        return None;
+    }
+    let record_len = be16(payload, 3)? as usize;
+    let record = payload.get(5..(5 + record_len).min(payload.len()))?;
+
+    // Handshake header: client_hello (1), 24 bit length
+    if *record.first()? != 1 {
+        return None;
+    }
+    let body = record.get(4..)?;
+    let version = be16(body, 0)?;
+    let mut offset = 2 + 32; // client_version + random
+    offset += 1 + *body.get(offset)? as usize; // session id
+    offset += 2 + be16(body, offset)? as usize; // cipher suites
+    offset += 1 + *body.get(offset)? as usize; // compression methods
+
+    let mut server_name = None;
+    let mut alpn = Vec::new();
+    let extensions_len = be16(body, offset).unwrap_or(0) as usize;
+    let extensions = body.get(offset + 2..(offset + 2 + extensions_len).min(body.len())).unwrap_or(&[]);
+
+    // A malformed extension ends the parse, keeping what came before it
+    let mut at = 0;
+    'extensions: while let (Some(kind), Some(len)) = (be16(extensions, at), be16(extensions, at + 2)) {
+        let data = match extensions.get(at + 4..at + 4 + len as usize) {
+            Some(data) => data,
+            None => break,
+        };
+        match kind {
+            0 => {
+                // server_name_list: length, then (type, length, name) entries
+                let mut entry = 2;
+                while let (Some(&name_type), Some(name_len)) = (data.get(entry), be16(data, entry + 1)) {
+                    let name = match data.get(entry + 3..entry + 3 + name_len as usize) {
+                        Some(name) => name,
+                        None => break 'extensions,
+                    };
+                    if name_type == 0 {
+                        server_name = Some(String::from_utf8_lossy(name).into_owned());
+                        break;
+                    }
+                    entry += 3 + name_len as usize;
+                }
+            }
+            16 => {
+                // protocol_name_list: length, then length prefixed names
+                let mut entry = 2;
+                while let Some(&name_len) = data.get(entry) {
+                    let name = match data.get(entry + 1..entry + 1 + name_len as usize) {
+                        Some(name) => name,
+                        None => break,
+                    };
+                    alpn.push(String::from_utf8_lossy(name).into_owned());
+                    entry += 1 + name_len as usize;
+                }
+            }
+            _ => {}
+        }
+        at += 4 + len as usize;
+    }
+
+    Some(AppLayerInfo::TlsClientHello { version, server_name, alpn })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dns_name(name: &str) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        for label in name.split('.') {
+            encoded.push(label.len() as u8);
+            encoded.extend_from_slice(label.as_bytes());
+        }
+        encoded.push(0);
+        encoded
+    }
+
+    #[test]
+    fn parses_dns_response_with_compressed_names() {
+        let mut message = vec![0x12, 0x34, 0x81, 0x80, 0, 1, 0, 2, 0, 0, 0, 0];
+        message.extend_from_slice(&dns_name("www.example.com"));
+        message.extend_from_slice(&[0, 1, 0, 1]);
+        // CNAME pointing back at offset 12 + 4 ("example.com")
+        message.extend_from_slice(&[0xc0, 12, 0, 5, 0, 1, 0, 0, 0, 60, 0, 2, 0xc0, 16]);
+        message.extend_from_slice(&[0xc0, 16, 0, 1, 0, 1, 0, 0, 1, 0, 0, 4, 93, 184, 216, 34]);
+
+        let dns = parse_dns(&message).unwrap();
+        assert!(dns.is_response);
+        assert_eq!(dns.questions, vec![DnsQuestion { name: "www.example.com".to_string(), record_type: 1 }]);
+        assert_eq!(dns.answers[0].data, DnsRecordData::Name("example.com".to_string()));
+        assert_eq!(dns.answers[1].name, "example.com");
+        assert_eq!(dns.answers[1].data, DnsRecordData::A(Ipv4Addr::new(93, 184, 216, 34)));
+        assert_eq!(dns.answers[1].ttl, 256);
+    }
+
+    #[test]
+    fn rejects_dns_pointer_loops() {
+        let mut message = vec![0, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+        message.extend_from_slice(&[0xc0, 12, 0, 1, 0, 1]);
+        assert_eq!(parse_dns(&message), None);
+    }
+
+    #[test]
+    fn parses_http_request_line_and_host() {
+        let request = b"GET /index.html HTTP/1.1\r\nUser-Agent: test\r\nhost: Example.org\r\n\r\n";
+        assert_eq!(
+            parse_http(request),
+            Some(AppLayerInfo::HttpRequest {
+                method: "GET".to_string(),
+                path: "/index.html".to_string(),
+                version: "HTTP/1.1".to_string(),
+                host: Some("Example.org".to_string()),
+            })
+        );
+        let response = parse_http(b"HTTP/1.0 404 Not Found\r\n\r\n").unwrap();
+        assert_eq!(response.summary(), "HTTP 404 Not Found");
+        assert_eq!(parse_http(b"\x16\x03\x01binary"), None);
+    }
+
+    // TLS record holding a ClientHello with the given extensions block
+    fn client_hello(extensions: &[u8]) -> Vec<u8> {
+        let mut hello = vec![3, 3];
+        hello.extend_from_slice(&[0u8; 32]);
+        hello.extend_from_slice(&[0, 0, 2, 0x13, 0x01, 1, 0]);
+        hello.extend_from_slice(&(extensions.len() as u16).to_be_bytes());
+        hello.extend_from_slice(extensions);
+
+        let mut record = vec![22, 3, 1];
+        record.extend_from_slice(&((hello.len() + 4) as u16).to_be_bytes());
+        record.extend_from_slice(&[1, 0]);
+        record.extend_from_slice(&(hello.len() as u16).to_be_bytes());
+        record.extend_from_slice(&hello);
+        record
+    }
+
+    #[test]
+    fn parses_tls_client_hello_sni_and_alpn() {
+        let host = b"example.net";
+        let mut sni = vec![0, 0];
+        sni.extend_from_slice(&((host.len() + 5) as u16).to_be_bytes());
+        sni.extend_from_slice(&((host.len() + 3) as u16).to_be_bytes());
+        sni.push(0);
+        sni.extend_from_slice(&(host.len() as u16).to_be_bytes());
+        sni.extend_from_slice(host);
+        let alpn = [0, 16, 0, 14, 0, 12, 2, b'h', b'2', 8, b'h', b't', b't', b'p', b'/', b'1', b'.', b'1'];
+
+        let info = parse_tls_client_hello(&client_hello(&[sni, alpn.to_vec()].concat())).unwrap();
+        assert_eq!(
+            info,
+            AppLayerInfo::TlsClientHello {
+                version: 0x0303,
+                server_name: Some("example.net".to_string()),
+                alpn: vec!["h2".to_string(), "http/1.1".to_string()],
+            }
+        );
+        assert_eq!(info.domain(), Some("example.net"));
+    }
+
+    #[test]
+    fn keeps_tls_fields_parsed_before_a_bad_extension() {
+        let alpn = [0, 16, 0, 5, 0, 3, 2, b'h', b'2'];
+        // server_name entry claiming 200 bytes inside a 7 byte extension
+        let sni = [0, 0, 0, 7, 0, 5, 0, 0, 200, b'a', b'b'];
+        let after = [0, 16, 0, 6, 0, 4, 3, b'x', b'y', b'z'];
+
+        let info = parse_tls_client_hello(&client_hello(&[&alpn[..], &sni, &after].concat())).unwrap();
+        assert_eq!(
+            info,
+            AppLayerInfo::TlsClientHello { version: 0x0303, server_name: None, alpn: vec!["h2".to_string()] }
+        );
+    }
+}
@@ -1,4 +1,5 @@
-use crate::logging;
+use s2o_net_lib::logging;
+use std::sync::{Arc, Mutex};
 
 pub fn init_module() -> Result<(), String> {
     // Placeholder for actual initialization logic
@@ -12,15 +13,91 @@ pub fn init_module() -> Result<(), String> {
     }
 }
 
+// One variant per registered screen (see gui_engine_screens)
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AppState {
     SMenu,
     PMenu,
     PCMenu,
     NSMenu,
+    FirewallMenu,
+    InterfaceMenu,
+    ConnectionsMenu,
     DSMenu,
+    ExitConfirm,
 }
 
 #[allow(dead_code)]
 pub trait SetAppState: Fn(AppState) {}
-impl<T> SetAppState for T where T: Fn(AppState) {}
\ No newline at end of file
+impl<T> SetAppState for T where T: Fn(AppState) {}
+// Screens visited to reach the current one, oldest first. The bottom entry
+// is the start screen and is never popped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Navigation {
+    history: Vec<AppState>,
+}
+
+// Shared between the GUI and the menu item actions
+pub type SharedNavigation = Arc<Mutex<Navigation>>;
+
+impl Navigation {
+    pub fn new(start: AppState) -> Self {
+        Navigation { history: vec![start] }
+    }
+
+    pub fn current(&self) -> AppState {
+        *self.history.last().expect("navigation history is never empty")
+    }
+
+    pub fn history(&self) -> &[AppState] {
+        &self.history
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        self.history.len() > 1
+    }
+
+    // Opening a screen already in the history returns to it rather than
+    // stacking a loop such as SMenu > PMenu > SMenu
+    pub fn push(&mut self, state: AppState) {
+        match self.history.iter().position(|visited| *visited == state) {
+            Some(index) => self.history.truncate(index + 1),
+            None => self.history.push(state),
+        }
+    }
+
+    // False when already on the start screen
+    pub fn back(&mut self) -> bool {
+        if !self.can_go_back() {
+            return false;
+        }
+        self.history.pop();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pushes_and_pops_screens() {
+        let mut navigation = Navigation::new(AppState::SMenu);
+        assert!(!navigation.back());
+        navigation.push(AppState::PMenu);
+        navigation.push(AppState::NSMenu);
+        navigation.push(AppState::InterfaceMenu);
+        assert_eq!(navigation.history(), [AppState::SMenu, AppState::PMenu, AppState::NSMenu, AppState::InterfaceMenu]);
+
+        assert!(navigation.back());
+        assert_eq!(navigation.current(), AppState::NSMenu);
+
+        // Revisiting an earlier screen unwinds to it
+        navigation.push(AppState::PMenu);
+        assert_eq!(navigation.history(), [AppState::SMenu, AppState::PMenu]);
+        assert!(navigation.back());
+        assert!(!navigation.can_go_back());
+        assert_eq!(navigation.current(), AppState::SMenu);
+    }
+}
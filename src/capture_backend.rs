@@ -0,0 +1,147 @@
+use crate::logging;
+use crate::capture_filter::CaptureFilter;
+use crate::app_dissector::{self, AppLayerInfo};
+use crate::packet_decoder::{self, Packet};
+use std::time::SystemTime;
+
+pub fn init_module() -> Result<(), String> {
+    // Placeholder for actual initialization logic
+    let initialization_passed = true;
+
+    if initialization_passed {
+        logging::debug_info("capture_backend module is online");
+        Ok(())
+    } else {
+        Err("capture_backend module initialization failed".to_string())
+    }
+}
+
+// Link layer of the bytes handed back by a backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkType {
+    Ethernet, // Full Ethernet frame
+    RawIp,    // Bare IPv4/IPv6 packet (WinDivert network layer)
+    LinuxSll, // Linux cooked capture v1 header, as from "any" device captures
+}
+
+// Metadata the backend reports alongside each received packet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PacketMeta {
+    pub timestamp: SystemTime,
+    pub captured_len: usize,
+    pub original_len: usize,
+    pub interface_index: u32,
+    pub outbound: bool,
+    pub link_type: LinkType,
+}
+
+// A single packet as stored in packet_capture::CAPTURED_PACKETS
+#[derive(Debug, Clone)]
+pub struct CapturedPacket {
+    pub meta: PacketMeta,
+    pub data: Vec<u8>,
+    // DNS/HTTP/TLS details found in the payload, if any
+    pub app_layer: Option<AppLayerInfo>,
+}
+
+impl CapturedPacket {
+    // Builds the record and runs the application layer dissectors over it
+    pub fn new(meta: PacketMeta, data: Vec<u8>) -> Self {
+        let app_layer = app_dissector::dissect(&packet_decoder::decode(&data, meta.link_type));
+        CapturedPacket { meta, data, app_layer }
+    }
+
+    // Same as new, for a caller that has already decoded `data`
+    pub fn with_decoded(meta: PacketMeta, data: Vec<u8>, decoded: &Packet) -> Self {
+        CapturedPacket { meta, data, app_layer: app_dissector::dissect(decoded) }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CaptureStats {
+    pub packets_received: u64,
+    pub bytes_received: u64,
+    pub recv_errors: u64,
+}
+
+impl CaptureStats {
+    // Bookkeeping shared by every backend after a successful recv
+    pub fn record_packet(&mut self, len: usize) {
+        self.packets_received += 1;
+        self.bytes_received += len as u64;
+    }
+}
+
+// Wakes a recv blocked in the capture thread; see CaptureBackend::interrupter
+pub type Interrupter = Box<dyn Fn() + Send>;
+
+// Common interface for every packet source (WinDivert, raw sockets, ...)
+pub trait CaptureBackend: Send {
+    // Short name used in logs and the GUI
+    fn name(&self) -> &str;
+
+    // Opens the source with a backend specific filter string
+    fn open(&mut self, filter: &str) -> Result<(), String>;
+
+    // Reads one packet into `buf`. Ok(None) means nothing arrived before the
+    // backend's read timeout, so the caller can check its stop flag.
+    fn recv(&mut self, buf: &mut [u8]) -> Result<Option<PacketMeta>, String>;
+
+    fn close(&mut self) -> Result<(), String>;
+
+    fn stats(&self) -> CaptureStats;
+
+    // Called after `open` for a way to wake a `recv` blocked in another
+    // thread, for backends whose recv has no timeout of its own
+    fn interrupter(&self) -> Option<Interrupter> {
+        None
+    }
+
+    // True once a finite source (e.g. a replayed file) has nothing left to read
+    fn is_finished(&self) -> bool {
+        false
+    }
+
+    // Switches to inline mode before `open`. Inline packets are diverted
+    // rather than copied and are lost unless handed back through `send`.
+    fn set_inline(&mut self, inline: bool) -> Result<(), String> {
+        if inline {
+            return Err(format!("{} cannot run inline", self.name()));
+        }
+        Ok(())
+    }
+
+    // Reinjects a packet the way the last one from `recv` was travelling.
+    // `checksums_valid` is false for untouched packets whose checksums may
+    // still be left to hardware offload.
+    fn send(&mut self, _data: &[u8], _checksums_valid: bool) -> Result<(), String> {
+        Err(format!("{} cannot reinject packets", self.name()))
+    }
+
+    // Filter string handed to `open`. Backends without a filter of their own
+    // take everything and leave it to the capture thread to evaluate `filter`.
+    fn native_filter(&self, _filter: &CaptureFilter) -> String {
+        "true".to_string()
+    }
+}
+
+// Returns the capture backend for the platform we were built for
+pub(crate) fn default_backend() -> Result<Box<dyn CaptureBackend>, String> {
+    #[cfg(windows)]
+    {
+        let mut backend = crate::nc::WinDivertBackend::new();
+        backend.set_interface(crate::interface_inventory::capture_interface().map(|interface| interface.index));
+        Ok(Box::new(backend))
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let interface = crate::interface_inventory::capture_interface().map(|interface| interface.name);
+        Ok(Box::new(crate::linux_capture::AfPacketBackend::new(interface)))
+    }
+
+    #[cfg(not(any(windows, target_os = "linux")))]
+    {
+        Err("No capture backend available on this platform".to_string())
+    }
+}
@@ -0,0 +1,595 @@
+use crate::logging;
+use crate::packet_decoder::{
+    NetworkLayer, Packet, IP_PROTO_ICMP, IP_PROTO_ICMPV6, IP_PROTO_TCP, IP_PROTO_UDP, TCP_ACK, TCP_CWR, TCP_ECE,
+    TCP_FIN, TCP_PSH, TCP_RST, TCP_SYN, TCP_URG,
+};
+use std::net::IpAddr;
+
+pub fn init_module() -> Result<(), String> {
+    // Placeholder for actual initialization logic
+    let initialization_passed = true;
+
+    if initialization_passed {
+        logging::debug_info("capture_filter module is online");
+        Ok(())
+    } else {
+        Err("capture_filter module initialization failed".to_string())
+    }
+}
+
+// Which end of the packet an address or port qualifier looks at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Src,
+    Dst,
+    Either,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Primitive {
+    Const(bool),
+    Host(Side, IpAddr),
+    Net(Side, IpAddr, u8),
+    Port(Side, u16),
+    PortRange(Side, u16, u16),
+    Proto(u8),
+    Ip,
+    Ip6,
+    Arp,
+    // Every flag in the mask must be set
+    TcpFlags(u16),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterExpr {
+    Primitive(Primitive),
+    Not(Box<FilterExpr>),
+    And(Box<FilterExpr>, Box<FilterExpr>),
+    Or(Box<FilterExpr>, Box<FilterExpr>),
+}
+
+// A parsed filter expression, e.g. "tcp port 443 and not host 10.0.0.1".
+//
+//   expr    := and ("or" and)*
+//   and     := unary ("and" unary)*
+//   unary   := "not" unary | "(" expr ")" | primitive
+//   primitive := [src|dst] host ADDR | [src|dst] net ADDR/LEN
+//              | [src|dst] port N | [src|dst] portrange N-M
+//              | proto N | tcp | udp | icmp | icmp6 | ip | ip6 | arp
+//              | tcpflags FLAG[,FLAG...] | true | false
+//
+// "&&", "||" and "!" work as aliases, and a protocol directly followed by a
+// port qualifier ("tcp port 80") is read as "tcp and port 80".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaptureFilter {
+    source: String,
+    expr: FilterExpr,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::LParen);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::RParen);
+            }
+            '!' => {
+                chars.next();
+                tokens.push(Token::Not);
+            }
+            '&' | '|' => {
+                chars.next();
+                if chars.next() != Some(c) {
+                    return Err(format!("Expected \"{}{}\"", c, c));
+                }
+                tokens.push(if c == '&' { Token::And } else { Token::Or });
+            }
+            c if c.is_ascii_alphanumeric() || ".:/-_,".contains(c) => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || ".:/-_,".contains(c)) {
+                        break;
+                    }
+                    word.push(c.to_ascii_lowercase());
+                    chars.next();
+                }
+                tokens.push(match word.as_str() {
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "not" => Token::Not,
+                    _ => Token::Word(word),
+                });
+            }
+            c => return Err(format!("Unexpected character '{}' in filter", c)),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_word(&self) -> Option<&str> {
+        match self.peek() {
+            Some(Token::Word(word)) => Some(word),
+            _ => None,
+        }
+    }
+
+    fn next_word(&mut self, what: &str) -> Result<String, String> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Word(word)) => {
+                self.pos += 1;
+                Ok(word.clone())
+            }
+            _ => Err(format!("Expected {}", what)),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<FilterExpr, String> {
+        let mut left = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            left = FilterExpr::Or(Box::new(left), Box::new(self.parse_and()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<FilterExpr, String> {
+        let mut left = self.parse_unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            left = FilterExpr::And(Box::new(left), Box::new(self.parse_unary()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<FilterExpr, String> {
+        match self.peek() {
+            Some(Token::Not) => {
+                self.pos += 1;
+                Ok(FilterExpr::Not(Box::new(self.parse_unary()?)))
+            }
+            Some(Token::LParen) => {
+                self.pos += 1;
+                let expr = self.parse_or()?;
+                if self.peek() != Some(&Token::RParen) {
+                    return Err("Expected \")\"".to_string());
+                }
+                self.pos += 1;
+                Ok(expr)
+            }
+            Some(Token::Word(_)) => self.parse_primitive(),
+            Some(token) => Err(format!("Unexpected {:?}", token)),
+            None => Err("Unexpected end of filter".to_string()),
+        }
+    }
+
+    fn parse_primitive(&mut self) -> Result<FilterExpr, String> {
+        let word = self.next_word("a filter primitive")?;
+        let side = match word.as_str() {
+            "src" => Some(Side::Src),
+            "dst" => Some(Side::Dst),
+            _ => None,
+        };
+        if let Some(side) = side {
+            let qualifier = self.next_word("host, net, port or portrange after src/dst")?;
+            return self.parse_qualified(side, &qualifier).map(FilterExpr::Primitive);
+        }
+
+        let primitive = match word.as_str() {
+            "true" => Primitive::Const(true),
+            "false" => Primitive::Const(false),
+            "ip" => Primitive::Ip,
+            "ip6" => Primitive::Ip6,
+            "arp" => Primitive::Arp,
+            "tcp" | "udp" | "icmp" | "icmp6" => {
+                let proto = Primitive::Proto(protocol_number(&word)?);
+                // "tcp port 80" shorthand
+                if matches!(self.peek_word(), Some("src" | "dst" | "port" | "portrange")) {
+                    let rest = self.parse_primitive()?;
+                    return Ok(FilterExpr::And(Box::new(FilterExpr::Primitive(proto)), Box::new(rest)));
+                }
+                proto
+            }
+            "proto" => Primitive::Proto(protocol_number(&self.next_word("a protocol after proto")?)?),
+            "tcpflags" => Primitive::TcpFlags(parse_tcp_flags(&self.next_word("flags after tcpflags")?)?),
+            "host" | "net" | "port" | "portrange" => self.parse_qualified(Side::Either, &word)?,
+            other => return Err(format!("Unknown filter primitive \"{}\"", other)),
+        };
+        Ok(FilterExpr::Primitive(primitive))
+    }
+
+    fn parse_qualified(&mut self, side: Side, qualifier: &str) -> Result<Primitive, String> {
+        match qualifier {
+            "host" => {
+                let value = self.next_word("an address after host")?;
+                let addr = value.parse().map_err(|_| format!("Invalid host address \"{}\"", value))?;
+                Ok(Primitive::Host(side, addr))
+            }
+            "net" => {
+                let (addr, prefix) = parse_network(&self.next_word("a network after net")?)?;
+                Ok(Primitive::Net(side, addr, prefix))
+            }
+            "port" => Ok(Primitive::Port(side, parse_port(&self.next_word("a port number")?)?)),
+            "portrange" => {
+                let value = self.next_word("a port range")?;
+                let (low, high) = value.split_once('-').ok_or(format!("Invalid port range \"{}\"", value))?;
+                let (low, high) = (parse_port(low)?, parse_port(high)?);
+                if low > high {
+                    return Err(format!("Invalid port range \"{}\"", value));
+                }
+                Ok(Primitive::PortRange(side, low, high))
+            }
+            other => Err(format!("Expected host, net, port or portrange, found \"{}\"", other)),
+        }
+    }
+}
+
+fn protocol_number(name: &str) -> Result<u8, String> {
+    match name {
+        "tcp" => Ok(IP_PROTO_TCP),
+        "udp" => Ok(IP_PROTO_UDP),
+        "icmp" => Ok(IP_PROTO_ICMP),
+        "icmp6" => Ok(IP_PROTO_ICMPV6),
+        _ => name.parse().map_err(|_| format!("Unknown protocol \"{}\"", name)),
+    }
+}
+
+fn parse_port(value: &str) -> Result<u16, String> {
+    value.parse().map_err(|_| format!("Invalid port \"{}\"", value))
+}
+
+fn parse_tcp_flags(value: &str) -> Result<u16, String> {
+    let mut mask = 0;
+    for name in value.split(',') {
+        mask |= match name {
+            "fin" => TCP_FIN,
+            "syn" => TCP_SYN,
+            "rst" => TCP_RST,
+            "psh" => TCP_PSH,
+            "ack" => TCP_ACK,
+            "urg" => TCP_URG,
+            "ece" => TCP_ECE,
+            "cwr" => TCP_CWR,
+            _ => return Err(format!("Unknown TCP flag \"{}\"", name)),
+        };
+    }
+    Ok(mask)
+}
+
+// "10.0.0.0/8"; a bare address is a host-length prefix. The host bits are cleared.
+pub(crate) fn parse_network(value: &str) -> Result<(IpAddr, u8), String> {
+    let (addr, prefix) = match value.split_once('/') {
+        Some((addr, prefix)) => (addr, Some(prefix)),
+        None => (value, None),
+    };
+    let addr: IpAddr = addr.parse().map_err(|_| format!("Invalid network address \"{}\"", value))?;
+    let max_prefix = if addr.is_ipv4() { 32 } else { 128 };
+    let prefix = match prefix {
+        Some(prefix) => prefix.parse().ok().filter(|p| *p <= max_prefix).ok_or(format!("Invalid prefix in \"{}\"", value))?,
+        None => max_prefix,
+    };
+    Ok((network_bounds(addr, prefix).0, prefix))
+}
+
+// First and last address of `addr/prefix`
+fn network_bounds(addr: IpAddr, prefix: u8) -> (IpAddr, IpAddr) {
+    match addr {
+        IpAddr::V4(v4) => {
+            let mask = if prefix == 0 { 0 } else { u32::MAX << (32 - prefix) };
+            let bits = u32::from(v4);
+            (IpAddr::V4((bits & mask).into()), IpAddr::V4((bits | !mask).into()))
+        }
+        IpAddr::V6(v6) => {
+            let mask = if prefix == 0 { 0 } else { u128::MAX << (128 - prefix) };
+            let bits = u128::from(v6);
+            (IpAddr::V6((bits & mask).into()), IpAddr::V6((bits | !mask).into()))
+        }
+    }
+}
+
+pub(crate) fn in_network(addr: IpAddr, network: IpAddr, prefix: u8) -> bool {
+    let (low, high) = network_bounds(network, prefix);
+    match (addr, low, high) {
+        (IpAddr::V4(a), IpAddr::V4(l), IpAddr::V4(h)) => l <= a && a <= h,
+        (IpAddr::V6(a), IpAddr::V6(l), IpAddr::V6(h)) => l <= a && a <= h,
+        _ => false,
+    }
+}
+
+// Applies `test` to the source and/or destination as the side asks
+fn either_side<T: Copy>(side: Side, pair: Option<(T, T)>, test: impl Fn(T) -> bool) -> bool {
+    match pair {
+        Some((src, dst)) => match side {
+            Side::Src => test(src),
+            Side::Dst => test(dst),
+            Side::Either => test(src) || test(dst),
+        },
+        None => false,
+    }
+}
+
+impl Primitive {
+    fn matches(&self, packet: &Packet) -> bool {
+        let addresses = packet.source_ip().zip(packet.destination_ip());
+        match *self {
+            Primitive::Const(value) => value,
+            Primitive::Host(side, host) => either_side(side, addresses, |addr| addr == host),
+            Primitive::Net(side, network, prefix) => {
+                either_side(side, addresses, |addr| in_network(addr, network, prefix))
+            }
+            Primitive::Port(side, port) => either_side(side, packet.ports(), |p| p == port),
+            Primitive::PortRange(side, low, high) => either_side(side, packet.ports(), |p| low <= p && p <= high),
+            Primitive::Proto(proto) => packet.ip_protocol() == Some(proto),
+            Primitive::Ip => matches!(packet.network, Some(NetworkLayer::Ipv4(_))),
+            Primitive::Ip6 => matches!(packet.network, Some(NetworkLayer::Ipv6(_))),
+            Primitive::Arp => matches!(packet.network, Some(NetworkLayer::Arp(_))),
+            Primitive::TcpFlags(mask) => packet.tcp().is_some_and(|tcp| tcp.flags & mask == mask),
+        }
+    }
+
+    // `negated` is true under an odd number of nots. Where WinDivert cannot
+    // test exactly what matches() does, the translation has to match more,
+    // so under a not it has to match less.
+    fn to_windivert(&self, negated: bool) -> Result<String, String> {
+        let address_field = |addr: &IpAddr, side: &str| match addr {
+            IpAddr::V4(_) => format!("ip.{}Addr", side),
+            IpAddr::V6(_) => format!("ipv6.{}Addr", side),
+        };
+        let sides = |side: Side| match side {
+            Side::Src => vec!["Src"],
+            Side::Dst => vec!["Dst"],
+            Side::Either => vec!["Src", "Dst"],
+        };
+        let any_of = |tests: Vec<String>| {
+            if tests.len() == 1 {
+                tests.into_iter().next().unwrap()
+            } else {
+                format!("({})", tests.join(" or "))
+            }
+        };
+
+        Ok(match *self {
+            Primitive::Const(value) => value.to_string(),
+            Primitive::Host(side, addr) => {
+                any_of(sides(side).iter().map(|s| format!("{} == {}", address_field(&addr, s), addr)).collect())
+            }
+            Primitive::Net(side, network, prefix) => {
+                let (low, high) = network_bounds(network, prefix);
+                any_of(
+                    sides(side)
+                        .iter()
+                        .map(|s| {
+                            let field = address_field(&network, s);
+                            format!("({} >= {} and {} <= {})", field, low, field, high)
+                        })
+                        .collect(),
+                )
+            }
+            Primitive::Port(side, port) => any_of(
+                ["tcp", "udp"]
+                    .iter()
+                    .flat_map(|proto| sides(side).into_iter().map(move |s| format!("{}.{}Port == {}", proto, s, port)))
+                    .collect(),
+            ),
+            Primitive::PortRange(side, low, high) => any_of(
+                ["tcp", "udp"]
+                    .iter()
+                    .flat_map(|proto| {
+                        sides(side).into_iter().map(move |s| {
+                            format!("({}.{}Port >= {} and {}.{}Port <= {})", proto, s, low, proto, s, high)
+                        })
+                    })
+                    .collect(),
+            ),
+            Primitive::Proto(IP_PROTO_TCP) => "tcp".to_string(),
+            Primitive::Proto(IP_PROTO_UDP) => "udp".to_string(),
+            Primitive::Proto(IP_PROTO_ICMP) => "icmp".to_string(),
+            Primitive::Proto(IP_PROTO_ICMPV6) => "icmpv6".to_string(),
+            // ipv6.NextHdr is the first header, before any extension headers
+            // that matches() looks past, so the Rust filter decides for IPv6
+            Primitive::Proto(proto) => format!("(ip.Protocol == {} or {})", proto, if negated { "false" } else { "ipv6" }),
+            Primitive::Ip => "ip".to_string(),
+            Primitive::Ip6 => "ipv6".to_string(),
+            // The network layer never sees ARP
+            Primitive::Arp => "false".to_string(),
+            Primitive::TcpFlags(mask) => {
+                let fields = [
+                    (TCP_FIN, "Fin"),
+                    (TCP_SYN, "Syn"),
+                    (TCP_RST, "Rst"),
+                    (TCP_PSH, "Psh"),
+                    (TCP_ACK, "Ack"),
+                    (TCP_URG, "Urg"),
+                ];
+                if mask & (TCP_ECE | TCP_CWR) != 0 {
+                    return Err("WinDivert cannot filter on the ECE or CWR flags".to_string());
+                }
+                let tests: Vec<String> = fields
+                    .iter()
+                    .filter(|(flag, _)| mask & flag != 0)
+                    .map(|(_, name)| format!("tcp.{}", name))
+                    .collect();
+                format!("({})", tests.join(" and "))
+            }
+        })
+    }
+}
+
+impl FilterExpr {
+    pub fn matches(&self, packet: &Packet) -> bool {
+        match self {
+            FilterExpr::Primitive(primitive) => primitive.matches(packet),
+            FilterExpr::Not(inner) => !inner.matches(packet),
+            FilterExpr::And(left, right) => left.matches(packet) && right.matches(packet),
+            FilterExpr::Or(left, right) => left.matches(packet) || right.matches(packet),
+        }
+    }
+
+    pub fn to_windivert(&self) -> Result<String, String> {
+        self.windivert(false)
+    }
+
+    fn windivert(&self, negated: bool) -> Result<String, String> {
+        match self {
+            FilterExpr::Primitive(primitive) => primitive.to_windivert(negated),
+            FilterExpr::Not(inner) => Ok(format!("not ({})", inner.windivert(!negated)?)),
+            FilterExpr::And(left, right) => Ok(format!("({} and {})", left.windivert(negated)?, right.windivert(negated)?)),
+            FilterExpr::Or(left, right) => Ok(format!("({} or {})", left.windivert(negated)?, right.windivert(negated)?)),
+        }
+    }
+}
+
+impl CaptureFilter {
+    // An empty string matches everything, like "true"
+    pub fn parse(input: &str) -> Result<CaptureFilter, String> {
+        let tokens = tokenize(input)?;
+        let expr = if tokens.is_empty() {
+            FilterExpr::Primitive(Primitive::Const(true))
+        } else {
+            let mut parser = Parser { tokens, pos: 0 };
+            let expr = parser.parse_or()?;
+            if let Some(token) = parser.peek() {
+                return Err(format!("Unexpected {:?} after end of filter", token));
+            }
+            expr
+        };
+        Ok(CaptureFilter { source: input.trim().to_string(), expr })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    pub fn expr(&self) -> &FilterExpr {
+        &self.expr
+    }
+
+    pub fn matches(&self, packet: &Packet) -> bool {
+        self.expr.matches(packet)
+    }
+
+    // WinDivert filter language equivalent, for backends that filter in the
+    // driver. Errors when the expression uses something WinDivert lacks.
+    pub fn to_windivert(&self) -> Result<String, String> {
+        self.expr.to_windivert()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture_backend::LinkType;
+    use crate::packet_decoder::decode;
+
+    // IPv4 192.168.1.10:51000 -> 93.184.216.34:443 TCP with the given flags
+    fn tcp_packet(flags: u8) -> Vec<u8> {
+        let mut ip = vec![0x45, 0, 0, 40, 0x12, 0x34, 0x40, 0x00, 64, IP_PROTO_TCP, 0, 0];
+        ip.extend_from_slice(&[192, 168, 1, 10, 93, 184, 216, 34]);
+        ip.extend_from_slice(&51000u16.to_be_bytes());
+        ip.extend_from_slice(&443u16.to_be_bytes());
+        ip.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 0, 0x50, flags, 0xff, 0xff, 0, 0, 0, 0]);
+        ip
+    }
+
+    fn matches(filter: &str, data: &[u8]) -> bool {
+        let packet = decode(data, LinkType::RawIp);
+        CaptureFilter::parse(filter).unwrap().matches(&packet)
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let parsed = CaptureFilter::parse("udp or tcp and port 80").unwrap();
+        let expected = FilterExpr::Or(
+            Box::new(FilterExpr::Primitive(Primitive::Proto(IP_PROTO_UDP))),
+            Box::new(FilterExpr::And(
+                Box::new(FilterExpr::Primitive(Primitive::Proto(IP_PROTO_TCP))),
+                Box::new(FilterExpr::Primitive(Primitive::Port(Side::Either, 80))),
+            )),
+        );
+        assert_eq!(parsed.expr(), &expected);
+        assert_eq!(CaptureFilter::parse("tcp port 80").unwrap().expr(), CaptureFilter::parse("tcp and port 80").unwrap().expr());
+    }
+
+    #[test]
+    fn evaluates_against_decoded_packets() {
+        let syn = tcp_packet(0x02);
+        assert!(matches("", &syn));
+        assert!(matches("tcp dst port 443 and src net 192.168.0.0/16", &syn));
+        assert!(matches("host 93.184.216.34 && !udp", &syn));
+        assert!(matches("tcpflags syn and not tcpflags ack", &syn));
+        assert!(matches("src portrange 50000-52000", &syn));
+        assert!(!matches("dst port 51000", &syn));
+        assert!(!matches("net 10.0.0.0/8 or ip6 or arp", &syn));
+        assert!(!matches("tcpflags syn,ack", &syn));
+        assert!(matches("tcpflags syn,ack", &tcp_packet(0x12)));
+    }
+
+    #[test]
+    fn translates_to_windivert() {
+        let filter = CaptureFilter::parse("tcp dst port 443 and not src net 10.1.2.3/8").unwrap();
+        assert_eq!(
+            filter.to_windivert().unwrap(),
+            "((tcp and (tcp.DstPort == 443 or udp.DstPort == 443)) and not ((ip.SrcAddr >= 10.0.0.0 and ip.SrcAddr <= 10.255.255.255)))"
+        );
+        let filter = CaptureFilter::parse("host 2001:db8::1 or tcpflags syn,ack").unwrap();
+        assert_eq!(
+            filter.to_windivert().unwrap(),
+            "((ipv6.SrcAddr == 2001:db8::1 or ipv6.DstAddr == 2001:db8::1) or (tcp.Syn and tcp.Ack))"
+        );
+        assert!(CaptureFilter::parse("tcpflags ece").unwrap().to_windivert().is_err());
+    }
+
+    #[test]
+    fn windivert_protocol_filter_keeps_ipv6_extension_headers() {
+        // IPv6 fd00::1 -> fd00::2, hop-by-hop options header, then GRE (47)
+        let mut ip = vec![0x60, 0, 0, 0, 0, 12, 0, 64];
+        ip.extend_from_slice(&[0xfd, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        ip.extend_from_slice(&[0xfd, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2]);
+        ip.extend_from_slice(&[47, 0, 1, 4, 0, 0, 0, 0]);
+        ip.extend_from_slice(&[0, 0, 0x08, 0x00]);
+        assert!(matches("proto 47", &ip));
+        assert!(!matches("not proto 47", &ip));
+
+        // ipv6.NextHdr would be 0 for this packet, so IPv6 is left to matches()
+        assert_eq!(CaptureFilter::parse("proto 47").unwrap().to_windivert().unwrap(), "(ip.Protocol == 47 or ipv6)");
+        assert_eq!(
+            CaptureFilter::parse("not proto 47").unwrap().to_windivert().unwrap(),
+            "not ((ip.Protocol == 47 or false))"
+        );
+    }
+
+    #[test]
+    fn reports_syntax_errors() {
+        for bad in ["tcp and", "(tcp", "port http", "net 10.0.0.0/33", "host", "tcp udp", "foo", "a & b", "portrange 9-1"] {
+            assert!(CaptureFilter::parse(bad).is_err(), "{} should not parse", bad);
+        }
+    }
+}
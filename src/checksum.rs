@@ -0,0 +1,56 @@
+use crate::logging;
+
+pub fn init_module() -> Result<(), String> {
+    // Placeholder for actual initialization logic
+    let initialization_passed = true;
+
+    if initialization_passed {
+        logging::debug_info("checksum module is online");
+        Ok(())
+    } else {
+        Err("checksum module initialization failed".to_string())
+    }
+}
+
+// Ones' complement sum used by the IP, TCP, UDP and ICMP checksums
+pub(crate) fn checksum_add(mut sum: u32, bytes: &[u8]) -> u32 {
+    let mut chunks = bytes.chunks_exact(2);
+    for chunk in &mut chunks {
+        sum += u16::from_be_bytes([chunk[0], chunk[1]]) as u32;
+    }
+    if let [last] = chunks.remainder() {
+        sum += (*last as u32) << 8;
+    }
+    sum
+}
+
+pub(crate) fn checksum_finish(mut sum: u32) -> u16 {
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+// Checksum of a self-contained header such as an ICMP echo request
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) fn internet_checksum(bytes: &[u8]) -> u16 {
+    checksum_finish(checksum_add(0, bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_rfc_1071_example() {
+        // Worked example from RFC 1071 section 3: the sum folds to 0xddf2
+        let bytes = [0x00, 0x01, 0xf2, 0x03, 0xf4, 0xf5, 0xf6, 0xf7];
+        assert_eq!(internet_checksum(&bytes), !0xddf2);
+        // Odd lengths pad the last byte with a zero
+        assert_eq!(internet_checksum(&[0x12, 0x34, 0x56]), internet_checksum(&[0x12, 0x34, 0x56, 0x00]));
+        // Appending the checksum makes the whole buffer sum to zero
+        let mut with_sum = bytes.to_vec();
+        with_sum.extend_from_slice(&internet_checksum(&bytes).to_be_bytes());
+        assert_eq!(internet_checksum(&with_sum), 0);
+    }
+}
@@ -0,0 +1,946 @@
+use s2o_net_lib::logging;
+use s2o_net_lib::capture_filter::CaptureFilter;
+use s2o_net_lib::connections::{self, SortColumn};
+use s2o_net_lib::diagnostics::{self, CheckResult, CheckStatus, DiagnosticCheck};
+use s2o_net_lib::firewall::{self, FirewallRule, RuleSet};
+use s2o_net_lib::flow_table;
+use s2o_net_lib::initialization;
+use s2o_net_lib::inline_verdict;
+use s2o_net_lib::interface_inventory::{self, NetworkInterface};
+use s2o_net_lib::linux_firewall::{self, IpFamily};
+use s2o_net_lib::packet_capture;
+use s2o_net_lib::pcap_writer;
+use s2o_net_lib::rule_store;
+use s2o_net_lib::speed_test::{self, SpeedTestServer};
+use s2o_net_lib::throughput_monitor::{self, format_rate};
+use s2o_net_lib::windows_firewall::WindowsFirewall;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub fn init_module() -> Result<(), String> {
+    // Placeholder for actual initialization logic
+    let initialization_passed = true;
+
+    if initialization_passed {
+        logging::debug_info("cli module is online");
+        Ok(())
+    } else {
+        Err("cli module initialization failed".to_string())
+    }
+}
+
+pub const EXIT_OK: i32 = 0;
+// The command ran and failed, e.g. the capture backend could not be opened
+pub const EXIT_FAILURE: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+// Diagnostics ran but at least one check failed
+pub const EXIT_CHECKS_FAILED: i32 = 3;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+const DEFAULT_THROUGHPUT_DURATION: Duration = Duration::from_secs(5);
+
+pub const USAGE: &str = "\
+Usage: s2o_net_lib [--json] <command> [options]
+
+Without a command the GUI starts.
+
+Commands:
+  capture [--filter EXPR] [--duration SECS] [--count N] [--interface NAME|INDEX]
+          [--inline [--rules FILE]] [--replay FILE] [--output FILE]
+      Capture until the duration or packet count is reached, the replayed file
+      ends, or Enter is pressed. --output exports the packets as pcap/pcapng.
+  verdicts [--input FILE] [--output FILE] [--rules FILE]
+      Replay a capture file through the firewall rules
+  check-backend     Open and close the capture backend
+  interfaces        List network interfaces
+  connections [--sort proto|local|remote|state|process] [--desc]
+  diagnose [CHECKS] [--timeout SECS]
+      Run connection checks, e.g. \"dns example.com; tcp example.com:443\"
+  speedtest [TARGET]
+      Measure throughput and latency; without a target loopback is tested
+  speedtest --serve [--duration SECS]
+  throughput [--duration SECS]
+  firewall show [--rules FILE]
+  firewall ruleset [--iptables [--ipv6]] [--rules FILE]
+  firewall import FILE [--rules FILE]
+  firewall apply [--rules FILE]     Load the rules into nftables (Linux, root)
+  firewall windows                  List Windows Firewall rules
+  help
+
+Options:
+  --json            Print JSON instead of tables
+
+Exit codes: 0 success, 1 failure, 2 usage error, 3 a diagnostic check failed
+";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaptureSource {
+    Live,
+    // Packets are held until the firewall rules decide them
+    Inline,
+    Replay(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaptureOptions {
+    pub source: CaptureSource,
+    pub filter: String,
+    pub duration: Option<Duration>,
+    pub count: Option<usize>,
+    // Interface name or index
+    pub interface: Option<String>,
+    // Rule file loaded before an inline capture
+    pub rules: Option<String>,
+    pub output: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FirewallCommand {
+    Show { rules: String },
+    // nftables script, or iptables-restore input for one family
+    Ruleset { rules: String, family: Option<IpFamily> },
+    Import { path: String, rules: String },
+    Apply { rules: String },
+    Windows,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Gui,
+    Help,
+    Capture(CaptureOptions),
+    Verdicts { input: String, output: String, rules: Option<String> },
+    CheckBackend,
+    Interfaces,
+    Connections { sort: SortColumn, descending: bool },
+    Diagnose { checks: Vec<DiagnosticCheck>, timeout: Duration },
+    SpeedTest { target: String },
+    SpeedServer { duration: Option<Duration> },
+    Throughput { duration: Duration },
+    Firewall(FirewallCommand),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invocation {
+    pub json: bool,
+    pub command: Command,
+}
+
+// The arguments after the command name
+struct Args(VecDeque<String>);
+
+impl Args {
+    fn next(&mut self) -> Option<String> {
+        self.0.pop_front()
+    }
+
+    // The value following `flag`
+    fn value(&mut self, flag: &str) -> Result<String, String> {
+        match self.next() {
+            Some(value) if !value.starts_with("--") => Ok(value),
+            _ => Err(format!("{} needs a value", flag)),
+        }
+    }
+
+    fn seconds(&mut self, flag: &str) -> Result<Duration, String> {
+        let value = self.value(flag)?;
+        value
+            .parse::<f64>()
+            .ok()
+            .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+            .ok_or(format!("{} expects seconds, got \"{}\"", flag, value))
+    }
+}
+
+fn unexpected(arg: &str) -> String {
+    format!("Unexpected argument \"{}\"", arg)
+}
+
+// `--json` may appear anywhere; everything else is positional per command
+pub fn parse_args(args: &[String]) -> Result<Invocation, String> {
+    let json = args.iter().any(|arg| arg == "--json");
+    let mut args = Args(args.iter().filter(|arg| *arg != "--json").cloned().collect());
+    let command = match args.next().as_deref() {
+        None | Some("gui") => Command::Gui,
+        Some("help" | "--help" | "-h") => Command::Help,
+        Some("capture") => Command::Capture(parse_capture(&mut args)?),
+        Some("verdicts") => parse_verdicts(&mut args)?,
+        Some("check-backend") => Command::CheckBackend,
+        Some("interfaces") => Command::Interfaces,
+        Some("connections") => parse_connections(&mut args)?,
+        Some("diagnose") => parse_diagnose(&mut args)?,
+        Some("speedtest") => parse_speedtest(&mut args)?,
+        Some("throughput") => {
+            let mut duration = DEFAULT_THROUGHPUT_DURATION;
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--duration" => duration = args.seconds(&arg)?,
+                    _ => return Err(unexpected(&arg)),
+                }
+            }
+            Command::Throughput { duration }
+        }
+        Some("firewall") => Command::Firewall(parse_firewall(&mut args)?),
+        Some(other) => return Err(format!("Unknown command \"{}\"", other)),
+    };
+    if let Some(arg) = args.next() {
+        return Err(unexpected(&arg));
+    }
+    Ok(Invocation { json, command })
+}
+
+fn parse_capture(args: &mut Args) -> Result<CaptureOptions, String> {
+    let mut options = CaptureOptions {
+        source: CaptureSource::Live,
+        filter: String::new(),
+        duration: None,
+        count: None,
+        interface: None,
+        rules: None,
+        output: None,
+    };
+    let mut inline = false;
+    let mut replay = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--filter" => options.filter = args.value(&arg)?,
+            "--duration" => options.duration = Some(args.seconds(&arg)?),
+            "--count" => {
+                let value = args.value(&arg)?;
+                options.count = Some(value.parse().map_err(|_| format!("--count expects a packet count, got \"{}\"", value))?);
+            }
+            "--interface" => options.interface = Some(args.value(&arg)?),
+            "--inline" => inline = true,
+            "--rules" => options.rules = Some(args.value(&arg)?),
+            "--replay" => replay = Some(args.value(&arg)?),
+            "--output" => options.output = Some(args.value(&arg)?),
+            _ => return Err(unexpected(&arg)),
+        }
+    }
+    // Reject a bad filter before anything is opened
+    CaptureFilter::parse(&options.filter).map_err(|e| format!("Invalid filter: {}", e))?;
+    options.source = match (inline, replay) {
+        (true, Some(_)) => return Err("--inline cannot replay a file; use the verdicts command".to_string()),
+        (true, None) => CaptureSource::Inline,
+        (false, Some(path)) => CaptureSource::Replay(path),
+        (false, None) => CaptureSource::Live,
+    };
+    if options.rules.is_some() && options.source != CaptureSource::Inline {
+        return Err("--rules only applies to --inline captures".to_string());
+    }
+    if options.interface.is_some() && matches!(options.source, CaptureSource::Replay(_)) {
+        return Err("--interface cannot be used with --replay".to_string());
+    }
+    Ok(options)
+}
+
+fn parse_verdicts(args: &mut Args) -> Result<Command, String> {
+    let mut input = packet_capture::DEFAULT_CAPTURE_FILE.to_string();
+    let mut output = packet_capture::INLINE_REPLAY_FILE.to_string();
+    let mut rules = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--input" => input = args.value(&arg)?,
+            "--output" => output = args.value(&arg)?,
+            "--rules" => rules = Some(args.value(&arg)?),
+            _ => return Err(unexpected(&arg)),
+        }
+    }
+    Ok(Command::Verdicts { input, output, rules })
+}
+
+fn parse_connections(args: &mut Args) -> Result<Command, String> {
+    let mut sort = SortColumn::Protocol;
+    let mut descending = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--sort" => {
+                sort = match args.value(&arg)?.to_lowercase().as_str() {
+                    "proto" | "protocol" => SortColumn::Protocol,
+                    "local" => SortColumn::Local,
+                    "remote" => SortColumn::Remote,
+                    "state" => SortColumn::State,
+                    "process" => SortColumn::Process,
+                    other => return Err(format!("Cannot sort by \"{}\"", other)),
+                }
+            }
+            "--desc" => descending = true,
+            _ => return Err(unexpected(&arg)),
+        }
+    }
+    Ok(Command::Connections { sort, descending })
+}
+
+fn parse_diagnose(args: &mut Args) -> Result<Command, String> {
+    let mut spec = None;
+    let mut timeout = diagnostics::DEFAULT_TIMEOUT;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--timeout" => timeout = args.seconds(&arg)?,
+            _ if spec.is_none() && !arg.starts_with("--") => spec = Some(arg),
+            _ => return Err(unexpected(&arg)),
+        }
+    }
+    let checks = diagnostics::parse_checks(spec.as_deref().unwrap_or(diagnostics::DEFAULT_CHECKS))?;
+    Ok(Command::Diagnose { checks, timeout })
+}
+
+fn parse_speedtest(args: &mut Args) -> Result<Command, String> {
+    let mut target = None;
+    let mut serve = false;
+    let mut duration = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--serve" => serve = true,
+            "--duration" => duration = Some(args.seconds(&arg)?),
+            _ if target.is_none() && !arg.starts_with("--") => target = Some(arg),
+            _ => return Err(unexpected(&arg)),
+        }
+    }
+    match (serve, target) {
+        (true, Some(_)) => Err("--serve does not take a target".to_string()),
+        (true, None) => Ok(Command::SpeedServer { duration }),
+        (false, _) if duration.is_some() => Err("--duration only applies to --serve".to_string()),
+        (false, target) => Ok(Command::SpeedTest { target: target.unwrap_or_default() }),
+    }
+}
+
+fn parse_firewall(args: &mut Args) -> Result<FirewallCommand, String> {
+    let action = args.next().ok_or("firewall needs show, ruleset, import, apply or windows")?;
+    let mut rules = rule_store::DEFAULT_RULES_FILE.to_string();
+    let mut iptables = false;
+    let mut ipv6 = false;
+    let mut path = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--rules" if action != "windows" => rules = args.value(&arg)?,
+            "--iptables" if action == "ruleset" => iptables = true,
+            "--ipv6" if action == "ruleset" => ipv6 = true,
+            _ if action == "import" && path.is_none() && !arg.starts_with("--") => path = Some(arg),
+            _ => return Err(unexpected(&arg)),
+        }
+    }
+    match action.as_str() {
+        "show" => Ok(FirewallCommand::Show { rules }),
+        "ruleset" if ipv6 && !iptables => Err("--ipv6 only applies to --iptables".to_string()),
+        "ruleset" => {
+            let family = iptables.then_some(if ipv6 { IpFamily::V6 } else { IpFamily::V4 });
+            Ok(FirewallCommand::Ruleset { rules, family })
+        }
+        "import" => Ok(FirewallCommand::Import { path: path.ok_or("firewall import needs a file")?, rules }),
+        "apply" => Ok(FirewallCommand::Apply { rules }),
+        "windows" => Ok(FirewallCommand::Windows),
+        other => Err(format!("Unknown firewall command \"{}\"", other)),
+    }
+}
+
+// What a command prints in either format, and the exit code it ends with
+struct Report {
+    json: Value,
+    table: String,
+    code: i32,
+}
+
+impl Report {
+    fn new(json: Value, table: String) -> Self {
+        Report { json, table, code: EXIT_OK }
+    }
+}
+
+// Left-aligned columns two spaces apart, with the headers on the first line
+pub fn format_table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|header| header.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let line = |cells: Vec<&str>| {
+        let padded: Vec<String> = cells.iter().zip(&widths).map(|(cell, width)| format!("{:<width$}", cell, width = width)).collect();
+        format!("{}\n", padded.join("  ").trim_end())
+    };
+    let mut out = line(headers.to_vec());
+    for row in rows {
+        out.push_str(&line(row.iter().map(String::as_str).collect()));
+    }
+    out
+}
+
+fn join<T: ToString>(items: &[T]) -> String {
+    if items.is_empty() {
+        "-".to_string()
+    } else {
+        items.iter().map(T::to_string).collect::<Vec<_>>().join(", ")
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+// Boots the modules without the GUI, runs the command and prints its
+// result, returning the process exit code
+pub fn run(invocation: Invocation) -> i32 {
+    if invocation.command == Command::Help {
+        print!("{}", USAGE);
+        return EXIT_OK;
+    }
+    if let Err(e) = initialization::s2o_bootup_headless().and_then(|()| init_module()) {
+        eprintln!("error: {}", e);
+        return EXIT_FAILURE;
+    }
+    let result = execute(invocation.command);
+    initialization::s2o_shutdown();
+    match result {
+        Ok(report) => {
+            if invocation.json {
+                println!("{}", serde_json::to_string_pretty(&report.json).unwrap());
+            } else {
+                print!("{}", report.table);
+            }
+            report.code
+        }
+        Err(e) => {
+            if invocation.json {
+                println!("{}", json!({ "error": e }));
+            }
+            eprintln!("error: {}", e);
+            EXIT_FAILURE
+        }
+    }
+}
+
+fn execute(command: Command) -> Result<Report, String> {
+    match command {
+        Command::Gui | Command::Help => Err("Not a command line action".to_string()),
+        Command::Capture(options) => capture(&options),
+        Command::Verdicts { input, output, rules } => verdicts(&input, &output, rules.as_deref()),
+        Command::CheckBackend => {
+            packet_capture::run_preliminary_tests()?;
+            Ok(Report::new(json!({ "backend": "ok" }), "Capture backend is usable\n".to_string()))
+        }
+        Command::Interfaces => interfaces(),
+        Command::Connections { sort, descending } => connections(sort, descending),
+        Command::Diagnose { checks, timeout } => Ok(diagnose(&checks, timeout)),
+        Command::SpeedTest { target } => speed_test(&target),
+        Command::SpeedServer { duration } => speed_server(duration),
+        Command::Throughput { duration } => throughput(duration),
+        Command::Firewall(command) => firewall_command(command),
+    }
+}
+
+// Set once a line, or the end of input, is read from stdin
+fn enter_pressed() -> Arc<AtomicBool> {
+    eprintln!("Press Enter to stop.");
+    let pressed = Arc::new(AtomicBool::new(false));
+    let flag = pressed.clone();
+    std::thread::spawn(move || {
+        let _ = std::io::stdin().read_line(&mut String::new());
+        flag.store(true, Ordering::SeqCst);
+    });
+    pressed
+}
+
+fn find_interface(selector: &str) -> Result<NetworkInterface, String> {
+    interface_inventory::enumerate()?
+        .into_iter()
+        .find(|interface| interface.name == selector || interface.index.to_string() == selector)
+        .ok_or(format!("No interface \"{}\"", selector))
+}
+
+fn load_rules(path: &str) -> Result<RuleSet, String> {
+    rule_store::load_rules(Path::new(path))
+}
+
+// Makes the rule file the live rule set, as Load Firewall Rules does
+fn activate_rules(path: &str) -> Result<(), String> {
+    let rules = load_rules(path)?;
+    logging::debug_info(&format!("Loaded {} firewall rules from {}", rules.rules().len(), path));
+    *firewall::FIREWALL.lock().unwrap() = rules;
+    Ok(())
+}
+
+fn capture(options: &CaptureOptions) -> Result<Report, String> {
+    if let Some(selector) = &options.interface {
+        interface_inventory::set_capture_interface(Some(find_interface(selector)?));
+    }
+    // --count counts packets stored by this capture, even ones evicted again
+    let accepted_before = packet_capture::get_accepted_count();
+    match &options.source {
+        CaptureSource::Live => packet_capture::start_capture(&options.filter)?,
+        CaptureSource::Inline => {
+            if let Some(rules) = &options.rules {
+                activate_rules(rules)?;
+            }
+            packet_capture::start_inline_capture(&options.filter)?
+        }
+        CaptureSource::Replay(path) => packet_capture::start_replay(path, &options.filter)?,
+    }
+
+    // A replay stops by itself; a live capture without limits waits for Enter
+    let unbounded = options.duration.is_none() && options.count.is_none();
+    let enter = (unbounded && !matches!(options.source, CaptureSource::Replay(_))).then(enter_pressed);
+    let started = Instant::now();
+    while packet_capture::is_capturing()
+        && options.duration.is_none_or(|duration| started.elapsed() < duration)
+        && options.count.is_none_or(|count| packet_capture::get_accepted_count() - accepted_before < count as u64)
+        && !enter.as_ref().is_some_and(|pressed| pressed.load(Ordering::SeqCst))
+    {
+        std::thread::sleep(POLL_INTERVAL);
+    }
+    packet_capture::stop_capture()?;
+
+    let exported = match &options.output {
+        Some(path) => Some((path, pcap_writer::export_capture(Path::new(path))?)),
+        None => None,
+    };
+    Ok(capture_report(options.source == CaptureSource::Inline, exported))
+}
+
+fn capture_report(inline: bool, exported: Option<(&String, usize)>) -> Report {
+    let stats = packet_capture::capture_stats();
+    let store = packet_capture::CAPTURED_PACKETS.stats();
+    let packets = packet_capture::get_packet_data();
+    let domains = packet_capture::get_domain_contacts();
+    let flows: Vec<String> = flow_table::snapshot().iter().map(|flow| flow.summary()).collect();
+    let verdicts = packet_capture::inline_stats();
+
+    let mut json = json!({
+        "packets_received": stats.packets_received,
+        "bytes_received": stats.bytes_received,
+        "recv_errors": stats.recv_errors,
+        "evicted": store.evicted_packets,
+        "rejected": store.rejected_packets,
+        "packets": packets,
+        "domains": domains.iter().map(|(client, domain)| json!({ "client": client, "domain": domain })).collect::<Vec<_>>(),
+        "flows": flows,
+        "exported": exported.map(|(path, count)| json!({ "path": path, "packets": count })),
+    });
+
+    let mut table = String::new();
+    for line in &packets {
+        table.push_str(line);
+        table.push('\n');
+    }
+    if !domains.is_empty() {
+        let rows: Vec<Vec<String>> = domains.into_iter().map(|(client, domain)| vec![client, domain]).collect();
+        table.push_str(&format!("\n{}", format_table(&["Client", "Domain"], &rows)));
+    }
+    if !flows.is_empty() {
+        table.push_str(&format!("\nFlows:\n{}\n", flows.join("\n")));
+    }
+    table.push_str(&format!(
+        "\n{} packets stored ({} received, {} bytes, {} receive errors, {} evicted, {} rejected)\n",
+        packets.len(),
+        stats.packets_received,
+        stats.bytes_received,
+        stats.recv_errors,
+        store.evicted_packets,
+        store.rejected_packets
+    ));
+    if inline {
+        json["verdicts"] = json!({
+            "accepted": verdicts.accepted,
+            "dropped": verdicts.dropped,
+            "rewritten": verdicts.rewritten,
+            "send_errors": verdicts.send_errors,
+        });
+        table.push_str(&format!(
+            "Inline verdicts: {} accepted, {} dropped, {} rewritten, {} send errors\n",
+            verdicts.accepted, verdicts.dropped, verdicts.rewritten, verdicts.send_errors
+        ));
+    }
+    if let Some((path, count)) = exported {
+        table.push_str(&format!("Exported {} packets to {}\n", count, path));
+    }
+    Report::new(json, table)
+}
+
+fn verdicts(input: &str, output: &str, rules: Option<&str>) -> Result<Report, String> {
+    if let Some(rules) = rules {
+        activate_rules(rules)?;
+    }
+    let mut verdict = inline_verdict::firewall_verdict();
+    let stats = inline_verdict::replay_inline(Path::new(input), Path::new(output), &mut verdict)?;
+    let json = json!({
+        "input": input,
+        "output": output,
+        "accepted": stats.accepted,
+        "dropped": stats.dropped,
+        "rewritten": stats.rewritten,
+        "send_errors": stats.send_errors,
+    });
+    let row = [stats.accepted, stats.dropped, stats.rewritten, stats.send_errors].map(|n| n.to_string()).to_vec();
+    let mut table = format_table(&["Accepted", "Dropped", "Rewritten", "Send errors"], &[row]);
+    table.push_str(&format!("Packets that would be reinjected were written to {}\n", output));
+    Ok(Report::new(json, table))
+}
+
+fn interfaces() -> Result<Report, String> {
+    let interfaces = interface_inventory::enumerate()?;
+    let networks = |interface: &NetworkInterface| -> Vec<String> {
+        let v4 = interface.ipv4.iter().map(|(addr, prefix)| format!("{}/{}", addr, prefix));
+        let v6 = interface.ipv6.iter().map(|(addr, prefix)| format!("{}/{}", addr, prefix));
+        v4.chain(v6).collect()
+    };
+    let json: Vec<Value> = interfaces
+        .iter()
+        .map(|interface| {
+            json!({
+                "name": interface.name,
+                "index": interface.index,
+                "up": interface.up,
+                "mac": interface.mac.map(|mac| interface_inventory::format_mac(&mac)),
+                "mtu": interface.mtu,
+                "addresses": networks(interface),
+                "gateways": interface.gateways,
+                "dns_servers": interface.dns_servers,
+            })
+        })
+        .collect();
+    let rows: Vec<Vec<String>> = interfaces
+        .iter()
+        .map(|interface| {
+            vec![
+                interface.index.to_string(),
+                interface.name.clone(),
+                if interface.up { "up" } else { "down" }.to_string(),
+                interface.mac.map(|mac| interface_inventory::format_mac(&mac)).unwrap_or_else(|| "-".to_string()),
+                interface.mtu.map(|mtu| mtu.to_string()).unwrap_or_else(|| "-".to_string()),
+                join(&networks(interface)),
+                join(&interface.gateways),
+                join(&interface.dns_servers),
+            ]
+        })
+        .collect();
+    let table = format_table(&["Index", "Name", "State", "MAC", "MTU", "Addresses", "Gateway", "DNS"], &rows);
+    Ok(Report::new(Value::Array(json), table))
+}
+
+fn connections(sort: SortColumn, descending: bool) -> Result<Report, String> {
+    let mut rows = connections::list_connections()?;
+    connections::sort_connections(&mut rows, sort, descending);
+    let json: Vec<Value> = rows
+        .iter()
+        .map(|row| {
+            json!({
+                "protocol": row.protocol_name(),
+                "local": connections::format_endpoint(&row.local),
+                "remote": row.remote.as_ref().map(connections::format_endpoint),
+                "state": row.state.map(|state| state.to_string()),
+                "pid": row.pid,
+                "process": row.process,
+            })
+        })
+        .collect();
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| {
+            vec![
+                row.protocol_name().to_string(),
+                connections::format_endpoint(&row.local),
+                row.remote.as_ref().map(connections::format_endpoint).unwrap_or_else(|| "*".to_string()),
+                row.state.map(|state| state.to_string()).unwrap_or_else(|| "-".to_string()),
+                row.owner(),
+            ]
+        })
+        .collect();
+    let headers = SortColumn::ALL.map(|column| column.title());
+    Ok(Report::new(Value::Array(json), format_table(&headers, &cells)))
+}
+
+fn status_name(status: CheckStatus) -> String {
+    format!("{:?}", status).to_lowercase()
+}
+
+fn diagnose(checks: &[DiagnosticCheck], timeout: Duration) -> Report {
+    let results = diagnostics::run_checks(checks, timeout, |result| logging::debug_info(&format!("Diagnostics {}", result)));
+    let failed = results.iter().any(|result| result.status == CheckStatus::Failed);
+
+    let check_json = |result: &CheckResult| {
+        json!({
+            "check": result.check.to_string(),
+            "status": status_name(result.status),
+            "duration_ms": millis(result.duration),
+            "summary": result.summary,
+            "steps": result.steps.iter().map(|step| json!({
+                "label": step.label,
+                "duration_ms": step.duration.map(millis),
+                "detail": step.detail,
+            })).collect::<Vec<_>>(),
+        })
+    };
+    let json = json!({ "passed": !failed, "checks": results.iter().map(check_json).collect::<Vec<_>>() });
+
+    let rows: Vec<Vec<String>> = results
+        .iter()
+        .map(|result| vec![status_name(result.status), result.check.to_string(), diagnostics::format_ms(result.duration), result.summary.clone()])
+        .collect();
+    let mut table = format_table(&["Status", "Check", "Time", "Summary"], &rows);
+    for result in results.iter().filter(|result| !result.steps.is_empty()) {
+        let steps: Vec<Vec<String>> = result
+            .steps
+            .iter()
+            .map(|step| vec![step.label.clone(), step.duration.map(diagnostics::format_ms).unwrap_or_else(|| "*".to_string()), step.detail.clone()])
+            .collect();
+        table.push_str(&format!("\n{}:\n{}", result.check, format_table(&["Step", "Time", "Detail"], &steps)));
+    }
+
+    let mut report = Report::new(json, table);
+    if failed {
+        report.code = EXIT_CHECKS_FAILED;
+    }
+    report
+}
+
+fn speed_test(target: &str) -> Result<Report, String> {
+    let result = speed_test::run_target(target)?;
+    let json = json!({
+        "target": if target.is_empty() { "loopback" } else { target },
+        "upload_bytes_per_second": result.upload,
+        "download_bytes_per_second": result.download,
+        "rtt_min_ms": millis(result.rtt_min),
+        "rtt_avg_ms": millis(result.rtt_avg),
+        "rtt_max_ms": millis(result.rtt_max),
+        "jitter_ms": millis(result.jitter),
+        "probes_sent": result.probes_sent,
+        "probes_received": result.probes_received,
+        "loss": result.loss(),
+    });
+    Ok(Report::new(json, format!("{}\n", result)))
+}
+
+fn speed_server(duration: Option<Duration>) -> Result<Report, String> {
+    let server = SpeedTestServer::start(SocketAddr::from(([0, 0, 0, 0], speed_test::DEFAULT_PORT)))?;
+    let address = server.local_addr();
+    eprintln!("Speed test server listening on {}", address);
+    match duration {
+        Some(duration) => std::thread::sleep(duration),
+        None => {
+            let pressed = enter_pressed();
+            while !pressed.load(Ordering::SeqCst) {
+                std::thread::sleep(POLL_INTERVAL);
+            }
+        }
+    }
+    server.stop();
+    Ok(Report::new(json!({ "address": address.to_string() }), format!("Speed test server on {} stopped\n", address)))
+}
+
+fn throughput(duration: Duration) -> Result<Report, String> {
+    throughput_monitor::start()?;
+    std::thread::sleep(duration);
+    let rates = throughput_monitor::snapshot();
+    throughput_monitor::stop();
+    let json: Vec<Value> = rates
+        .iter()
+        .map(|rate| {
+            json!({
+                "name": rate.name,
+                "rx_bytes_per_second": rate.rx,
+                "tx_bytes_per_second": rate.tx,
+                "rx_bytes": rate.rx_total,
+                "tx_bytes": rate.tx_total,
+            })
+        })
+        .collect();
+    let rows: Vec<Vec<String>> = rates
+        .iter()
+        .map(|rate| vec![rate.name.clone(), format_rate(rate.rx), format_rate(rate.tx), rate.rx_total.to_string(), rate.tx_total.to_string()])
+        .collect();
+    Ok(Report::new(Value::Array(json), format_table(&["Interface", "RX", "TX", "RX bytes", "TX bytes"], &rows)))
+}
+
+fn rule_row(rule: &FirewallRule) -> Vec<String> {
+    vec![
+        rule.priority.to_string(),
+        rule.name.clone(),
+        format!("{:?}", rule.direction),
+        format!("{:?}", rule.action),
+        rule.protocol_name(),
+        join(&rule.local_addresses),
+        join(&rule.local_ports),
+        join(&rule.remote_addresses),
+        join(&rule.remote_ports),
+        rule.process.clone().unwrap_or_else(|| "-".to_string()),
+        if rule.enabled { "yes" } else { "no" }.to_string(),
+    ]
+}
+
+fn firewall_command(command: FirewallCommand) -> Result<Report, String> {
+    match command {
+        FirewallCommand::Show { rules } => {
+            let rules = load_rules(&rules)?;
+            // The rule file format doubles as the JSON output
+            let json = serde_json::from_str(&rule_store::to_json(&rules)).map_err(|e| e.to_string())?;
+            let rows: Vec<Vec<String>> = rules.rules().iter().map(rule_row).collect();
+            let mut table = format_table(
+                &["Priority", "Name", "Direction", "Action", "Protocol", "Local", "Local ports", "Remote", "Remote ports", "Process", "Enabled"],
+                &rows,
+            );
+            table.push_str(&format!("Default inbound: {:?}, outbound: {:?}\n", rules.default_inbound, rules.default_outbound));
+            Ok(Report::new(json, table))
+        }
+        FirewallCommand::Ruleset { rules, family } => {
+            let rules = load_rules(&rules)?;
+            let (format, script) = match family {
+                None => ("nftables", linux_firewall::nftables_ruleset(&rules)),
+                Some(IpFamily::V4) => ("iptables", linux_firewall::iptables_ruleset(&rules, IpFamily::V4)),
+                Some(IpFamily::V6) => ("ip6tables", linux_firewall::iptables_ruleset(&rules, IpFamily::V6)),
+            };
+            Ok(Report::new(json!({ "format": format, "script": script }), script))
+        }
+        FirewallCommand::Import { path, rules } => {
+            let text = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+            let report = rule_store::import_text(&text)?;
+            // Importing into a missing file starts a new rule set
+            let mut current = if Path::new(&rules).exists() { load_rules(&rules)? } else { RuleSet::new() };
+            let imported = report.rules.len();
+            let skipped = report.skipped.clone();
+            report.apply_to(&mut current)?;
+            rule_store::save_rules(&current, Path::new(&rules))?;
+
+            let json = json!({
+                "source": path,
+                "rules_file": rules,
+                "imported": imported,
+                "skipped": skipped.iter().map(|(name, reason)| json!({ "name": name, "reason": reason })).collect::<Vec<_>>(),
+            });
+            let mut table = format!("Imported {} rules from {} into {}\n", imported, path, rules);
+            if !skipped.is_empty() {
+                let rows: Vec<Vec<String>> = skipped.into_iter().map(|(name, reason)| vec![name, reason]).collect();
+                table.push_str(&format!("\n{}", format_table(&["Skipped", "Reason"], &rows)));
+            }
+            Ok(Report::new(json, table))
+        }
+        FirewallCommand::Apply { rules: path } => {
+            if !cfg!(target_os = "linux") {
+                return Err("firewall apply deploys to nftables on Linux; elsewhere use capture --inline --rules".to_string());
+            }
+            let rules = load_rules(&path)?;
+            linux_firewall::deploy_nftables(&rules)?;
+            let count = rules.rules().len();
+            Ok(Report::new(
+                json!({ "rules_file": path, "applied": count, "table": linux_firewall::NFT_TABLE }),
+                format!("Applied {} rules from {} to nftables table {}\n", count, path, linux_firewall::NFT_TABLE),
+            ))
+        }
+        FirewallCommand::Windows => {
+            let rules = WindowsFirewall::new().list_rules()?;
+            let json: Vec<Value> = rules
+                .iter()
+                .map(|rule| {
+                    json!({
+                        "name": rule.name,
+                        "enabled": rule.enabled,
+                        "direction": format!("{:?}", rule.direction),
+                        "action": format!("{:?}", rule.action),
+                        "protocol": format!("{:?}", rule.protocol),
+                        "local_addresses": rule.local_addresses.iter().map(ToString::to_string).collect::<Vec<_>>(),
+                        "remote_addresses": rule.remote_addresses.iter().map(ToString::to_string).collect::<Vec<_>>(),
+                        "local_ports": rule.local_ports.iter().map(ToString::to_string).collect::<Vec<_>>(),
+                        "remote_ports": rule.remote_ports.iter().map(ToString::to_string).collect::<Vec<_>>(),
+                        "program": rule.program,
+                    })
+                })
+                .collect();
+            let rows: Vec<Vec<String>> = rules
+                .iter()
+                .map(|rule| {
+                    vec![
+                        rule.name.clone(),
+                        format!("{:?}", rule.direction),
+                        format!("{:?}", rule.action),
+                        format!("{:?}", rule.protocol),
+                        join(&rule.remote_addresses),
+                        join(&rule.local_ports),
+                        rule.program.clone().unwrap_or_else(|| "-".to_string()),
+                        if rule.enabled { "yes" } else { "no" }.to_string(),
+                    ]
+                })
+                .collect();
+            let table = format_table(&["Name", "Direction", "Action", "Protocol", "Remote", "Local ports", "Program", "Enabled"], &rows);
+            Ok(Report::new(Value::Array(json), table))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Result<Invocation, String> {
+        parse_args(&line.split_whitespace().map(str::to_string).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn parses_commands_and_options() {
+        assert_eq!(parse("").unwrap().command, Command::Gui);
+        let invocation = parse("capture --duration 1.5 --interface eth0 --output out.pcap --json").unwrap();
+        assert!(invocation.json);
+        assert_eq!(
+            invocation.command,
+            Command::Capture(CaptureOptions {
+                source: CaptureSource::Live,
+                filter: String::new(),
+                duration: Some(Duration::from_millis(1500)),
+                count: None,
+                interface: Some("eth0".to_string()),
+                rules: None,
+                output: Some("out.pcap".to_string()),
+            })
+        );
+        let args: Vec<String> = ["capture", "--inline", "--rules", "r.json", "--filter", "tcp port 80"].map(String::from).to_vec();
+        let Command::Capture(options) = parse_args(&args).unwrap().command else { panic!("not a capture") };
+        assert_eq!((options.source, options.filter.as_str(), options.rules.as_deref()), (CaptureSource::Inline, "tcp port 80", Some("r.json")));
+
+        assert_eq!(
+            parse("connections --sort process --desc").unwrap().command,
+            Command::Connections { sort: SortColumn::Process, descending: true }
+        );
+        assert_eq!(
+            parse("firewall ruleset --iptables --ipv6").unwrap().command,
+            Command::Firewall(FirewallCommand::Ruleset { rules: rule_store::DEFAULT_RULES_FILE.to_string(), family: Some(IpFamily::V6) })
+        );
+        assert_eq!(parse("speedtest --serve").unwrap().command, Command::SpeedServer { duration: None });
+        let Command::Diagnose { checks, timeout } = parse("diagnose --timeout 3").unwrap().command else { panic!("not diagnose") };
+        assert_eq!((checks.len(), timeout), (4, Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        for line in [
+            "bogus",
+            "interfaces extra",
+            "capture --duration soon",
+            "capture --duration -1",
+            "capture --duration 1e30",
+            "capture --duration inf",
+            "capture --duration NaN",
+            "capture --filter",
+            "capture --rules r.json",
+            "capture --inline --replay a.pcap",
+            "connections --sort size",
+            "firewall",
+            "firewall import",
+            "firewall show --ipv6",
+            "speedtest host --serve",
+            "diagnose ping",
+        ] {
+            assert!(parse(line).is_err(), "{} should not parse", line);
+        }
+        let args: Vec<String> = ["capture", "--filter", "tcp and ("].map(String::from).to_vec();
+        assert!(parse_args(&args).unwrap_err().starts_with("Invalid filter"));
+    }
+
+    #[test]
+    fn formats_aligned_tables() {
+        let rows = vec![vec!["eth0".to_string(), "up".to_string()], vec!["wlan0-long".to_string(), "down".to_string()]];
+        assert_eq!(format_table(&["Name", "State"], &rows), "Name        State\neth0        up\nwlan0-long  down\n");
+    }
+}
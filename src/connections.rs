@@ -0,0 +1,491 @@
+use crate::logging;
+use crate::flow_table::Endpoint;
+use crate::packet_decoder::{IP_PROTO_TCP, IP_PROTO_UDP};
+use crate::process_attribution::parse_proc_endpoint;
+use once_cell::sync::Lazy;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub fn init_module() -> Result<(), String> {
+    // Placeholder for actual initialization logic
+    let initialization_passed = true;
+
+    if initialization_passed {
+        logging::debug_info("connections module is online");
+        Ok(())
+    } else {
+        Err("connections module initialization failed".to_string())
+    }
+}
+
+// How old the table shown in the NS menu may get before it is re-read
+pub const REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+// Last listing and when it was taken; refreshed off the GUI thread since
+// finding owners walks every process's fds on Linux
+static CONNECTIONS: Lazy<Mutex<(Vec<Connection>, Option<Instant>)>> = Lazy::new(|| Mutex::new((Vec::new(), None)));
+static REFRESHING: AtomicBool = AtomicBool::new(false);
+static SORT: Lazy<Mutex<(SortColumn, bool)>> = Lazy::new(|| Mutex::new((SortColumn::Protocol, false)));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SocketState {
+    Listen,
+    SynSent,
+    SynReceived,
+    Established,
+    FinWait1,
+    FinWait2,
+    CloseWait,
+    Closing,
+    LastAck,
+    TimeWait,
+    Closed,
+    DeleteTcb,
+}
+
+impl fmt::Display for SocketState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            SocketState::Listen => "LISTEN",
+            SocketState::SynSent => "SYN_SENT",
+            SocketState::SynReceived => "SYN_RECV",
+            SocketState::Established => "ESTABLISHED",
+            SocketState::FinWait1 => "FIN_WAIT1",
+            SocketState::FinWait2 => "FIN_WAIT2",
+            SocketState::CloseWait => "CLOSE_WAIT",
+            SocketState::Closing => "CLOSING",
+            SocketState::LastAck => "LAST_ACK",
+            SocketState::TimeWait => "TIME_WAIT",
+            SocketState::Closed => "CLOSE",
+            SocketState::DeleteTcb => "DELETE_TCB",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl SocketState {
+    // The "st" column of /proc/net/tcp (include/net/tcp_states.h)
+    pub fn from_linux(code: u8) -> Option<Self> {
+        Some(match code {
+            0x01 => SocketState::Established,
+            0x02 => SocketState::SynSent,
+            0x03 => SocketState::SynReceived,
+            0x04 => SocketState::FinWait1,
+            0x05 => SocketState::FinWait2,
+            0x06 => SocketState::TimeWait,
+            0x07 => SocketState::Closed,
+            0x08 => SocketState::CloseWait,
+            0x09 => SocketState::LastAck,
+            0x0a => SocketState::Listen,
+            0x0b => SocketState::Closing,
+            _ => return None,
+        })
+    }
+
+    // MIB_TCP_STATE as returned by GetExtendedTcpTable
+    pub fn from_windows(code: u32) -> Option<Self> {
+        Some(match code {
+            1 => SocketState::Closed,
+            2 => SocketState::Listen,
+            3 => SocketState::SynSent,
+            4 => SocketState::SynReceived,
+            5 => SocketState::Established,
+            6 => SocketState::FinWait1,
+            7 => SocketState::FinWait2,
+            8 => SocketState::CloseWait,
+            9 => SocketState::Closing,
+            10 => SocketState::LastAck,
+            11 => SocketState::TimeWait,
+            12 => SocketState::DeleteTcb,
+            _ => return None,
+        })
+    }
+}
+
+// One socket row. UDP sockets have no state, and unconnected ones no remote.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Connection {
+    pub protocol: u8,
+    pub local: Endpoint,
+    pub remote: Option<Endpoint>,
+    pub state: Option<SocketState>,
+    pub pid: Option<u32>,
+    pub process: Option<String>,
+}
+
+pub fn format_endpoint(endpoint: &Endpoint) -> String {
+    match endpoint.0 {
+        IpAddr::V6(addr) => format!("[{}]:{}", addr, endpoint.1),
+        IpAddr::V4(addr) => format!("{}:{}", addr, endpoint.1),
+    }
+}
+
+impl Connection {
+    pub fn protocol_name(&self) -> &'static str {
+        match (self.protocol, self.local.0.is_ipv6()) {
+            (IP_PROTO_TCP, false) => "tcp",
+            (IP_PROTO_TCP, true) => "tcp6",
+            (_, false) => "udp",
+            (_, true) => "udp6",
+        }
+    }
+
+    pub fn owner(&self) -> String {
+        match (&self.process, self.pid) {
+            (Some(name), Some(pid)) => format!("{} ({})", name, pid),
+            (None, Some(pid)) => pid.to_string(),
+            _ => "-".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Connection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} {}",
+            self.protocol_name(),
+            format_endpoint(&self.local),
+            self.remote.as_ref().map(format_endpoint).unwrap_or_else(|| "*".to_string()),
+            self.state.map(|state| state.to_string()).unwrap_or_else(|| "-".to_string()),
+            self.owner()
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortColumn {
+    Protocol,
+    Local,
+    Remote,
+    State,
+    Process,
+}
+
+impl SortColumn {
+    pub const ALL: [SortColumn; 5] = [SortColumn::Protocol, SortColumn::Local, SortColumn::Remote, SortColumn::State, SortColumn::Process];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            SortColumn::Protocol => "Proto",
+            SortColumn::Local => "Local Address",
+            SortColumn::Remote => "Remote Address",
+            SortColumn::State => "State",
+            SortColumn::Process => "Process",
+        }
+    }
+}
+
+// Stable sort on one column, ties kept in protocol/local order
+pub fn sort_connections(connections: &mut [Connection], column: SortColumn, descending: bool) {
+    connections.sort_by(|a, b| {
+        let order = match column {
+            SortColumn::Protocol => a.protocol_name().cmp(b.protocol_name()),
+            SortColumn::Local => a.local.cmp(&b.local),
+            SortColumn::Remote => a.remote.cmp(&b.remote),
+            SortColumn::State => a.state.cmp(&b.state),
+            SortColumn::Process => {
+                let name = |c: &Connection| c.process.as_ref().map(|name| name.to_lowercase());
+                name(a).cmp(&name(b)).then(a.pid.cmp(&b.pid))
+            }
+        }
+        .then_with(|| a.protocol_name().cmp(b.protocol_name()))
+        .then_with(|| a.local.cmp(&b.local));
+        if descending {
+            order.reverse()
+        } else {
+            order
+        }
+    });
+}
+
+// Reads /proc/net/{tcp,tcp6,udp,udp6} into connections and their socket
+// inodes. Unlike process_attribution this keeps TIME_WAIT sockets (inode 0).
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) fn parse_proc_connections(text: &str, protocol: u8) -> Vec<(Connection, u64)> {
+    text.lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let local = parse_proc_endpoint(fields.get(1)?)?;
+            let remote = parse_proc_endpoint(fields.get(2)?)?;
+            let state = u8::from_str_radix(fields.get(3)?, 16).ok()?;
+            let inode: u64 = fields.get(9)?.parse().ok()?;
+            let connection = Connection {
+                protocol,
+                local,
+                remote: (remote.1 != 0).then_some(remote),
+                state: if protocol == IP_PROTO_TCP { SocketState::from_linux(state) } else { None },
+                pid: None,
+                process: None,
+            };
+            Some((connection, inode))
+        })
+        .collect()
+}
+
+// Rows of a GetExtendedTcpTable/GetExtendedUdpTable buffer with the
+// *_OWNER_PID classes: a u32 count, then fixed size rows. Ports sit in the
+// low 16 bits of a u32 in network order; IPv4 addresses are in network order.
+#[cfg_attr(not(windows), allow(dead_code))]
+pub(crate) fn parse_windows_table(data: &[u8], protocol: u8, ipv6: bool) -> Vec<Connection> {
+    let u32_at = |row: &[u8], offset: usize| u32::from_le_bytes(row[offset..offset + 4].try_into().unwrap());
+    let port_at = |row: &[u8], offset: usize| u16::from_be_bytes([row[offset], row[offset + 1]]);
+    let v4_at = |row: &[u8], offset: usize| IpAddr::V4(Ipv4Addr::new(row[offset], row[offset + 1], row[offset + 2], row[offset + 3]));
+    let v6_at = |row: &[u8], offset: usize| {
+        let octets: [u8; 16] = row[offset..offset + 16].try_into().unwrap();
+        IpAddr::V6(Ipv6Addr::from(octets))
+    };
+    let row_size = match (protocol, ipv6) {
+        (IP_PROTO_TCP, false) => 24,
+        (IP_PROTO_TCP, true) => 56,
+        (_, false) => 12,
+        (_, true) => 28,
+    };
+    let count = match data.get(..4) {
+        Some(count) => u32::from_le_bytes(count.try_into().unwrap()) as usize,
+        None => return Vec::new(),
+    };
+    data[4..]
+        .chunks_exact(row_size)
+        .take(count)
+        .map(|row| {
+            // MIB_TCPROW_OWNER_PID, MIB_TCP6ROW_OWNER_PID, MIB_UDPROW_OWNER_PID, MIB_UDP6ROW_OWNER_PID
+            let (local, remote, state, pid) = match (protocol, ipv6) {
+                (IP_PROTO_TCP, false) => (
+                    (v4_at(row, 4), port_at(row, 8)),
+                    Some((v4_at(row, 12), port_at(row, 16))),
+                    Some(u32_at(row, 0)),
+                    u32_at(row, 20),
+                ),
+                (IP_PROTO_TCP, true) => (
+                    (v6_at(row, 0), port_at(row, 20)),
+                    Some((v6_at(row, 24), port_at(row, 44))),
+                    Some(u32_at(row, 48)),
+                    u32_at(row, 52),
+                ),
+                (_, false) => ((v4_at(row, 0), port_at(row, 4)), None, None, u32_at(row, 8)),
+                (_, true) => ((v6_at(row, 0), port_at(row, 20)), None, None, u32_at(row, 24)),
+            };
+            let state = state.and_then(SocketState::from_windows);
+            Connection {
+                protocol,
+                local,
+                // Listeners report a zero remote endpoint
+                remote: remote.filter(|_| state != Some(SocketState::Listen)),
+                state,
+                pid: Some(pid),
+                process: None,
+            }
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+pub fn list_connections() -> Result<Vec<Connection>, String> {
+    let mut sockets = Vec::new();
+    for (file, protocol) in [("tcp", IP_PROTO_TCP), ("tcp6", IP_PROTO_TCP), ("udp", IP_PROTO_UDP), ("udp6", IP_PROTO_UDP)] {
+        // IPv6 may be disabled, but the IPv4 tables are always there
+        match std::fs::read_to_string(format!("/proc/net/{}", file)) {
+            Ok(text) => sockets.extend(parse_proc_connections(&text, protocol)),
+            Err(e) if !file.ends_with('6') => return Err(format!("Failed to read /proc/net/{}: {}", file, e)),
+            Err(_) => {}
+        }
+    }
+    let owners = crate::process_attribution::socket_owners();
+    Ok(sockets
+        .into_iter()
+        .map(|(mut connection, inode)| {
+            if let Some(owner) = owners.get(&inode) {
+                connection.pid = Some(owner.pid);
+                connection.process = Some(owner.name.clone());
+            }
+            connection
+        })
+        .collect())
+}
+
+#[cfg(windows)]
+mod win32 {
+    use std::ffi::c_void;
+
+    pub const AF_INET: u32 = 2;
+    pub const AF_INET6: u32 = 23;
+    pub const TCP_TABLE_OWNER_PID_ALL: i32 = 5;
+    pub const UDP_TABLE_OWNER_PID: i32 = 1;
+    pub const ERROR_INSUFFICIENT_BUFFER: u32 = 122;
+
+    #[link(name = "iphlpapi")]
+    extern "system" {
+        pub fn GetExtendedTcpTable(table: *mut c_void, size: *mut u32, order: i32, af: u32, class: i32, reserved: u32) -> u32;
+        pub fn GetExtendedUdpTable(table: *mut c_void, size: *mut u32, order: i32, af: u32, class: i32, reserved: u32) -> u32;
+    }
+}
+
+#[cfg(windows)]
+pub fn list_connections() -> Result<Vec<Connection>, String> {
+    use std::collections::HashMap;
+    use win32::*;
+
+    let mut connections = Vec::new();
+    for (protocol, ipv6) in [(IP_PROTO_TCP, false), (IP_PROTO_TCP, true), (IP_PROTO_UDP, false), (IP_PROTO_UDP, true)] {
+        let af = if ipv6 { AF_INET6 } else { AF_INET };
+        let mut size: u32 = 0;
+        let mut buffer: Vec<u8> = Vec::new();
+        // The table can grow between the size query and the read
+        let status = loop {
+            buffer.resize(size as usize, 0);
+            let table = buffer.as_mut_ptr() as *mut std::ffi::c_void;
+            let status = unsafe {
+                match protocol {
+                    IP_PROTO_TCP => GetExtendedTcpTable(table, &mut size, 1, af, TCP_TABLE_OWNER_PID_ALL, 0),
+                    _ => GetExtendedUdpTable(table, &mut size, 1, af, UDP_TABLE_OWNER_PID, 0),
+                }
+            };
+            if status != ERROR_INSUFFICIENT_BUFFER {
+                break status;
+            }
+        };
+        if status != 0 {
+            return Err(format!("Reading the {} socket table failed with status {}", if protocol == IP_PROTO_TCP { "TCP" } else { "UDP" }, status));
+        }
+        connections.extend(parse_windows_table(&buffer, protocol, ipv6));
+    }
+
+    let mut names: HashMap<u32, String> = HashMap::new();
+    for connection in &mut connections {
+        if let Some(pid) = connection.pid.filter(|pid| *pid != 0) {
+            let name = names.entry(pid).or_insert_with(|| crate::process_attribution::windows_process_info(pid).name);
+            connection.process = Some(name.clone());
+        }
+    }
+    Ok(connections)
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+pub fn list_connections() -> Result<Vec<Connection>, String> {
+    Err("Listing connections is not supported on this platform".to_string())
+}
+
+fn refresh_in_background() {
+    if REFRESHING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    std::thread::spawn(|| {
+        let connections = list_connections().unwrap_or_else(|e| {
+            logging::debug_error(&format!("Failed to list connections: {}", e));
+            Vec::new()
+        });
+        *CONNECTIONS.lock().unwrap() = (connections, Some(Instant::now()));
+        REFRESHING.store(false, Ordering::SeqCst);
+    });
+}
+
+// Sorted connections for the table view, kicking off a refresh once the
+// listing is older than REFRESH_INTERVAL
+pub fn snapshot() -> Vec<Connection> {
+    let (mut connections, taken) = CONNECTIONS.lock().unwrap().clone();
+    if taken.is_none_or(|at| at.elapsed() >= REFRESH_INTERVAL) {
+        refresh_in_background();
+    }
+    let (column, descending) = sort_order();
+    sort_connections(&mut connections, column, descending);
+    connections
+}
+
+pub fn sort_order() -> (SortColumn, bool) {
+    *SORT.lock().unwrap()
+}
+
+// Sorting on the current column again flips the direction
+pub fn sort_by(column: SortColumn) {
+    let mut sort = SORT.lock().unwrap();
+    *sort = (column, sort.0 == column && !sort.1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROC_NET_TCP: &str = include_str!("../testdata/proc/net_tcp");
+    const PROC_NET_UDP: &str = include_str!("../testdata/proc/net_udp");
+
+    fn endpoint(addr: &str, port: u16) -> Endpoint {
+        (addr.parse().unwrap(), port)
+    }
+
+    #[test]
+    fn parses_proc_tcp_and_udp() {
+        let tcp: Vec<Connection> = parse_proc_connections(PROC_NET_TCP, IP_PROTO_TCP).into_iter().map(|(c, _)| c).collect();
+        let lines: Vec<String> = tcp.iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            lines,
+            [
+                "tcp 127.0.0.53:53 * LISTEN -",
+                "tcp 0.0.0.0:22 * LISTEN -",
+                "tcp 192.168.1.20:22 192.168.1.5:51234 ESTABLISHED -",
+                "tcp 192.168.1.20:42226 192.168.1.5:80 TIME_WAIT -",
+            ]
+        );
+
+        let udp = parse_proc_connections(PROC_NET_UDP, IP_PROTO_UDP);
+        assert_eq!(udp.len(), 3);
+        assert_eq!(udp[1].0.local, endpoint("0.0.0.0", 68));
+        assert_eq!(udp[2].0.remote, Some(endpoint("8.8.8.8", 53)));
+        assert!(udp.iter().all(|(c, _)| c.state.is_none()));
+        assert_eq!(udp[2].1, 61001);
+    }
+
+    #[test]
+    fn parses_windows_owner_pid_tables() {
+        let mut table = 2u32.to_le_bytes().to_vec();
+        // Listener on 0.0.0.0:135 and an established connection
+        for (state, local, lport, remote, rport, pid) in
+            [(2u32, [0, 0, 0, 0], 135u16, [0, 0, 0, 0], 0u16, 1200u32), (5, [10, 0, 0, 2], 50000, [93, 184, 216, 34], 443, 4321)]
+        {
+            table.extend_from_slice(&state.to_le_bytes());
+            table.extend_from_slice(&local);
+            table.extend_from_slice(&(lport.to_be() as u32).to_le_bytes());
+            table.extend_from_slice(&remote);
+            table.extend_from_slice(&(rport.to_be() as u32).to_le_bytes());
+            table.extend_from_slice(&pid.to_le_bytes());
+        }
+        let lines: Vec<String> = parse_windows_table(&table, IP_PROTO_TCP, false).iter().map(|c| c.to_string()).collect();
+        assert_eq!(lines, ["tcp 0.0.0.0:135 * LISTEN 1200", "tcp 10.0.0.2:50000 93.184.216.34:443 ESTABLISHED 4321"]);
+
+        let mut udp6 = 1u32.to_le_bytes().to_vec();
+        udp6.extend_from_slice(&Ipv6Addr::LOCALHOST.octets());
+        udp6.extend_from_slice(&0u32.to_le_bytes());
+        udp6.extend_from_slice(&(5353u16.to_be() as u32).to_le_bytes());
+        udp6.extend_from_slice(&77u32.to_le_bytes());
+        let lines: Vec<String> = parse_windows_table(&udp6, IP_PROTO_UDP, true).iter().map(|c| c.to_string()).collect();
+        assert_eq!(lines, ["udp6 [::1]:5353 * - 77"]);
+    }
+
+    #[test]
+    fn sorts_by_column() {
+        let mut connections: Vec<Connection> = parse_proc_connections(PROC_NET_TCP, IP_PROTO_TCP)
+            .into_iter()
+            .chain(parse_proc_connections(PROC_NET_UDP, IP_PROTO_UDP))
+            .map(|(c, _)| c)
+            .collect();
+        connections[0].process = Some("systemd-resolve".to_string());
+        connections[1].process = Some("sshd".to_string());
+
+        sort_connections(&mut connections, SortColumn::State, false);
+        // Stateless UDP sockets first, then TCP from LISTEN onwards
+        assert_eq!(connections[0].state, None);
+        assert_eq!(connections[3].state, Some(SocketState::Listen));
+        assert_eq!(connections.last().unwrap().state, Some(SocketState::TimeWait));
+
+        sort_connections(&mut connections, SortColumn::Process, true);
+        assert_eq!(connections[0].process.as_deref(), Some("systemd-resolve"));
+        assert_eq!(connections[1].process.as_deref(), Some("sshd"));
+
+        sort_connections(&mut connections, SortColumn::Remote, false);
+        assert!(connections.iter().take(4).all(|c| c.remote.is_none()));
+    }
+}
@@ -0,0 +1,725 @@
+use crate::logging;
+use once_cell::sync::Lazy;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub fn init_module() -> Result<(), String> {
+    // Placeholder for actual initialization logic
+    let initialization_passed = true;
+
+    if initialization_passed {
+        logging::debug_info("diagnostics module is online");
+        Ok(())
+    } else {
+        Err("diagnostics module initialization failed".to_string())
+    }
+}
+
+// Checks the DS menu's "Check connection" starts with
+pub const DEFAULT_CHECKS: &str = "dns example.com; tcp example.com:443; ping 1.1.1.1; traceroute 1.1.1.1";
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(2);
+const DEFAULT_PING_COUNT: u16 = 4;
+const DEFAULT_MAX_HOPS: u8 = 30;
+// First UDP port traceroute aims at, as in the classic tool
+const TRACEROUTE_PORT: u16 = 33434;
+
+// Results of the run started from the DS menu, appended as each check ends
+static RESULTS: Lazy<Mutex<Vec<CheckResult>>> = Lazy::new(|| Mutex::new(Vec::new()));
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiagnosticCheck {
+    Dns { host: String },
+    TcpConnect { host: String, port: u16 },
+    Ping { host: String, count: u16 },
+    Traceroute { host: String, max_hops: u8 },
+}
+
+impl fmt::Display for DiagnosticCheck {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiagnosticCheck::Dns { host } => write!(f, "dns {}", host),
+            DiagnosticCheck::TcpConnect { host, port } if host.contains(':') => write!(f, "tcp [{}]:{}", host, port),
+            DiagnosticCheck::TcpConnect { host, port } => write!(f, "tcp {}:{}", host, port),
+            DiagnosticCheck::Ping { host, count } => write!(f, "ping {} {}", host, count),
+            DiagnosticCheck::Traceroute { host, max_hops } => write!(f, "traceroute {} {}", host, max_hops),
+        }
+    }
+}
+
+// Parses checks separated by ';' or newlines, e.g.
+// "dns example.com; tcp example.com:443; ping 10.0.0.1 3; traceroute 10.0.0.1 15"
+pub fn parse_checks(spec: &str) -> Result<Vec<DiagnosticCheck>, String> {
+    let mut checks = Vec::new();
+    for item in spec.split([';', '\n']).map(str::trim).filter(|item| !item.is_empty()) {
+        let words: Vec<&str> = item.split_whitespace().collect();
+        let number = |index: usize, default| match words.get(index) {
+            Some(word) => word.parse().map_err(|_| format!("Bad number \"{}\" in \"{}\"", word, item)),
+            None => Ok(default),
+        };
+        let host = words.get(1).map(|host| host.to_string()).ok_or(format!("\"{}\" needs a host", item))?;
+        if words.len() > 3 {
+            return Err(format!("Too many arguments in \"{}\"", item));
+        }
+        checks.push(match words[0] {
+            "dns" => DiagnosticCheck::Dns { host },
+            "tcp" => {
+                let (host, port) = host.rsplit_once(':').ok_or(format!("\"{}\" needs host:port", item))?;
+                let port = port.parse().map_err(|_| format!("Bad port \"{}\" in \"{}\"", port, item))?;
+                DiagnosticCheck::TcpConnect { host: host.trim_matches(|c| c == '[' || c == ']').to_string(), port }
+            }
+            "ping" => DiagnosticCheck::Ping { host, count: number(2, DEFAULT_PING_COUNT as u64)?.clamp(1, 100) as u16 },
+            "traceroute" => DiagnosticCheck::Traceroute { host, max_hops: number(2, DEFAULT_MAX_HOPS as u64)?.clamp(1, 64) as u8 },
+            other => return Err(format!("Unknown check \"{}\"", other)),
+        });
+    }
+    Ok(checks)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Passed,
+    Failed,
+    // The check cannot run here, e.g. ICMP sockets are not permitted
+    Skipped,
+}
+
+// One line of a check's progress, such as a single ping or traceroute hop
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckStep {
+    pub label: String,
+    pub duration: Option<Duration>,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckResult {
+    pub check: DiagnosticCheck,
+    pub status: CheckStatus,
+    pub duration: Duration,
+    pub summary: String,
+    pub steps: Vec<CheckStep>,
+}
+
+impl fmt::Display for CheckResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{:?}] {}: {} ({})", self.status, self.check, self.summary, format_ms(self.duration))
+    }
+}
+
+pub fn format_ms(duration: Duration) -> String {
+    format!("{:.2} ms", duration.as_secs_f64() * 1000.0)
+}
+
+fn step(label: impl Into<String>, duration: Option<Duration>, detail: impl Into<String>) -> CheckStep {
+    CheckStep { label: label.into(), duration, detail: detail.into() }
+}
+
+// What came back for one ICMP echo or traceroute probe
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HopReply {
+    // The destination answered (echo reply, or port unreachable for UDP)
+    Reached(IpAddr),
+    // A router on the way reported the TTL ran out
+    Router(IpAddr),
+    Unreachable(IpAddr),
+    Timeout,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProbeError {
+    // Raw or ICMP sockets are not available to this user or platform
+    NotPermitted(String),
+    Failed(String),
+}
+
+fn resolve(host: &str, steps: &mut Vec<CheckStep>) -> Result<Vec<IpAddr>, String> {
+    let start = Instant::now();
+    let addrs: Vec<IpAddr> = (host, 0)
+        .to_socket_addrs()
+        .map_err(|e| format!("Cannot resolve {}: {}", host, e))?
+        .map(|addr| addr.ip())
+        .collect();
+    let list: Vec<String> = addrs.iter().map(|addr| addr.to_string()).collect();
+    steps.push(step(format!("resolve {}", host), Some(start.elapsed()), list.join(", ")));
+    if addrs.is_empty() {
+        return Err(format!("{} has no addresses", host));
+    }
+    Ok(addrs)
+}
+
+fn check_dns(host: &str, steps: &mut Vec<CheckStep>) -> Result<String, String> {
+    let addrs = resolve(host, steps)?;
+    Ok(format!("{} address{}", addrs.len(), if addrs.len() == 1 { "" } else { "es" }))
+}
+
+// Tries each resolved address in turn until one accepts
+fn check_tcp(host: &str, port: u16, timeout: Duration, steps: &mut Vec<CheckStep>) -> Result<String, String> {
+    let mut last_error = String::new();
+    for addr in resolve(host, steps)? {
+        let target = SocketAddr::new(addr, port);
+        let start = Instant::now();
+        match TcpStream::connect_timeout(&target, timeout) {
+            Ok(_) => {
+                let elapsed = start.elapsed();
+                steps.push(step(format!("connect {}", target), Some(elapsed), "connected"));
+                return Ok(format!("connected to {} in {}", target, format_ms(elapsed)));
+            }
+            Err(e) => {
+                steps.push(step(format!("connect {}", target), Some(start.elapsed()), e.to_string()));
+                last_error = format!("{}: {}", target, e);
+            }
+        }
+    }
+    Err(last_error)
+}
+
+fn check_ping(host: &str, count: u16, timeout: Duration, steps: &mut Vec<CheckStep>) -> Result<String, ProbeError> {
+    let addr = resolve(host, steps).map_err(ProbeError::Failed)?[0];
+    let mut rtts = Vec::new();
+    for seq in 1..=count {
+        let (reply, rtt) = ping_probe(addr, seq, timeout)?;
+        let label = format!("echo {}", seq);
+        match reply {
+            HopReply::Reached(from) => {
+                rtts.push(rtt);
+                steps.push(step(label, Some(rtt), format!("reply from {}", from)));
+            }
+            HopReply::Unreachable(from) | HopReply::Router(from) => {
+                steps.push(step(label, Some(rtt), format!("unreachable, reported by {}", from)))
+            }
+            HopReply::Timeout => steps.push(step(label, None, "timed out")),
+        }
+    }
+    if rtts.is_empty() {
+        return Err(ProbeError::Failed(format!("no replies from {}", addr)));
+    }
+    let avg = rtts.iter().sum::<Duration>() / rtts.len() as u32;
+    Ok(format!("{}/{} replies from {}, avg {}", rtts.len(), count, addr, format_ms(avg)))
+}
+
+fn check_traceroute(host: &str, max_hops: u8, timeout: Duration, steps: &mut Vec<CheckStep>) -> Result<String, ProbeError> {
+    let addr = resolve(host, steps).map_err(ProbeError::Failed)?[0];
+    for ttl in 1..=max_hops {
+        let (reply, rtt) = trace_probe(addr, ttl, timeout)?;
+        let label = format!("hop {}", ttl);
+        match reply {
+            HopReply::Reached(from) => {
+                steps.push(step(label, Some(rtt), from.to_string()));
+                return Ok(format!("reached {} in {} hop{}", from, ttl, if ttl == 1 { "" } else { "s" }));
+            }
+            HopReply::Router(from) => steps.push(step(label, Some(rtt), from.to_string())),
+            HopReply::Unreachable(from) => {
+                steps.push(step(label, Some(rtt), format!("{} (unreachable)", from)));
+                return Err(ProbeError::Failed(format!("{} reported {} unreachable", from, addr)));
+            }
+            HopReply::Timeout => steps.push(step(label, None, "*")),
+        }
+    }
+    Err(ProbeError::Failed(format!("{} not reached within {} hops", addr, max_hops)))
+}
+
+pub fn run_check(check: &DiagnosticCheck, timeout: Duration) -> CheckResult {
+    let start = Instant::now();
+    let mut steps = Vec::new();
+    let outcome = match check {
+        DiagnosticCheck::Dns { host } => check_dns(host, &mut steps).map_err(ProbeError::Failed),
+        DiagnosticCheck::TcpConnect { host, port } => check_tcp(host, *port, timeout, &mut steps).map_err(ProbeError::Failed),
+        DiagnosticCheck::Ping { host, count } => check_ping(host, *count, timeout, &mut steps),
+        DiagnosticCheck::Traceroute { host, max_hops } => check_traceroute(host, *max_hops, timeout, &mut steps),
+    };
+    let (status, summary) = match outcome {
+        Ok(summary) => (CheckStatus::Passed, summary),
+        Err(ProbeError::Failed(e)) => (CheckStatus::Failed, e),
+        Err(ProbeError::NotPermitted(e)) => (CheckStatus::Skipped, e),
+    };
+    CheckResult { check: check.clone(), status, duration: start.elapsed(), summary, steps }
+}
+
+// Runs the checks in order, handing each result over as soon as it is ready
+pub fn run_checks(checks: &[DiagnosticCheck], timeout: Duration, mut on_result: impl FnMut(&CheckResult)) -> Vec<CheckResult> {
+    checks
+        .iter()
+        .map(|check| {
+            let result = run_check(check, timeout);
+            on_result(&result);
+            result
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+mod probe {
+    use super::{HopReply, ProbeError, TRACEROUTE_PORT};
+    use crate::checksum::internet_checksum;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+    use std::time::{Duration, Instant};
+
+    const ICMP_ECHO_REPLY: u8 = 0;
+    const ICMP_DEST_UNREACH: u8 = 3;
+    const ICMP_ECHO_REQUEST: u8 = 8;
+    const ICMP_TIME_EXCEEDED: u8 = 11;
+    const ICMP_PORT_UNREACH: u8 = 3;
+    const ICMPV6_DEST_UNREACH: u8 = 1;
+    const ICMPV6_TIME_EXCEEDED: u8 = 3;
+    const ICMPV6_ECHO_REQUEST: u8 = 128;
+    const ICMPV6_ECHO_REPLY: u8 = 129;
+    const ICMPV6_PORT_UNREACH: u8 = 4;
+
+    fn last_error() -> std::io::Error {
+        std::io::Error::last_os_error()
+    }
+
+    // Closes the descriptor however the probe ends
+    struct Socket(libc::c_int);
+
+    impl Drop for Socket {
+        fn drop(&mut self) {
+            unsafe { libc::close(self.0) };
+        }
+    }
+
+    fn sockaddr(addr: SocketAddr) -> (libc::sockaddr_storage, libc::socklen_t) {
+        let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+        let len = match addr {
+            SocketAddr::V4(v4) => {
+                let sin = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in) };
+                sin.sin_family = libc::AF_INET as libc::sa_family_t;
+                sin.sin_port = v4.port().to_be();
+                sin.sin_addr.s_addr = u32::from_ne_bytes(v4.ip().octets());
+                std::mem::size_of::<libc::sockaddr_in>()
+            }
+            SocketAddr::V6(v6) => {
+                let sin6 = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in6) };
+                sin6.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+                sin6.sin6_port = v6.port().to_be();
+                sin6.sin6_addr.s6_addr = v6.ip().octets();
+                std::mem::size_of::<libc::sockaddr_in6>()
+            }
+        };
+        (storage, len as libc::socklen_t)
+    }
+
+    fn sockaddr_ip(storage: *const libc::sockaddr) -> Option<IpAddr> {
+        unsafe {
+            match (*storage).sa_family as libc::c_int {
+                libc::AF_INET => {
+                    let sin = &*(storage as *const libc::sockaddr_in);
+                    Some(IpAddr::V4(Ipv4Addr::from(sin.sin_addr.s_addr.to_ne_bytes())))
+                }
+                libc::AF_INET6 => Some(IpAddr::V6(Ipv6Addr::from((*(storage as *const libc::sockaddr_in6)).sin6_addr.s6_addr))),
+                _ => None,
+            }
+        }
+    }
+
+    fn set_option(fd: libc::c_int, level: libc::c_int, name: libc::c_int, value: libc::c_int) -> Result<(), ProbeError> {
+        let rc = unsafe {
+            libc::setsockopt(
+                fd,
+                level,
+                name,
+                &value as *const libc::c_int as *const libc::c_void,
+                std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        };
+        if rc != 0 {
+            return Err(ProbeError::Failed(format!("setsockopt failed: {}", last_error())));
+        }
+        Ok(())
+    }
+
+    // Waits for `events` until the deadline; false on timeout
+    fn wait(fd: libc::c_int, events: libc::c_short, deadline: Instant) -> Result<bool, ProbeError> {
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let mut pfd = libc::pollfd { fd, events, revents: 0 };
+            let rc = unsafe { libc::poll(&mut pfd, 1, remaining.as_millis() as libc::c_int) };
+            match rc {
+                0 => return Ok(false),
+                n if n > 0 => return Ok(true),
+                _ if last_error().kind() == std::io::ErrorKind::Interrupted => continue,
+                _ => return Err(ProbeError::Failed(format!("poll failed: {}", last_error()))),
+            }
+        }
+    }
+
+    // Unprivileged ICMP "ping" sockets first (net.ipv4.ping_group_range),
+    // then raw sockets for root or CAP_NET_RAW
+    fn open_icmp(v6: bool) -> Result<(Socket, bool), ProbeError> {
+        let (family, protocol) = if v6 { (libc::AF_INET6, libc::IPPROTO_ICMPV6) } else { (libc::AF_INET, libc::IPPROTO_ICMP) };
+        let fd = unsafe { libc::socket(family, libc::SOCK_DGRAM, protocol) };
+        if fd >= 0 {
+            return Ok((Socket(fd), false));
+        }
+        let fd = unsafe { libc::socket(family, libc::SOCK_RAW, protocol) };
+        if fd >= 0 {
+            return Ok((Socket(fd), true));
+        }
+        Err(ProbeError::NotPermitted(format!("ICMP sockets not permitted: {}", last_error())))
+    }
+
+    pub fn ping_probe(addr: IpAddr, seq: u16, timeout: Duration) -> Result<(HopReply, Duration), ProbeError> {
+        let v6 = addr.is_ipv6();
+        let (socket, raw) = open_icmp(v6)?;
+        // Ping sockets replace the identifier with their own, so only raw ones check it
+        let id = std::process::id() as u16;
+        let mut request = vec![if v6 { ICMPV6_ECHO_REQUEST } else { ICMP_ECHO_REQUEST }, 0, 0, 0];
+        request.extend_from_slice(&id.to_be_bytes());
+        request.extend_from_slice(&seq.to_be_bytes());
+        request.extend_from_slice(b"s2o_net_lib diagnostics ping");
+        if !v6 {
+            // The kernel fills in the ICMPv6 checksum itself
+            let checksum = internet_checksum(&request);
+            request[2..4].copy_from_slice(&checksum.to_be_bytes());
+        }
+
+        let (target, target_len) = sockaddr(SocketAddr::new(addr, 0));
+        let start = Instant::now();
+        let sent = unsafe {
+            libc::sendto(
+                socket.0,
+                request.as_ptr() as *const libc::c_void,
+                request.len(),
+                0,
+                &target as *const _ as *const libc::sockaddr,
+                target_len,
+            )
+        };
+        if sent < 0 {
+            return Err(ProbeError::Failed(format!("Failed to send echo request: {}", last_error())));
+        }
+
+        let deadline = start + timeout;
+        let mut buf = [0u8; 1500];
+        while wait(socket.0, libc::POLLIN, deadline)? {
+            let mut from: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+            let mut from_len = std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+            let len = unsafe {
+                libc::recvfrom(
+                    socket.0,
+                    buf.as_mut_ptr() as *mut libc::c_void,
+                    buf.len(),
+                    0,
+                    &mut from as *mut _ as *mut libc::sockaddr,
+                    &mut from_len,
+                )
+            };
+            if len < 0 {
+                return Err(ProbeError::Failed(format!("Failed to receive echo reply: {}", last_error())));
+            }
+            let mut reply = &buf[..len as usize];
+            // Raw IPv4 sockets hand over the IP header too
+            if raw && !v6 {
+                let header_len = reply.first().map(|b| (b & 0x0f) as usize * 4).unwrap_or(0);
+                reply = reply.get(header_len..).unwrap_or(&[]);
+            }
+            if reply.len() < 8 || u16::from_be_bytes([reply[6], reply[7]]) != seq {
+                continue;
+            }
+            if raw && u16::from_be_bytes([reply[4], reply[5]]) != id {
+                continue;
+            }
+            let from = sockaddr_ip(&from as *const _ as *const libc::sockaddr).unwrap_or(addr);
+            match reply[0] {
+                ICMP_ECHO_REPLY if !v6 => return Ok((HopReply::Reached(from), start.elapsed())),
+                ICMPV6_ECHO_REPLY if v6 => return Ok((HopReply::Reached(from), start.elapsed())),
+                _ => continue,
+            }
+        }
+        Ok((HopReply::Timeout, timeout))
+    }
+
+    // One UDP probe with a limited TTL. IP_RECVERR queues the ICMP error
+    // each router sends back on the socket, so no raw socket is needed.
+    pub fn trace_probe(addr: IpAddr, ttl: u8, timeout: Duration) -> Result<(HopReply, Duration), ProbeError> {
+        let v6 = addr.is_ipv6();
+        let family = if v6 { libc::AF_INET6 } else { libc::AF_INET };
+        let fd = unsafe { libc::socket(family, libc::SOCK_DGRAM, 0) };
+        if fd < 0 {
+            return Err(ProbeError::NotPermitted(format!("UDP sockets not available: {}", last_error())));
+        }
+        let socket = Socket(fd);
+        if v6 {
+            set_option(fd, libc::IPPROTO_IPV6, libc::IPV6_RECVERR, 1)?;
+            set_option(fd, libc::IPPROTO_IPV6, libc::IPV6_UNICAST_HOPS, ttl as libc::c_int)?;
+        } else {
+            set_option(fd, libc::IPPROTO_IP, libc::IP_RECVERR, 1)?;
+            set_option(fd, libc::IPPROTO_IP, libc::IP_TTL, ttl as libc::c_int)?;
+        }
+
+        let (target, target_len) = sockaddr(SocketAddr::new(addr, TRACEROUTE_PORT + ttl as u16));
+        let payload = [0u8; 32];
+        let start = Instant::now();
+        let sent = unsafe {
+            libc::sendto(
+                socket.0,
+                payload.as_ptr() as *const libc::c_void,
+                payload.len(),
+                0,
+                &target as *const _ as *const libc::sockaddr,
+                target_len,
+            )
+        };
+        if sent < 0 {
+            return Err(ProbeError::Failed(format!("Failed to send probe: {}", last_error())));
+        }
+
+        let deadline = start + timeout;
+        // The error queue is signalled as POLLERR, which poll always reports
+        while wait(socket.0, 0, deadline)? {
+            let mut data = [0u8; 512];
+            let mut control = [0u8; 512];
+            let mut iov = libc::iovec { iov_base: data.as_mut_ptr() as *mut libc::c_void, iov_len: data.len() };
+            let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+            msg.msg_iov = &mut iov;
+            msg.msg_iovlen = 1;
+            msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+            msg.msg_controllen = control.len() as _;
+            let rc = unsafe { libc::recvmsg(socket.0, &mut msg, libc::MSG_ERRQUEUE) };
+            if rc < 0 {
+                if last_error().kind() == std::io::ErrorKind::WouldBlock {
+                    continue;
+                }
+                return Err(ProbeError::Failed(format!("Failed to read error queue: {}", last_error())));
+            }
+            let elapsed = start.elapsed();
+
+            let mut cmsg = unsafe { libc::CMSG_FIRSTHDR(&msg) };
+            while !cmsg.is_null() {
+                let header = unsafe { &*cmsg };
+                let is_error = (header.cmsg_level == libc::SOL_IP && header.cmsg_type == libc::IP_RECVERR)
+                    || (header.cmsg_level == libc::SOL_IPV6 && header.cmsg_type == libc::IPV6_RECVERR);
+                if is_error {
+                    let err = unsafe { &*(libc::CMSG_DATA(cmsg) as *const libc::sock_extended_err) };
+                    // SO_EE_OFFENDER: the reporting address follows the error
+                    let offender = unsafe { (err as *const libc::sock_extended_err).add(1) as *const libc::sockaddr };
+                    let from = sockaddr_ip(offender).unwrap_or(addr);
+                    let reply = match (err.ee_origin, err.ee_type, err.ee_code) {
+                        (libc::SO_EE_ORIGIN_ICMP, ICMP_TIME_EXCEEDED, _) => HopReply::Router(from),
+                        (libc::SO_EE_ORIGIN_ICMP, ICMP_DEST_UNREACH, ICMP_PORT_UNREACH) => HopReply::Reached(from),
+                        (libc::SO_EE_ORIGIN_ICMP, ICMP_DEST_UNREACH, _) => HopReply::Unreachable(from),
+                        (libc::SO_EE_ORIGIN_ICMP6, ICMPV6_TIME_EXCEEDED, _) => HopReply::Router(from),
+                        (libc::SO_EE_ORIGIN_ICMP6, ICMPV6_DEST_UNREACH, ICMPV6_PORT_UNREACH) => HopReply::Reached(from),
+                        (libc::SO_EE_ORIGIN_ICMP6, ICMPV6_DEST_UNREACH, _) => HopReply::Unreachable(from),
+                        _ => {
+                            cmsg = unsafe { libc::CMSG_NXTHDR(&msg, cmsg) };
+                            continue;
+                        }
+                    };
+                    return Ok((reply, elapsed));
+                }
+                cmsg = unsafe { libc::CMSG_NXTHDR(&msg, cmsg) };
+            }
+        }
+        Ok((HopReply::Timeout, timeout))
+    }
+}
+
+#[cfg(windows)]
+mod probe {
+    use super::{HopReply, ProbeError};
+    use std::ffi::c_void;
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::time::Duration;
+
+    const IP_SUCCESS: u32 = 0;
+    const IP_DEST_PORT_UNREACHABLE: u32 = 11005;
+    const IP_REQ_TIMED_OUT: u32 = 11010;
+    const IP_TTL_EXPIRED_TRANSIT: u32 = 11013;
+
+    #[repr(C)]
+    struct IpOptionInformation {
+        ttl: u8,
+        tos: u8,
+        flags: u8,
+        options_size: u8,
+        options_data: *mut u8,
+    }
+
+    #[repr(C)]
+    struct IcmpEchoReply {
+        address: u32,
+        status: u32,
+        round_trip_time: u32,
+        data_size: u16,
+        reserved: u16,
+        data: *mut c_void,
+        options: IpOptionInformation,
+    }
+
+    #[link(name = "iphlpapi")]
+    extern "system" {
+        fn IcmpCreateFile() -> *mut c_void;
+        fn IcmpCloseHandle(handle: *mut c_void) -> i32;
+        fn IcmpSendEcho(
+            handle: *mut c_void,
+            destination: u32,
+            request: *const c_void,
+            request_size: u16,
+            options: *const IpOptionInformation,
+            reply: *mut c_void,
+            reply_size: u32,
+            timeout: u32,
+        ) -> u32;
+    }
+
+    // IcmpSendEcho needs no administrator rights and reports both echo
+    // replies and TTL expiry, so it serves ping and traceroute alike
+    fn send_echo(addr: IpAddr, ttl: Option<u8>, timeout: Duration) -> Result<(HopReply, Duration), ProbeError> {
+        let v4 = match addr {
+            IpAddr::V4(v4) => v4,
+            IpAddr::V6(_) => return Err(ProbeError::NotPermitted("ICMPv6 probes are not supported on Windows".to_string())),
+        };
+        let handle = unsafe { IcmpCreateFile() };
+        if handle.is_null() || handle as isize == -1 {
+            return Err(ProbeError::NotPermitted(format!("IcmpCreateFile failed: {}", std::io::Error::last_os_error())));
+        }
+        let request = b"s2o_net_lib diagnostics ping";
+        let options = IpOptionInformation { ttl: ttl.unwrap_or(128), tos: 0, flags: 0, options_size: 0, options_data: std::ptr::null_mut() };
+        let mut reply = vec![0u8; std::mem::size_of::<IcmpEchoReply>() + request.len() + 8];
+        let count = unsafe {
+            IcmpSendEcho(
+                handle,
+                u32::from_ne_bytes(v4.octets()),
+                request.as_ptr() as *const c_void,
+                request.len() as u16,
+                &options,
+                reply.as_mut_ptr() as *mut c_void,
+                reply.len() as u32,
+                timeout.as_millis() as u32,
+            )
+        };
+        let error = std::io::Error::last_os_error();
+        unsafe { IcmpCloseHandle(handle) };
+        if count == 0 {
+            return match error.raw_os_error().map(|code| code as u32) {
+                Some(IP_REQ_TIMED_OUT) => Ok((HopReply::Timeout, timeout)),
+                _ => Err(ProbeError::Failed(format!("IcmpSendEcho failed: {}", error))),
+            };
+        }
+        let echo = unsafe { (reply.as_ptr() as *const IcmpEchoReply).read_unaligned() };
+        let from = IpAddr::V4(Ipv4Addr::from(echo.address.to_ne_bytes()));
+        let rtt = Duration::from_millis(echo.round_trip_time as u64);
+        let hop = match echo.status {
+            IP_SUCCESS | IP_DEST_PORT_UNREACHABLE => HopReply::Reached(from),
+            IP_TTL_EXPIRED_TRANSIT => HopReply::Router(from),
+            IP_REQ_TIMED_OUT => HopReply::Timeout,
+            _ => HopReply::Unreachable(from),
+        };
+        Ok((hop, rtt))
+    }
+
+    pub fn ping_probe(addr: IpAddr, _seq: u16, timeout: Duration) -> Result<(HopReply, Duration), ProbeError> {
+        send_echo(addr, None, timeout)
+    }
+
+    pub fn trace_probe(addr: IpAddr, ttl: u8, timeout: Duration) -> Result<(HopReply, Duration), ProbeError> {
+        send_echo(addr, Some(ttl), timeout)
+    }
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+mod probe {
+    use super::{HopReply, ProbeError};
+    use std::net::IpAddr;
+    use std::time::Duration;
+
+    pub fn ping_probe(_addr: IpAddr, _seq: u16, _timeout: Duration) -> Result<(HopReply, Duration), ProbeError> {
+        Err(ProbeError::NotPermitted("ICMP probes are not supported on this platform".to_string()))
+    }
+
+    pub fn trace_probe(_addr: IpAddr, _ttl: u8, _timeout: Duration) -> Result<(HopReply, Duration), ProbeError> {
+        Err(ProbeError::NotPermitted("Traceroute is not supported on this platform".to_string()))
+    }
+}
+
+use probe::{ping_probe, trace_probe};
+
+// Runs the checks on a background thread for the DS menu, which shows each
+// result as it arrives
+pub fn start(spec: &str) {
+    let checks = match parse_checks(spec) {
+        Ok(checks) => checks,
+        Err(e) => return logging::debug_error(&format!("Invalid diagnostics: {}", e)),
+    };
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        return logging::debug_error("Diagnostics already running.");
+    }
+    RESULTS.lock().unwrap().clear();
+    std::thread::spawn(move || {
+        run_checks(&checks, DEFAULT_TIMEOUT, |result| {
+            logging::debug_info(&format!("Diagnostics {}", result));
+            RESULTS.lock().unwrap().push(result.clone());
+        });
+        RUNNING.store(false, Ordering::SeqCst);
+    });
+}
+
+pub fn is_running() -> bool {
+    RUNNING.load(Ordering::SeqCst)
+}
+
+pub fn results() -> Vec<CheckResult> {
+    RESULTS.lock().unwrap().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn parses_check_specs() {
+        let checks = parse_checks("dns localhost; tcp [::1]:22\nping 127.0.0.1 2; traceroute 10.0.0.1").unwrap();
+        assert_eq!(
+            checks,
+            [
+                DiagnosticCheck::Dns { host: "localhost".to_string() },
+                DiagnosticCheck::TcpConnect { host: "::1".to_string(), port: 22 },
+                DiagnosticCheck::Ping { host: "127.0.0.1".to_string(), count: 2 },
+                DiagnosticCheck::Traceroute { host: "10.0.0.1".to_string(), max_hops: DEFAULT_MAX_HOPS },
+            ]
+        );
+        let text: Vec<String> = checks.iter().map(|c| c.to_string()).collect();
+        assert_eq!(parse_checks(&text.join(";")).unwrap(), checks);
+        assert!(parse_checks("tcp localhost").is_err());
+        assert!(parse_checks("whois example.com").is_err());
+        assert!(parse_checks(DEFAULT_CHECKS).is_ok());
+    }
+
+    #[test]
+    fn checks_dns_and_tcp_on_localhost() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let open = listener.local_addr().unwrap().port();
+        let closed = {
+            let spare = TcpListener::bind("127.0.0.1:0").unwrap();
+            spare.local_addr().unwrap().port()
+        };
+        let checks = parse_checks(&format!("dns localhost; tcp 127.0.0.1:{}; tcp 127.0.0.1:{}", open, closed)).unwrap();
+        let mut seen = 0;
+        let results = run_checks(&checks, DEFAULT_TIMEOUT, |_| seen += 1);
+        assert_eq!(seen, 3);
+
+        let statuses: Vec<CheckStatus> = results.iter().map(|r| r.status).collect();
+        assert_eq!(statuses, [CheckStatus::Passed, CheckStatus::Passed, CheckStatus::Failed]);
+        assert!(results[0].steps[0].detail.contains("127.0.0.1") || results[0].steps[0].detail.contains("::1"));
+        assert_eq!(results[1].steps.len(), 2);
+        assert!(results[1].steps[1].duration.is_some());
+    }
+
+    #[test]
+    fn probes_localhost_where_permitted() {
+        // ICMP may be off limits in CI; it must then be skipped, never failed
+        for check in parse_checks("ping 127.0.0.1 2; traceroute 127.0.0.1 3").unwrap() {
+            let result = run_check(&check, DEFAULT_TIMEOUT);
+            assert_ne!(result.status, CheckStatus::Failed, "{}", result);
+            if result.status == CheckStatus::Passed {
+                assert!(result.steps.len() >= 2);
+            }
+        }
+    }
+}
@@ -1,4 +1,14 @@
-use crate::logging;
+use s2o_net_lib::logging;
+use crate::app_state::AppState;
+use s2o_net_lib::diagnostics::{self, CheckStatus};
+use crate::gui_engine_menu::MenuState;
+use crate::gui_engine_screens::{MenuEntry, Screen};
+use crate::gui_engine_style::MenuSettings;
+use s2o_net_lib::speed_test;
+use s2o_net_lib::throughput_monitor::{self, InterfaceRates};
+use eframe::egui::{self, Align2, Area, Color32, Context, Id, Pos2, RichText, Sense, Shape, Stroke, Vec2};
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
 
 pub fn init_module() -> Result<(), String> {
     // Placeholder for actual initialization logic
@@ -12,21 +22,156 @@ pub fn init_module() -> Result<(), String> {
     }
 }
 
-// Placeholder menu items for ds_menu
-#[allow(dead_code)]
-pub fn menu_items() -> Vec<crate::gui_engine_menu::MenuItem> {
+// Interfaces graphed at once; the rest are busiest-first and rarely matter
+const MAX_GRAPHS: usize = 4;
+const GRAPH_SIZE: Vec2 = Vec2::new(360.0, 70.0);
+const RX_COLOR: Color32 = Color32::from_rgb(0, 255, 0);
+const TX_COLOR: Color32 = Color32::from_rgb(0, 170, 255);
+
+// Speed test server typed under the menu; empty tests loopback
+static TARGET_INPUT: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new(String::new()));
+// Checks run by "Check connection", editable in the diagnostics panel
+static CHECKS_INPUT: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new(diagnostics::DEFAULT_CHECKS.to_string()));
+
+pub fn screen() -> Screen {
+    Screen {
+        state: AppState::DSMenu,
+        title: "DS Menu",
+        items,
+        on_enter: None,
+        render: Some(render),
+    }
+}
+
+fn render(ctx: &Context, menu_state: &MenuState) {
+    let settings = menu_state.get_settings();
+    render_throughput(ctx, settings);
+    render_speed_test(ctx, settings);
+    render_diagnostics(ctx, settings);
+}
+
+fn items() -> Vec<MenuEntry> {
+    let monitor_label = if throughput_monitor::is_running() { "Stop Throughput Monitor" } else { "Start Throughput Monitor" };
+    let server_label = if speed_test::is_server_running() { "Stop Speed Test Server" } else { "Start Speed Test Server" };
     vec![
-        crate::gui_engine_menu::MenuItem {
-            label: "Check connection".to_string(),
-            action: None,
-        },
-        crate::gui_engine_menu::MenuItem {
-            label: "Start Speed Test ".to_string(),
-            action: None,
-        },
-		crate::gui_engine_menu::MenuItem {
-			label: "Exit".to_string(),
-			action: None,
-		},
+        MenuEntry::run(monitor_label, throughput_monitor::toggle),
+        MenuEntry::run("Check connection", || diagnostics::start(&CHECKS_INPUT.lock().unwrap()))
+            .enabled_if(|| !diagnostics::is_running()),
+        MenuEntry::run("Start Speed Test", || speed_test::start_client(&TARGET_INPUT.lock().unwrap()))
+            .enabled_if(|| !speed_test::is_client_running()),
+        MenuEntry::run(server_label, speed_test::toggle_server),
     ]
-}
\ No newline at end of file
+}
+
+// Receive and transmit history as two lines, scaled to the busiest sample
+fn draw_graph(ui: &mut egui::Ui, rates: &InterfaceRates) {
+    let (response, painter) = ui.allocate_painter(GRAPH_SIZE, Sense::hover());
+    let rect = response.rect;
+    painter.rect_stroke(rect, 2.0, Stroke::new(1.0, Color32::from_gray(90)));
+
+    let peak = rates.history.iter().map(|(rx, tx)| rx.max(*tx)).fold(1.0, f64::max);
+    let step = rect.width() / (throughput_monitor::HISTORY_LEN.max(2) - 1) as f32;
+    // Newest sample on the right edge
+    let start = rect.right() - step * rates.history.len().saturating_sub(1) as f32;
+    let line = |value: fn(&(f64, f64)) -> f64, color: Color32| {
+        let points: Vec<Pos2> = rates
+            .history
+            .iter()
+            .enumerate()
+            .map(|(i, sample)| {
+                let height = (value(sample) / peak) as f32 * (rect.height() - 4.0);
+                Pos2::new(start + step * i as f32, rect.bottom() - 2.0 - height)
+            })
+            .collect();
+        Shape::line(points, Stroke::new(1.5, color))
+    };
+    painter.add(line(|sample| sample.0, RX_COLOR));
+    painter.add(line(|sample| sample.1, TX_COLOR));
+}
+
+// Live throughput graphs under the DS menu while the monitor runs
+fn render_throughput(ctx: &Context, settings: &MenuSettings) {
+    if !throughput_monitor::is_running() {
+        return;
+    }
+    let rates = throughput_monitor::snapshot();
+    Area::new(Id::new("throughput_area"))
+        .anchor(Align2::CENTER_BOTTOM, (0.0, -120.0))
+        .show(ctx, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                for interface in rates.iter().take(MAX_GRAPHS) {
+                    ui.vertical(|ui| {
+                        ui.label(settings.apply_label(&interface.name, false));
+                        ui.label(
+                            RichText::new(format!(
+                                "↓ {}  ↑ {}",
+                                throughput_monitor::format_rate(interface.rx),
+                                throughput_monitor::format_rate(interface.tx)
+                            ))
+                            .color(settings.option_color_unselected),
+                        );
+                        draw_graph(ui, interface);
+                    });
+                }
+            });
+        });
+}
+
+// Speed test target box and the outcome of the last run
+fn render_speed_test(ctx: &Context, settings: &MenuSettings) {
+    Area::new(Id::new("speed_test_area"))
+        .anchor(Align2::CENTER_BOTTOM, (0.0, -40.0))
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(settings.apply_label("Speed test server:", false));
+                ui.add(
+                    egui::TextEdit::singleline(&mut *TARGET_INPUT.lock().unwrap())
+                        .hint_text(format!("host[:{}], empty for loopback", speed_test::DEFAULT_PORT))
+                        .desired_width(300.0),
+                );
+            });
+            let status = if speed_test::is_client_running() {
+                RichText::new("Speed test running...").color(settings.option_color_unselected)
+            } else {
+                match speed_test::last_result() {
+                    Some(Ok(result)) => RichText::new(result.to_string()).color(settings.selected_font_color),
+                    Some(Err(e)) => RichText::new(e).color(Color32::from_rgb(255, 80, 80)),
+                    None => return,
+                }
+            };
+            ui.label(status);
+        });
+}
+
+// Checks to run and each result with its steps, filled in as checks finish
+fn render_diagnostics(ctx: &Context, settings: &MenuSettings) {
+    Area::new(Id::new("diagnostics_area"))
+        .anchor(Align2::LEFT_CENTER, (20.0, 0.0))
+        .show(ctx, |ui| {
+            ui.label(settings.apply_label("Checks:", false));
+            ui.add(
+                egui::TextEdit::multiline(&mut *CHECKS_INPUT.lock().unwrap())
+                    .hint_text("dns host; tcp host:port; ping host [count]; traceroute host [max_hops]")
+                    .desired_rows(2)
+                    .desired_width(320.0),
+            );
+            for result in diagnostics::results() {
+                let color = match result.status {
+                    CheckStatus::Passed => Color32::from_rgb(0, 255, 0),
+                    CheckStatus::Failed => Color32::from_rgb(255, 80, 80),
+                    CheckStatus::Skipped => Color32::from_rgb(255, 200, 0),
+                };
+                ui.label(RichText::new(result.to_string()).color(color));
+                for step in &result.steps {
+                    let time = step.duration.map(diagnostics::format_ms).unwrap_or_else(|| "-".to_string());
+                    ui.label(
+                        RichText::new(format!("    {}: {} ({})", step.label, step.detail, time))
+                            .color(settings.option_color_unselected),
+                    );
+                }
+            }
+            if diagnostics::is_running() {
+                ui.label(RichText::new("Checking...").color(settings.option_color_unselected));
+            }
+        });
+}
@@ -0,0 +1,412 @@
+use crate::logging;
+use crate::capture_filter;
+use crate::packet_decoder::Packet;
+use crate::rule_store;
+use once_cell::sync::Lazy;
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::Mutex;
+
+pub fn init_module() -> Result<(), String> {
+    // Placeholder for actual initialization logic
+    let initialization_passed = true;
+
+    if initialization_passed {
+        logging::debug_info("firewall module is online");
+        Ok(())
+    } else {
+        Err("firewall module initialization failed".to_string())
+    }
+}
+
+// Rules the capture pipeline checks packets against
+pub static FIREWALL: Lazy<Mutex<RuleSet>> = Lazy::new(|| Mutex::new(RuleSet::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleDirection {
+    Inbound,
+    Outbound,
+    Any,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleAction {
+    Allow,
+    Deny,
+    // Records the match and keeps evaluating lower priority rules
+    Log,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Allow,
+    Deny,
+}
+
+// An address block such as 10.0.0.0/8; a single host is a /32 or /128
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNetwork {
+    pub addr: IpAddr,
+    pub prefix: u8,
+}
+
+impl IpNetwork {
+    pub fn parse(value: &str) -> Result<IpNetwork, String> {
+        let (addr, prefix) = capture_filter::parse_network(value.trim())?;
+        Ok(IpNetwork { addr, prefix })
+    }
+
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        capture_filter::in_network(addr, self.addr, self.prefix)
+    }
+}
+
+impl fmt::Display for IpNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortRange {
+    pub start: u16,
+    pub end: u16,
+}
+
+impl PortRange {
+    pub fn single(port: u16) -> PortRange {
+        PortRange { start: port, end: port }
+    }
+
+    // "443" or "8000-8080"
+    pub fn parse(value: &str) -> Result<PortRange, String> {
+        let port = |s: &str| s.trim().parse::<u16>().map_err(|_| format!("Invalid port \"{}\"", value));
+        let range = match value.split_once('-') {
+            Some((start, end)) => PortRange { start: port(start)?, end: port(end)? },
+            None => PortRange::single(port(value)?),
+        };
+        if range.start > range.end {
+            return Err(format!("Invalid port range \"{}\"", value));
+        }
+        Ok(range)
+    }
+
+    pub fn contains(&self, port: u16) -> bool {
+        self.start <= port && port <= self.end
+    }
+}
+
+impl fmt::Display for PortRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.start == self.end {
+            write!(f, "{}", self.start)
+        } else {
+            write!(f, "{}-{}", self.start, self.end)
+        }
+    }
+}
+
+// One firewall rule. Empty address/port lists and a missing protocol or
+// process match anything. "Local" and "remote" follow the packet direction:
+// for outbound traffic the local side is the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FirewallRule {
+    pub name: String,
+    pub enabled: bool,
+    // Lower values are evaluated first; equal priorities keep insertion order
+    pub priority: i32,
+    pub direction: RuleDirection,
+    pub protocol: Option<u8>,
+    pub local_addresses: Vec<IpNetwork>,
+    pub remote_addresses: Vec<IpNetwork>,
+    pub local_ports: Vec<PortRange>,
+    pub remote_ports: Vec<PortRange>,
+    // Executable path or file name, compared by file name without case
+    pub process: Option<String>,
+    pub action: RuleAction,
+}
+
+// What the evaluator knows about a packet besides its headers
+#[derive(Debug, Clone, Copy)]
+pub struct PacketContext<'p, 'a> {
+    pub packet: &'p Packet<'a>,
+    pub outbound: bool,
+    pub process: Option<&'p str>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verdict {
+    pub decision: Decision,
+    // Rule that decided, or None when the default policy applied
+    pub rule: Option<String>,
+    // Log rules that matched on the way
+    pub logged: Vec<String>,
+}
+
+// Splits on both separators so Windows paths work on every platform
+fn process_file_name(process: &str) -> &str {
+    process.rsplit(['/', '\\']).next().unwrap_or(process)
+}
+
+impl FirewallRule {
+    // A rule that matches all traffic; set the fields that should narrow it
+    pub fn new(name: &str, action: RuleAction) -> FirewallRule {
+        FirewallRule {
+            name: name.to_string(),
+            enabled: true,
+            priority: 0,
+            direction: RuleDirection::Any,
+            protocol: None,
+            local_addresses: Vec::new(),
+            remote_addresses: Vec::new(),
+            local_ports: Vec::new(),
+            remote_ports: Vec::new(),
+            process: None,
+            action,
+        }
+    }
+
+    // "tcp", "udp", ... or the protocol number; "any" when unset
+    pub fn protocol_name(&self) -> String {
+        self.protocol.map(rule_store::protocol_name).unwrap_or_else(|| "any".to_string())
+    }
+
+    pub fn matches(&self, ctx: &PacketContext) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        match (self.direction, ctx.outbound) {
+            (RuleDirection::Inbound, true) | (RuleDirection::Outbound, false) => return false,
+            _ => {}
+        }
+        if let Some(protocol) = self.protocol {
+            if ctx.packet.ip_protocol() != Some(protocol) {
+                return false;
+            }
+        }
+
+        let needs_addresses = !self.local_addresses.is_empty() || !self.remote_addresses.is_empty();
+        if needs_addresses {
+            let (local, remote) = match (ctx.packet.source_ip(), ctx.packet.destination_ip()) {
+                (Some(src), Some(dst)) if ctx.outbound => (src, dst),
+                (Some(src), Some(dst)) => (dst, src),
+                _ => return false,
+            };
+            let any_contains = |networks: &[IpNetwork], addr| networks.is_empty() || networks.iter().any(|n| n.contains(addr));
+            if !any_contains(&self.local_addresses, local) || !any_contains(&self.remote_addresses, remote) {
+                return false;
+            }
+        }
+
+        if !self.local_ports.is_empty() || !self.remote_ports.is_empty() {
+            let (local, remote) = match ctx.packet.ports() {
+                Some((src, dst)) if ctx.outbound => (src, dst),
+                Some((src, dst)) => (dst, src),
+                None => return false,
+            };
+            let any_contains = |ranges: &[PortRange], port| ranges.is_empty() || ranges.iter().any(|r| r.contains(port));
+            if !any_contains(&self.local_ports, local) || !any_contains(&self.remote_ports, remote) {
+                return false;
+            }
+        }
+
+        if let Some(process) = &self.process {
+            match ctx.process {
+                Some(actual) if process_file_name(actual).eq_ignore_ascii_case(process_file_name(process)) => {}
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+// An ordered rule list with a default decision for each direction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleSet {
+    rules: Vec<FirewallRule>,
+    pub default_inbound: Decision,
+    pub default_outbound: Decision,
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        RuleSet::new()
+    }
+}
+
+impl RuleSet {
+    // Allows everything until rules say otherwise
+    pub fn new() -> RuleSet {
+        RuleSet { rules: Vec::new(), default_inbound: Decision::Allow, default_outbound: Decision::Allow }
+    }
+
+    // Rules in evaluation order
+    pub fn rules(&self) -> &[FirewallRule] {
+        &self.rules
+    }
+
+    // Rule names are unique so the GUI and persisted files can refer to them
+    pub fn add(&mut self, rule: FirewallRule) -> Result<(), String> {
+        if rule.name.trim().is_empty() {
+            return Err("Firewall rule needs a name".to_string());
+        }
+        if self.rules.iter().any(|existing| existing.name == rule.name) {
+            return Err(format!("Firewall rule \"{}\" already exists", rule.name));
+        }
+        let position = self.rules.iter().position(|existing| existing.priority > rule.priority).unwrap_or(self.rules.len());
+        self.rules.insert(position, rule);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<FirewallRule> {
+        let position = self.rules.iter().position(|rule| rule.name == name)?;
+        Some(self.rules.remove(position))
+    }
+
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> Result<(), String> {
+        let rule = self
+            .rules
+            .iter_mut()
+            .find(|rule| rule.name == name)
+            .ok_or(format!("No firewall rule named \"{}\"", name))?;
+        rule.enabled = enabled;
+        Ok(())
+    }
+
+    pub fn clear(&mut self) {
+        self.rules.clear();
+    }
+
+    // First matching allow/deny rule wins; log rules only record the match
+    pub fn evaluate(&self, ctx: &PacketContext) -> Verdict {
+        let mut logged = Vec::new();
+        for rule in self.rules.iter().filter(|rule| rule.matches(ctx)) {
+            match rule.action {
+                RuleAction::Log => logged.push(rule.name.clone()),
+                RuleAction::Allow | RuleAction::Deny => {
+                    let decision = if rule.action == RuleAction::Allow { Decision::Allow } else { Decision::Deny };
+                    return Verdict { decision, rule: Some(rule.name.clone()), logged };
+                }
+            }
+        }
+        let decision = if ctx.outbound { self.default_outbound } else { self.default_inbound };
+        Verdict { decision, rule: None, logged }
+    }
+}
+
+impl fmt::Display for FirewallRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let list = |items: Vec<String>| if items.is_empty() { "any".to_string() } else { items.join(",") };
+        write!(
+            f,
+            "[{}] {} {:?} {:?} proto={} local={}:{} remote={}:{} process={}{}",
+            self.priority,
+            self.name,
+            self.action,
+            self.direction,
+            self.protocol.map_or("any".to_string(), |p| p.to_string()),
+            list(self.local_addresses.iter().map(|n| n.to_string()).collect()),
+            list(self.local_ports.iter().map(|r| r.to_string()).collect()),
+            list(self.remote_addresses.iter().map(|n| n.to_string()).collect()),
+            list(self.remote_ports.iter().map(|r| r.to_string()).collect()),
+            self.process.as_deref().unwrap_or("any"),
+            if self.enabled { "" } else { " (disabled)" }
+        )
+    }
+}
+
+pub fn print_rules() {
+    let firewall = FIREWALL.lock().unwrap();
+    logging::debug_info(&format!(
+        "Firewall rules: {} (default inbound {:?}, outbound {:?})",
+        firewall.rules().len(),
+        firewall.default_inbound,
+        firewall.default_outbound
+    ));
+    for rule in firewall.rules() {
+        logging::debug_info(&rule.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture_backend::LinkType;
+    use crate::packet_decoder::{decode, IP_PROTO_TCP, IP_PROTO_UDP};
+
+    // IPv4 UDP or TCP from 192.168.1.10:51000 to 93.184.216.34:443
+    fn packet_bytes(protocol: u8) -> Vec<u8> {
+        let mut ip = vec![0x45, 0, 0, 0, 0, 0, 0x40, 0, 64, protocol, 0, 0, 192, 168, 1, 10, 93, 184, 216, 34];
+        ip.extend_from_slice(&51000u16.to_be_bytes());
+        ip.extend_from_slice(&443u16.to_be_bytes());
+        if protocol == IP_PROTO_TCP {
+            ip.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 0, 0x50, 0x02, 0xff, 0xff, 0, 0, 0, 0]);
+        } else {
+            ip.extend_from_slice(&[0, 8, 0, 0]);
+        }
+        let len = ip.len() as u16;
+        ip[2..4].copy_from_slice(&len.to_be_bytes());
+        ip
+    }
+
+    fn rule(name: &str, priority: i32, action: RuleAction) -> FirewallRule {
+        FirewallRule { priority, ..FirewallRule::new(name, action) }
+    }
+
+    #[test]
+    fn first_matching_rule_by_priority_decides() {
+        let data = packet_bytes(IP_PROTO_TCP);
+        let packet = decode(&data, LinkType::RawIp);
+        let ctx = PacketContext { packet: &packet, outbound: true, process: Some("C:\\Apps\\Browser.EXE") };
+
+        let mut rules = RuleSet::new();
+        rules.add(FirewallRule { remote_ports: vec![PortRange::single(443)], ..rule("allow-https", 10, RuleAction::Allow) }).unwrap();
+        rules.add(FirewallRule { process: Some("browser.exe".to_string()), ..rule("deny-browser", 5, RuleAction::Deny) }).unwrap();
+        rules.add(rule("log-all", 0, RuleAction::Log)).unwrap();
+        assert!(rules.add(rule("log-all", 1, RuleAction::Log)).is_err());
+
+        let names: Vec<&str> = rules.rules().iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["log-all", "deny-browser", "allow-https"]);
+        assert_eq!(
+            rules.evaluate(&ctx),
+            Verdict { decision: Decision::Deny, rule: Some("deny-browser".to_string()), logged: vec!["log-all".to_string()] }
+        );
+
+        rules.set_enabled("deny-browser", false).unwrap();
+        assert_eq!(rules.evaluate(&ctx).rule.as_deref(), Some("allow-https"));
+    }
+
+    #[test]
+    fn local_and_remote_follow_direction() {
+        let data = packet_bytes(IP_PROTO_UDP);
+        let packet = decode(&data, LinkType::RawIp);
+        let deny_lan = FirewallRule {
+            protocol: Some(IP_PROTO_UDP),
+            local_addresses: vec![IpNetwork::parse("192.168.0.0/16").unwrap()],
+            remote_ports: vec![PortRange::parse("400-500").unwrap()],
+            ..FirewallRule::new("deny-lan", RuleAction::Deny)
+        };
+        let outbound = PacketContext { packet: &packet, outbound: true, process: None };
+        let inbound = PacketContext { outbound: false, ..outbound };
+        assert!(deny_lan.matches(&outbound));
+        // Seen as inbound, 192.168.1.10 is the remote end
+        assert!(!deny_lan.matches(&inbound));
+        assert!(!FirewallRule { direction: RuleDirection::Inbound, ..deny_lan.clone() }.matches(&outbound));
+        assert!(!FirewallRule { protocol: Some(IP_PROTO_TCP), ..deny_lan.clone() }.matches(&outbound));
+        assert!(!FirewallRule { process: Some("svc.exe".to_string()), ..deny_lan }.matches(&outbound));
+    }
+
+    #[test]
+    fn default_policy_applies_when_nothing_matches() {
+        let data = packet_bytes(IP_PROTO_TCP);
+        let packet = decode(&data, LinkType::RawIp);
+        let mut rules = RuleSet { default_inbound: Decision::Deny, ..RuleSet::new() };
+        rules.add(FirewallRule { remote_addresses: vec![IpNetwork::parse("10.0.0.0/8").unwrap()], ..rule("allow-vpn", 0, RuleAction::Allow) }).unwrap();
+
+        let inbound = PacketContext { packet: &packet, outbound: false, process: None };
+        assert_eq!(rules.evaluate(&inbound), Verdict { decision: Decision::Deny, rule: None, logged: Vec::new() });
+        assert_eq!(rules.evaluate(&PacketContext { outbound: true, ..inbound }).decision, Decision::Allow);
+        assert!(rules.remove("allow-vpn").is_some());
+        assert!(rules.rules().is_empty());
+    }
+}
@@ -0,0 +1,636 @@
+use crate::logging;
+use crate::packet_decoder::{self, Packet, TransportLayer};
+use crate::process_attribution::ProcessInfo;
+use once_cell::sync::Lazy;
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+pub fn init_module() -> Result<(), String> {
+    // Placeholder for actual initialization logic
+    let initialization_passed = true;
+
+    if initialization_passed {
+        logging::debug_info("flow_table module is online");
+        Ok(())
+    } else {
+        Err("flow_table module initialization failed".to_string())
+    }
+}
+
+// Per direction cap on reassembled bytes kept in memory
+pub const DEFAULT_MAX_STREAM_BYTES: usize = 1024 * 1024;
+// Cap on reassembled and queued bytes across every flow
+pub const DEFAULT_MAX_TOTAL_STREAM_BYTES: usize = 64 * 1024 * 1024;
+pub const DEFAULT_MAX_FLOWS: usize = 65_536;
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+// Closed and reset flows linger briefly so stray ACKs and retransmissions
+// do not open a new flow
+pub const DEFAULT_CLOSED_TIMEOUT: Duration = Duration::from_secs(10);
+// Out of order segments held per direction while waiting for a gap to fill
+const MAX_PENDING_SEGMENTS: usize = 256;
+// How often, in capture time, expired flows are swept out
+const SWEEP_INTERVAL: Duration = Duration::from_secs(1);
+
+// Flow table fed by the capture thread
+pub static FLOW_TABLE: Lazy<Mutex<FlowTable>> = Lazy::new(|| Mutex::new(FlowTable::new(FlowLimits::default())));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlowLimits {
+    pub max_flows: usize,
+    pub max_stream_bytes: usize,
+    pub max_total_stream_bytes: usize,
+    pub idle_timeout: Duration,
+    pub closed_timeout: Duration,
+}
+
+impl Default for FlowLimits {
+    fn default() -> Self {
+        FlowLimits {
+            max_flows: DEFAULT_MAX_FLOWS,
+            max_stream_bytes: DEFAULT_MAX_STREAM_BYTES,
+            max_total_stream_bytes: DEFAULT_MAX_TOTAL_STREAM_BYTES,
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
+            closed_timeout: DEFAULT_CLOSED_TIMEOUT,
+        }
+    }
+}
+
+pub type Endpoint = (IpAddr, u16);
+
+// Same key for both directions: the lower endpoint always comes first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FlowKey {
+    pub protocol: u8,
+    pub lower: Endpoint,
+    pub upper: Endpoint,
+}
+
+impl FlowKey {
+    pub fn new(protocol: u8, a: Endpoint, b: Endpoint) -> Self {
+        let (lower, upper) = if a <= b { (a, b) } else { (b, a) };
+        FlowKey { protocol, lower, upper }
+    }
+
+    // Key of the TCP/UDP flow a packet belongs to
+    pub fn from_packet(packet: &Packet) -> Option<Self> {
+        let (sport, dport) = packet.ports()?;
+        let source = (packet.source_ip()?, sport);
+        let destination = (packet.destination_ip()?, dport);
+        Some(FlowKey::new(packet.ip_protocol()?, source, destination))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    ToResponder, // Sent by whoever opened the flow
+    ToInitiator,
+}
+
+impl Direction {
+    fn index(self) -> usize {
+        match self {
+            Direction::ToResponder => 0,
+            Direction::ToInitiator => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlowState {
+    SynSent,
+    SynReceived,
+    Established,
+    Closing, // One side sent FIN
+    Closed,  // Both sides sent FIN
+    Reset,
+    Active, // Non TCP flows
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlowRecord {
+    pub key: FlowKey,
+    pub initiator: Endpoint,
+    pub responder: Endpoint,
+    pub state: FlowState,
+    pub first_seen: SystemTime,
+    pub last_seen: SystemTime,
+    // Indexed by Direction: [to responder, to initiator]
+    pub packets: [u64; 2],
+    pub bytes: [u64; 2],
+    // Stream bytes dropped because of the memory cap or unfillable gaps
+    pub dropped_bytes: u64,
+    // Local program that owns the flow, when attribution found it
+    pub process: Option<ProcessInfo>,
+}
+
+impl FlowRecord {
+    pub fn summary(&self) -> String {
+        let protocol = match self.key.protocol {
+            packet_decoder::IP_PROTO_TCP => "TCP".to_string(),
+            packet_decoder::IP_PROTO_UDP => "UDP".to_string(),
+            other => format!("proto {}", other),
+        };
+        let duration = self.last_seen.duration_since(self.first_seen).unwrap_or_default();
+        let process = self.process.as_ref().map(|p| format!(" [{}]", p)).unwrap_or_default();
+        format!(
+            "{} {}:{} -> {}:{} {:?} {}/{} pkts {}/{} bytes {:.1}s{}",
+            protocol,
+            self.initiator.0,
+            self.initiator.1,
+            self.responder.0,
+            self.responder.1,
+            self.state,
+            self.packets[0],
+            self.packets[1],
+            self.bytes[0],
+            self.bytes[1],
+            duration.as_secs_f64(),
+            process
+        )
+    }
+}
+
+// Reassembles one direction of a TCP connection
+#[derive(Debug, Default)]
+struct StreamBuffer {
+    // Sequence number of the next byte we expect
+    next_seq: Option<u32>,
+    fin_seen: bool,
+    data: Vec<u8>,
+    // Segments beyond a gap, keyed by their sequence number
+    pending: BTreeMap<u32, Vec<u8>>,
+    pending_bytes: usize,
+    // Set once bytes were cut for lack of room, so the kept data stays a
+    // prefix of the stream rather than gaining holes when room frees up
+    truncated: bool,
+    dropped: u64,
+}
+
+impl StreamBuffer {
+    // Bytes held for this direction, counted against the table's budget
+    fn memory(&self) -> usize {
+        self.data.len() + self.pending_bytes
+    }
+
+    // `room` is what is left of the table's byte budget; it shrinks as bytes
+    // are kept and grows back as queued segments are consumed
+    fn push(&mut self, seq: u32, syn: bool, payload: &[u8], max_bytes: usize, room: &mut usize) {
+        // The SYN consumes one sequence number
+        let seq = if syn {
+            self.next_seq = Some(seq.wrapping_add(1));
+            seq.wrapping_add(1)
+        } else {
+            seq
+        };
+        let next = match self.next_seq {
+            Some(next) => next,
+            None => {
+                // Joined mid-stream: start at the first segment we see
+                self.next_seq = Some(seq);
+                seq
+            }
+        };
+        if payload.is_empty() {
+            return;
+        }
+
+        let offset = seq.wrapping_sub(next) as i32;
+        if offset > 0 {
+            if self.pending.contains_key(&seq) {
+                return;
+            }
+            if self.pending.len() < MAX_PENDING_SEGMENTS && payload.len() <= *room {
+                self.pending.insert(seq, payload.to_vec());
+                self.pending_bytes += payload.len();
+                *room -= payload.len();
+            } else {
+                self.dropped += payload.len() as u64;
+            }
+            return;
+        }
+
+        // Trim what we already delivered (retransmission or overlap)
+        let skip = offset.unsigned_abs() as usize;
+        if skip < payload.len() {
+            self.append(&payload[skip..], max_bytes, room);
+        }
+        self.drain_pending(max_bytes, room);
+    }
+
+    fn append(&mut self, bytes: &[u8], max_bytes: usize, room: &mut usize) {
+        let next = self.next_seq.unwrap_or(0);
+        self.next_seq = Some(next.wrapping_add(bytes.len() as u32));
+        let keep = if self.truncated { 0 } else { bytes.len().min(max_bytes.saturating_sub(self.data.len())).min(*room) };
+        self.truncated |= keep < bytes.len();
+        self.data.extend_from_slice(&bytes[..keep]);
+        *room -= keep;
+        self.dropped += (bytes.len() - keep) as u64;
+    }
+
+    // Moves queued segments that now line up onto the stream
+    fn drain_pending(&mut self, max_bytes: usize, room: &mut usize) {
+        loop {
+            let next = match self.next_seq {
+                Some(next) => next,
+                None => return,
+            };
+            let ready = self
+                .pending
+                .keys()
+                .copied()
+                .find(|seq| (seq.wrapping_sub(next) as i32) <= 0);
+            let seq = match ready {
+                Some(seq) => seq,
+                None => return,
+            };
+            let segment = self.pending.remove(&seq).unwrap_or_default();
+            self.pending_bytes -= segment.len();
+            *room += segment.len();
+            let skip = (next.wrapping_sub(seq)) as usize;
+            if skip < segment.len() {
+                self.append(&segment[skip..], max_bytes, room);
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Flow {
+    record: FlowRecord,
+    streams: [StreamBuffer; 2],
+}
+
+impl Flow {
+    fn memory(&self) -> usize {
+        self.streams.iter().map(StreamBuffer::memory).sum()
+    }
+
+    fn is_finished(&self) -> bool {
+        matches!(self.record.state, FlowState::Closed | FlowState::Reset)
+    }
+}
+
+pub struct FlowTable {
+    flows: HashMap<FlowKey, Flow>,
+    limits: FlowLimits,
+    // Bytes held by every stream buffer
+    stream_bytes: usize,
+    // Latest packet time seen, the clock flows expire against
+    now: SystemTime,
+    last_sweep: SystemTime,
+    evicted_flows: u64,
+}
+
+impl FlowTable {
+    pub fn new(limits: FlowLimits) -> Self {
+        FlowTable {
+            flows: HashMap::new(),
+            limits,
+            stream_bytes: 0,
+            now: SystemTime::UNIX_EPOCH,
+            last_sweep: SystemTime::UNIX_EPOCH,
+            evicted_flows: 0,
+        }
+    }
+
+    pub fn flow_count(&self) -> usize {
+        self.flows.len()
+    }
+
+    pub fn stream_bytes(&self) -> usize {
+        self.stream_bytes
+    }
+
+    // Flows removed for being idle, finished or over the flow cap
+    pub fn evicted_flows(&self) -> u64 {
+        self.evicted_flows
+    }
+
+    fn remove(&mut self, key: &FlowKey) {
+        if let Some(flow) = self.flows.remove(key) {
+            self.stream_bytes -= flow.memory();
+            self.evicted_flows += 1;
+        }
+    }
+
+    // Drops flows idle for longer than the timeout, and finished flows once
+    // their shorter linger time has passed
+    fn sweep(&mut self) {
+        let now = self.now;
+        let limits = self.limits;
+        let expired: Vec<FlowKey> = self
+            .flows
+            .iter()
+            .filter(|(_, flow)| {
+                let idle = now.duration_since(flow.record.last_seen).unwrap_or_default();
+                idle >= limits.idle_timeout || (flow.is_finished() && idle >= limits.closed_timeout)
+            })
+            .map(|(key, _)| *key)
+            .collect();
+        for key in expired {
+            self.remove(&key);
+        }
+        self.last_sweep = now;
+    }
+
+    // Makes room for one more flow, preferring finished flows over the least
+    // recently seen one
+    fn evict_for_new_flow(&mut self) {
+        self.sweep();
+        while self.flows.len() >= self.limits.max_flows.max(1) {
+            let victim = self
+                .flows
+                .iter()
+                .min_by_key(|(_, flow)| (!flow.is_finished(), flow.record.last_seen))
+                .map(|(key, _)| *key);
+            match victim {
+                Some(key) => self.remove(&key),
+                None => return,
+            }
+        }
+    }
+
+    // Feeds one decoded packet into the table; non TCP/UDP packets are ignored
+    pub fn process(&mut self, packet: &Packet, timestamp: SystemTime) {
+        let (source, destination) = match (packet.source_ip(), packet.destination_ip(), packet.ports()) {
+            (Some(src), Some(dst), Some((sport, dport))) => ((src, sport), (dst, dport)),
+            _ => return,
+        };
+        let key = match FlowKey::from_packet(packet) {
+            Some(key) => key,
+            None => return,
+        };
+        let max_stream_bytes = self.limits.max_stream_bytes;
+        let tcp = match &packet.transport {
+            Some(TransportLayer::Tcp(tcp)) => Some(tcp),
+            _ => None,
+        };
+
+        if timestamp > self.now {
+            self.now = timestamp;
+        }
+        if self.now.duration_since(self.last_sweep).unwrap_or_default() >= SWEEP_INTERVAL {
+            self.sweep();
+        }
+        if !self.flows.contains_key(&key) && self.flows.len() >= self.limits.max_flows {
+            self.evict_for_new_flow();
+        }
+        let mut room = self.limits.max_total_stream_bytes.saturating_sub(self.stream_bytes);
+
+        let flow = self.flows.entry(key).or_insert_with(|| {
+            // A SYN+ACK seen first means we missed the SYN from the other side
+            let reply_first = tcp.is_some_and(|tcp| {
+                tcp.has_flag(packet_decoder::TCP_SYN) && tcp.has_flag(packet_decoder::TCP_ACK)
+            });
+            let (initiator, responder) = if reply_first { (destination, source) } else { (source, destination) };
+            Flow {
+                record: FlowRecord {
+                    key,
+                    initiator,
+                    responder,
+                    state: if tcp.is_some() { FlowState::SynSent } else { FlowState::Active },
+                    first_seen: timestamp,
+                    last_seen: timestamp,
+                    packets: [0; 2],
+                    bytes: [0; 2],
+                    dropped_bytes: 0,
+                    process: None,
+                },
+                streams: [StreamBuffer::default(), StreamBuffer::default()],
+            }
+        });
+
+        let direction = if source == flow.record.initiator { Direction::ToResponder } else { Direction::ToInitiator };
+        let record = &mut flow.record;
+        record.packets[direction.index()] += 1;
+        record.bytes[direction.index()] += packet.payload.len() as u64;
+        if timestamp > record.last_seen {
+            record.last_seen = timestamp;
+        }
+
+        let tcp = match tcp {
+            Some(tcp) => tcp,
+            None => return,
+        };
+        let syn = tcp.has_flag(packet_decoder::TCP_SYN);
+        let ack = tcp.has_flag(packet_decoder::TCP_ACK);
+        let fin = tcp.has_flag(packet_decoder::TCP_FIN);
+
+        let stream = &mut flow.streams[direction.index()];
+        let dropped_before = stream.dropped;
+        let memory_before = stream.memory();
+        stream.push(tcp.sequence, syn, packet.payload, max_stream_bytes, &mut room);
+        if fin {
+            stream.fin_seen = true;
+        }
+        let newly_dropped = stream.dropped - dropped_before;
+        record.dropped_bytes += newly_dropped;
+        self.stream_bytes = self.stream_bytes - memory_before + stream.memory();
+
+        let both_fin = flow.streams[0].fin_seen && flow.streams[1].fin_seen;
+        record.state = match record.state {
+            _ if tcp.has_flag(packet_decoder::TCP_RST) => FlowState::Reset,
+            FlowState::Reset => FlowState::Reset,
+            _ if both_fin => FlowState::Closed,
+            _ if fin => FlowState::Closing,
+            FlowState::Closing => FlowState::Closing,
+            FlowState::SynSent if syn && ack && direction == Direction::ToInitiator => FlowState::SynReceived,
+            FlowState::SynSent if !syn => FlowState::Established, // joined mid-stream
+            FlowState::SynReceived if ack && !syn => FlowState::Established,
+            state => state,
+        };
+    }
+
+    // Records the owning program the first time a flow is attributed
+    pub fn attribute(&mut self, key: &FlowKey, process: &ProcessInfo) {
+        if let Some(flow) = self.flows.get_mut(key) {
+            if flow.record.process.is_none() {
+                flow.record.process = Some(process.clone());
+            }
+        }
+    }
+
+    pub fn flows(&self) -> Vec<FlowRecord> {
+        let mut records: Vec<FlowRecord> = self.flows.values().map(|flow| flow.record.clone()).collect();
+        records.sort_by_key(|record| record.first_seen);
+        records
+    }
+
+    pub fn flow(&self, key: &FlowKey) -> Option<&FlowRecord> {
+        self.flows.get(key).map(|flow| &flow.record)
+    }
+
+    // Reassembled, in order bytes sent in one direction so far
+    pub fn stream(&self, key: &FlowKey, direction: Direction) -> Option<&[u8]> {
+        self.flows.get(key).map(|flow| flow.streams[direction.index()].data.as_slice())
+    }
+}
+
+pub fn snapshot() -> Vec<FlowRecord> {
+    FLOW_TABLE.lock().unwrap().flows()
+}
+
+pub fn print_flows() {
+    let flows = snapshot();
+    logging::debug_info(&format!("Tracked flows: {}", flows.len()));
+    for flow in flows {
+        logging::debug_info(&flow.summary());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture_backend::LinkType;
+    use crate::packet_decoder::{TCP_ACK, TCP_FIN, TCP_RST, TCP_SYN};
+
+    const CLIENT: [u8; 4] = [10, 0, 0, 1];
+    const SERVER: [u8; 4] = [10, 0, 0, 2];
+
+    // Raw IPv4 + TCP segment
+    fn segment(from_client: bool, seq: u32, flags: u16, payload: &[u8]) -> Vec<u8> {
+        let (src, dst, sport, dport) = if from_client { (CLIENT, SERVER, 40000u16, 80u16) } else { (SERVER, CLIENT, 80, 40000) };
+        let total = 40 + payload.len();
+        let mut ip = vec![0x45, 0];
+        ip.extend_from_slice(&(total as u16).to_be_bytes());
+        ip.extend_from_slice(&[0, 0, 0, 0, 64, 6, 0, 0]);
+        ip.extend_from_slice(&src);
+        ip.extend_from_slice(&dst);
+        ip.extend_from_slice(&sport.to_be_bytes());
+        ip.extend_from_slice(&dport.to_be_bytes());
+        ip.extend_from_slice(&seq.to_be_bytes());
+        ip.extend_from_slice(&0u32.to_be_bytes());
+        ip.push(5 << 4);
+        ip.push(flags as u8);
+        ip.extend_from_slice(&[0xff, 0xff, 0, 0, 0, 0]);
+        ip.extend_from_slice(payload);
+        ip
+    }
+
+    fn feed(table: &mut FlowTable, frames: &[Vec<u8>]) {
+        for (i, frame) in frames.iter().enumerate() {
+            let at = SystemTime::UNIX_EPOCH + Duration::from_secs(i as u64);
+            table.process(&packet_decoder::decode(frame, LinkType::RawIp), at);
+        }
+    }
+
+    fn key() -> FlowKey {
+        FlowKey::new(6, (IpAddr::from(CLIENT), 40000), (IpAddr::from(SERVER), 80))
+    }
+
+    fn process_at(table: &mut FlowTable, frame: &[u8], secs: u64) {
+        table.process(&packet_decoder::decode(frame, LinkType::RawIp), SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+    }
+
+    // Single client segment from the given source port
+    fn from_port(port: u16) -> Vec<u8> {
+        let mut frame = segment(true, 100, TCP_SYN, b"");
+        frame[20..22].copy_from_slice(&port.to_be_bytes());
+        frame
+    }
+
+    fn limits(max_stream_bytes: usize) -> FlowLimits {
+        FlowLimits { max_stream_bytes, ..FlowLimits::default() }
+    }
+
+    #[test]
+    fn tracks_handshake_and_teardown() {
+        let mut table = FlowTable::new(FlowLimits::default());
+        feed(&mut table, &[
+            segment(true, 100, TCP_SYN, b""),
+            segment(false, 500, TCP_SYN | TCP_ACK, b""),
+            segment(true, 101, TCP_ACK, b""),
+        ]);
+        let flow = table.flow(&key()).unwrap().clone();
+        assert_eq!(flow.state, FlowState::Established);
+        assert_eq!(flow.initiator, (IpAddr::from(CLIENT), 40000));
+        assert_eq!(flow.packets, [2, 1]);
+
+        let browser = ProcessInfo { pid: 42, name: "browser".to_string(), path: None };
+        table.attribute(&key(), &browser);
+        table.attribute(&key(), &ProcessInfo { pid: 7, ..browser.clone() });
+        assert_eq!(table.flow(&key()).unwrap().process, Some(browser));
+        assert!(table.flow(&key()).unwrap().summary().ends_with("[browser (42)]"));
+
+        feed(&mut table, &[
+            segment(true, 101, TCP_FIN | TCP_ACK, b""),
+            segment(false, 501, TCP_FIN | TCP_ACK, b""),
+        ]);
+        assert_eq!(table.flow(&key()).unwrap().state, FlowState::Closed);
+    }
+
+    #[test]
+    fn reorders_and_deduplicates_segments() {
+        let mut table = FlowTable::new(FlowLimits::default());
+        feed(&mut table, &[
+            segment(true, 100, TCP_SYN, b""),
+            segment(true, 107, TCP_ACK, b"world"),
+            segment(true, 101, TCP_ACK, b"hello "),
+            segment(true, 101, TCP_ACK, b"hello "), // retransmission
+            segment(true, 112, TCP_ACK, b"!"),
+        ]);
+        assert_eq!(table.stream(&key(), Direction::ToResponder), Some(&b"hello world!"[..]));
+        assert_eq!(table.flow(&key()).unwrap().bytes[0], 18);
+    }
+
+    #[test]
+    fn caps_stream_memory() {
+        let mut table = FlowTable::new(limits(4));
+        feed(&mut table, &[segment(true, 100, TCP_SYN, b""), segment(true, 101, TCP_ACK, b"abcdefgh")]);
+        assert_eq!(table.stream(&key(), Direction::ToResponder), Some(&b"abcd"[..]));
+        assert_eq!(table.flow(&key()).unwrap().dropped_bytes, 4);
+    }
+
+    #[test]
+    fn evicts_idle_and_closed_flows() {
+        let limits = FlowLimits { idle_timeout: Duration::from_secs(60), closed_timeout: Duration::from_secs(5), ..FlowLimits::default() };
+        let mut table = FlowTable::new(limits);
+        feed(&mut table, &[
+            segment(true, 100, TCP_SYN, b""),
+            segment(true, 101, TCP_RST, b""),
+        ]);
+        process_at(&mut table, &from_port(1000), 2);
+        assert_eq!(table.flow(&key()).unwrap().state, FlowState::Reset);
+        assert_eq!(table.flow_count(), 2);
+
+        // The reset flow goes once its linger time passes, the live one stays
+        process_at(&mut table, &from_port(1000), 10);
+        assert!(table.flow(&key()).is_none());
+        assert_eq!(table.flow_count(), 1);
+
+        // Nothing for longer than the idle timeout
+        process_at(&mut table, &from_port(2000), 71);
+        assert_eq!(table.flow_count(), 1);
+        assert_eq!(table.evicted_flows(), 2);
+    }
+
+    #[test]
+    fn caps_flow_count() {
+        let mut table = FlowTable::new(FlowLimits { max_flows: 2, ..FlowLimits::default() });
+        process_at(&mut table, &from_port(1000), 0);
+        process_at(&mut table, &from_port(2000), 1);
+        process_at(&mut table, &from_port(1000), 2);
+        process_at(&mut table, &from_port(3000), 3);
+        assert_eq!(table.flow_count(), 2);
+        let ports: Vec<u16> = table.flows().iter().map(|flow| flow.initiator.1).collect();
+        assert!(ports.contains(&1000) && ports.contains(&3000), "{ports:?}");
+    }
+
+    #[test]
+    fn caps_total_stream_memory() {
+        let mut table = FlowTable::new(FlowLimits { max_total_stream_bytes: 6, ..FlowLimits::default() });
+        feed(&mut table, &[
+            segment(true, 100, TCP_SYN, b""),
+            segment(true, 101, TCP_ACK, b"abcd"),
+            segment(false, 500, TCP_SYN | TCP_ACK, b""),
+            segment(false, 505, TCP_ACK, b"efgh"), // beyond a gap, no room to queue it
+            segment(false, 501, TCP_ACK, b"wxyz"),
+        ]);
+        assert_eq!(table.stream(&key(), Direction::ToResponder), Some(&b"abcd"[..]));
+        assert_eq!(table.stream(&key(), Direction::ToInitiator), Some(&b"wx"[..]));
+        assert_eq!(table.stream_bytes(), 6);
+        assert_eq!(table.flow(&key()).unwrap().dropped_bytes, 6);
+    }
+}
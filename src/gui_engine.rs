@@ -1,14 +1,11 @@
-use crate::logging;
-use crate::admin_check;
-use crate::s_menu;
-use crate::p_menu;
-use crate::app_state::AppState;
+use s2o_net_lib::logging;
+use s2o_net_lib::admin_check;
+use crate::app_state::{AppState, Navigation, SharedNavigation};
 use crate::gui_engine_animation::{AnimationState, speedometer};
-use crate::gui_engine_menu::MenuItem;
+use crate::gui_engine_menu::{MenuItem, MenuResponse};
 use crate::gui_engine_style::MenuSettings;
 use eframe::egui::{self, Context, FontDefinitions, FontData, FontFamily};
 use std::sync::{Arc, Mutex};
-use std::io::{self, Write};
 
 pub fn init_module() -> Result<(), String> {
     let initialization_passed = true;
@@ -21,43 +18,100 @@ pub fn init_module() -> Result<(), String> {
 }
 
 struct MyApp {
-    app_state: Arc<Mutex<AppState>>,
-    menu_settings: crate::gui_engine_style::MenuSettings,
+    navigation: SharedNavigation,
+    // Screen the selection belongs to; it resets when another screen opens
+    shown_state: AppState,
     menu_items: Vec<crate::gui_engine_menu::MenuItem>,
     selected_index: usize,
     is_elevated: bool,
     menu_state: crate::gui_engine_menu::MenuState,
     animation_state: AnimationState,
+    // Item under the pointer last frame; only a change moves the selection,
+    // so a resting mouse does not fight the arrow keys
+    last_hovered: Option<usize>,
+    // Wheel movement not yet turned into selection steps
+    scroll_remainder: f32,
 }
 
+// Wheel distance, in points, that moves the selection by one item
+const SCROLL_STEP: f32 = 24.0;
+
 impl MyApp {
-    #[allow(dead_code)]
-    fn set_app_state(&mut self, new_state: AppState) {
-        logging::debug_info(&format!("Setting app state to: {:?}", new_state));
-        let mut app_state = self.app_state.lock().unwrap();
-        *app_state = new_state;
-        self.menu_items = match new_state {
-            AppState::SMenu => s_menu::menu_items(self.get_set_app_state_closure(), &self.menu_settings),
-            AppState::PMenu => p_menu::menu_items(self.get_set_app_state_closure(), &self.menu_settings),
-            _ => vec![],
-        };
-        logging::debug_info("App state set successfully");
-    }
-
-    fn get_set_app_state_closure(&self) -> impl Fn(AppState) + 'static + Clone {
-        let app_state_clone = self.app_state.clone();
-        move |state| {
-            logging::debug_info(&format!("Updating app state to: {:?}", state));
-            let mut app_state = app_state_clone.lock().unwrap();
-            *app_state = state;
+    fn select(&mut self, index: usize) {
+        self.selected_index = index;
+        self.menu_state.set_selected(index);
+    }
+
+    // Moves the selection by `step` enabled items, wrapping around at either
+    // end; disabled items are skipped, and nothing moves if all are disabled
+    fn move_selection(&mut self, step: isize) {
+        let len = self.menu_items.len() as isize;
+        if !self.menu_items.iter().any(|item| item.enabled) {
+            return;
+        }
+        let mut index = self.selected_index as isize;
+        for _ in 0..step.unsigned_abs() {
+            loop {
+                index = (index + step.signum()).rem_euclid(len);
+                if self.menu_items[index as usize].enabled {
+                    break;
+                }
+            }
+        }
+        self.select(index as usize);
+    }
+
+    fn activate(&mut self, index: usize) {
+        self.select(index);
+        if let Some(action) = &self.menu_items[index].action {
+            action();
+        }
+    }
+
+    // Hover follows the pointer, clicks and taps activate, and the wheel
+    // steps through the items while the pointer is over the menu. A hover
+    // change in the same frame as a key press leaves the key's selection.
+    fn apply_pointer(&mut self, response: MenuResponse, scroll: f32, keyboard_moved: bool) {
+        if response.hovered != self.last_hovered {
+            if let Some(index) = response.hovered.filter(|index| *index < self.menu_items.len() && !keyboard_moved) {
+                self.select(index);
+            }
+            self.last_hovered = response.hovered;
+        }
+        if let Some(index) = response.clicked.filter(|index| *index < self.menu_items.len()) {
+            logging::debug_info("Menu item clicked");
+            self.activate(index);
+        }
+        if !response.pointer_over_menu {
+            self.scroll_remainder = 0.0;
+            return;
+        }
+        self.scroll_remainder += scroll;
+        while self.scroll_remainder.abs() >= SCROLL_STEP {
+            // Scrolling up (positive delta) moves towards the first item
+            let step = -self.scroll_remainder.signum();
+            self.move_selection(step as isize);
+            self.scroll_remainder += step * SCROLL_STEP;
         }
     }
 }
 
 impl eframe::App for MyApp {
     fn update(&mut self, ctx: &Context, _frame: &mut eframe::Frame) {
-        // Handle keyboard input
-        ctx.input(|input| handle_input(input, self));
+        // Keep the keyboard actions in sync with the screen being shown
+        let current_state = self.navigation.lock().unwrap().current();
+        if current_state != self.shown_state {
+            logging::debug_info(&format!("App state changed to: {:?}", current_state));
+            self.shown_state = current_state;
+            self.select(0);
+        }
+        self.menu_items = crate::gui_engine_menu::menu_items_for_state(&self.navigation);
+        if self.selected_index >= self.menu_items.len() {
+            self.select(0);
+        }
+
+        // Handle keyboard input, unless a text box (e.g. the PC menu filter) has focus
+        let keyboard_moved = !ctx.wants_keyboard_input() && ctx.input(|input| handle_input(input, self));
 
         // Update the animation
         self.animation_state.update();
@@ -70,38 +124,37 @@ impl eframe::App for MyApp {
         let rect = ctx.screen_rect();
         self.animation_state.draw_background(&painter, rect);
 
-        let app_state = self.app_state.lock().unwrap().clone();
         logging::debug_info("Rendering app state");
 
-        crate::gui_engine_menu::render_app_state(
+        let response = crate::gui_engine_menu::render_app_state(
             ctx,
-            &app_state,
+            &self.navigation,
             &self.menu_state,
             self.is_elevated,
             self.menu_state.format_runtime(),
-            self.get_set_app_state_closure(),
         );
+        let scroll = ctx.input(|input| input.raw_scroll_delta.y);
+        self.apply_pointer(response, scroll, keyboard_moved);
         logging::debug_info("App state rendered successfully");
     }
+
+    // Closing the window skips the Exit screen, so shut down here as well
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        s2o_net_lib::initialization::s2o_shutdown();
+    }
 }
 
 pub fn start_gui() {
     logging::debug_info("Starting GUI");
 
-    // Add a pause and wait for any key to be pressed
-    println!("Starting GUI application pause");
-    println!("Press any key to continue...");
-    io::stdout().flush().unwrap(); // Ensure the message is printed before waiting
-    let _ = io::stdin().read_line(&mut String::new()); // Wait for any key press
-
     // Load menu settings
     let menu_settings = load_menu_settings();
     
     // Load app state and menu items
-    let (app_state, menu_items) = load_app_state();
+    let (navigation, menu_items) = load_app_state();
 
     // Create the app instance
-    let app = create_app_instance(app_state, menu_settings, menu_items);
+    let app = create_app_instance(navigation, menu_settings, menu_items);
 
     // Define native options
     let native_options = eframe::NativeOptions::default();
@@ -147,7 +200,7 @@ fn load_menu_settings() -> MenuSettings {
     crate::gui_engine_style::default_settings()
 }
 
-fn load_app_state() -> (Arc<Mutex<AppState>>, Vec<MenuItem>) {
+fn load_app_state() -> (SharedNavigation, Vec<MenuItem>) {
     let initial_app_state = if admin_check::is_admin_user() {
         logging::debug_info("User is elevated. Starting with PMenu.");
         AppState::PMenu
@@ -156,31 +209,18 @@ fn load_app_state() -> (Arc<Mutex<AppState>>, Vec<MenuItem>) {
         AppState::SMenu
     };
 
-    let app_state = Arc::new(Mutex::new(initial_app_state));
-    let app_state_clone = app_state.clone(); // Clone to use within closures
-
-    let menu_settings = load_menu_settings();
-    let menu_items = match initial_app_state {
-        AppState::SMenu => s_menu::menu_items(Box::new(move |state| {
-            let mut app_state = app_state_clone.lock().unwrap();
-            *app_state = state;
-        }), &menu_settings),
-        AppState::PMenu => p_menu::menu_items(Box::new(move |state| {
-            let mut app_state = app_state_clone.lock().unwrap();
-            *app_state = state;
-        }), &menu_settings),
-        _ => vec![],
-    };
+    let navigation = Arc::new(Mutex::new(Navigation::new(initial_app_state)));
+    let menu_items = crate::gui_engine_menu::menu_items_for_state(&navigation);
 
-    (app_state, menu_items)
+    (navigation, menu_items)
 }
 
 fn create_app_instance(
-    app_state: Arc<Mutex<AppState>>,
+    navigation: SharedNavigation,
     menu_settings: MenuSettings,
     menu_items: Vec<MenuItem>
 ) -> MyApp {
-    let initial_app_state = *app_state.lock().unwrap();
+    let initial_app_state = navigation.lock().unwrap().current();
     
     let mut animation_state = AnimationState::new();
 
@@ -190,35 +230,155 @@ fn create_app_instance(
     animation_state.set_speed_factor(speed_factor);
 
     MyApp {
-        app_state,
-        menu_settings: menu_settings.clone(), // Use menu_settings here
+        navigation,
+        shown_state: initial_app_state,
         menu_items,
         selected_index: 0,
         is_elevated: initial_app_state == AppState::PMenu,
         menu_state: crate::gui_engine_menu::MenuState::new(menu_settings),
         animation_state,
+        last_hovered: None,
+        scroll_remainder: 0.0,
     }
 }
 
-fn handle_input(input: &egui::InputState, app: &mut MyApp) {
+// Returns whether a key changed the selection this frame
+fn handle_input(input: &egui::InputState, app: &mut MyApp) -> bool {
+    if app.menu_items.is_empty() {
+        return false;
+    }
+    let mut moved = false;
     if input.key_pressed(egui::Key::ArrowDown) {
         logging::debug_info("ArrowDown key pressed");
-        app.selected_index = (app.selected_index + 1) % app.menu_items.len();
-        app.menu_state.set_selected(app.selected_index); // Update menu_state
+        app.move_selection(1);
+        moved = true;
     }
     if input.key_pressed(egui::Key::ArrowUp) {
         logging::debug_info("ArrowUp key pressed");
-        if app.selected_index == 0 {
-            app.selected_index = app.menu_items.len() - 1;
-        } else {
-            app.selected_index -= 1;
-        }
-        app.menu_state.set_selected(app.selected_index); // Update menu_state
+        app.move_selection(-1);
+        moved = true;
+    }
+    if input.key_pressed(egui::Key::Escape) || input.key_pressed(egui::Key::Backspace) {
+        logging::debug_info("Back key pressed");
+        crate::gui_engine_screens::back(&app.navigation);
+        return moved;
     }
     if input.key_pressed(egui::Key::Enter) {
         logging::debug_info("Enter key pressed");
-        if let Some(action) = &app.menu_items[app.selected_index].action {
-            action();
+        app.activate(app.selected_index);
+        return moved;
+    }
+    let shortcut = (0..app.menu_items.len())
+        .find(|index| crate::gui_engine_menu::shortcut_key(*index).is_some_and(|key| input.key_pressed(key)));
+    if let Some(index) = shortcut {
+        logging::debug_info("Shortcut key pressed");
+        app.activate(index);
+        moved = true;
+    }
+    moved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    // A menu whose enabled items count their activations
+    fn app(enabled: &[bool], activations: &Rc<Cell<usize>>) -> MyApp {
+        let menu_items = enabled
+            .iter()
+            .enumerate()
+            .map(|(index, enabled)| {
+                let activations = Rc::clone(activations);
+                let action: Box<dyn Fn()> = Box::new(move || activations.set(activations.get() + 1));
+                MenuItem { label: format!("Item {}", index + 1), action: enabled.then_some(action), enabled: *enabled }
+            })
+            .collect();
+        let navigation = Arc::new(Mutex::new(Navigation::new(AppState::SMenu)));
+        create_app_instance(navigation, load_menu_settings(), menu_items)
+    }
+
+    fn pressed(keys: &[egui::Key]) -> egui::InputState {
+        let mut input = egui::InputState::default();
+        for key in keys {
+            input.events.push(egui::Event::Key {
+                key: *key,
+                physical_key: None,
+                pressed: true,
+                repeat: false,
+                modifiers: egui::Modifiers::NONE,
+            });
         }
+        input
+    }
+
+    fn over_menu(hovered: Option<usize>) -> MenuResponse {
+        MenuResponse { hovered, clicked: None, pointer_over_menu: true }
+    }
+
+    #[test]
+    fn selection_wraps_around_disabled_items() {
+        let activations = Rc::new(Cell::new(0));
+        let mut app = app(&[true, false, true, false], &activations);
+        app.move_selection(1);
+        assert_eq!(app.selected_index, 2);
+        // Past the disabled last item back to the first
+        app.move_selection(1);
+        assert_eq!(app.selected_index, 0);
+        app.move_selection(-1);
+        assert_eq!(app.selected_index, 2);
+        assert_eq!(app.menu_state.selected, 2);
+
+        let mut none_enabled = self::app(&[false, false], &activations);
+        none_enabled.move_selection(1);
+        assert_eq!(none_enabled.selected_index, 0);
+    }
+
+    #[test]
+    fn wheel_moves_one_item_per_step() {
+        let activations = Rc::new(Cell::new(0));
+        let mut app = app(&[true, true, true], &activations);
+        // Small deltas add up until they reach a step
+        app.apply_pointer(over_menu(None), -(SCROLL_STEP - 1.0), false);
+        assert_eq!(app.selected_index, 0);
+        app.apply_pointer(over_menu(None), -1.0, false);
+        assert_eq!(app.selected_index, 1);
+        app.apply_pointer(over_menu(None), SCROLL_STEP * 2.0, false);
+        assert_eq!(app.selected_index, 2);
+
+        // Scrolling outside the menu is ignored and forgets the remainder
+        let outside = MenuResponse { pointer_over_menu: false, ..over_menu(None) };
+        app.apply_pointer(over_menu(None), SCROLL_STEP - 1.0, false);
+        app.apply_pointer(outside, SCROLL_STEP, false);
+        app.apply_pointer(over_menu(None), 1.0, false);
+        assert_eq!(app.selected_index, 2);
+    }
+
+    #[test]
+    fn number_keys_only_reach_existing_items() {
+        let activations = Rc::new(Cell::new(0));
+        let mut app = app(&[true, true], &activations);
+        assert!(!handle_input(&pressed(&[egui::Key::Num5]), &mut app));
+        assert_eq!((app.selected_index, activations.get()), (0, 0));
+
+        assert!(handle_input(&pressed(&[egui::Key::Num2]), &mut app));
+        assert_eq!((app.selected_index, activations.get()), (1, 1));
+    }
+
+    #[test]
+    fn hover_does_not_override_a_key_press_in_the_same_frame() {
+        let activations = Rc::new(Cell::new(0));
+        let mut app = app(&[true, true, true], &activations);
+        let moved = handle_input(&pressed(&[egui::Key::ArrowDown]), &mut app);
+        app.apply_pointer(over_menu(Some(2)), 0.0, moved);
+        assert_eq!(app.selected_index, 1);
+
+        // The pointer resting there afterwards does not take it back either
+        app.apply_pointer(over_menu(Some(2)), 0.0, false);
+        assert_eq!(app.selected_index, 1);
+        // Moving it to another item does
+        app.apply_pointer(over_menu(Some(0)), 0.0, false);
+        assert_eq!(app.selected_index, 0);
     }
 }
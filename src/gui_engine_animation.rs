@@ -1,4 +1,4 @@
-use crate::logging;
+use s2o_net_lib::logging;
 use eframe::egui::{self, Painter, Rect, Rgba, Pos2};
 use rand::Rng;
 use rand::prelude::SliceRandom; // Import the SliceRandom trait
@@ -102,8 +102,7 @@ impl AnimationState {
 
 pub fn speedometer(speed: u8) -> f32 {
     // Convert the speed to a factor (e.g., 01-99 maps to 0.01-0.99)
-    let factor = speed as f32 / 100.0;
-    factor
+    speed as f32 / 100.0
 }
 
 pub fn init_module() -> Result<(), String> {
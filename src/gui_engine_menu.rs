@@ -1,11 +1,19 @@
-use crate::logging;
-use crate::app_state::AppState;
-use eframe::egui::{self, RichText, CentralPanel, Align2, Area, Id, Context};
+use s2o_net_lib::logging;
+use crate::app_state::{AppState, SharedNavigation};
+use crate::gui_engine_screens::{self, MenuAction};
+use eframe::egui::{self, RichText, CentralPanel, Align2, Area, Id, Context, CursorIcon, Sense};
 use std::time::{Instant, Duration};
 
+// Colour of items whose enabled predicate is false
+const DISABLED_COLOR: egui::Color32 = egui::Color32::from_gray(110);
+// Controls legend in the bottom left corner
+const KEY_HINTS: &str = "↑/↓ or wheel: select   Enter or click: open   1-9: shortcut   Esc: back";
+
 pub struct MenuItem {
     pub label: String,
     pub action: Option<Box<dyn Fn() + 'static>>,
+    // Disabled items are greyed out and have no action
+    pub enabled: bool,
 }
 
 pub struct MenuState {
@@ -50,41 +58,98 @@ impl MenuState {
     }
 }
 
+// What the pointer did to the menu this frame, applied by the app so mouse
+// and keyboard share MenuState::selected
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MenuResponse {
+    pub hovered: Option<usize>,
+    pub clicked: Option<usize>,
+    // Whether the pointer is over the item list, so wheel scrolling elsewhere
+    // (e.g. the connections table) does not move the selection
+    pub pointer_over_menu: bool,
+}
+
+// Digit key that activates the item at `index`, for the first nine items
+pub fn shortcut_key(index: usize) -> Option<egui::Key> {
+    const KEYS: [egui::Key; 9] = [
+        egui::Key::Num1,
+        egui::Key::Num2,
+        egui::Key::Num3,
+        egui::Key::Num4,
+        egui::Key::Num5,
+        egui::Key::Num6,
+        egui::Key::Num7,
+        egui::Key::Num8,
+        egui::Key::Num9,
+    ];
+    KEYS.get(index).copied()
+}
+
 pub fn render_menu(
     ctx: &Context,
     title: &str,
+    breadcrumbs: &[&str],
     menu_items: &[MenuItem],
     menu_state: &MenuState,
     is_elevated: bool,
     runtime: String,
-) {
+) -> MenuResponse {
     logging::debug_info("Rendering menu");
     let settings = &menu_state.settings;
     let selected_index = menu_state.selected;
+    let mut response = MenuResponse::default();
 
     CentralPanel::default()
         .frame(egui::Frame::none()) // Make the menu panel transparent
         .show(ctx, |ui| {
             Area::new(Id::new("title_area"))
                 .anchor(Align2::CENTER_TOP, (0.0, 50.0))
-                .show(&ui.ctx(), |ui| {
-                    ui.heading(settings.apply_title(title));
+                .show(ui.ctx(), |ui| {
+                    ui.vertical_centered(|ui| {
+                        // Path from the top level screen, shown once there is one
+                        if breadcrumbs.len() > 1 {
+                            ui.label(RichText::new(breadcrumbs.join(" › ")).color(settings.option_color_unselected));
+                        }
+                        ui.heading(settings.apply_title(title));
+                    });
                 });
 
-            Area::new(Id::new("menu_area"))
+            let menu_area = Area::new(Id::new("menu_area"))
                 .anchor(Align2::CENTER_CENTER, (0.0, 0.0))
-                .show(&ui.ctx(), |ui| {
+                .show(ui.ctx(), |ui| {
                     ui.vertical_centered(|ui| {
                         for (index, item) in menu_items.iter().enumerate() {
                             let selected = index == selected_index;
-                            ui.label(settings.apply_label(&item.label, selected));
+                            let label = settings.apply_label(&item.label, selected);
+                            let label = if item.enabled { label } else { label.color(DISABLED_COLOR) };
+                            let mut item_response = ui.add(egui::Label::new(label).selectable(false).sense(Sense::click()));
+                            if let Some(key) = shortcut_key(index) {
+                                item_response = item_response.on_hover_text(format!("Shortcut: {}", key.symbol_or_name()));
+                            }
+                            if item.enabled {
+                                item_response = item_response.on_hover_cursor(CursorIcon::PointingHand);
+                            }
+                            if item_response.hovered() {
+                                response.hovered = Some(index);
+                            }
+                            // A tap on a touch screen arrives as a click too
+                            if item_response.clicked() {
+                                response.clicked = Some(index);
+                            }
                         }
                     });
                 });
+            response.pointer_over_menu = menu_area.response.contains_pointer();
+
+            Area::new(Id::new("hint_area"))
+                .anchor(Align2::LEFT_BOTTOM, (10.0, -10.0))
+                .show(ui.ctx(), |ui| {
+                    ui.label(RichText::new(KEY_HINTS).color(DISABLED_COLOR).font(egui::FontId::proportional(14.0)));
+                });
 
             Area::new(Id::new("status_area"))
                 .anchor(Align2::RIGHT_BOTTOM, (-10.0, -10.0))
-                .show(&ui.ctx(), |ui| {
+                .show(ui.ctx(), |ui| {
                     ui.horizontal(|ui| {
                         ui.label(
                             RichText::new("■")
@@ -99,48 +164,59 @@ pub fn render_menu(
                 });
         });
     logging::debug_info("Menu rendered successfully");
+    response
+}
+
+// Menu items for the screen that is currently shown, used for keyboard actions
+pub fn menu_items_for_state(navigation: &SharedNavigation) -> Vec<MenuItem> {
+    let (app_state, can_go_back) = {
+        let navigation = navigation.lock().unwrap();
+        (navigation.current(), navigation.can_go_back())
+    };
+    gui_engine_screens::entries(app_state, can_go_back)
+        .into_iter()
+        .map(|entry| {
+            let enabled = entry.is_enabled();
+            let navigation = navigation.clone();
+            let action: Box<dyn Fn() + 'static> = match entry.action {
+                MenuAction::Open(state) => Box::new(move || gui_engine_screens::open(state, &navigation)),
+                MenuAction::Back => Box::new(move || gui_engine_screens::back(&navigation)),
+                MenuAction::Run(action) => action,
+                MenuAction::Exit => Box::new(move || gui_engine_screens::open(AppState::ExitConfirm, &navigation)),
+            };
+            MenuItem { label: entry.label, action: enabled.then_some(action), enabled }
+        })
+        .collect()
 }
 
+// Draws the current screen with the navigation history as its breadcrumb
 pub fn render_app_state(
     ctx: &Context,
-    app_state: &AppState,
+    navigation: &SharedNavigation,
     menu_state: &MenuState,
     is_elevated: bool,
     runtime: String,
-    set_app_state: impl Fn(AppState) + 'static + Clone,
-) {
-    let set_app_state = set_app_state.clone();
+) -> MenuResponse {
+    let (app_state, history) = {
+        let navigation = navigation.lock().unwrap();
+        (navigation.current(), navigation.history().to_vec())
+    };
     logging::debug_info(&format!("Rendering app state: {:?}", app_state));
-    match app_state {
-        AppState::SMenu => {
-            render_menu(
-                ctx,
-                "Security Menu",
-                &crate::s_menu::menu_items(set_app_state.clone(), &menu_state.get_settings()),
-                menu_state,
-                is_elevated,
-                runtime,
-            );
-        }
-        AppState::PMenu => {
-            render_menu(
-                ctx,
-                "P Menu",
-                &crate::p_menu::menu_items(set_app_state.clone(), &menu_state.get_settings()),
-                menu_state,
-                is_elevated,
-                runtime,
-            );
-        }
-        AppState::PCMenu => {
-            // Add rendering for PCMenu
-        }
-        AppState::NSMenu => {
-            // Add rendering for NSMenu
-        }
-        AppState::DSMenu => {
-            // Add rendering for DSMenu
-        }
+    let Some(screen) = gui_engine_screens::screen(app_state) else {
+        return MenuResponse::default();
+    };
+    let response = render_menu(
+        ctx,
+        screen.title,
+        &gui_engine_screens::breadcrumbs(&history),
+        &menu_items_for_state(navigation),
+        menu_state,
+        is_elevated,
+        runtime,
+    );
+    if let Some(render) = screen.render {
+        render(ctx, menu_state);
     }
     logging::debug_info("App state rendered successfully");
+    response
 }
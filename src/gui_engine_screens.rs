@@ -0,0 +1,195 @@
+use s2o_net_lib::logging;
+use crate::app_state::{AppState, SharedNavigation};
+use crate::gui_engine_menu::MenuState;
+use s2o_net_lib::initialization;
+use eframe::egui::Context;
+use once_cell::sync::Lazy;
+
+pub fn init_module() -> Result<(), String> {
+    let initialization_passed = !SCREENS.is_empty();
+    if initialization_passed {
+        logging::debug_info(&format!("gui_engine_screens module is online ({} screens)", SCREENS.len()));
+        Ok(())
+    } else {
+        Err("gui_engine_screens module initialization failed".to_string())
+    }
+}
+
+// What activating a menu item does
+pub enum MenuAction {
+    // Switch to another screen
+    Open(AppState),
+    Run(Box<dyn Fn() + 'static>),
+    // Return to the previous screen
+    Back,
+    // Ask for confirmation, then shut down
+    Exit,
+}
+
+// One declared menu item. Items whose `enabled` predicate is false are
+// drawn greyed out and do nothing.
+pub struct MenuEntry {
+    pub label: String,
+    pub action: MenuAction,
+    pub enabled: Option<fn() -> bool>,
+}
+
+impl MenuEntry {
+    pub fn open(label: impl Into<String>, state: AppState) -> Self {
+        MenuEntry { label: label.into(), action: MenuAction::Open(state), enabled: None }
+    }
+
+    pub fn run(label: impl Into<String>, action: impl Fn() + 'static) -> Self {
+        MenuEntry { label: label.into(), action: MenuAction::Run(Box::new(action)), enabled: None }
+    }
+
+    pub fn enabled_if(mut self, enabled: fn() -> bool) -> Self {
+        self.enabled = Some(enabled);
+        self
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.is_none_or(|enabled| enabled())
+    }
+}
+
+// A menu screen. `items` is called every frame so labels and predicates can
+// follow background state; Back and Exit are appended automatically.
+pub struct Screen {
+    pub state: AppState,
+    pub title: &'static str,
+    pub items: fn() -> Vec<MenuEntry>,
+    // Called when the screen is opened, e.g. to reload what it lists
+    pub on_enter: Option<fn()>,
+    // Widgets drawn around the menu, such as text boxes and tables
+    pub render: Option<fn(&Context, &MenuState)>,
+}
+
+// Every screen, registered once
+static SCREENS: Lazy<Vec<Screen>> = Lazy::new(|| {
+    vec![
+        crate::s_menu::screen(),
+        crate::p_menu::screen(),
+        crate::pc_menu::screen(),
+        crate::ds_menu::screen(),
+        crate::ns_menu::screen(),
+        crate::ns_menu::firewall_screen(),
+        crate::ns_menu::interface_screen(),
+        crate::ns_menu::connections_screen(),
+        exit_screen(),
+    ]
+});
+
+fn find(state: AppState) -> Option<&'static Screen> {
+    SCREENS.iter().find(|screen| screen.state == state)
+}
+
+pub fn screen(state: AppState) -> Option<&'static Screen> {
+    let screen = find(state);
+    if screen.is_none() {
+        logging::debug_error(&format!("No screen registered for {:?}", state));
+    }
+    screen
+}
+
+// Asked before quitting so a stray Enter does not end a capture
+fn exit_screen() -> Screen {
+    Screen {
+        state: AppState::ExitConfirm,
+        title: "Exit?",
+        items: || {
+            vec![
+                MenuEntry::run("Exit", exit),
+                MenuEntry { label: "Cancel".to_string(), action: MenuAction::Back, enabled: None },
+            ]
+        },
+        on_enter: None,
+        render: None,
+    }
+}
+
+// Titles of the screens in the navigation history, for the breadcrumb
+pub fn breadcrumbs(history: &[AppState]) -> Vec<&'static str> {
+    history.iter().filter_map(|state| find(*state)).map(|screen| screen.title).collect()
+}
+
+// The screen's own items followed by Back (when there is somewhere to go
+// back to) and Exit. The exit confirmation has only its own items.
+pub fn entries(state: AppState, can_go_back: bool) -> Vec<MenuEntry> {
+    let Some(screen) = screen(state) else {
+        return Vec::new();
+    };
+    let mut entries = (screen.items)();
+    if state == AppState::ExitConfirm {
+        return entries;
+    }
+    if can_go_back {
+        entries.push(MenuEntry { label: "Back".to_string(), action: MenuAction::Back, enabled: None });
+    }
+    entries.push(MenuEntry { label: "Exit".to_string(), action: MenuAction::Exit, enabled: None });
+    entries
+}
+
+// Switches to `state`, running its on_enter hook first
+pub fn open(state: AppState, navigation: &SharedNavigation) {
+    if let Some(on_enter) = screen(state).and_then(|screen| screen.on_enter) {
+        on_enter();
+    }
+    navigation.lock().unwrap().push(state);
+}
+
+// Returns to the previous screen; does nothing on the start screen
+pub fn back(navigation: &SharedNavigation) {
+    if !navigation.lock().unwrap().back() {
+        logging::debug_info("Already on the start screen");
+    }
+}
+
+// Stops the background workers before the process ends
+pub fn exit() {
+    initialization::s2o_shutdown();
+    std::process::exit(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_state_has_a_screen() {
+        let states = [
+            AppState::SMenu,
+            AppState::PMenu,
+            AppState::PCMenu,
+            AppState::NSMenu,
+            AppState::DSMenu,
+            AppState::FirewallMenu,
+            AppState::InterfaceMenu,
+            AppState::ConnectionsMenu,
+            AppState::ExitConfirm,
+        ];
+        for state in states {
+            let screen = find(state).unwrap_or_else(|| panic!("{:?} has no screen", state));
+            assert_eq!(screen.state, state);
+        }
+        assert_eq!(SCREENS.len(), states.len());
+    }
+
+    #[test]
+    fn adds_back_and_exit_and_breadcrumbs() {
+        let history = [AppState::SMenu, AppState::PMenu, AppState::NSMenu, AppState::InterfaceMenu];
+        assert_eq!(breadcrumbs(&history), ["Security Menu", "P Menu", "NS Menu", "Interface Menu"]);
+
+        let labels: Vec<String> = entries(AppState::PMenu, false).into_iter().map(|entry| entry.label).collect();
+        assert_eq!(labels, ["PC Menu", "DS Menu", "NS Menu", "Exit"]);
+        let entries_pc = entries(AppState::PCMenu, true);
+        let back = &entries_pc[entries_pc.len() - 2];
+        assert!(back.label == "Back" && matches!(back.action, MenuAction::Back));
+        assert!(matches!(entries_pc.last().unwrap().action, MenuAction::Exit));
+        // Nothing is capturing in tests, so Stop Capture is disabled
+        assert!(!entries_pc.iter().find(|entry| entry.label == "Stop Capture").unwrap().is_enabled());
+
+        let labels: Vec<String> = entries(AppState::ExitConfirm, true).into_iter().map(|entry| entry.label).collect();
+        assert_eq!(labels, ["Exit", "Cancel"]);
+    }
+}
@@ -0,0 +1,53 @@
+use crate::app_state;
+use crate::gui_engine;
+use crate::gui_engine_animation;
+use crate::gui_engine_screens;
+use crate::s_menu;
+use crate::p_menu;
+use crate::pc_menu;
+use crate::ds_menu;
+use crate::ns_menu;
+use s2o_net_lib::initialization;
+use s2o_net_lib::logging;
+
+pub fn s2o_bootup() -> Result<(), String> {
+    // Print a message to indicate the initialization process has started
+    logging::debug_info("Initialization is being called");
+
+    // Step 1: Check the library modules, then the GUI ones
+    initialization::s2o_bootup_headless()?;
+    check_modules()?;
+
+    // Step 2: Set up configurations
+    if let Err(e) = setup_configurations() {
+        return Err(format!("Configuration setup failed: {}", e));
+    }
+
+    logging::debug_info("Initialization complete");
+    gui_engine::start_gui();
+
+    Ok(())
+}
+
+fn check_modules() -> Result<(), String> {
+    app_state::init_module()?;
+    gui_engine::init_module()?;
+    gui_engine_animation::init_module()?;
+    gui_engine_screens::init_module()?;
+    s_menu::init_module()?;
+    p_menu::init_module()?;
+    pc_menu::init_module()?;
+    ns_menu::init_module()?;
+    ds_menu::init_module()?;
+
+    logging::debug_info("All GUI modules initialized successfully");
+
+    Ok(())
+}
+
+fn setup_configurations() -> Result<(), String> {
+    // Load fonts
+    gui_engine::load_fonts()?;
+    logging::debug_info("Fonts setup complete.");
+    Ok(())
+}
@@ -1,45 +1,58 @@
 use crate::admin_check;
 use crate::logging;
-use crate::app_state;
-use crate::gui_engine;
-use crate::gui_engine_animation;
-use crate::s_menu;
-use crate::p_menu;
-use crate::pc_menu;
-use crate::ds_menu;
-use crate::ns_menu;
-
-
-
-pub fn s2o_bootup() -> Result<(), String> {
-    // Print a message to indicate the initialization process has started
-    logging::debug_info("Initialization is being called");
-
-    
-    // Step 1: Check modules
+use crate::capture_backend;
+use crate::packet_capture;
+use crate::pcap_reader;
+use crate::pcap_writer;
+use crate::packet_decoder;
+use crate::app_dissector;
+use crate::flow_table;
+use crate::packet_store;
+use crate::capture_filter;
+use crate::firewall;
+use crate::inline_verdict;
+use crate::linux_firewall;
+use crate::windows_firewall;
+use crate::rule_store;
+use crate::process_attribution;
+use crate::throughput_monitor;
+use crate::speed_test;
+use crate::diagnostics;
+use crate::interface_inventory;
+use crate::connections;
+
+
+
+// Checks every library module; the GUI binary runs this
+// before its own checks
+pub fn s2o_bootup_headless() -> Result<(), String> {
+    logging::debug_info("Headless initialization is being called");
     check_modules()?;
-
-    // Step 2: Set up configurations
-    if let Err(e) = setup_configurations() {
-        return Err(format!("Configuration setup failed: {}", e));
-    }
-
-    // Step 3: Check environment settings
     if let Err(e) = check_environment() {
         return Err(format!("Environment check failed: {}", e));
     }
-
-    // Step 4: Initialize core components
     if let Err(e) = initialize_components() {
         return Err(format!("Component initialization failed: {}", e));
     }
-
-    logging::debug_info("Initialization complete");
-    gui_engine::start_gui();
-
+    logging::debug_info("Headless initialization complete");
     Ok(())
 }
 
+// Stops every background worker so capture handles and sockets are closed
+// before the process ends
+pub fn s2o_shutdown() {
+    logging::debug_info("Shutdown is being called");
+    if packet_capture::is_capturing() {
+        if let Err(e) = packet_capture::stop_capture() {
+            logging::debug_error(&format!("Failed to stop capture: {}", e));
+        }
+    }
+    process_attribution::stop();
+    throughput_monitor::stop();
+    speed_test::stop_server();
+    logging::debug_info("Shutdown complete");
+}
+
 fn check_modules() -> Result<(), String> {
     if let Err(e) = logging::init_module() {
         logging::debug_info("Failed to initialize logging module...");
@@ -47,16 +60,31 @@ fn check_modules() -> Result<(), String> {
     }
 
     admin_check::init_module()?;
-    app_state::init_module()?;
-    gui_engine::init_module()?;
-	gui_engine_animation::init_module()?;
-    s_menu::init_module()?;
-    p_menu::init_module()?;
-    pc_menu::init_module()?;
-    ns_menu::init_module()?;
-    ds_menu::init_module()?;
-    // packet_capture::init_module()?;
-    // nc::init_module()?;
+    capture_backend::init_module()?;
+    packet_capture::init_module()?;
+    pcap_reader::init_module()?;
+    pcap_writer::init_module()?;
+    packet_decoder::init_module()?;
+    app_dissector::init_module()?;
+    flow_table::init_module()?;
+    packet_store::init_module()?;
+    capture_filter::init_module()?;
+    firewall::init_module()?;
+    inline_verdict::init_module()?;
+    linux_firewall::init_module()?;
+    windows_firewall::init_module()?;
+    rule_store::init_module()?;
+    process_attribution::init_module()?;
+    throughput_monitor::init_module()?;
+    speed_test::init_module()?;
+    diagnostics::init_module()?;
+    interface_inventory::init_module()?;
+    connections::init_module()?;
+    crate::checksum::init_module()?;
+    #[cfg(windows)]
+    crate::nc::init_module()?;
+    #[cfg(target_os = "linux")]
+    crate::linux_capture::init_module()?;
     // module_12::init_module()?;
     // module_13::init_module()?;
     // module_14::init_module()?;
@@ -68,19 +96,6 @@ fn check_modules() -> Result<(), String> {
     Ok(())
 }
 
-fn setup_configurations() -> Result<(), String> {
-    // Add your configuration setup logic here
-	
-	// Load fonts
-    gui_engine::load_fonts()?;
-    logging::debug_info("Fonts setup complete.");
-
-    
-    logging::debug_info("Check 1");
-    // Simulate configuration setup
-    Ok(())
-}
-
 fn check_environment() -> Result<(), String> {
     // Add your environment check logic here
     logging::debug_info("Check 2");
@@ -0,0 +1,427 @@
+use crate::logging;
+use crate::checksum::{checksum_add, checksum_finish};
+use crate::capture_backend::{CaptureBackend, CapturedPacket, LinkType, PacketMeta};
+use crate::firewall::{self, Decision, PacketContext, Verdict};
+use crate::packet_decoder::{self, Packet, ETHERTYPE_QINQ, ETHERTYPE_VLAN, IP_PROTO_ICMP, IP_PROTO_ICMPV6, IP_PROTO_TCP, IP_PROTO_UDP, SLL_HEADER_LEN};
+use crate::pcap_reader::PcapReader;
+use crate::pcap_writer::{self, ExportFormat};
+use crate::process_attribution::{self, ProcessInfo};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static REPLAY_RUNNING: AtomicBool = AtomicBool::new(false);
+
+pub fn init_module() -> Result<(), String> {
+    // Placeholder for actual initialization logic
+    let initialization_passed = true;
+
+    if initialization_passed {
+        logging::debug_info("inline_verdict module is online");
+        Ok(())
+    } else {
+        Err("inline_verdict module initialization failed".to_string())
+    }
+}
+
+// What happens to a diverted packet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PacketVerdict {
+    Accept,
+    Drop,
+    // Send these bytes instead; lengths and checksums are fixed up before sending
+    Rewrite(Vec<u8>),
+}
+
+// Decides each packet in inline mode. Gets the raw bytes as well as the
+// decoded view so a rewrite can start from a copy of the original, and the
+// firewall's verdict for the packet, which has already been evaluated.
+pub type VerdictFn = Box<dyn FnMut(&PacketMeta, &[u8], &Packet, &Verdict) -> PacketVerdict + Send>;
+
+// Drops whatever the firewall rule set denies
+pub fn firewall_verdict() -> VerdictFn {
+    Box::new(|_meta, _data, _decoded, evaluation| from_decision(evaluation.decision))
+}
+
+// Looks up the packet's owner and runs it through the firewall rules; the
+// capture thread and the offline replay share this
+pub(crate) fn evaluate(decoded: &Packet, outbound: bool) -> (Option<ProcessInfo>, Verdict) {
+    let owner = process_attribution::lookup(decoded, outbound);
+    let process = owner.as_ref().map(|p| p.path.as_deref().unwrap_or(&p.name));
+    let ctx = PacketContext { packet: decoded, outbound, process };
+    let evaluation = firewall::FIREWALL.lock().unwrap().evaluate(&ctx);
+    (owner, evaluation)
+}
+
+fn from_decision(decision: Decision) -> PacketVerdict {
+    match decision {
+        Decision::Allow => PacketVerdict::Accept,
+        Decision::Deny => PacketVerdict::Drop,
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InlineStats {
+    pub accepted: u64,
+    pub dropped: u64,
+    pub rewritten: u64,
+    pub send_errors: u64,
+}
+
+impl InlineStats {
+    fn record(&mut self, verdict: &PacketVerdict) {
+        match verdict {
+            PacketVerdict::Accept => self.accepted += 1,
+            PacketVerdict::Drop => self.dropped += 1,
+            PacketVerdict::Rewrite(_) => self.rewritten += 1,
+        }
+    }
+}
+
+// Offset of the IP header inside a frame of the given link type
+fn ip_offset(data: &[u8], link_type: LinkType) -> Result<usize, String> {
+    match link_type {
+        LinkType::RawIp => Ok(0),
+        LinkType::LinuxSll if data.len() >= SLL_HEADER_LEN => Ok(SLL_HEADER_LEN),
+        LinkType::LinuxSll => Err("Truncated Linux cooked header".to_string()),
+        LinkType::Ethernet => {
+            let mut offset = 12;
+            loop {
+                let ethertype = data
+                    .get(offset..offset + 2)
+                    .map(|b| u16::from_be_bytes([b[0], b[1]]))
+                    .ok_or("Truncated Ethernet header")?;
+                if ethertype != ETHERTYPE_VLAN && ethertype != ETHERTYPE_QINQ {
+                    return Ok(offset + 2);
+                }
+                offset += 4;
+            }
+        }
+    }
+}
+
+// Fills in the transport checksum of `segment`. `pseudo_sum` is the partial
+// sum of the pseudo-header, or None for ICMPv4 which does not use one.
+fn fix_transport_checksum(protocol: u8, segment: &mut [u8], pseudo_sum: Option<u32>) {
+    let field = match protocol {
+        IP_PROTO_TCP => 16,
+        IP_PROTO_UDP => 6,
+        IP_PROTO_ICMP | IP_PROTO_ICMPV6 => 2,
+        _ => return,
+    };
+    if segment.len() < field + 2 {
+        return;
+    }
+    if protocol == IP_PROTO_UDP {
+        let len = segment.len() as u16;
+        segment[4..6].copy_from_slice(&len.to_be_bytes());
+    }
+    segment[field..field + 2].copy_from_slice(&[0, 0]);
+    let sum = checksum_add(pseudo_sum.unwrap_or(0), segment);
+    let mut checksum = checksum_finish(sum);
+    // A zero UDP checksum means "none", so send the equivalent all-ones value
+    if protocol == IP_PROTO_UDP && checksum == 0 {
+        checksum = 0xffff;
+    }
+    segment[field..field + 2].copy_from_slice(&checksum.to_be_bytes());
+}
+
+// IP packet length the header of `original` claims, if it has the same IP
+// version at the same offset
+fn original_ip_len(original: &[u8], offset: usize, version: u8) -> Option<usize> {
+    let ip = original.get(offset..)?;
+    if ip.first()? >> 4 != version {
+        return None;
+    }
+    match version {
+        4 => ip.get(2..4).map(|len| u16::from_be_bytes([len[0], len[1]]) as usize),
+        _ => ip.get(4..6).map(|len| 40 + u16::from_be_bytes([len[0], len[1]]) as usize),
+    }
+}
+
+// Updates the IP length of a rewrite of `original` and recomputes the IPv4
+// header and TCP/UDP/ICMP checksums. The new length is the original one plus
+// however much the rewrite grew or shrank, so link layer padding after the
+// IP packet stays out of it. Fragments keep their transport checksum, since
+// it covers bytes that are not in this packet.
+pub(crate) fn recalculate_checksums(data: &mut [u8], original: &[u8], link_type: LinkType) -> Result<(), String> {
+    let offset = ip_offset(data, link_type)?;
+    let delta = data.len() as isize - original.len() as isize;
+    let ip = data.get_mut(offset..).ok_or("Truncated packet")?;
+    let ip_len = |version: u8, min: usize, available: usize| {
+        original_ip_len(original, offset, version)
+            .map_or(available, |len| (len as isize + delta).clamp(min as isize, available as isize) as usize)
+    };
+    match ip.first().map(|b| b >> 4) {
+        Some(4) => {
+            let header_len = ((ip[0] & 0x0f) as usize) * 4;
+            if header_len < 20 || ip.len() < header_len {
+                return Err("Invalid IPv4 header length".to_string());
+            }
+            let total = ip_len(4, header_len, ip.len());
+            let ip = &mut ip[..total];
+            let total_len = u16::try_from(total).map_err(|_| "Packet too large for IPv4")?;
+            ip[2..4].copy_from_slice(&total_len.to_be_bytes());
+            ip[10..12].copy_from_slice(&[0, 0]);
+            let checksum = checksum_finish(checksum_add(0, &ip[..header_len]));
+            ip[10..12].copy_from_slice(&checksum.to_be_bytes());
+
+            let fragmented = u16::from_be_bytes([ip[6], ip[7]]) & 0x3fff != 0;
+            if !fragmented {
+                let protocol = ip[9];
+                let segment_len = (ip.len() - header_len) as u32;
+                let pseudo = (protocol != IP_PROTO_ICMP)
+                    .then(|| checksum_add(protocol as u32 + segment_len, &ip[12..20]));
+                let (_, segment) = ip.split_at_mut(header_len);
+                fix_transport_checksum(protocol, segment, pseudo);
+            }
+            Ok(())
+        }
+        Some(6) => {
+            if ip.len() < 40 {
+                return Err("Truncated IPv6 header".to_string());
+            }
+            let end = ip_len(6, 40, ip.len());
+            let ip = &mut ip[..end];
+            let payload_len = u16::try_from(ip.len() - 40).map_err(|_| "Packet too large for IPv6")?;
+            ip[4..6].copy_from_slice(&payload_len.to_be_bytes());
+
+            // Walk the extension headers to the upper layer protocol
+            let mut next = ip[6];
+            let mut header_end = 40;
+            loop {
+                let ext_len = match next {
+                    0 | 43 | 60 => ip.get(header_end + 1).map(|len| (*len as usize + 1) * 8),
+                    51 => ip.get(header_end + 1).map(|len| (*len as usize + 2) * 4),
+                    // Fragment header
+                    44 => return Ok(()),
+                    _ => break,
+                }
+                .ok_or("Truncated IPv6 extension header")?;
+                next = ip[header_end];
+                header_end += ext_len;
+                if header_end > ip.len() {
+                    return Err("Truncated IPv6 extension header".to_string());
+                }
+            }
+
+            let segment_len = (ip.len() - header_end) as u32;
+            let pseudo = checksum_add(next as u32 + segment_len, &ip[8..40]);
+            let (_, segment) = ip.split_at_mut(header_end);
+            fix_transport_checksum(next, segment, Some(pseudo));
+            Ok(())
+        }
+        _ => Err("Not an IP packet".to_string()),
+    }
+}
+
+// Carries out a verdict on `backend`: accepted packets go back unchanged,
+// rewrites are fixed up first and dropped packets are simply not sent
+pub fn apply_verdict(
+    backend: &mut dyn CaptureBackend,
+    verdict: PacketVerdict,
+    data: &[u8],
+    link_type: LinkType,
+) -> Result<(), String> {
+    match verdict {
+        PacketVerdict::Accept => backend.send(data, false),
+        PacketVerdict::Drop => Ok(()),
+        PacketVerdict::Rewrite(mut rewritten) => {
+            recalculate_checksums(&mut rewritten, data, link_type)?;
+            backend.send(&rewritten, true)
+        }
+    }
+}
+
+// Runs `verdict` over every packet of a capture file and writes what would
+// have been reinjected to `output`, so verdict logic can be tried offline
+pub fn replay_inline(input: &Path, output: &Path, verdict: &mut VerdictFn) -> Result<InlineStats, String> {
+    let mut reader = PcapReader::open(input)?;
+    let mut stats = InlineStats::default();
+    let mut reinjected = Vec::new();
+
+    while let Some(packet) = reader.next_packet()? {
+        let decoded = packet_decoder::decode(&packet.data, packet.meta.link_type);
+        let (_, evaluation) = evaluate(&decoded, packet.meta.outbound);
+        let result = verdict(&packet.meta, &packet.data, &decoded, &evaluation);
+        stats.record(&result);
+        match result {
+            PacketVerdict::Accept => reinjected.push(packet),
+            PacketVerdict::Drop => {}
+            PacketVerdict::Rewrite(mut data) => match recalculate_checksums(&mut data, &packet.data, packet.meta.link_type) {
+                Ok(()) => {
+                    let meta = PacketMeta { captured_len: data.len(), original_len: data.len(), ..packet.meta };
+                    reinjected.push(CapturedPacket::new(meta, data));
+                }
+                Err(e) => {
+                    logging::debug_error(&format!("Rewritten packet not sent: {}", e));
+                    stats.send_errors += 1;
+                }
+            },
+        }
+    }
+
+    let file = File::create(output).map_err(|e| format!("Failed to create {}: {}", output.display(), e))?;
+    pcap_writer::write_capture(BufWriter::new(file), &reinjected, ExportFormat::from_path(output))?;
+    logging::debug_info(&format!("Inline replay of {}: {:?}", input.display(), stats));
+    Ok(stats)
+}
+
+// Runs replay_inline on a worker thread so large files do not stall the caller
+pub fn start_replay_inline(input: PathBuf, output: PathBuf, mut verdict: VerdictFn) {
+    if REPLAY_RUNNING.swap(true, Ordering::SeqCst) {
+        return logging::debug_error("Verdict replay already running.");
+    }
+    std::thread::spawn(move || {
+        if let Err(e) = replay_inline(&input, &output, &mut verdict) {
+            logging::debug_error(&format!("Firewall verdict replay failed: {}", e));
+        }
+        REPLAY_RUNNING.store(false, Ordering::SeqCst);
+    });
+}
+
+pub fn is_replaying() -> bool {
+    REPLAY_RUNNING.load(Ordering::SeqCst)
+}
+
+// Counts the verdict and applies it, logging rather than failing on send errors
+pub(crate) fn handle_packet(
+    backend: &mut dyn CaptureBackend,
+    stats: &mut InlineStats,
+    verdict: PacketVerdict,
+    data: &[u8],
+    link_type: LinkType,
+) {
+    stats.record(&verdict);
+    if let Err(e) = apply_verdict(backend, verdict, data, link_type) {
+        stats.send_errors += 1;
+        logging::debug_error(&format!("Failed to reinject packet: {}", e));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pcap_reader::PcapReader;
+    use std::time::UNIX_EPOCH;
+
+    // IPv4 UDP 10.0.0.1:5000 -> 10.0.0.2:53 with zeroed checksums
+    fn udp_packet(payload: &[u8]) -> Vec<u8> {
+        let mut ip = vec![0x45, 0, 0, 0, 0, 1, 0, 0, 64, IP_PROTO_UDP, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2];
+        ip[2..4].copy_from_slice(&(28 + payload.len() as u16).to_be_bytes());
+        ip.extend_from_slice(&5000u16.to_be_bytes());
+        ip.extend_from_slice(&53u16.to_be_bytes());
+        ip.extend_from_slice(&(8 + payload.len() as u16).to_be_bytes());
+        ip.extend_from_slice(&[0, 0]);
+        ip.extend_from_slice(payload);
+        ip
+    }
+
+    fn verify(sum_over: &[u8], pseudo: u32) -> bool {
+        checksum_finish(checksum_add(pseudo, sum_over)) == 0
+    }
+
+    #[test]
+    fn recalculates_ipv4_udp_checksums_and_lengths() {
+        let mut data = udp_packet(b"hello");
+        let original = data.clone();
+        recalculate_checksums(&mut data, &original, LinkType::RawIp).unwrap();
+        assert_eq!(u16::from_be_bytes([data[2], data[3]]) as usize, data.len());
+        assert_eq!(u16::from_be_bytes([data[24], data[25]]), 13);
+        assert!(verify(&data[..20], 0));
+        let pseudo = checksum_add(IP_PROTO_UDP as u32 + 13, &data[12..20]);
+        assert!(verify(&data[20..], pseudo));
+    }
+
+    #[test]
+    fn recalculates_ipv6_tcp_checksum_over_ethernet() {
+        let mut frame = vec![0u8; 12];
+        frame.extend_from_slice(&0x86ddu16.to_be_bytes());
+        frame.extend_from_slice(&[0x60, 0, 0, 0, 0, 38, IP_PROTO_TCP, 64]);
+        frame.extend_from_slice(&[0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        frame.extend_from_slice(&[0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2]);
+        frame.extend_from_slice(&[0xc3, 0x50, 0x01, 0xbb, 0, 0, 0, 1, 0, 0, 0, 0, 0x50, 0x18, 0xff, 0xff, 0xaa, 0xaa, 0, 0]);
+        frame.extend_from_slice(b"GET / HTTP/1.1\r\n\r\n");
+
+        let original = frame.clone();
+        recalculate_checksums(&mut frame, &original, LinkType::Ethernet).unwrap();
+        let ip = &frame[14..];
+        assert_eq!(u16::from_be_bytes([ip[4], ip[5]]) as usize, ip.len() - 40);
+        let pseudo = checksum_add(IP_PROTO_TCP as u32 + (ip.len() - 40) as u32, &ip[8..40]);
+        assert!(verify(&ip[40..], pseudo));
+    }
+
+    #[test]
+    fn keeps_ethernet_padding_out_of_the_ipv4_length() {
+        // A 28 byte IPv4 packet padded to the 46 byte Ethernet minimum
+        let mut original = vec![0u8; 12];
+        original.extend_from_slice(&0x0800u16.to_be_bytes());
+        original.extend_from_slice(&udp_packet(b""));
+        original.resize(14 + 46, 0);
+
+        let mut same_size = original.clone();
+        same_size[14 + 22..14 + 24].copy_from_slice(&5353u16.to_be_bytes());
+        recalculate_checksums(&mut same_size, &original, LinkType::Ethernet).unwrap();
+        assert_eq!(u16::from_be_bytes([same_size[16], same_size[17]]), 28);
+        assert_eq!(u16::from_be_bytes([same_size[38], same_size[39]]), 8);
+        assert!(verify(&same_size[14..34], 0));
+        let pseudo = checksum_add(IP_PROTO_UDP as u32 + 8, &same_size[26..34]);
+        assert!(verify(&same_size[34..42], pseudo));
+
+        // Growing the frame grows the packet by the same amount
+        let mut grown = original.clone();
+        grown.extend_from_slice(&[0; 4]);
+        recalculate_checksums(&mut grown, &original, LinkType::Ethernet).unwrap();
+        assert_eq!(u16::from_be_bytes([grown[16], grown[17]]), 32);
+    }
+
+    #[test]
+    fn replays_verdicts_from_a_capture_file() {
+        let dir = std::env::temp_dir().join(format!("s2o_inline_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (input, output) = (dir.join("in.pcap"), dir.join("out.pcap"));
+
+        let meta = |len| PacketMeta {
+            timestamp: UNIX_EPOCH,
+            captured_len: len,
+            original_len: len,
+            interface_index: 1,
+            outbound: true,
+            link_type: LinkType::RawIp,
+        };
+        let packets: Vec<CapturedPacket> = [b"keep".as_slice(), b"drop", b"edit"]
+            .iter()
+            .map(|payload| {
+                let data = udp_packet(payload);
+                CapturedPacket::new(meta(data.len()), data)
+            })
+            .collect();
+        pcap_writer::write_capture(File::create(&input).unwrap(), &packets, ExportFormat::Pcap).unwrap();
+
+        // Drop "drop", and redirect "edit" to port 5353 with a longer payload
+        let mut verdict: VerdictFn = Box::new(|_meta, data, decoded, _evaluation| match decoded.payload {
+            b"drop" => PacketVerdict::Drop,
+            b"edit" => {
+                let mut rewritten = data.to_vec();
+                rewritten[22..24].copy_from_slice(&5353u16.to_be_bytes());
+                rewritten.extend_from_slice(b"ed");
+                PacketVerdict::Rewrite(rewritten)
+            }
+            _ => PacketVerdict::Accept,
+        });
+        let stats = replay_inline(&input, &output, &mut verdict).unwrap();
+        assert_eq!(stats, InlineStats { accepted: 1, dropped: 1, rewritten: 1, send_errors: 0 });
+
+        let mut reader = PcapReader::open(&output).unwrap();
+        let first = reader.next_packet().unwrap().unwrap();
+        assert_eq!(first.data, packets[0].data);
+        let edited = reader.next_packet().unwrap().unwrap();
+        let decoded = packet_decoder::decode(&edited.data, LinkType::RawIp);
+        assert_eq!(decoded.ports(), Some((5000, 5353)));
+        assert_eq!(decoded.payload, b"edited");
+        assert!(verify(&edited.data[..20], 0));
+        assert!(reader.next_packet().unwrap().is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
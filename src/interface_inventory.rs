@@ -0,0 +1,454 @@
+use crate::logging;
+use once_cell::sync::Lazy;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::Mutex;
+
+pub fn init_module() -> Result<(), String> {
+    // Placeholder for actual initialization logic
+    let initialization_passed = true;
+
+    if initialization_passed {
+        logging::debug_info("interface_inventory module is online");
+        Ok(())
+    } else {
+        Err("interface_inventory module initialization failed".to_string())
+    }
+}
+
+// Last enumeration shown in the Interface Menu; refreshed on demand since
+// menus are rebuilt every frame
+static INVENTORY: Lazy<Mutex<Option<Vec<NetworkInterface>>>> = Lazy::new(|| Mutex::new(None));
+// Interface the capture backends bind to; None captures on all of them
+static CAPTURE_INTERFACE: Lazy<Mutex<Option<NetworkInterface>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NetworkInterface {
+    pub name: String,
+    pub index: u32,
+    pub mac: Option<[u8; 6]>,
+    pub mtu: Option<u32>,
+    // Addresses with their prefix length
+    pub ipv4: Vec<(Ipv4Addr, u8)>,
+    pub ipv6: Vec<(Ipv6Addr, u8)>,
+    pub up: bool,
+    pub gateways: Vec<IpAddr>,
+    pub dns_servers: Vec<IpAddr>,
+}
+
+impl fmt::Display for NetworkInterface {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (#{}, {})", self.name, self.index, if self.up { "up" } else { "down" })
+    }
+}
+
+impl NetworkInterface {
+    // One line per property, as shown next to the Interface Menu
+    pub fn details(&self) -> Vec<String> {
+        let join = |items: Vec<String>| if items.is_empty() { "-".to_string() } else { items.join(", ") };
+        vec![
+            format!("Name: {}", self.name),
+            format!("Index: {}", self.index),
+            format!("State: {}", if self.up { "up" } else { "down" }),
+            format!("MAC: {}", self.mac.map(|mac| format_mac(&mac)).unwrap_or_else(|| "-".to_string())),
+            format!("MTU: {}", self.mtu.map(|mtu| mtu.to_string()).unwrap_or_else(|| "-".to_string())),
+            format!("IPv4: {}", join(self.ipv4.iter().map(|(addr, prefix)| format!("{}/{}", addr, prefix)).collect())),
+            format!("IPv6: {}", join(self.ipv6.iter().map(|(addr, prefix)| format!("{}/{}", addr, prefix)).collect())),
+            format!("Gateway: {}", join(self.gateways.iter().map(|addr| addr.to_string()).collect())),
+            format!("DNS: {}", join(self.dns_servers.iter().map(|addr| addr.to_string()).collect())),
+        ]
+    }
+}
+
+pub fn format_mac(mac: &[u8; 6]) -> String {
+    mac.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(":")
+}
+
+// Gateways of /proc/net/route, which prints addresses in host byte order
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) fn parse_proc_net_route(text: &str) -> Vec<(String, Ipv4Addr)> {
+    text.lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let gateway = u32::from_str_radix(fields.get(2)?, 16).ok()?;
+            if gateway == 0 {
+                return None;
+            }
+            Some((fields[0].to_string(), Ipv4Addr::from(gateway.to_ne_bytes())))
+        })
+        .collect()
+}
+
+// Next hops of /proc/net/ipv6_route; the loopback's "::" hops are skipped
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) fn parse_proc_net_ipv6_route(text: &str) -> Vec<(String, Ipv6Addr)> {
+    text.lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let next_hop = u128::from_str_radix(fields.get(4)?, 16).ok()?;
+            if next_hop == 0 {
+                return None;
+            }
+            Some((fields.get(9)?.to_string(), Ipv6Addr::from(next_hop)))
+        })
+        .collect()
+}
+
+// Name servers of a resolv.conf, ignoring any %scope suffix
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) fn parse_resolv_conf(text: &str) -> Vec<IpAddr> {
+    text.lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            if words.next()? != "nameserver" {
+                return None;
+            }
+            words.next()?.split('%').next()?.parse().ok()
+        })
+        .collect()
+}
+
+fn prefix_len(mask: &[u8]) -> u8 {
+    mask.iter().map(|b| b.count_ones() as u8).sum()
+}
+
+// Walks getifaddrs for addresses, MACs and flags; the rest comes from
+// /sys, /proc and resolv.conf
+#[cfg(target_os = "linux")]
+pub fn enumerate() -> Result<Vec<NetworkInterface>, String> {
+    use std::collections::BTreeMap;
+    use std::ffi::CStr;
+
+    let mut interfaces: BTreeMap<String, NetworkInterface> = BTreeMap::new();
+    let mut head: *mut libc::ifaddrs = std::ptr::null_mut();
+    if unsafe { libc::getifaddrs(&mut head) } != 0 {
+        return Err(format!("getifaddrs failed: {}", std::io::Error::last_os_error()));
+    }
+    let mut entry = head;
+    while !entry.is_null() {
+        let ifa = unsafe { &*entry };
+        entry = ifa.ifa_next;
+        let name = unsafe { CStr::from_ptr(ifa.ifa_name) }.to_string_lossy().into_owned();
+        let interface = interfaces.entry(name.clone()).or_insert_with(|| NetworkInterface {
+            index: unsafe { libc::if_nametoindex(ifa.ifa_name) },
+            mtu: std::fs::read_to_string(format!("/sys/class/net/{}/mtu", name)).ok().and_then(|mtu| mtu.trim().parse().ok()),
+            up: ifa.ifa_flags & libc::IFF_UP as u32 != 0,
+            name,
+            ..NetworkInterface::default()
+        });
+        if ifa.ifa_addr.is_null() {
+            continue;
+        }
+        unsafe {
+            match (*ifa.ifa_addr).sa_family as libc::c_int {
+                libc::AF_PACKET => {
+                    let ll = &*(ifa.ifa_addr as *const libc::sockaddr_ll);
+                    if ll.sll_halen == 6 && ll.sll_addr[..6].iter().any(|b| *b != 0) {
+                        let mut mac = [0u8; 6];
+                        mac.copy_from_slice(&ll.sll_addr[..6]);
+                        interface.mac = Some(mac);
+                    }
+                }
+                libc::AF_INET => {
+                    let addr = &*(ifa.ifa_addr as *const libc::sockaddr_in);
+                    let prefix = match ifa.ifa_netmask.is_null() {
+                        true => 32,
+                        false => prefix_len(&(*(ifa.ifa_netmask as *const libc::sockaddr_in)).sin_addr.s_addr.to_ne_bytes()),
+                    };
+                    interface.ipv4.push((Ipv4Addr::from(addr.sin_addr.s_addr.to_ne_bytes()), prefix));
+                }
+                libc::AF_INET6 => {
+                    let addr = &*(ifa.ifa_addr as *const libc::sockaddr_in6);
+                    let prefix = match ifa.ifa_netmask.is_null() {
+                        true => 128,
+                        false => prefix_len(&(*(ifa.ifa_netmask as *const libc::sockaddr_in6)).sin6_addr.s6_addr),
+                    };
+                    interface.ipv6.push((Ipv6Addr::from(addr.sin6_addr.s6_addr), prefix));
+                }
+                _ => {}
+            }
+        }
+    }
+    unsafe { libc::freeifaddrs(head) };
+
+    let routes = std::fs::read_to_string("/proc/net/route").unwrap_or_default();
+    let routes6 = std::fs::read_to_string("/proc/net/ipv6_route").unwrap_or_default();
+    let gateways = parse_proc_net_route(&routes)
+        .into_iter()
+        .map(|(name, addr)| (name, IpAddr::V4(addr)))
+        .chain(parse_proc_net_ipv6_route(&routes6).into_iter().map(|(name, addr)| (name, IpAddr::V6(addr))));
+    for (name, gateway) in gateways {
+        if let Some(interface) = interfaces.get_mut(&name) {
+            if !interface.gateways.contains(&gateway) {
+                interface.gateways.push(gateway);
+            }
+        }
+    }
+    // resolv.conf is system wide, so it applies to every interface in use
+    let dns_servers = parse_resolv_conf(&std::fs::read_to_string("/etc/resolv.conf").unwrap_or_default());
+    let mut interfaces: Vec<NetworkInterface> = interfaces.into_values().collect();
+    for interface in interfaces.iter_mut().filter(|i| i.up && i.name != "lo") {
+        interface.dns_servers = dns_servers.clone();
+    }
+    interfaces.sort_by_key(|interface| interface.index);
+    Ok(interfaces)
+}
+
+#[cfg(windows)]
+mod win32 {
+    use std::ffi::c_void;
+
+    pub const AF_UNSPEC: u32 = 0;
+    pub const AF_INET: u16 = 2;
+    pub const AF_INET6: u16 = 23;
+    pub const GAA_FLAG_INCLUDE_GATEWAYS: u32 = 0x80;
+    pub const ERROR_BUFFER_OVERFLOW: u32 = 111;
+    pub const IF_OPER_STATUS_UP: u32 = 1;
+
+    #[repr(C)]
+    pub struct SocketAddress {
+        pub sockaddr: *const u8,
+        pub length: i32,
+    }
+
+    // Shared head of the unicast, DNS server and gateway address lists
+    #[repr(C)]
+    pub struct AddressEntry {
+        pub length: u32,
+        pub flags: u32,
+        pub next: *const AddressEntry,
+        pub address: SocketAddress,
+    }
+
+    #[repr(C)]
+    pub struct UnicastAddress {
+        pub head: AddressEntry,
+        pub prefix_origin: u32,
+        pub suffix_origin: u32,
+        pub dad_state: u32,
+        pub valid_lifetime: u32,
+        pub preferred_lifetime: u32,
+        pub lease_lifetime: u32,
+        pub on_link_prefix_length: u8,
+    }
+
+    // IP_ADAPTER_ADDRESSES_LH up to FirstGatewayAddress
+    #[repr(C)]
+    pub struct AdapterAddresses {
+        pub length: u32,
+        pub if_index: u32,
+        pub next: *const AdapterAddresses,
+        pub adapter_name: *const i8,
+        pub first_unicast: *const UnicastAddress,
+        pub first_anycast: *const c_void,
+        pub first_multicast: *const c_void,
+        pub first_dns_server: *const AddressEntry,
+        pub dns_suffix: *const u16,
+        pub description: *const u16,
+        pub friendly_name: *const u16,
+        pub physical_address: [u8; 8],
+        pub physical_address_length: u32,
+        pub flags: u32,
+        pub mtu: u32,
+        pub if_type: u32,
+        pub oper_status: u32,
+        pub ipv6_if_index: u32,
+        pub zone_indices: [u32; 16],
+        pub first_prefix: *const c_void,
+        pub transmit_link_speed: u64,
+        pub receive_link_speed: u64,
+        pub first_wins_server: *const c_void,
+        pub first_gateway: *const AddressEntry,
+    }
+
+    #[link(name = "iphlpapi")]
+    extern "system" {
+        pub fn GetAdaptersAddresses(
+            family: u32,
+            flags: u32,
+            reserved: *mut c_void,
+            addresses: *mut AdapterAddresses,
+            size: *mut u32,
+        ) -> u32;
+    }
+}
+
+#[cfg(windows)]
+fn socket_address_ip(address: &win32::SocketAddress) -> Option<IpAddr> {
+    if address.sockaddr.is_null() {
+        return None;
+    }
+    let bytes = unsafe { std::slice::from_raw_parts(address.sockaddr, address.length.max(0) as usize) };
+    match u16::from_ne_bytes([*bytes.first()?, *bytes.get(1)?]) {
+        win32::AF_INET => {
+            let octets: [u8; 4] = bytes.get(4..8)?.try_into().ok()?;
+            Some(IpAddr::V4(Ipv4Addr::from(octets)))
+        }
+        win32::AF_INET6 => {
+            let octets: [u8; 16] = bytes.get(8..24)?.try_into().ok()?;
+            Some(IpAddr::V6(Ipv6Addr::from(octets)))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(windows)]
+fn address_list(mut entry: *const win32::AddressEntry) -> Vec<IpAddr> {
+    let mut addrs = Vec::new();
+    while let Some(current) = unsafe { entry.as_ref() } {
+        addrs.extend(socket_address_ip(&current.address));
+        entry = current.next;
+    }
+    addrs
+}
+
+// GetAdaptersAddresses already reports everything, gateways included
+#[cfg(windows)]
+pub fn enumerate() -> Result<Vec<NetworkInterface>, String> {
+    use win32::*;
+
+    let mut size: u32 = 16 * 1024;
+    // u64 storage keeps the adapter structs aligned
+    let mut buffer: Vec<u64>;
+    loop {
+        buffer = vec![0u64; size as usize / 8 + 1];
+        let status = unsafe {
+            GetAdaptersAddresses(
+                AF_UNSPEC,
+                GAA_FLAG_INCLUDE_GATEWAYS,
+                std::ptr::null_mut(),
+                buffer.as_mut_ptr() as *mut AdapterAddresses,
+                &mut size,
+            )
+        };
+        match status {
+            0 => break,
+            ERROR_BUFFER_OVERFLOW => continue,
+            _ => return Err(format!("GetAdaptersAddresses failed with status {}", status)),
+        }
+    }
+
+    let mut interfaces = Vec::new();
+    let mut adapter = buffer.as_ptr() as *const AdapterAddresses;
+    while let Some(current) = unsafe { adapter.as_ref() } {
+        adapter = current.next;
+        let mut name = Vec::new();
+        let mut cursor = current.friendly_name;
+        while !cursor.is_null() && unsafe { *cursor } != 0 {
+            name.push(unsafe { *cursor });
+            cursor = unsafe { cursor.add(1) };
+        }
+        let mut interface = NetworkInterface {
+            name: String::from_utf16_lossy(&name),
+            index: if current.if_index != 0 { current.if_index } else { current.ipv6_if_index },
+            mac: match current.physical_address_length {
+                6 => current.physical_address[..6].try_into().ok(),
+                _ => None,
+            },
+            mtu: Some(current.mtu).filter(|mtu| *mtu != u32::MAX),
+            up: current.oper_status == IF_OPER_STATUS_UP,
+            gateways: address_list(current.first_gateway),
+            dns_servers: address_list(current.first_dns_server),
+            ..NetworkInterface::default()
+        };
+        let mut unicast = current.first_unicast;
+        while let Some(address) = unsafe { unicast.as_ref() } {
+            match socket_address_ip(&address.head.address) {
+                Some(IpAddr::V4(v4)) => interface.ipv4.push((v4, address.on_link_prefix_length)),
+                Some(IpAddr::V6(v6)) => interface.ipv6.push((v6, address.on_link_prefix_length)),
+                None => {}
+            }
+            unicast = address.head.next as *const UnicastAddress;
+        }
+        interfaces.push(interface);
+    }
+    interfaces.sort_by_key(|interface| interface.index);
+    Ok(interfaces)
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+pub fn enumerate() -> Result<Vec<NetworkInterface>, String> {
+    Err("Interface enumeration is not supported on this platform".to_string())
+}
+
+// Re-reads the interfaces for the Interface Menu
+pub fn refresh() {
+    let interfaces = enumerate().unwrap_or_else(|e| {
+        logging::debug_error(&format!("Failed to list interfaces: {}", e));
+        Vec::new()
+    });
+    *INVENTORY.lock().unwrap() = Some(interfaces);
+}
+
+// Cached interfaces, enumerated on first use
+pub fn inventory() -> Vec<NetworkInterface> {
+    if INVENTORY.lock().unwrap().is_none() {
+        refresh();
+    }
+    INVENTORY.lock().unwrap().clone().unwrap_or_default()
+}
+
+pub fn set_capture_interface(interface: Option<NetworkInterface>) {
+    match &interface {
+        Some(interface) => logging::debug_info(&format!("Capture interface set to {}", interface)),
+        None => logging::debug_info("Capture interface set to all interfaces"),
+    }
+    *CAPTURE_INTERFACE.lock().unwrap() = interface;
+}
+
+pub fn capture_interface() -> Option<NetworkInterface> {
+    CAPTURE_INTERFACE.lock().unwrap().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ipv4_gateways() {
+        let text = include_str!("../testdata/proc/net_route");
+        assert_eq!(
+            parse_proc_net_route(text),
+            [
+                ("wlan0".to_string(), Ipv4Addr::new(192, 168, 1, 1)),
+                ("wg0".to_string(), Ipv4Addr::new(10, 0, 0, 1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_ipv6_gateways() {
+        let text = include_str!("../testdata/proc/net_ipv6_route");
+        assert_eq!(
+            parse_proc_net_ipv6_route(text),
+            [("wlan0".to_string(), "fe80::211:22ff:fe33:4455".parse::<Ipv6Addr>().unwrap())]
+        );
+    }
+
+    #[test]
+    fn parses_name_servers_and_formats_details() {
+        let text = include_str!("../testdata/resolv.conf");
+        let dns_servers = parse_resolv_conf(text);
+        assert_eq!(
+            dns_servers,
+            ["192.168.1.1", "fe80::1", "2001:4860:4860::8888"].map(|addr| addr.parse::<IpAddr>().unwrap())
+        );
+
+        let interface = NetworkInterface {
+            name: "wlan0".to_string(),
+            index: 3,
+            mac: Some([0x00, 0x11, 0x22, 0xaa, 0xbb, 0xcc]),
+            mtu: Some(1500),
+            ipv4: vec![(Ipv4Addr::new(192, 168, 1, 20), prefix_len(&[255, 255, 255, 0]))],
+            up: true,
+            dns_servers,
+            ..NetworkInterface::default()
+        };
+        let details = interface.details();
+        assert_eq!(interface.to_string(), "wlan0 (#3, up)");
+        assert!(details.contains(&"MAC: 00:11:22:aa:bb:cc".to_string()));
+        assert!(details.contains(&"IPv4: 192.168.1.20/24".to_string()));
+        assert!(details.contains(&"IPv6: -".to_string()));
+    }
+}
@@ -0,0 +1,44 @@
+// Capture, decode, firewall and diagnostics APIs shared by the s2o_net_lib
+// binary and the xallfirewall frontend. Call initialization::s2o_bootup_headless
+// once before using the background workers, and s2o_shutdown before exiting.
+// Messages are logged through the `log` crate; the host installs the logger.
+
+pub mod logging;
+pub mod initialization;
+pub mod admin_check;
+pub mod capture_backend;
+pub mod packet_capture;
+pub mod pcap_reader;
+pub mod pcap_writer;
+pub mod packet_decoder;
+pub mod app_dissector;
+pub mod flow_table;
+pub mod packet_store;
+pub mod capture_filter;
+pub mod firewall;
+pub mod inline_verdict;
+pub mod linux_firewall;
+pub mod windows_firewall;
+pub mod rule_store;
+pub mod process_attribution;
+pub mod throughput_monitor;
+pub mod speed_test;
+pub mod diagnostics;
+pub mod interface_inventory;
+pub mod connections;
+mod checksum;
+#[cfg(windows)]
+mod nc;
+#[cfg(target_os = "linux")]
+mod linux_capture;
+
+// The types most callers need, without spelling out the modules
+pub use capture_backend::{CaptureBackend, CapturedPacket, LinkType, PacketMeta};
+pub use capture_filter::CaptureFilter;
+pub use diagnostics::{CheckResult, CheckStatus, DiagnosticCheck};
+pub use firewall::{FirewallRule, RuleSet, Verdict};
+pub use flow_table::{FlowKey, FlowRecord};
+pub use packet_decoder::{decode, Packet};
+pub use pcap_reader::{PcapFormat, PcapReader};
+pub use pcap_writer::ExportFormat;
+pub use process_attribution::ProcessInfo;
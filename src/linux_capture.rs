@@ -0,0 +1,227 @@
+use crate::logging;
+use crate::capture_backend::{CaptureBackend, CaptureStats, LinkType, PacketMeta};
+use std::ffi::CString;
+use std::time::SystemTime;
+
+pub fn init_module() -> Result<(), String> {
+    // Placeholder for actual initialization logic
+    let initialization_passed = true;
+
+    if initialization_passed {
+        logging::debug_info("linux_capture module is online");
+        Ok(())
+    } else {
+        Err("linux_capture module initialization failed".to_string())
+    }
+}
+
+// How long recv waits before handing control back to the capture loop
+const RECV_TIMEOUT_MS: libc::suseconds_t = 200;
+
+// Not in the libc crate yet; used by cellular modems and some tunnels
+const ARPHRD_RAWIP: u16 = 519;
+
+fn last_os_error() -> String {
+    std::io::Error::last_os_error().to_string()
+}
+
+// Link layer of frames from a device of the given ARPHRD_* type, or None for
+// headers the decoder does not understand
+fn link_type_for_hatype(hatype: u16) -> Option<LinkType> {
+    match hatype {
+        libc::ARPHRD_ETHER | libc::ARPHRD_LOOPBACK => Some(LinkType::Ethernet),
+        // TUN devices, WireGuard and the like hand over bare IP packets
+        libc::ARPHRD_NONE | ARPHRD_RAWIP => Some(LinkType::RawIp),
+        _ => None,
+    }
+}
+
+// Raw AF_PACKET socket receiving frames on one or all interfaces, tagged with
+// each device's link layer. Needs root or CAP_NET_RAW.
+pub struct AfPacketBackend {
+    interface: Option<String>,
+    fd: libc::c_int,
+    stats: CaptureStats,
+}
+
+impl AfPacketBackend {
+    // `interface` of None captures on every interface
+    pub fn new(interface: Option<String>) -> Self {
+        AfPacketBackend {
+            interface,
+            fd: -1,
+            stats: CaptureStats::default(),
+        }
+    }
+
+    fn is_open(&self) -> bool {
+        self.fd >= 0
+    }
+
+    fn bind_interface(&self, name: &str) -> Result<(), String> {
+        let c_name = CString::new(name).map_err(|e| format!("Invalid interface name {}: {}", name, e))?;
+        let index = unsafe { libc::if_nametoindex(c_name.as_ptr()) };
+        if index == 0 {
+            return Err(format!("Unknown interface {}: {}", name, last_os_error()));
+        }
+
+        let mut addr: libc::sockaddr_ll = unsafe { std::mem::zeroed() };
+        addr.sll_family = libc::AF_PACKET as u16;
+        addr.sll_protocol = (libc::ETH_P_ALL as u16).to_be();
+        addr.sll_ifindex = index as i32;
+        let rc = unsafe {
+            libc::bind(
+                self.fd,
+                &addr as *const libc::sockaddr_ll as *const libc::sockaddr,
+                std::mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t,
+            )
+        };
+        if rc != 0 {
+            return Err(format!("Failed to bind to {}: {}", name, last_os_error()));
+        }
+        Ok(())
+    }
+
+    fn set_recv_timeout(&self) -> Result<(), String> {
+        let timeout = libc::timeval { tv_sec: 0, tv_usec: RECV_TIMEOUT_MS * 1000 };
+        let rc = unsafe {
+            libc::setsockopt(
+                self.fd,
+                libc::SOL_SOCKET,
+                libc::SO_RCVTIMEO,
+                &timeout as *const libc::timeval as *const libc::c_void,
+                std::mem::size_of::<libc::timeval>() as libc::socklen_t,
+            )
+        };
+        if rc != 0 {
+            return Err(format!("Failed to set receive timeout: {}", last_os_error()));
+        }
+        Ok(())
+    }
+}
+
+impl CaptureBackend for AfPacketBackend {
+    fn name(&self) -> &str {
+        "AF_PACKET"
+    }
+
+    fn open(&mut self, filter: &str) -> Result<(), String> {
+        if self.is_open() {
+            return Err("AF_PACKET socket already open.".to_string());
+        }
+        // The kernel side has no filter language of its own here
+        if !filter.trim().is_empty() && filter.trim() != "true" {
+            return Err(format!("AF_PACKET backend does not support filter \"{}\"", filter));
+        }
+
+        let fd = unsafe { libc::socket(libc::AF_PACKET, libc::SOCK_RAW, (libc::ETH_P_ALL as u16).to_be() as libc::c_int) };
+        if fd < 0 {
+            let error_msg = format!("Failed to open AF_PACKET socket. Error: {}", last_os_error());
+            logging::debug_error(&error_msg);
+            return Err(error_msg);
+        }
+        self.fd = fd;
+
+        let setup = self.set_recv_timeout().and_then(|_| match &self.interface {
+            Some(name) => self.bind_interface(name),
+            None => Ok(()),
+        });
+        if let Err(e) = setup {
+            let _ = self.close();
+            logging::debug_error(&e);
+            return Err(e);
+        }
+
+        logging::debug_info("AF_PACKET socket opened successfully.");
+        Ok(())
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> Result<Option<PacketMeta>, String> {
+        if !self.is_open() {
+            return Err("AF_PACKET socket is not open.".to_string());
+        }
+
+        let mut addr: libc::sockaddr_ll = unsafe { std::mem::zeroed() };
+        let mut addr_len = std::mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t;
+        // MSG_TRUNC makes the kernel report the full length even if buf is shorter
+        let n = unsafe {
+            libc::recvfrom(
+                self.fd,
+                buf.as_mut_ptr() as *mut libc::c_void,
+                buf.len(),
+                libc::MSG_TRUNC,
+                &mut addr as *mut libc::sockaddr_ll as *mut libc::sockaddr,
+                &mut addr_len,
+            )
+        };
+        if n < 0 {
+            let err = std::io::Error::last_os_error();
+            return match err.kind() {
+                std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut | std::io::ErrorKind::Interrupted => Ok(None),
+                _ => {
+                    self.stats.recv_errors += 1;
+                    Err(format!("Failed to capture packet. Error: {}", err))
+                }
+            };
+        }
+
+        let link_type = match link_type_for_hatype(addr.sll_hatype) {
+            Some(link_type) => link_type,
+            None => {
+                // Skipped rather than decoded as Ethernet; logged once per type
+                logging::debug_info(&format!("Skipping frames of unsupported ARPHRD type {}", addr.sll_hatype));
+                return Ok(None);
+            }
+        };
+        let original_len = n as usize;
+        let captured_len = original_len.min(buf.len());
+        self.stats.record_packet(captured_len);
+        Ok(Some(PacketMeta {
+            timestamp: SystemTime::now(),
+            captured_len,
+            original_len,
+            interface_index: addr.sll_ifindex as u32,
+            outbound: addr.sll_pkttype == libc::PACKET_OUTGOING,
+            link_type,
+        }))
+    }
+
+    fn close(&mut self) -> Result<(), String> {
+        if !self.is_open() {
+            return Ok(());
+        }
+        let rc = unsafe { libc::close(self.fd) };
+        self.fd = -1;
+        if rc != 0 {
+            let error_msg = format!("Failed to close AF_PACKET socket. Error: {}", last_os_error());
+            logging::debug_error(&error_msg);
+            return Err(error_msg);
+        }
+        logging::debug_info("AF_PACKET socket closed.");
+        Ok(())
+    }
+
+    fn stats(&self) -> CaptureStats {
+        self.stats
+    }
+}
+
+impl Drop for AfPacketBackend {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_device_types_to_link_types() {
+        assert_eq!(link_type_for_hatype(libc::ARPHRD_ETHER), Some(LinkType::Ethernet));
+        assert_eq!(link_type_for_hatype(libc::ARPHRD_LOOPBACK), Some(LinkType::Ethernet));
+        assert_eq!(link_type_for_hatype(libc::ARPHRD_NONE), Some(LinkType::RawIp));
+        assert_eq!(link_type_for_hatype(ARPHRD_RAWIP), Some(LinkType::RawIp));
+        assert_eq!(link_type_for_hatype(libc::ARPHRD_IEEE80211_RADIOTAP), None);
+    }
+}
@@ -0,0 +1,452 @@
+use crate::logging;
+use crate::firewall::{self, Decision, FirewallRule, IpNetwork, PortRange, RuleAction, RuleDirection, RuleSet};
+use crate::packet_decoder::{IP_PROTO_ICMP, IP_PROTO_ICMPV6, IP_PROTO_TCP, IP_PROTO_UDP};
+use std::fmt::Write;
+
+pub fn init_module() -> Result<(), String> {
+    // Placeholder for actual initialization logic
+    let initialization_passed = true;
+
+    if initialization_passed {
+        logging::debug_info("linux_firewall module is online");
+        Ok(())
+    } else {
+        Err("linux_firewall module initialization failed".to_string())
+    }
+}
+
+// nftables table the generated ruleset owns
+pub const NFT_TABLE: &str = "s2o_firewall";
+// Accepts put ahead of the rules in the input chain, so replies to our own
+// connections and loopback traffic survive a drop policy
+pub(crate) const NFT_INPUT_PREAMBLE: [&str; 2] = ["ct state established,related accept", "iif \"lo\" accept"];
+const IPTABLES_INPUT_PREAMBLE: [&str; 2] =
+    ["-A INPUT -m conntrack --ctstate ESTABLISHED,RELATED -j ACCEPT", "-A INPUT -i lo -j ACCEPT"];
+
+// iptables-restore only speaks one address family per file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpFamily {
+    V4,
+    V6,
+}
+
+impl IpFamily {
+    fn of(network: &IpNetwork) -> IpFamily {
+        if network.addr.is_ipv4() {
+            IpFamily::V4
+        } else {
+            IpFamily::V6
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Chain {
+    Input,
+    Output,
+}
+
+// A rule after direction and address family have been fixed, with local and
+// remote mapped onto source and destination for its chain
+struct ChainRule<'r> {
+    rule: &'r FirewallRule,
+    chain: Chain,
+    family: Option<IpFamily>,
+    saddr: Vec<IpNetwork>,
+    daddr: Vec<IpNetwork>,
+    sport: &'r [PortRange],
+    dport: &'r [PortRange],
+}
+
+// Why a rule has no Linux equivalent, if it has none
+fn unsupported(rule: &FirewallRule) -> Option<&'static str> {
+    if !rule.enabled {
+        return Some("disabled");
+    }
+    if rule.process.is_some() {
+        return Some("process matching has no netfilter equivalent");
+    }
+    let has_ports = !rule.local_ports.is_empty() || !rule.remote_ports.is_empty();
+    if has_ports && !matches!(rule.protocol, None | Some(IP_PROTO_TCP) | Some(IP_PROTO_UDP)) {
+        return Some("ports only apply to TCP and UDP");
+    }
+    None
+}
+
+// Splits a rule per chain and per address family. Rules without addresses
+// stay family-neutral; a family is only kept if every non-empty address list
+// has an entry in it.
+fn chain_rules(rule: &FirewallRule) -> Vec<ChainRule<'_>> {
+    let chains: &[Chain] = match rule.direction {
+        RuleDirection::Inbound => &[Chain::Input],
+        RuleDirection::Outbound => &[Chain::Output],
+        RuleDirection::Any => &[Chain::Input, Chain::Output],
+    };
+    let families: Vec<Option<IpFamily>> = if rule.local_addresses.is_empty() && rule.remote_addresses.is_empty() {
+        vec![None]
+    } else {
+        [IpFamily::V4, IpFamily::V6]
+            .into_iter()
+            .filter(|family| {
+                [&rule.local_addresses, &rule.remote_addresses]
+                    .iter()
+                    .all(|list| list.is_empty() || list.iter().any(|n| IpFamily::of(n) == *family))
+            })
+            .map(Some)
+            .collect()
+    };
+
+    let mut out = Vec::new();
+    for &chain in chains {
+        for &family in &families {
+            let pick = |list: &[IpNetwork]| -> Vec<IpNetwork> {
+                list.iter().filter(|n| family.is_none_or(|f| IpFamily::of(n) == f)).copied().collect()
+            };
+            let (local, remote) = (pick(&rule.local_addresses), pick(&rule.remote_addresses));
+            let (saddr, daddr, sport, dport) = match chain {
+                Chain::Input => (remote, local, &rule.remote_ports[..], &rule.local_ports[..]),
+                Chain::Output => (local, remote, &rule.local_ports[..], &rule.remote_ports[..]),
+            };
+            out.push(ChainRule { rule, chain, family, saddr, daddr, sport, dport });
+        }
+    }
+    out
+}
+
+fn protocol_name(protocol: u8) -> String {
+    match protocol {
+        IP_PROTO_TCP => "tcp".to_string(),
+        IP_PROTO_UDP => "udp".to_string(),
+        IP_PROTO_ICMP => "icmp".to_string(),
+        IP_PROTO_ICMPV6 => "ipv6-icmp".to_string(),
+        other => other.to_string(),
+    }
+}
+
+// Keeps names usable inside quoted strings and log prefixes
+fn sanitize(name: &str) -> String {
+    name.chars().map(|c| if c.is_ascii_graphic() && c != '"' && c != '\\' { c } else { '_' }).collect()
+}
+
+fn nft_set<T: ToString>(items: &[T]) -> String {
+    let items: Vec<String> = items.iter().map(|item| item.to_string()).collect();
+    if items.len() == 1 {
+        items[0].clone()
+    } else {
+        format!("{{ {} }}", items.join(", "))
+    }
+}
+
+fn nft_verdict(action: RuleAction, name: &str) -> String {
+    match action {
+        RuleAction::Allow => "accept".to_string(),
+        RuleAction::Deny => "drop".to_string(),
+        RuleAction::Log => format!("log prefix \"s2o {}: \"", sanitize(name)),
+    }
+}
+
+fn nft_rule(rule: &ChainRule) -> String {
+    let mut parts = Vec::new();
+    let family = match rule.family {
+        Some(IpFamily::V4) => "ip",
+        Some(IpFamily::V6) => "ip6",
+        None => "",
+    };
+    if !rule.saddr.is_empty() {
+        parts.push(format!("{} saddr {}", family, nft_set(&rule.saddr)));
+    }
+    if !rule.daddr.is_empty() {
+        parts.push(format!("{} daddr {}", family, nft_set(&rule.daddr)));
+    }
+
+    let has_ports = !rule.sport.is_empty() || !rule.dport.is_empty();
+    let port_prefix = match rule.rule.protocol {
+        Some(protocol) if has_ports => protocol_name(protocol),
+        Some(protocol) => {
+            parts.push(format!("meta l4proto {}", protocol_name(protocol)));
+            String::new()
+        }
+        None if has_ports => {
+            parts.push("meta l4proto { tcp, udp }".to_string());
+            "th".to_string()
+        }
+        None => String::new(),
+    };
+    if !rule.sport.is_empty() {
+        parts.push(format!("{} sport {}", port_prefix, nft_set(rule.sport)));
+    }
+    if !rule.dport.is_empty() {
+        parts.push(format!("{} dport {}", port_prefix, nft_set(rule.dport)));
+    }
+
+    parts.push(nft_verdict(rule.rule.action, &rule.rule.name));
+    parts.push(format!("comment \"{}\"", sanitize(&rule.rule.name)));
+    parts.join(" ")
+}
+
+fn policy(decision: Decision) -> &'static str {
+    match decision {
+        Decision::Allow => "accept",
+        Decision::Deny => "drop",
+    }
+}
+
+// An `nft -f` script that replaces the s2o table with the rule set. Rules
+// keep their evaluation order; log rules use nftables' non-terminal log.
+pub fn nftables_ruleset(rules: &RuleSet) -> String {
+    let mut out = String::new();
+    writeln!(out, "#!/usr/sbin/nft -f").unwrap();
+    writeln!(out, "# Generated by s2o_net_lib; changes are overwritten on the next deploy").unwrap();
+    // Creating before deleting makes the script work whether or not the table exists
+    writeln!(out, "table inet {}", NFT_TABLE).unwrap();
+    writeln!(out, "delete table inet {}", NFT_TABLE).unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "table inet {} {{", NFT_TABLE).unwrap();
+
+    for (chain, hook, default) in [
+        (Chain::Input, "input", rules.default_inbound),
+        (Chain::Output, "output", rules.default_outbound),
+    ] {
+        if chain == Chain::Output {
+            writeln!(out).unwrap();
+        }
+        writeln!(out, "    chain {} {{", hook).unwrap();
+        writeln!(out, "        type filter hook {} priority filter; policy {};", hook, policy(default)).unwrap();
+        if chain == Chain::Input {
+            for line in NFT_INPUT_PREAMBLE {
+                writeln!(out, "        {}", line).unwrap();
+            }
+        }
+        for rule in rules.rules() {
+            if let Some(reason) = unsupported(rule) {
+                writeln!(out, "        # skipped \"{}\": {}", sanitize(&rule.name), reason).unwrap();
+                continue;
+            }
+            for chain_rule in chain_rules(rule).iter().filter(|r| r.chain == chain) {
+                writeln!(out, "        {}", nft_rule(chain_rule)).unwrap();
+            }
+        }
+        writeln!(out, "    }}").unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+    out
+}
+
+fn iptables_ports(ports: &[PortRange]) -> String {
+    let ports: Vec<String> = ports
+        .iter()
+        .map(|range| {
+            if range.start == range.end {
+                range.start.to_string()
+            } else {
+                format!("{}:{}", range.start, range.end)
+            }
+        })
+        .collect();
+    ports.join(",")
+}
+
+fn iptables_rules(rule: &ChainRule) -> Vec<String> {
+    let chain = match rule.chain {
+        Chain::Input => "INPUT",
+        Chain::Output => "OUTPUT",
+    };
+    let target = match rule.rule.action {
+        RuleAction::Allow => "-j ACCEPT".to_string(),
+        RuleAction::Deny => "-j DROP".to_string(),
+        // The kernel caps log prefixes at 29 characters
+        RuleAction::Log => {
+            let prefix: String = format!("s2o {}", sanitize(&rule.rule.name)).chars().take(27).collect();
+            format!("-j LOG --log-prefix \"{}: \"", prefix)
+        }
+    };
+
+    let has_ports = !rule.sport.is_empty() || !rule.dport.is_empty();
+    let protocols: Vec<Option<u8>> = match rule.rule.protocol {
+        Some(protocol) => vec![Some(protocol)],
+        // multiport needs a protocol, so port-only rules become one per protocol
+        None if has_ports => vec![Some(IP_PROTO_TCP), Some(IP_PROTO_UDP)],
+        None => vec![None],
+    };
+
+    protocols
+        .into_iter()
+        .map(|protocol| {
+            let mut parts = vec![format!("-A {}", chain)];
+            if !rule.saddr.is_empty() {
+                let list: Vec<String> = rule.saddr.iter().map(|n| n.to_string()).collect();
+                parts.push(format!("-s {}", list.join(",")));
+            }
+            if !rule.daddr.is_empty() {
+                let list: Vec<String> = rule.daddr.iter().map(|n| n.to_string()).collect();
+                parts.push(format!("-d {}", list.join(",")));
+            }
+            if let Some(protocol) = protocol {
+                parts.push(format!("-p {}", protocol_name(protocol)));
+            }
+            if has_ports {
+                parts.push("-m multiport".to_string());
+                if !rule.sport.is_empty() {
+                    parts.push(format!("--sports {}", iptables_ports(rule.sport)));
+                }
+                if !rule.dport.is_empty() {
+                    parts.push(format!("--dports {}", iptables_ports(rule.dport)));
+                }
+            }
+            parts.push(format!("-m comment --comment \"{}\"", sanitize(&rule.rule.name)));
+            parts.push(target.clone());
+            parts.join(" ")
+        })
+        .collect()
+}
+
+// An iptables-restore (V4) or ip6tables-restore (V6) file for the filter
+// table. Loading it without --noflush replaces the whole filter table.
+pub fn iptables_ruleset(rules: &RuleSet, family: IpFamily) -> String {
+    let mut out = String::new();
+    writeln!(out, "# Generated by s2o_net_lib for {}", if family == IpFamily::V4 { "iptables-restore" } else { "ip6tables-restore" }).unwrap();
+    writeln!(out, "*filter").unwrap();
+    writeln!(out, ":INPUT {} [0:0]", policy(rules.default_inbound).to_uppercase()).unwrap();
+    writeln!(out, ":FORWARD ACCEPT [0:0]").unwrap();
+    writeln!(out, ":OUTPUT {} [0:0]", policy(rules.default_outbound).to_uppercase()).unwrap();
+    for line in IPTABLES_INPUT_PREAMBLE {
+        writeln!(out, "{}", line).unwrap();
+    }
+
+    // iptables has no inet family, so ICMP of the other family never matches
+    let other_icmp = if family == IpFamily::V4 { IP_PROTO_ICMPV6 } else { IP_PROTO_ICMP };
+    for chain in [Chain::Input, Chain::Output] {
+        for rule in rules.rules() {
+            if let Some(reason) = unsupported(rule) {
+                if chain == Chain::Input {
+                    writeln!(out, "# skipped \"{}\": {}", sanitize(&rule.name), reason).unwrap();
+                }
+                continue;
+            }
+            if rule.protocol == Some(other_icmp) {
+                continue;
+            }
+            for chain_rule in chain_rules(rule)
+                .iter()
+                .filter(|r| r.chain == chain && r.family.is_none_or(|f| f == family))
+            {
+                for line in iptables_rules(chain_rule) {
+                    writeln!(out, "{}", line).unwrap();
+                }
+            }
+        }
+    }
+    writeln!(out, "COMMIT").unwrap();
+    out
+}
+
+// Dry run: logs what would be deployed for the current rule set
+pub fn print_rulesets() {
+    let rules = firewall::FIREWALL.lock().unwrap().clone();
+    logging::debug_info("nftables ruleset (nft -f):");
+    for line in nftables_ruleset(&rules).lines() {
+        logging::debug_info(line);
+    }
+    for family in [IpFamily::V4, IpFamily::V6] {
+        logging::debug_info(&format!("{:?} ruleset for iptables-restore:", family));
+        for line in iptables_ruleset(&rules, family).lines() {
+            logging::debug_info(line);
+        }
+    }
+}
+
+// Loads the rule set into the kernel by piping the generated script to
+// `nft -f -`. Needs root and the nft tool.
+pub fn deploy_nftables(rules: &RuleSet) -> Result<(), String> {
+    use std::io::Write as _;
+    use std::process::{Command, Stdio};
+
+    let mut child = Command::new("nft")
+        .args(["-f", "-"])
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run nft: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(nftables_ruleset(rules).as_bytes())
+            .map_err(|e| format!("Failed to write to nft: {}", e))?;
+    }
+    let output = child.wait_with_output().map_err(|e| format!("Failed to run nft: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("nft failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    logging::debug_info(&format!("Deployed {} firewall rules to nftables table {}", rules.rules().len(), NFT_TABLE));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn net(value: &str) -> IpNetwork {
+        IpNetwork::parse(value).unwrap()
+    }
+
+    fn sample_rules() -> RuleSet {
+        let mut rules = RuleSet::new();
+        rules.default_inbound = Decision::Deny;
+        let add = |rules: &mut RuleSet, rule| rules.add(rule).unwrap();
+        add(&mut rules, FirewallRule {
+            priority: -10,
+            protocol: Some(IP_PROTO_UDP),
+            remote_ports: vec![PortRange::single(53)],
+            ..FirewallRule::new("log dns", RuleAction::Log)
+        });
+        add(&mut rules, FirewallRule {
+            direction: RuleDirection::Inbound,
+            protocol: Some(IP_PROTO_TCP),
+            remote_addresses: vec![net("10.0.0.0/8"), net("fd00::/8")],
+            local_ports: vec![PortRange::single(22), PortRange::parse("8000-8080").unwrap()],
+            ..FirewallRule::new("ssh-from-lan", RuleAction::Allow)
+        });
+        add(&mut rules, FirewallRule {
+            direction: RuleDirection::Outbound,
+            remote_addresses: vec![net("2001:db8::/32")],
+            ..FirewallRule::new("block-doc-net", RuleAction::Deny)
+        });
+        add(&mut rules, FirewallRule {
+            direction: RuleDirection::Inbound,
+            remote_ports: vec![PortRange::single(123)],
+            ..FirewallRule::new("ntp", RuleAction::Allow)
+        });
+        add(&mut rules, FirewallRule {
+            direction: RuleDirection::Inbound,
+            protocol: Some(IP_PROTO_ICMPV6),
+            ..FirewallRule::new("icmpv6", RuleAction::Allow)
+        });
+        add(&mut rules, FirewallRule {
+            process: Some("browser.exe".to_string()),
+            ..FirewallRule::new("browser", RuleAction::Deny)
+        });
+        add(&mut rules, FirewallRule { enabled: false, ..FirewallRule::new("old \"rule\"", RuleAction::Deny) });
+        rules
+    }
+
+    // Set S2O_UPDATE_GOLDEN=1 to rewrite the golden files after an intended change
+    fn check_golden(name: &str, actual: &str) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/firewall").join(name);
+        if std::env::var_os("S2O_UPDATE_GOLDEN").is_some() {
+            std::fs::write(&path, actual).unwrap();
+        }
+        let expected = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        assert_eq!(actual, expected, "output differs from {}", path.display());
+    }
+
+    #[test]
+    fn nftables_matches_golden_file() {
+        check_golden("ruleset.nft", &nftables_ruleset(&sample_rules()));
+    }
+
+    #[test]
+    fn iptables_matches_golden_files() {
+        check_golden("ruleset.iptables", &iptables_ruleset(&sample_rules(), IpFamily::V4));
+        check_golden("ruleset.ip6tables", &iptables_ruleset(&sample_rules(), IpFamily::V6));
+    }
+}
@@ -1,16 +1,14 @@
 use log::{info, error};
-use simplelog::{Config, LevelFilter, TermLogger, TerminalMode, ColorChoice};
 use once_cell::sync::Lazy;
 use std::collections::HashSet;
 use std::sync::Mutex;
 
 static LOGGED_MESSAGES: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
 
+// Messages go through the `log` facade; installing a logger is left to the
+// application embedding the library
 pub fn init_module() -> Result<(), String> {
-    // Initialize the logger
-    TermLogger::init(LevelFilter::Debug, Config::default(), TerminalMode::Mixed, ColorChoice::Auto)
-        .map_err(|e| format!("Failed to boot logger: {}", e))?;
-
+    // Placeholder for actual initialization logic
     let initialization_passed = true;
 
     if initialization_passed {
@@ -29,6 +27,12 @@ pub fn debug_info(message: &str) {
     }
 }
 
+// Logs every time, for messages that repeat on purpose (rule matches, packet
+// listings) and would otherwise fill LOGGED_MESSAGES
+pub fn log_info(message: &str) {
+    info!("{}", message);
+}
+
 pub fn debug_error(message: &str) {
     error!("{}", message);
 }
\ No newline at end of file
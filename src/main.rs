@@ -1,34 +1,67 @@
- 
-mod logging;
-mod initialization;
+use simplelog::{ColorChoice, Config, LevelFilter, TermLogger, TerminalMode};
+
+mod cli;
+#[cfg(feature = "gui")]
+mod gui_initialization;
+#[cfg(feature = "gui")]
 mod app_state;
-mod admin_check;
+#[cfg(feature = "gui")]
 mod gui_engine;
+#[cfg(feature = "gui")]
 mod gui_engine_animation;
+#[cfg(feature = "gui")]
 mod gui_engine_menu;
+#[cfg(feature = "gui")]
 mod gui_engine_style;
+#[cfg(feature = "gui")]
+mod gui_engine_screens;
+#[cfg(feature = "gui")]
 mod s_menu;
+#[cfg(feature = "gui")]
 mod p_menu;
+#[cfg(feature = "gui")]
 mod pc_menu;
+#[cfg(feature = "gui")]
 mod ns_menu;
+#[cfg(feature = "gui")]
 mod ds_menu;
 
-//mod packet_capture;
-//mod nc;
+fn main() {
+    // Any command line arguments select the headless CLI instead of the GUI
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let invocation = match cli::parse_args(&args) {
+        Ok(invocation) => invocation,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, cli::USAGE);
+            std::process::exit(cli::EXIT_USAGE);
+        }
+    };
+    if invocation.command != cli::Command::Gui {
+        // The command line keeps stdout for its own output
+        init_logger(TerminalMode::Stderr);
+        std::process::exit(cli::run(invocation));
+    }
+    init_logger(TerminalMode::Mixed);
+    start_gui();
+}
 
-//use std::sync::Arc;
-//use std::sync::Mutex;
+fn init_logger(mode: TerminalMode) {
+    if let Err(e) = TermLogger::init(LevelFilter::Debug, Config::default(), mode, ColorChoice::Auto) {
+        eprintln!("warning: failed to boot logger: {}", e);
+    }
+}
 
-fn main() {
-    // Add a pause and wait for any key to be pressed
-    
+#[cfg(feature = "gui")]
+fn start_gui() {
     // Call the s2o_bootup function
-    if let Err(e) = initialization::s2o_bootup() {
-        logging::debug_error(&format!("Failed to boot application: {}", e));
+    if let Err(e) = gui_initialization::s2o_bootup() {
+        s2o_net_lib::logging::debug_error(&format!("Failed to boot application: {}", e));
         std::process::exit(1);
     }
+}
 
-    // Example usage of logging
-    //logging::debug_info("Application has started.");
-    //logging::debug_error("No errors encountered.");
+#[cfg(not(feature = "gui"))]
+fn start_gui() {
+    eprintln!("error: this build has no GUI (the gui feature is off)\n\n{}", cli::USAGE);
+    std::process::exit(cli::EXIT_USAGE);
 }
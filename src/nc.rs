@@ -1,180 +1,392 @@
-use std::ffi::CString;
+use crate::logging;
+use crate::capture_backend::{CaptureBackend, CaptureStats, Interrupter, LinkType, PacketMeta};
+use crate::capture_filter::CaptureFilter;
+use libloading::Library;
+use std::ffi::{c_void, CString};
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
-use std::thread;
-use std::time::Duration;
-use libloading::{Library, Symbol};
-use once_cell::sync::Lazy;
-use std::thread::JoinHandle;
-use crate::logging::{log_info, log_error, LogBuffers}; // Correct import for LogBuffers
-use std::io::Error;
+use std::time::SystemTime;
 
-// Define function types for WinDivert
-type WinDivertOpen = unsafe extern "C" fn(filter: *const i8, layer: i32, priority: i16, flags: u64) -> *mut std::ffi::c_void;
-type WinDivertRecv = unsafe extern "C" fn(handle: *mut std::ffi::c_void, p_packet: *mut u8, packet_len: u32, addr: *mut u8) -> bool;
-type WinDivertClose = unsafe extern "C" fn(handle: *mut std::ffi::c_void) -> bool;
-
-static CAPTURED_PACKETS: Lazy<Arc<Mutex<Vec<Vec<u8>>>>> = Lazy::new(|| Arc::new(Mutex::new(Vec::new())));
+pub fn init_module() -> Result<(), String> {
+    // Placeholder for actual initialization logic
+    let initialization_passed = true;
 
-pub extern "C" fn packet_callback(data: *const u8, length: i32) {
-    println!("Packet captured: length = {}", length);
-    if length > 0 {
-        let packet = unsafe { std::slice::from_raw_parts(data, length as usize) };
-        let mut packets = CAPTURED_PACKETS.lock().unwrap();
-        packets.push(packet.to_vec());
-        println!("Packet added. New count: {}", packets.len());
+    if initialization_passed {
+        logging::debug_info("nc module is online");
+        Ok(())
+    } else {
+        Err("nc module initialization failed".to_string())
     }
 }
 
-pub fn init_nc(log_buffers: &Arc<LogBuffers>) -> Result<(), Error> {
-    log_info(log_buffers, "Initializing nc.", false);
-    
-    Ok(())
+// WinDivert 2.x constants
+const WINDIVERT_LAYER_NETWORK: i32 = 0;
+const WINDIVERT_LAYER_FLOW: i32 = 2;
+const WINDIVERT_FLAG_SNIFF: u64 = 0x0001;
+const WINDIVERT_FLAG_RECV_ONLY: u64 = 0x0004;
+const WINDIVERT_SHUTDOWN_RECV: i32 = 0x1;
+const WINDIVERT_EVENT_FLOW_ESTABLISHED: u32 = 1;
+const WINDIVERT_EVENT_FLOW_DELETED: u32 = 2;
+const INVALID_HANDLE_VALUE: isize = -1;
+
+// WINDIVERT_ADDRESS is 80 bytes; only the fields we read are named here
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct WinDivertAddress {
+    pub timestamp: i64,
+    pub flags: u32, // Layer:8, Event:8, Sniffed:1, Outbound:1, Loopback:1, ...
+    pub reserved2: u32,
+    pub data: [u8; 64], // Network { IfIdx, SubIfIdx } / Flow / Socket union
 }
 
-pub fn load_dll(log_buffers: &LogBuffers) -> Result<(Arc<Library>, CString), String> {
-    let library_path = "c:/s2o/dll_tester/src/windivert/windivert.dll";
-    log_info(log_buffers, &format!("Attempting to load DLL from path: {}", library_path), false); // Logging the attempt to load DLL
+impl WinDivertAddress {
+    pub fn zeroed() -> Self {
+        WinDivertAddress { timestamp: 0, flags: 0, reserved2: 0, data: [0u8; 64] }
+    }
 
-    unsafe {
-        match Library::new(library_path) {
-            Ok(lib) => {
-                log_info(log_buffers, "DLL loaded successfully.", false); // Logging successful DLL load
-                match CString::new("true") {
-                    Ok(filter) => {
-                        log_info(log_buffers, "Filter created successfully.", false); // Logging successful filter creation
-                        Ok((Arc::new(lib), filter))
-                    },
-                    Err(e) => {
-                        log_error(log_buffers, &format!("Failed to create filter: {}", e), false); // Logging filter creation failure
-                        Err(format!("CString::new failed: {}", e))
-                    }
-                }
-            },
-            Err(e) => {
-                log_error(log_buffers, &format!("Failed to load DLL: {}", e), false); // Logging DLL load failure
-                Err(format!("Failed to load DLL: {}", e))
-            }
+    pub fn outbound(&self) -> bool {
+        self.flags & (1 << 17) != 0
+    }
+
+    pub fn event(&self) -> u32 {
+        (self.flags >> 8) & 0xff
+    }
+
+    pub fn interface_index(&self) -> u32 {
+        u32::from_le_bytes([self.data[0], self.data[1], self.data[2], self.data[3]])
+    }
+
+    // IPChecksum, TCPChecksum and UDPChecksum: tells WinDivert the packet's
+    // checksums are already correct rather than left to offload
+    pub fn set_checksums_valid(&mut self, valid: bool) {
+        let bits = 0b111 << 21;
+        if valid {
+            self.flags |= bits;
+        } else {
+            self.flags &= !bits;
         }
     }
 }
 
-pub fn unload_dll(log_buffers: &LogBuffers, lib: Option<Arc<Library>>) {
-    if let Some(lib) = lib {
-        drop(lib);
-        log_info(log_buffers, "DLL unloaded successfully.", false); // Logging successful DLL unload
-    } else {
-        log_error(log_buffers, "DLL is not loaded.", false); // Logging DLL not loaded
+// Define function types for WinDivert
+type WinDivertOpen = unsafe extern "C" fn(filter: *const i8, layer: i32, priority: i16, flags: u64) -> *mut c_void;
+type WinDivertRecv = unsafe extern "C" fn(handle: *mut c_void, p_packet: *mut c_void, packet_len: u32, p_recv_len: *mut u32, p_addr: *mut WinDivertAddress) -> i32;
+type WinDivertSend = unsafe extern "C" fn(handle: *mut c_void, p_packet: *const c_void, packet_len: u32, p_send_len: *mut u32, p_addr: *const WinDivertAddress) -> i32;
+type WinDivertClose = unsafe extern "C" fn(handle: *mut c_void) -> i32;
+type WinDivertShutdown = unsafe extern "C" fn(handle: *mut c_void, how: i32) -> i32;
+
+// Places we look for WinDivert.dll, in order
+fn dll_candidates() -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+    if let Ok(path) = std::env::var("S2O_WINDIVERT_DLL") {
+        candidates.push(PathBuf::from(path));
     }
+    if let Ok(exe) = std::env::current_exe() {
+        if let Some(dir) = exe.parent() {
+            candidates.push(dir.join("WinDivert.dll"));
+        }
+    }
+    candidates.push(PathBuf::from("src/windivert/WinDivert.dll"));
+    candidates.push(PathBuf::from("WinDivert.dll"));
+    candidates
 }
 
-pub fn start_packet_capture(log_buffers: &LogBuffers, lib: Arc<Library>, filter: CString, stop_flag: Arc<Mutex<bool>>) -> JoinHandle<()> {
-    let log_buffers = log_buffers.clone();
-    thread::spawn(move || {
-        log_info(&log_buffers, "Capture thread started", false); // Logging the start of the capture thread
-        unsafe {
-            let win_divert_open: Symbol<WinDivertOpen> = match lib.get(b"WinDivertOpen\0") {
-                Ok(symbol) => symbol,
-                Err(e) => {
-                    log_error(&log_buffers, &format!("Failed to load WinDivertOpen function: {:?}", e), false); // Logging function load failure
-                    return;
-                }
-            };
-            let win_divert_recv: Symbol<WinDivertRecv> = match lib.get(b"WinDivertRecv\0") {
-                Ok(symbol) => symbol,
-                Err(e) => {
-                    log_error(&log_buffers, &format!("Failed to load WinDivertRecv function: {:?}", e), false); // Logging function load failure
-                    return;
-                }
-            };
-            let win_divert_close: Symbol<WinDivertClose> = match lib.get(b"WinDivertClose\0") {
-                Ok(symbol) => symbol,
-                Err(e) => {
-                    log_error(&log_buffers, &format!("Failed to load WinDivertClose function: {:?}", e), false); // Logging function load failure
-                    return;
-                }
-            };
-
-            let layer = 0; // Use the appropriate layer as needed
-            let handle = win_divert_open(filter.as_ptr(), layer, 0, 0);
-            if handle.is_null() {
-                log_error(&log_buffers, "Failed to open WinDivert handle.", false); // Logging handle open failure
-                return;
-            }
-            log_info(&log_buffers, "WinDivert handle opened successfully.", false); // Logging handle open success
-
-            let mut packet = vec![0u8; 1500];
-            let mut addr = vec![0u8; 64];
-
-            log_info(&log_buffers, "Entering packet capture loop...", false); // Logging capture loop entry
-            while !*stop_flag.lock().unwrap() {
-                let received = win_divert_recv(handle, packet.as_mut_ptr(), packet.len() as u32, addr.as_mut_ptr());
-                log_info(&log_buffers, &format!("win_divert_recv returned: {}", received), false); // Logging packet receive result
-
-                if received {
-                    log_info(&log_buffers, "Packet received.", false); // Logging packet receipt
-                    packet_callback(packet.as_ptr(), packet.len() as i32);
-                } else {
-                    log_info(&log_buffers, "No packet received.", false); // Logging no packet received
-                }
-                thread::sleep(Duration::from_millis(100));
+pub fn load_dll() -> Result<Library, String> {
+    let mut last_error = String::from("no candidate paths");
+    for path in dll_candidates() {
+        logging::debug_info(&format!("Attempting to load DLL from path: {}", path.display()));
+        match unsafe { Library::new(&path) } {
+            Ok(lib) => {
+                logging::debug_info("DLL loaded successfully.");
+                return Ok(lib);
             }
+            Err(e) => last_error = e.to_string(),
+        }
+    }
+    logging::debug_error(&format!("Failed to load DLL: {}", last_error));
+    Err(format!("Failed to load DLL: {}", last_error))
+}
 
-            win_divert_close(handle);
-            log_info(&log_buffers, "Capture thread stopped.", false); // Logging capture thread stop
+// Resolved entry points; only valid while `lib` is alive
+struct WinDivertApi {
+    open: WinDivertOpen,
+    recv: WinDivertRecv,
+    send: WinDivertSend,
+    close: WinDivertClose,
+    shutdown: WinDivertShutdown,
+    _lib: Library,
+}
+
+impl WinDivertApi {
+    fn load() -> Result<Self, String> {
+        let lib = load_dll()?;
+        unsafe {
+            let open = *lib.get::<WinDivertOpen>(b"WinDivertOpen\0")
+                .map_err(|e| format!("Failed to load WinDivertOpen function: {:?}", e))?;
+            let recv = *lib.get::<WinDivertRecv>(b"WinDivertRecv\0")
+                .map_err(|e| format!("Failed to load WinDivertRecv function: {:?}", e))?;
+            let send = *lib.get::<WinDivertSend>(b"WinDivertSend\0")
+                .map_err(|e| format!("Failed to load WinDivertSend function: {:?}", e))?;
+            let close = *lib.get::<WinDivertClose>(b"WinDivertClose\0")
+                .map_err(|e| format!("Failed to load WinDivertClose function: {:?}", e))?;
+            let shutdown = *lib.get::<WinDivertShutdown>(b"WinDivertShutdown\0")
+                .map_err(|e| format!("Failed to load WinDivertShutdown function: {:?}", e))?;
+            Ok(WinDivertApi { open, recv, send, close, shutdown, _lib: lib })
         }
-    })
+    }
 }
 
-pub fn stop_packet_capture(log_buffers: &LogBuffers, stop_flag: Arc<Mutex<bool>>) {
-    *stop_flag.lock().unwrap() = true;
-    log_info(log_buffers, "Packet capture stop signal sent.", false); // Logging stop signal sent
+// A handle another thread may shut down. Cleared under the lock before the
+// handle is closed, so a shutdown never reaches a closed handle.
+struct SharedHandle(*mut c_void);
+
+unsafe impl Send for SharedHandle {}
+
+type OpenHandle = Arc<Mutex<Option<SharedHandle>>>;
+
+// Makes a blocked WinDivertRecv on `handle` return with an error
+fn shutdown_recv(api: &WinDivertApi, handle: &OpenHandle) {
+    if let Some(handle) = &*handle.lock().unwrap() {
+        unsafe { (api.shutdown)(handle.0, WINDIVERT_SHUTDOWN_RECV) };
+    }
 }
 
-pub fn count_packets(log_buffers: &LogBuffers) {
-    let packets = CAPTURED_PACKETS.lock().unwrap();
-    log_info(log_buffers, &format!("Total packets captured: {}", packets.len()), false); // Logging packet count
+// WinDivert handle on the network layer, sniffing by default or inline when
+// asked. WinDivertRecv blocks until the next packet matches, so stopping
+// goes through `interrupter`, which shuts the handle down for receiving.
+pub struct WinDivertBackend {
+    api: Option<Arc<WinDivertApi>>,
+    handle: *mut c_void,
+    open_handle: OpenHandle,
+    inline: bool,
+    // Only packets on this interface index are diverted when set
+    interface: Option<u32>,
+    // Address of the last received packet, reused to reinject it
+    last_addr: WinDivertAddress,
+    stats: CaptureStats,
 }
 
-pub fn print_packet_data(log_buffers: &LogBuffers) {
-    let packets = CAPTURED_PACKETS.lock().unwrap();
-    log_info(log_buffers, &format!("Total packets captured: {}", packets.len()), false); // Logging total packet count
-    for packet in packets.iter() {
-        log_info(log_buffers, &format!("Captured packet: {:?}", packet), false); // Logging each captured packet
+// The raw handle is only ever used from the thread that owns the backend
+unsafe impl Send for WinDivertBackend {}
+
+impl WinDivertBackend {
+    pub fn new() -> Self {
+        WinDivertBackend {
+            api: None,
+            handle: std::ptr::null_mut(),
+            open_handle: Arc::new(Mutex::new(None)),
+            inline: false,
+            interface: None,
+            last_addr: WinDivertAddress::zeroed(),
+            stats: CaptureStats::default(),
+        }
+    }
+
+    fn is_open(&self) -> bool {
+        !self.handle.is_null()
+    }
+
+    pub fn set_interface(&mut self, index: Option<u32>) {
+        self.interface = index;
     }
 }
 
-pub fn run_preliminary_tests(log_buffers: &LogBuffers) -> Result<(), String> {
-    log_info(log_buffers, "Running preliminary tests...", false); // Logging start of preliminary tests
+impl CaptureBackend for WinDivertBackend {
+    fn name(&self) -> &str {
+        "WinDivert"
+    }
+
+    fn open(&mut self, filter: &str) -> Result<(), String> {
+        if self.is_open() {
+            return Err("WinDivert handle already open.".to_string());
+        }
+        let api = WinDivertApi::load()?;
+        let filter = CString::new(filter).map_err(|e| format!("CString::new failed: {}", e))?;
+
+        let flags = if self.inline { 0 } else { WINDIVERT_FLAG_SNIFF };
+        let handle = unsafe { (api.open)(filter.as_ptr(), WINDIVERT_LAYER_NETWORK, 0, flags) };
+        if handle.is_null() || handle as isize == INVALID_HANDLE_VALUE {
+            let error_msg = format!("Failed to open WinDivert handle. Error: {}", std::io::Error::last_os_error());
+            logging::debug_error(&error_msg);
+            return Err(error_msg);
+        }
+
+        logging::debug_info("WinDivert handle opened successfully.");
+        self.handle = handle;
+        *self.open_handle.lock().unwrap() = Some(SharedHandle(handle));
+        self.api = Some(Arc::new(api));
+        Ok(())
+    }
 
-    match load_dll(log_buffers) {
-        Ok((lib, filter)) => {
-            log_info(log_buffers, "DLL loaded successfully for preliminary tests.", false); // Logging DLL load success
+    fn set_inline(&mut self, inline: bool) -> Result<(), String> {
+        if self.is_open() {
+            return Err("Cannot change mode while the WinDivert handle is open.".to_string());
+        }
+        self.inline = inline;
+        Ok(())
+    }
 
-            unsafe {
-                let win_divert_open: Symbol<WinDivertOpen> = match lib.get(b"WinDivertOpen\0") {
-                    Ok(symbol) => symbol,
-                    Err(e) => {
-                        log_error(log_buffers, &format!("Failed to load WinDivertOpen function: {:?}", e), false); // Logging function load failure
-                        return Err(format!("Failed to load WinDivertOpen function: {:?}", e));
-                    }
-                };
-                let handle = win_divert_open(filter.as_ptr(), 0, 0, 0);
-                if handle.is_null() {
-                    log_error(log_buffers, "Failed to open WinDivert handle in preliminary test.", false); // Logging handle open failure
-                    return Err("Failed to open WinDivert handle in preliminary test.".to_string());
-                } else {
-                    log_info(log_buffers, "WinDivert handle opened successfully in preliminary test.", false); // Logging handle open success
-                }
+    fn send(&mut self, data: &[u8], checksums_valid: bool) -> Result<(), String> {
+        let api = self.api.as_ref().ok_or("WinDivert handle is not open.")?;
+        if !self.inline {
+            return Err("WinDivert handle is sniffing; nothing to reinject.".to_string());
+        }
+        let mut addr = self.last_addr;
+        if checksums_valid {
+            addr.set_checksums_valid(true);
+        }
+        let mut send_len: u32 = 0;
+        let ok = unsafe {
+            (api.send)(self.handle, data.as_ptr() as *const c_void, data.len() as u32, &mut send_len, &addr)
+        };
+        if ok == 0 {
+            return Err(format!("Failed to reinject packet. Error: {}", std::io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+
+    // Let the driver drop what it can; the Rust side still checks every packet
+    fn native_filter(&self, filter: &CaptureFilter) -> String {
+        let native = match filter.to_windivert() {
+            Ok(native) => native,
+            Err(e) => {
+                logging::debug_error(&format!("Filter \"{}\" not translated for WinDivert: {}", filter.as_str(), e));
+                "true".to_string()
             }
+        };
+        match self.interface {
+            Some(index) => format!("ifIdx == {} and ({})", index, native),
+            None => native,
+        }
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> Result<Option<PacketMeta>, String> {
+        let api = self.api.as_ref().ok_or("WinDivert handle is not open.")?;
+        let mut recv_len: u32 = 0;
+        let mut addr = WinDivertAddress::zeroed();
 
-            unload_dll(log_buffers, Some(lib));
-        },
-        Err(e) => {
-            log_error(log_buffers, &format!("Failed to load DLL during preliminary tests: {}", e), false); // Logging DLL load failure
-            return Err(format!("Failed to load DLL during preliminary tests: {}", e));
+        let ok = unsafe {
+            (api.recv)(self.handle, buf.as_mut_ptr() as *mut c_void, buf.len() as u32, &mut recv_len, &mut addr)
+        };
+        if ok == 0 {
+            self.stats.recv_errors += 1;
+            return Err(format!("Failed to capture packet. Error: {}", std::io::Error::last_os_error()));
         }
+
+        let len = recv_len as usize;
+        self.stats.record_packet(len);
+        self.last_addr = addr;
+        Ok(Some(PacketMeta {
+            timestamp: SystemTime::now(),
+            captured_len: len,
+            original_len: len,
+            interface_index: addr.interface_index(),
+            outbound: addr.outbound(),
+            link_type: LinkType::RawIp,
+        }))
     }
 
-    log_info(log_buffers, "Preliminary tests completed successfully.", false); // Logging test completion
-    Ok(())
+    fn close(&mut self) -> Result<(), String> {
+        let api = match self.api.take() {
+            Some(api) => api,
+            None => return Ok(()),
+        };
+        let ok = {
+            let mut open_handle = self.open_handle.lock().unwrap();
+            *open_handle = None;
+            unsafe { (api.close)(self.handle) }
+        };
+        self.handle = std::ptr::null_mut();
+        if ok == 0 {
+            let error_msg = format!("Failed to close WinDivert handle. Error: {}", std::io::Error::last_os_error());
+            logging::debug_error(&error_msg);
+            return Err(error_msg);
+        }
+        logging::debug_info("WinDivert handle closed.");
+        Ok(())
+    }
+
+    fn stats(&self) -> CaptureStats {
+        self.stats
+    }
+
+    fn interrupter(&self) -> Option<Interrupter> {
+        let api = self.api.clone()?;
+        let open_handle = self.open_handle.clone();
+        Some(Box::new(move || shutdown_recv(&api, &open_handle)))
+    }
+}
+
+impl Drop for WinDivertBackend {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlowEvent {
+    Established,
+    Deleted,
+}
+
+// WinDivert handle on the flow layer. It carries no packets, only an event
+// with the owning process ID whenever a connection is set up or torn down
+// (WINDIVERT_DATA_FLOW in the address union, see process_attribution).
+pub struct WinDivertFlowMonitor {
+    api: Arc<WinDivertApi>,
+    handle: *mut c_void,
+    open_handle: OpenHandle,
+}
+
+unsafe impl Send for WinDivertFlowMonitor {}
+
+impl WinDivertFlowMonitor {
+    pub fn open() -> Result<Self, String> {
+        let api = WinDivertApi::load()?;
+        let filter = CString::new("true").map_err(|e| format!("CString::new failed: {}", e))?;
+        // The flow layer only supports sniffing
+        let flags = WINDIVERT_FLAG_SNIFF | WINDIVERT_FLAG_RECV_ONLY;
+        let handle = unsafe { (api.open)(filter.as_ptr(), WINDIVERT_LAYER_FLOW, 0, flags) };
+        if handle.is_null() || handle as isize == INVALID_HANDLE_VALUE {
+            return Err(format!("Failed to open WinDivert flow handle. Error: {}", std::io::Error::last_os_error()));
+        }
+        logging::debug_info("WinDivert flow handle opened successfully.");
+        Ok(WinDivertFlowMonitor {
+            api: Arc::new(api),
+            handle,
+            open_handle: Arc::new(Mutex::new(Some(SharedHandle(handle)))),
+        })
+    }
+
+    // Wakes a recv blocked on another thread, which then returns an error
+    pub fn interrupter(&self) -> Interrupter {
+        let api = self.api.clone();
+        let open_handle = self.open_handle.clone();
+        Box::new(move || shutdown_recv(&api, &open_handle))
+    }
+
+    // Blocks until the next flow event
+    pub fn recv(&mut self) -> Result<(FlowEvent, [u8; 64]), String> {
+        loop {
+            let mut addr = WinDivertAddress::zeroed();
+            let ok = unsafe { (self.api.recv)(self.handle, std::ptr::null_mut(), 0, std::ptr::null_mut(), &mut addr) };
+            if ok == 0 {
+                return Err(format!("Failed to receive flow event. Error: {}", std::io::Error::last_os_error()));
+            }
+            match addr.event() {
+                WINDIVERT_EVENT_FLOW_ESTABLISHED => return Ok((FlowEvent::Established, addr.data)),
+                WINDIVERT_EVENT_FLOW_DELETED => return Ok((FlowEvent::Deleted, addr.data)),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Drop for WinDivertFlowMonitor {
+    fn drop(&mut self) {
+        let mut open_handle = self.open_handle.lock().unwrap();
+        *open_handle = None;
+        unsafe { (self.api.close)(self.handle) };
+        logging::debug_info("WinDivert flow handle closed.");
+    }
 }
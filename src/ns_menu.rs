@@ -1,4 +1,11 @@
-use crate::logging;
+use s2o_net_lib::logging;
+use crate::app_state::AppState;
+use crate::gui_engine_menu::MenuState;
+use crate::gui_engine_screens::{MenuEntry, Screen};
+use crate::s_menu;
+use s2o_net_lib::connections::{self, SortColumn};
+use s2o_net_lib::interface_inventory::{self, NetworkInterface};
+use eframe::egui::{self, Align2, Area, Context, Id, RichText};
 
 pub fn init_module() -> Result<(), String> {
     // Placeholder for actual initialization logic
@@ -12,21 +19,136 @@ pub fn init_module() -> Result<(), String> {
     }
 }
 
-// Placeholder menu items for ns_menu
-#[allow(dead_code)]
-pub fn menu_items() -> Vec<crate::gui_engine_menu::MenuItem> {
+pub fn screen() -> Screen {
+    Screen {
+        state: AppState::NSMenu,
+        title: "NS Menu",
+        items,
+        on_enter: None,
+        render: None,
+    }
+}
+
+pub fn firewall_screen() -> Screen {
+    Screen {
+        state: AppState::FirewallMenu,
+        title: "Firewall Menu",
+        items: s_menu::firewall_entries,
+        on_enter: None,
+        render: None,
+    }
+}
+
+pub fn interface_screen() -> Screen {
+    Screen {
+        state: AppState::InterfaceMenu,
+        title: "Interface Menu",
+        items: interface_items,
+        // Adapters come and go, so re-read them whenever the screen opens
+        on_enter: Some(interface_inventory::refresh),
+        render: Some(render_interface_details),
+    }
+}
+
+pub fn connections_screen() -> Screen {
+    Screen {
+        state: AppState::ConnectionsMenu,
+        title: "Connections",
+        items: connections_items,
+        on_enter: None,
+        render: Some(render_connections),
+    }
+}
+
+fn items() -> Vec<MenuEntry> {
     vec![
-        crate::gui_engine_menu::MenuItem {
-            label: "Firewall Menu".to_string(),
-            action: None,
-        },
-        crate::gui_engine_menu::MenuItem {
-            label: "Interface Menu".to_string(),
-            action: None,
-        },
-		crate::gui_engine_menu::MenuItem {
-			label: "Exit".to_string(),
-			action: None,
-		},
+        MenuEntry::open("Firewall Menu", AppState::FirewallMenu),
+        MenuEntry::open("Interface Menu", AppState::InterfaceMenu),
+        MenuEntry::open("Connections", AppState::ConnectionsMenu),
     ]
-}
\ No newline at end of file
+}
+
+// "All Interfaces", then one item per interface, then Refresh.
+// The capture interface is marked with '*'.
+fn interface_items() -> Vec<MenuEntry> {
+    let chosen = interface_inventory::capture_interface().map(|interface| interface.name);
+    let mark = |selected: bool, label: &str| format!("{} {}", if selected { "*" } else { " " }, label);
+
+    let mut items = vec![MenuEntry::run(mark(chosen.is_none(), "All Interfaces"), || {
+        interface_inventory::set_capture_interface(None)
+    })];
+    for interface in interface_inventory::inventory() {
+        let selected = chosen.as_deref() == Some(interface.name.as_str());
+        items.push(MenuEntry::run(mark(selected, &interface.to_string()), move || {
+            interface_inventory::set_capture_interface(Some(interface.clone()))
+        }));
+    }
+    items.push(MenuEntry::run("Refresh", interface_inventory::refresh));
+    items
+}
+
+// Details of the highlighted interface, beside the Interface Menu
+fn render_interface_details(ctx: &Context, menu_state: &MenuState) {
+    let settings = menu_state.get_settings();
+    // Item 0 is "All Interfaces"
+    let interfaces = interface_inventory::inventory();
+    let Some(interface): Option<&NetworkInterface> = menu_state.selected.checked_sub(1).and_then(|index| interfaces.get(index)) else {
+        return;
+    };
+    Area::new(Id::new("interface_details_area"))
+        .anchor(Align2::LEFT_CENTER, (20.0, 0.0))
+        .show(ctx, |ui| {
+            for line in interface.details() {
+                ui.label(RichText::new(line).color(settings.option_color_unselected));
+            }
+        });
+}
+
+fn sort_label(column: SortColumn) -> String {
+    match connections::sort_order() {
+        (current, descending) if current == column => {
+            format!("{} {}", column.title(), if descending { "▼" } else { "▲" })
+        }
+        _ => column.title().to_string(),
+    }
+}
+
+// One "Sort by" item per column; picking the current column again reverses it
+fn connections_items() -> Vec<MenuEntry> {
+    SortColumn::ALL
+        .into_iter()
+        .map(|column| MenuEntry::run(format!("Sort by {}", sort_label(column)), move || connections::sort_by(column)))
+        .collect()
+}
+
+// Socket table under the Connections menu; the headers sort on click too
+fn render_connections(ctx: &Context, menu_state: &MenuState) {
+    let settings = menu_state.get_settings();
+    let rows = connections::snapshot();
+    Area::new(Id::new("connections_area"))
+        .anchor(Align2::CENTER_BOTTOM, (0.0, -40.0))
+        .show(ctx, |ui| {
+            ui.label(
+                RichText::new(format!("{} sockets, refreshed every {} s", rows.len(), connections::REFRESH_INTERVAL.as_secs()))
+                    .color(settings.option_color_unselected),
+            );
+            egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                egui::Grid::new("connections_grid").striped(true).spacing([24.0, 2.0]).show(ui, |ui| {
+                    for column in SortColumn::ALL {
+                        if ui.selectable_label(connections::sort_order().0 == column, sort_label(column)).clicked() {
+                            connections::sort_by(column);
+                        }
+                    }
+                    ui.end_row();
+                    for row in &rows {
+                        ui.label(row.protocol_name());
+                        ui.label(connections::format_endpoint(&row.local));
+                        ui.label(row.remote.as_ref().map(connections::format_endpoint).unwrap_or_else(|| "*".to_string()));
+                        ui.label(row.state.map(|state| state.to_string()).unwrap_or_else(|| "-".to_string()));
+                        ui.label(row.owner());
+                        ui.end_row();
+                    }
+                });
+            });
+        });
+}
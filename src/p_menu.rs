@@ -1,7 +1,6 @@
-use crate::logging;
+use s2o_net_lib::logging;
 use crate::app_state::AppState;
-use crate::gui_engine_menu::MenuItem;
-use crate::gui_engine_style::MenuSettings;  // Import MenuSettings
+use crate::gui_engine_screens::{MenuEntry, Screen};
 
 pub fn init_module() -> Result<(), String> {
     // Placeholder for actual initialization logic
@@ -15,36 +14,20 @@ pub fn init_module() -> Result<(), String> {
     }
 }
 
-// Placeholder menu items for p_menu
-pub fn menu_items<S: Fn(AppState) + Clone + 'static>(
-    set_app_state: S,
-    menu_settings: &MenuSettings,  // Add menu_settings parameter
-) -> Vec<MenuItem> {
+pub fn screen() -> Screen {
+    Screen {
+        state: AppState::PMenu,
+        title: "P Menu",
+        items,
+        on_enter: None,
+        render: None,
+    }
+}
+
+fn items() -> Vec<MenuEntry> {
     vec![
-        MenuItem {
-            label: menu_settings.apply_label("PC Menu", false).text().to_string(),  // Use menu_settings and convert to String
-            action: Some(Box::new({
-                let set_app_state = set_app_state.clone();
-                move || set_app_state(AppState::PCMenu)
-            })),
-        },
-        MenuItem {
-            label: menu_settings.apply_label("DS Menu", false).text().to_string(),  // Use menu_settings and convert to String
-            action: Some(Box::new({
-                let set_app_state = set_app_state.clone();
-                move || set_app_state(AppState::DSMenu)
-            })),
-        },
-        MenuItem {
-            label: menu_settings.apply_label("NS Menu", false).text().to_string(),  // Use menu_settings and convert to String
-            action: Some(Box::new({
-                let set_app_state = set_app_state.clone();
-                move || set_app_state(AppState::NSMenu)
-            })),
-        },
-        MenuItem {
-            label: "Exit".to_string(),  // No need to use menu_settings for a simple label
-            action: Some(Box::new(|| std::process::exit(0))),
-        },
+        MenuEntry::open("PC Menu", AppState::PCMenu),
+        MenuEntry::open("DS Menu", AppState::DSMenu),
+        MenuEntry::open("NS Menu", AppState::NSMenu),
     ]
 }
@@ -1,120 +1,331 @@
-use std::sync::{Arc, Mutex, mpsc::{channel, Sender, Receiver}};
-use std::sync::atomic::{AtomicBool};
-use eframe::egui::Context;
+use crate::logging;
+use crate::app_dissector::AppLayerInfo;
+use crate::capture_backend::{self, CaptureBackend, CaptureStats, CapturedPacket, Interrupter};
+use crate::capture_filter::CaptureFilter;
+use crate::flow_table;
+use crate::inline_verdict::{self, InlineStats, PacketVerdict, VerdictFn};
+use crate::packet_decoder;
+use crate::packet_store::{FullPolicy, PacketStore, PushOutcome, StoreLimits};
+use crate::pcap_reader::PcapReplayBackend;
+use crate::process_attribution;
 use once_cell::sync::Lazy;
-use crate::logging::{LogBuffers, log_info, log_error};
-use crate::nc::{start_packet_capture, stop_packet_capture, count_packets, print_packet_data};
-use std::io::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
 
-// Wrapping AtomicBool in Lazy for proper initialization
-pub static CAPTURING: Lazy<Arc<Mutex<bool>>> = Lazy::new(|| Arc::new(Mutex::new(false)));
-pub static SHOW_PACKET_POPUP: Lazy<AtomicBool> = Lazy::new(|| AtomicBool::new(false));
-pub static SHOW_PACKET_COUNT_POPUP: Lazy<AtomicBool> = Lazy::new(|| AtomicBool::new(false));
+// Largest packet any backend can hand us
+const MAX_PACKET_SIZE: usize = 65535;
+// recv errors in a row before the capture gives up on the backend, and the
+// pause after each one, growing with the count up to MAX_ERROR_BACKOFF
+const MAX_CONSECUTIVE_ERRORS: u32 = 20;
+const ERROR_BACKOFF: Duration = Duration::from_millis(50);
+const MAX_ERROR_BACKOFF: Duration = Duration::from_secs(1);
 
-// Channel for sending stop signals
-pub static STOP_CAPTURE_SENDER: Lazy<Mutex<Option<Sender<bool>>>> = Lazy::new(|| Mutex::new(None));
+// File used by the PC menu replay and export items
+pub const DEFAULT_CAPTURE_FILE: &str = "capture.pcapng";
+// Where an offline inline replay writes the packets it would have reinjected
+pub const INLINE_REPLAY_FILE: &str = "capture_inline.pcapng";
 
-pub fn init_packet(log_buffers: &Arc<LogBuffers>) -> Result<(), Error> {
-    log_info(log_buffers, "Initializing packet_capture.", false);
-    
+pub static CAPTURED_PACKETS: Lazy<PacketStore> = Lazy::new(|| PacketStore::new(StoreLimits::default()));
+pub(crate) static CAPTURING: AtomicBool = AtomicBool::new(false);
+static STOP_REQUESTED: Lazy<Arc<AtomicBool>> = Lazy::new(|| Arc::new(AtomicBool::new(false)));
+static CAPTURE_THREAD: Lazy<Mutex<Option<JoinHandle<()>>>> = Lazy::new(|| Mutex::new(None));
+// Wakes the capture thread when its backend's recv blocks without a timeout
+static CAPTURE_INTERRUPT: Lazy<Mutex<Option<Interrupter>>> = Lazy::new(|| Mutex::new(None));
+static CAPTURE_STATS: Lazy<Mutex<CaptureStats>> = Lazy::new(|| Mutex::new(CaptureStats::default()));
+static INLINE_STATS: Lazy<Mutex<InlineStats>> = Lazy::new(|| Mutex::new(InlineStats::default()));
+
+pub fn init_module() -> Result<(), String> {
+    // Placeholder for actual initialization logic
+    let initialization_passed = true;
+
+    if initialization_passed {
+        logging::debug_info("packet_capture module is online");
+        Ok(())
+    } else {
+        Err("packet_capture module initialization failed".to_string())
+    }
+}
+
+// Opens and closes the platform backend once to check it is usable
+pub fn run_preliminary_tests() -> Result<(), String> {
+    logging::debug_info("Running preliminary tests...");
+    let mut backend = capture_backend::default_backend()?;
+    backend.open("true")?;
+    backend.close()?;
+    logging::debug_info(&format!("Preliminary tests for {} completed successfully.", backend.name()));
     Ok(())
 }
 
-pub fn start_capture(log_buffers: &LogBuffers) {
-    if let Err(e) = crate::nc::run_preliminary_tests(log_buffers) {
-        log_error(log_buffers, &format!("Failed preliminary tests: {}", e), false);
-    } else {
-        let mut capturing = CAPTURING.lock().unwrap();
-        *capturing = true;
-        log_info(log_buffers, "Packet capture started.", false);
+// Starts capturing on the platform's default backend, keeping only packets
+// that match the filter expression
+pub fn start_capture(filter: &str) -> Result<(), String> {
+    let filter = CaptureFilter::parse(filter)?;
+    let backend = capture_backend::default_backend()?;
+    start_attribution();
+    spawn_capture_thread(backend, filter, None)
+}
+
+// Attribution is best effort: without it flows simply have no process
+fn start_attribution() {
+    if let Err(e) = process_attribution::start() {
+        logging::debug_error(&format!("Process attribution unavailable: {}", e));
     }
 }
 
-pub fn stop_capture(log_buffers: &LogBuffers) {
-    let sender = STOP_CAPTURE_SENDER.lock().unwrap().clone();
-    let log_buffers = log_buffers.clone();
-    std::thread::spawn(move || {
-        if let Some(sender) = sender {
-            sender.send(true).expect("Failed to send stop signal");
-            log_info(&log_buffers, "Packet capture stopped.", false);
-        } else {
-            log_error(&log_buffers, "Stop sender not initialized.", false);
-        }
-    });
+// Like start_capture, but packets matching the filter are held until the
+// firewall rules decide them, and only allowed ones are reinjected
+pub fn start_inline_capture(filter: &str) -> Result<(), String> {
+    start_inline_capture_with(filter, inline_verdict::firewall_verdict())
+}
+
+// Inline capture where `verdict` accepts, drops or rewrites each packet
+pub fn start_inline_capture_with(filter: &str, verdict: VerdictFn) -> Result<(), String> {
+    let filter = CaptureFilter::parse(filter)?;
+    let mut backend = capture_backend::default_backend()?;
+    backend.set_inline(true)?;
+    start_attribution();
+    spawn_capture_thread(backend, filter, Some(verdict))
+}
+
+// Plays a pcap/pcapng file through the same pipeline as a live capture
+pub fn start_replay(path: &str, filter: &str) -> Result<(), String> {
+    let filter = CaptureFilter::parse(filter)?;
+    spawn_capture_thread(Box::new(PcapReplayBackend::new(path)), filter, None)
 }
 
-pub fn spawn_capture_thread(ctx: Arc<Context>, log_buffers: Arc<LogBuffers>) {
-    log_info(&log_buffers, "Spawning packet capture thread...", false);
-    let (sender, receiver): (Sender<bool>, Receiver<bool>) = channel(); // Create a new channel for each thread
-    {
-        let mut stop_sender = STOP_CAPTURE_SENDER.lock().unwrap();
-        *stop_sender = Some(sender.clone()); // Use the sender to send the stop signal
+// Opens `backend` and moves it onto a worker thread that fills
+// CAPTURED_PACKETS. With a verdict callback the backend must already be
+// inline, and every packet it diverts is decided and handed back.
+pub(crate) fn spawn_capture_thread(
+    mut backend: Box<dyn CaptureBackend>,
+    filter: CaptureFilter,
+    mut verdict: Option<VerdictFn>,
+) -> Result<(), String> {
+    if CAPTURING.swap(true, Ordering::SeqCst) {
+        return Err("Capture already running.".to_string());
     }
-    std::thread::spawn(move || {
-        let (lib, filter) = match crate::nc::load_dll(&log_buffers) {
-            Ok((lib, filter)) => {
-                log_info(&log_buffers, "DLL loaded successfully.", false);
-                (lib, filter)
-            },
-            Err(e) => {
-                log_error(&log_buffers, &format!("Failed to load DLL: {}", e), false);
-                return;
-            }
-        };
 
-        start_packet_capture(&log_buffers, lib.clone(), filter, CAPTURING.clone());
-        log_info(&log_buffers, "Packet capture started in thread.", false);
+    let native_filter = backend.native_filter(&filter);
+    if let Err(e) = backend.open(&native_filter) {
+        CAPTURING.store(false, Ordering::SeqCst);
+        return Err(e);
+    }
+
+    logging::debug_info(&format!(
+        "Spawning packet capture thread on {} with filter \"{}\"...",
+        backend.name(),
+        filter.as_str()
+    ));
+    STOP_REQUESTED.store(false, Ordering::SeqCst);
+    let stop_requested = STOP_REQUESTED.clone();
+    *CAPTURE_INTERRUPT.lock().unwrap() = backend.interrupter();
+    *INLINE_STATS.lock().unwrap() = InlineStats::default();
+
+    let handle = std::thread::spawn(move || {
+        let mut buf = vec![0u8; MAX_PACKET_SIZE];
+        let mut consecutive_errors = 0;
 
-        loop {
-            ctx.request_repaint();
-            if let Ok(_) = receiver.try_recv() {
-                break;
+        while !stop_requested.load(Ordering::SeqCst) && !backend.is_finished() {
+            match backend.recv(&mut buf) {
+                Ok(Some(meta)) => {
+                    consecutive_errors = 0;
+                    let data = &buf[..meta.captured_len];
+                    let decoded = packet_decoder::decode(data, meta.link_type);
+                    let wanted = filter.matches(&decoded);
+                    // One attribution lookup and rule evaluation serves the
+                    // inline verdict, the log rules and the flow table alike
+                    let checked = wanted.then(|| inline_verdict::evaluate(&decoded, meta.outbound));
+                    if let Some(verdict) = verdict.as_mut() {
+                        // The driver may divert more than the Rust filter wants; pass those through
+                        let decision = match &checked {
+                            Some((_, evaluation)) => verdict(&meta, data, &decoded, evaluation),
+                            None => PacketVerdict::Accept,
+                        };
+                        let mut inline_stats = INLINE_STATS.lock().unwrap();
+                        inline_verdict::handle_packet(backend.as_mut(), &mut inline_stats, decision, data, meta.link_type);
+                    }
+                    if let Some((owner, evaluation)) = checked {
+                        for rule in &evaluation.logged {
+                            logging::log_info(&format!("Firewall rule {} matched: {}", rule, decoded.summary()));
+                        }
+                        let mut flows = flow_table::FLOW_TABLE.lock().unwrap();
+                        flows.process(&decoded, meta.timestamp);
+                        if let (Some(owner), Some(key)) = (&owner, flow_table::FlowKey::from_packet(&decoded)) {
+                            flows.attribute(&key, owner);
+                        }
+                        drop(flows);
+                        let packet = CapturedPacket::with_decoded(meta, data.to_vec(), &decoded);
+                        // Inline mode keeps enforcing even once nothing more can be stored
+                        if CAPTURED_PACKETS.push(packet) == PushOutcome::Rejected && verdict.is_none() {
+                            logging::debug_error("Packet store is full; stopping capture.");
+                            break;
+                        }
+                    }
+                }
+                Ok(None) => consecutive_errors = 0,
+                // A shutdown from stop_capture makes the pending recv fail
+                Err(_) if stop_requested.load(Ordering::SeqCst) => break,
+                Err(e) => {
+                    logging::debug_error(&e);
+                    consecutive_errors += 1;
+                    if consecutive_errors >= MAX_CONSECUTIVE_ERRORS {
+                        logging::debug_error(&format!("{} receive errors in a row; stopping capture.", consecutive_errors));
+                        break;
+                    }
+                    std::thread::sleep((ERROR_BACKOFF * consecutive_errors).min(MAX_ERROR_BACKOFF));
+                }
             }
-            std::thread::sleep(std::time::Duration::from_millis(100));
-            log_info(&log_buffers, "Packet capture thread running...", false);
+            *CAPTURE_STATS.lock().unwrap() = backend.stats();
         }
 
-        stop_packet_capture(&log_buffers, CAPTURING.clone());
-        crate::nc::unload_dll(&log_buffers, Some(lib));
-        log_info(&log_buffers, "Packet capture thread terminated.", false);
+        if let Err(e) = backend.close() {
+            logging::debug_error(&format!("Failed to close {}: {}", backend.name(), e));
+        }
+        process_attribution::stop();
+        CAPTURING.store(false, Ordering::SeqCst);
+        logging::debug_info("Packet capture thread terminated.");
     });
+
+    *CAPTURE_THREAD.lock().unwrap() = Some(handle);
+    Ok(())
+}
+
+// Signals the capture thread to stop and waits for it to close its backend
+pub fn stop_capture() -> Result<(), String> {
+    let handle = CAPTURE_THREAD.lock().unwrap().take();
+    match handle {
+        Some(handle) => {
+            STOP_REQUESTED.store(true, Ordering::SeqCst);
+            if let Some(interrupt) = CAPTURE_INTERRUPT.lock().unwrap().take() {
+                interrupt();
+            }
+            logging::debug_info("Packet capture stop signal sent.");
+            handle.join().map_err(|_| "Capture thread panicked".to_string())?;
+            logging::debug_info("Packet capture stopped.");
+            Ok(())
+        }
+        None => Err("No capture to stop.".to_string()),
+    }
+}
+
+pub fn is_capturing() -> bool {
+    CAPTURING.load(Ordering::SeqCst)
 }
 
-pub fn get_packet_data(log_buffers: &LogBuffers) -> Vec<String> {
-    // Capture the output of print_packet_data and return as Vec<String>
-    let mut buffer = Vec::new();
-    let mut writer = std::io::Cursor::new(&mut buffer);
+pub fn capture_stats() -> CaptureStats {
+    *CAPTURE_STATS.lock().unwrap()
+}
+
+pub fn inline_stats() -> InlineStats {
+    *INLINE_STATS.lock().unwrap()
+}
 
-    // Redirect stdout temporarily
-    let stdout = std::io::stdout();
-    let mut handle = stdout.lock();
-    std::io::copy(&mut writer, &mut handle).unwrap();
+pub fn get_packet_count() -> usize {
+    CAPTURED_PACKETS.len()
+}
 
-    // Execute function to print packet data
-    print_packet_data(log_buffers);
+// Packets stored since the process started, whether or not they have been
+// evicted since; unlike get_packet_count it is not capped by the store limits
+pub fn get_accepted_count() -> u64 {
+    CAPTURED_PACKETS.stats().accepted_packets
+}
 
-    // Restore stdout
-    std::io::copy(&mut writer, &mut handle).unwrap();
+// One summary line per captured packet
+pub fn get_packet_data() -> Vec<String> {
+    let packets = CAPTURED_PACKETS.snapshot();
+    packets
+        .iter()
+        .enumerate()
+        .map(|(index, packet)| {
+            let mut line = format!(
+                "#{} if={} {} {} bytes: {}",
+                index,
+                packet.meta.interface_index,
+                if packet.meta.outbound { "out" } else { "in" },
+                packet.meta.original_len,
+                packet_decoder::decode(&packet.data, packet.meta.link_type).summary()
+            );
+            if let Some(app) = &packet.app_layer {
+                line.push_str(&format!(" | {}", app.summary()));
+            }
+            line
+        })
+        .collect()
+}
 
-    // Convert buffer to Vec<String>
-    String::from_utf8(buffer).unwrap().lines().map(|s| s.to_string()).collect()
+// Which host asked about which domain, one entry per distinct pair
+pub fn get_domain_contacts() -> Vec<(String, String)> {
+    let packets = CAPTURED_PACKETS.snapshot();
+    let mut contacts: Vec<(String, String)> = Vec::new();
+    for packet in packets.iter() {
+        let domain = match packet.app_layer.as_ref().and_then(|app| app.domain()) {
+            Some(domain) => domain.to_string(),
+            None => continue,
+        };
+        let decoded = packet_decoder::decode(&packet.data, packet.meta.link_type);
+        // For DNS answers the asking host is the destination
+        let client = match &packet.app_layer {
+            Some(AppLayerInfo::Dns(dns)) if dns.is_response => decoded.destination_ip(),
+            _ => decoded.source_ip(),
+        };
+        let client = client.map(|ip| ip.to_string()).unwrap_or_else(|| "?".to_string());
+        if !contacts.iter().any(|(c, d)| *c == client && *d == domain) {
+            contacts.push((client, domain));
+        }
+    }
+    contacts
 }
 
-pub fn get_packet_count(log_buffers: &LogBuffers) -> usize {
-    let mut buffer = Vec::new();
-    let mut writer = std::io::Cursor::new(&mut buffer);
+pub fn print_domain_contacts() {
+    let contacts = get_domain_contacts();
+    logging::debug_info(&format!("Domains seen in capture: {}", contacts.len()));
+    for (client, domain) in contacts {
+        logging::debug_info(&format!("{} -> {}", client, domain));
+    }
+}
 
-    // Redirect stdout temporarily
-    let stdout = std::io::stdout();
-    let mut handle = stdout.lock();
-    std::io::copy(&mut writer, &mut handle).unwrap();
+// Caps how much the capture keeps in memory and what happens when it is full
+pub fn set_store_limits(limits: StoreLimits) {
+    CAPTURED_PACKETS.set_limits(limits);
+    logging::debug_info(&format!("Packet store limits set to {:?}", limits));
+}
 
-    // Execute function to count packets
-    count_packets(log_buffers);
+// Switches between ring-buffer and stop-on-full, keeping the current caps
+pub fn toggle_full_policy() {
+    let mut limits = CAPTURED_PACKETS.limits();
+    limits.policy = match limits.policy {
+        FullPolicy::DropOldest => FullPolicy::StopOnFull,
+        FullPolicy::StopOnFull => FullPolicy::DropOldest,
+    };
+    set_store_limits(limits);
+}
 
-    // Restore stdout
-    std::io::copy(&mut writer, &mut handle).unwrap();
+pub fn clear_packets() {
+    CAPTURED_PACKETS.clear();
+    logging::debug_info("Captured packets cleared.");
+}
 
-    // Convert buffer to usize
-    String::from_utf8(buffer).unwrap().trim().parse::<usize>().unwrap_or(0)
+pub fn print_packet_data() {
+    let stats = capture_stats();
+    let store = CAPTURED_PACKETS.stats();
+    logging::log_info(&format!(
+        "Total packets captured: {} ({} bytes received, {} receive errors, {} evicted, {} rejected)",
+        get_packet_count(),
+        stats.bytes_received,
+        stats.recv_errors,
+        store.evicted_packets,
+        store.rejected_packets
+    ));
+    let inline = inline_stats();
+    if inline != InlineStats::default() {
+        logging::log_info(&format!(
+            "Inline verdicts: {} accepted, {} dropped, {} rewritten, {} send errors",
+            inline.accepted, inline.dropped, inline.rewritten, inline.send_errors
+        ));
+    }
+    for line in get_packet_data() {
+        logging::log_info(&line);
+    }
 }
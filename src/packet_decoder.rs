@@ -0,0 +1,852 @@
+use crate::logging;
+use crate::capture_backend::LinkType;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+pub fn init_module() -> Result<(), String> {
+    // Placeholder for actual initialization logic
+    let initialization_passed = true;
+
+    if initialization_passed {
+        logging::debug_info("packet_decoder module is online");
+        Ok(())
+    } else {
+        Err("packet_decoder module initialization failed".to_string())
+    }
+}
+
+pub const ETHERTYPE_IPV4: u16 = 0x0800;
+pub const ETHERTYPE_ARP: u16 = 0x0806;
+pub const ETHERTYPE_VLAN: u16 = 0x8100;
+pub const ETHERTYPE_QINQ: u16 = 0x88a8;
+pub const ETHERTYPE_IPV6: u16 = 0x86dd;
+
+pub const IP_PROTO_ICMP: u8 = 1;
+pub const IP_PROTO_TCP: u8 = 6;
+pub const IP_PROTO_UDP: u8 = 17;
+pub const IP_PROTO_ICMPV6: u8 = 58;
+
+// IPv6 extension headers we walk past to reach the transport header
+const IPV6_HOP_BY_HOP: u8 = 0;
+const IPV6_ROUTING: u8 = 43;
+const IPV6_FRAGMENT: u8 = 44;
+const IPV6_AUTH: u8 = 51;
+const IPV6_DEST_OPTS: u8 = 60;
+const IPV6_NO_NEXT: u8 = 59;
+
+// Linux cooked capture v1: packet type, ARPHRD type, address length,
+// 8 address bytes and the protocol as an EtherType
+pub const SLL_HEADER_LEN: usize = 16;
+
+pub const TCP_FIN: u16 = 0x001;
+pub const TCP_SYN: u16 = 0x002;
+pub const TCP_RST: u16 = 0x004;
+pub const TCP_PSH: u16 = 0x008;
+pub const TCP_ACK: u16 = 0x010;
+pub const TCP_URG: u16 = 0x020;
+pub const TCP_ECE: u16 = 0x040;
+pub const TCP_CWR: u16 = 0x080;
+
+pub type MacAddr = [u8; 6];
+
+pub fn format_mac(mac: &MacAddr) -> String {
+    mac.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(":")
+}
+
+fn be16(data: &[u8], at: usize) -> u16 {
+    u16::from_be_bytes([data[at], data[at + 1]])
+}
+
+fn be32(data: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]])
+}
+
+fn need(data: &[u8], len: usize, what: &str) -> Result<(), String> {
+    if data.len() < len {
+        Err(format!("{} truncated: {} of {} bytes", what, data.len(), len))
+    } else {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EthernetHeader {
+    pub destination: MacAddr,
+    pub source: MacAddr,
+    // EtherType after any VLAN tags
+    pub ethertype: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VlanTag {
+    pub tpid: u16,
+    pub priority: u8,
+    pub drop_eligible: bool,
+    pub vlan_id: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArpPacket {
+    pub hardware_type: u16,
+    pub protocol_type: u16,
+    pub operation: u16,
+    pub sender_mac: MacAddr,
+    pub sender_ip: Ipv4Addr,
+    pub target_mac: MacAddr,
+    pub target_ip: Ipv4Addr,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ipv4Header<'a> {
+    pub header_len: usize,
+    pub dscp_ecn: u8,
+    pub total_len: u16,
+    pub identification: u16,
+    pub dont_fragment: bool,
+    pub more_fragments: bool,
+    pub fragment_offset: u16,
+    pub ttl: u8,
+    pub protocol: u8,
+    pub checksum: u16,
+    pub source: Ipv4Addr,
+    pub destination: Ipv4Addr,
+    pub options: &'a [u8],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ipv6ExtensionHeader<'a> {
+    pub kind: u8,
+    pub data: &'a [u8],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ipv6Header<'a> {
+    pub traffic_class: u8,
+    pub flow_label: u32,
+    pub payload_len: u16,
+    pub next_header: u8,
+    pub hop_limit: u8,
+    pub source: Ipv6Addr,
+    pub destination: Ipv6Addr,
+    pub extensions: Vec<Ipv6ExtensionHeader<'a>>,
+    // Protocol after walking the extension headers
+    pub upper_protocol: u8,
+    // Offset, if the packet is a non-first fragment
+    pub fragment_offset: Option<u16>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NetworkLayer<'a> {
+    Ipv4(Ipv4Header<'a>),
+    Ipv6(Ipv6Header<'a>),
+    Arp(ArpPacket),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TcpOption<'a> {
+    MaxSegmentSize(u16),
+    WindowScale(u8),
+    SackPermitted,
+    Sack(Vec<(u32, u32)>),
+    Timestamp { value: u32, echo: u32 },
+    Other { kind: u8, data: &'a [u8] },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TcpHeader<'a> {
+    pub source_port: u16,
+    pub destination_port: u16,
+    pub sequence: u32,
+    pub acknowledgement: u32,
+    pub header_len: usize,
+    pub flags: u16,
+    pub window: u16,
+    pub checksum: u16,
+    pub urgent_pointer: u16,
+    pub options: &'a [u8],
+}
+
+impl<'a> TcpHeader<'a> {
+    pub fn has_flag(&self, flag: u16) -> bool {
+        self.flags & flag != 0
+    }
+
+    pub fn flag_names(&self) -> String {
+        let names = [
+            (TCP_SYN, "SYN"), (TCP_ACK, "ACK"), (TCP_FIN, "FIN"), (TCP_RST, "RST"),
+            (TCP_PSH, "PSH"), (TCP_URG, "URG"), (TCP_ECE, "ECE"), (TCP_CWR, "CWR"),
+        ];
+        names
+            .iter()
+            .filter(|(flag, _)| self.has_flag(*flag))
+            .map(|(_, name)| *name)
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    // Parses the raw option bytes; stops at End of Option List or garbage
+    pub fn parsed_options(&self) -> Vec<TcpOption<'a>> {
+        let mut parsed = Vec::new();
+        let mut rest = self.options;
+        while let Some(&kind) = rest.first() {
+            match kind {
+                0 => break,
+                1 => {
+                    rest = &rest[1..];
+                    continue;
+                }
+                _ => {}
+            }
+            if rest.len() < 2 || (rest[1] as usize) < 2 || rest.len() < rest[1] as usize {
+                break;
+            }
+            let len = rest[1] as usize;
+            let data = &rest[2..len];
+            parsed.push(match (kind, data.len()) {
+                (2, 2) => TcpOption::MaxSegmentSize(be16(data, 0)),
+                (3, 1) => TcpOption::WindowScale(data[0]),
+                (4, 0) => TcpOption::SackPermitted,
+                (5, n) if n % 8 == 0 => TcpOption::Sack(
+                    data.chunks(8).map(|block| (be32(block, 0), be32(block, 4))).collect(),
+                ),
+                (8, 8) => TcpOption::Timestamp { value: be32(data, 0), echo: be32(data, 4) },
+                _ => TcpOption::Other { kind, data },
+            });
+            rest = &rest[len..];
+        }
+        parsed
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UdpHeader {
+    pub source_port: u16,
+    pub destination_port: u16,
+    pub length: u16,
+    pub checksum: u16,
+}
+
+// Shared by ICMP and ICMPv6; `rest` is the 4 type specific header bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IcmpHeader {
+    pub icmp_type: u8,
+    pub code: u8,
+    pub checksum: u16,
+    pub rest: [u8; 4],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransportLayer<'a> {
+    Tcp(TcpHeader<'a>),
+    Udp(UdpHeader),
+    Icmp(IcmpHeader),
+    Icmpv6(IcmpHeader),
+}
+
+// A decoded packet. Layers are filled in as far as decoding got; `error`
+// explains why it stopped early (truncated capture, bad header, ...).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packet<'a> {
+    pub ethernet: Option<EthernetHeader>,
+    pub vlans: Vec<VlanTag>,
+    pub network: Option<NetworkLayer<'a>>,
+    pub transport: Option<TransportLayer<'a>>,
+    // Bytes after the deepest decoded header
+    pub payload: &'a [u8],
+    pub error: Option<String>,
+}
+
+impl<'a> Packet<'a> {
+    pub fn source_ip(&self) -> Option<IpAddr> {
+        match &self.network {
+            Some(NetworkLayer::Ipv4(ip)) => Some(IpAddr::V4(ip.source)),
+            Some(NetworkLayer::Ipv6(ip)) => Some(IpAddr::V6(ip.source)),
+            _ => None,
+        }
+    }
+
+    pub fn destination_ip(&self) -> Option<IpAddr> {
+        match &self.network {
+            Some(NetworkLayer::Ipv4(ip)) => Some(IpAddr::V4(ip.destination)),
+            Some(NetworkLayer::Ipv6(ip)) => Some(IpAddr::V6(ip.destination)),
+            _ => None,
+        }
+    }
+
+    // IP protocol number of the transport layer
+    pub fn ip_protocol(&self) -> Option<u8> {
+        match &self.network {
+            Some(NetworkLayer::Ipv4(ip)) => Some(ip.protocol),
+            Some(NetworkLayer::Ipv6(ip)) => Some(ip.upper_protocol),
+            _ => None,
+        }
+    }
+
+    pub fn ports(&self) -> Option<(u16, u16)> {
+        match &self.transport {
+            Some(TransportLayer::Tcp(tcp)) => Some((tcp.source_port, tcp.destination_port)),
+            Some(TransportLayer::Udp(udp)) => Some((udp.source_port, udp.destination_port)),
+            _ => None,
+        }
+    }
+
+    pub fn tcp(&self) -> Option<&TcpHeader<'a>> {
+        match &self.transport {
+            Some(TransportLayer::Tcp(tcp)) => Some(tcp),
+            _ => None,
+        }
+    }
+
+    // One line description used by the PC menu packet listing
+    pub fn summary(&self) -> String {
+        let endpoint = |ip: Option<IpAddr>, port: Option<u16>| match (ip, port) {
+            (Some(IpAddr::V6(ip)), Some(port)) => format!("[{}]:{}", ip, port),
+            (Some(ip), Some(port)) => format!("{}:{}", ip, port),
+            (Some(ip), None) => ip.to_string(),
+            _ => "?".to_string(),
+        };
+        let ports = self.ports();
+        let from = endpoint(self.source_ip(), ports.map(|p| p.0));
+        let to = endpoint(self.destination_ip(), ports.map(|p| p.1));
+
+        let mut line = match (&self.network, &self.transport) {
+            (Some(NetworkLayer::Arp(arp)), _) => format!(
+                "ARP {} {} -> {}",
+                if arp.operation == 1 { "request" } else { "reply" },
+                arp.sender_ip,
+                arp.target_ip
+            ),
+            (_, Some(TransportLayer::Tcp(tcp))) => format!("TCP {} -> {} [{}]", from, to, tcp.flag_names()),
+            (_, Some(TransportLayer::Udp(_))) => format!("UDP {} -> {}", from, to),
+            (_, Some(TransportLayer::Icmp(icmp))) => format!("ICMP {} -> {} type {}", from, to, icmp.icmp_type),
+            (_, Some(TransportLayer::Icmpv6(icmp))) => format!("ICMPv6 {} -> {} type {}", from, to, icmp.icmp_type),
+            (Some(_), None) => format!("IP proto {} {} -> {}", self.ip_protocol().unwrap_or(0), from, to),
+            (None, _) => match &self.ethernet {
+                Some(eth) => format!("Ethernet type 0x{:04x}", eth.ethertype),
+                None => "Unknown".to_string(),
+            },
+        };
+        if !self.vlans.is_empty() {
+            line.push_str(&format!(" vlan {}", self.vlans.iter().map(|v| v.vlan_id.to_string()).collect::<Vec<_>>().join("/")));
+        }
+        line.push_str(&format!(" payload {}", self.payload.len()));
+        if let Some(error) = &self.error {
+            line.push_str(&format!(" ({})", error));
+        }
+        line
+    }
+}
+
+// Decodes a packet starting at the given link layer
+pub fn decode(data: &[u8], link_type: LinkType) -> Packet<'_> {
+    let mut packet = Packet {
+        ethernet: None,
+        vlans: Vec::new(),
+        network: None,
+        transport: None,
+        payload: data,
+        error: None,
+    };
+    let result = match link_type {
+        LinkType::Ethernet => decode_ethernet(data, &mut packet),
+        LinkType::RawIp => decode_raw_ip(data, &mut packet),
+        LinkType::LinuxSll => decode_linux_sll(data, &mut packet),
+    };
+    if let Err(e) = result {
+        packet.error = Some(e);
+    }
+    packet
+}
+
+fn decode_ethernet<'a>(data: &'a [u8], packet: &mut Packet<'a>) -> Result<(), String> {
+    need(data, 14, "Ethernet header")?;
+    let mut destination = [0u8; 6];
+    let mut source = [0u8; 6];
+    destination.copy_from_slice(&data[0..6]);
+    source.copy_from_slice(&data[6..12]);
+    let mut ethertype = be16(data, 12);
+    let mut offset = 14;
+
+    while ethertype == ETHERTYPE_VLAN || ethertype == ETHERTYPE_QINQ {
+        need(&data[offset..], 4, "VLAN tag")?;
+        let tci = be16(data, offset);
+        packet.vlans.push(VlanTag {
+            tpid: ethertype,
+            priority: (tci >> 13) as u8,
+            drop_eligible: tci & 0x1000 != 0,
+            vlan_id: tci & 0x0fff,
+        });
+        ethertype = be16(data, offset + 2);
+        offset += 4;
+    }
+
+    packet.ethernet = Some(EthernetHeader { destination, source, ethertype });
+    packet.payload = &data[offset..];
+    let rest = &data[offset..];
+    match ethertype {
+        ETHERTYPE_IPV4 => decode_ipv4(rest, packet),
+        ETHERTYPE_IPV6 => decode_ipv6(rest, packet),
+        ETHERTYPE_ARP => decode_arp(rest, packet),
+        _ => Ok(()),
+    }
+}
+
+fn decode_linux_sll<'a>(data: &'a [u8], packet: &mut Packet<'a>) -> Result<(), String> {
+    need(data, SLL_HEADER_LEN, "Linux cooked header")?;
+    let rest = &data[SLL_HEADER_LEN..];
+    packet.payload = rest;
+    match be16(data, 14) {
+        ETHERTYPE_IPV4 => decode_ipv4(rest, packet),
+        ETHERTYPE_IPV6 => decode_ipv6(rest, packet),
+        ETHERTYPE_ARP => decode_arp(rest, packet),
+        _ => Ok(()),
+    }
+}
+
+// WinDivert and LINKTYPE_RAW hand us the IP header directly
+fn decode_raw_ip<'a>(data: &'a [u8], packet: &mut Packet<'a>) -> Result<(), String> {
+    need(data, 1, "IP header")?;
+    match data[0] >> 4 {
+        4 => decode_ipv4(data, packet),
+        6 => decode_ipv6(data, packet),
+        version => Err(format!("Unknown IP version {}", version)),
+    }
+}
+
+fn decode_arp<'a>(data: &'a [u8], packet: &mut Packet<'a>) -> Result<(), String> {
+    need(data, 8, "ARP header")?;
+    let hardware_type = be16(data, 0);
+    let protocol_type = be16(data, 2);
+    let (hlen, plen) = (data[4], data[5]);
+    if hlen != 6 || plen != 4 {
+        return Err(format!("Unsupported ARP address sizes {}/{}", hlen, plen));
+    }
+    need(data, 28, "ARP packet")?;
+    let mac = |at: usize| {
+        let mut mac = [0u8; 6];
+        mac.copy_from_slice(&data[at..at + 6]);
+        mac
+    };
+    let ip = |at: usize| Ipv4Addr::new(data[at], data[at + 1], data[at + 2], data[at + 3]);
+    packet.network = Some(NetworkLayer::Arp(ArpPacket {
+        hardware_type,
+        protocol_type,
+        operation: be16(data, 6),
+        sender_mac: mac(8),
+        sender_ip: ip(14),
+        target_mac: mac(18),
+        target_ip: ip(24),
+    }));
+    packet.payload = &data[28..];
+    Ok(())
+}
+
+fn decode_ipv4<'a>(data: &'a [u8], packet: &mut Packet<'a>) -> Result<(), String> {
+    need(data, 20, "IPv4 header")?;
+    if data[0] >> 4 != 4 {
+        return Err(format!("IPv4 header has version {}", data[0] >> 4));
+    }
+    let header_len = (data[0] & 0x0f) as usize * 4;
+    if header_len < 20 {
+        return Err(format!("IPv4 IHL of {} bytes is too small", header_len));
+    }
+    need(data, header_len, "IPv4 options")?;
+
+    let total_len = be16(data, 2);
+    let flags_fragment = be16(data, 6);
+    let header = Ipv4Header {
+        header_len,
+        dscp_ecn: data[1],
+        total_len,
+        identification: be16(data, 4),
+        dont_fragment: flags_fragment & 0x4000 != 0,
+        more_fragments: flags_fragment & 0x2000 != 0,
+        fragment_offset: flags_fragment & 0x1fff,
+        ttl: data[8],
+        protocol: data[9],
+        checksum: be16(data, 10),
+        source: Ipv4Addr::new(data[12], data[13], data[14], data[15]),
+        destination: Ipv4Addr::new(data[16], data[17], data[18], data[19]),
+        options: &data[20..header_len],
+    };
+
+    // Trim Ethernet padding; a short capture keeps what we have
+    let end = (total_len as usize).clamp(header_len, data.len());
+    let protocol = header.protocol;
+    let first_fragment = header.fragment_offset == 0;
+    packet.network = Some(NetworkLayer::Ipv4(header));
+    packet.payload = &data[header_len..end];
+
+    if first_fragment {
+        decode_transport(protocol, &data[header_len..end], packet)
+    } else {
+        Ok(())
+    }
+}
+
+fn decode_ipv6<'a>(data: &'a [u8], packet: &mut Packet<'a>) -> Result<(), String> {
+    need(data, 40, "IPv6 header")?;
+    if data[0] >> 4 != 6 {
+        return Err(format!("IPv6 header has version {}", data[0] >> 4));
+    }
+    let mut source = [0u8; 16];
+    let mut destination = [0u8; 16];
+    source.copy_from_slice(&data[8..24]);
+    destination.copy_from_slice(&data[24..40]);
+
+    let payload_len = be16(data, 4);
+    let end = (40 + payload_len as usize).min(data.len());
+    let mut header = Ipv6Header {
+        traffic_class: ((be16(data, 0) >> 4) & 0xff) as u8,
+        flow_label: be32(data, 0) & 0x000f_ffff,
+        payload_len,
+        next_header: data[6],
+        hop_limit: data[7],
+        source: Ipv6Addr::from(source),
+        destination: Ipv6Addr::from(destination),
+        extensions: Vec::new(),
+        upper_protocol: data[6],
+        fragment_offset: None,
+    };
+
+    let mut offset = 40;
+    let mut walk = || -> Result<(), String> {
+        loop {
+            let kind = header.upper_protocol;
+            let rest = &data[offset..end];
+            let len = match kind {
+                IPV6_HOP_BY_HOP | IPV6_ROUTING | IPV6_DEST_OPTS => {
+                    need(rest, 2, "IPv6 extension header")?;
+                    (rest[1] as usize + 1) * 8
+                }
+                IPV6_FRAGMENT => 8,
+                IPV6_AUTH => {
+                    need(rest, 2, "IPv6 authentication header")?;
+                    (rest[1] as usize + 2) * 4
+                }
+                _ => return Ok(()),
+            };
+            need(rest, len, "IPv6 extension header")?;
+            if kind == IPV6_FRAGMENT {
+                let offset_field = be16(rest, 2) >> 3;
+                if offset_field != 0 {
+                    header.fragment_offset = Some(offset_field);
+                }
+            }
+            header.extensions.push(Ipv6ExtensionHeader { kind, data: &rest[..len] });
+            header.upper_protocol = rest[0];
+            offset += len;
+        }
+    };
+    let walked = walk();
+
+    let protocol = header.upper_protocol;
+    let fragmented = header.fragment_offset.is_some();
+    packet.network = Some(NetworkLayer::Ipv6(header));
+    packet.payload = &data[offset..end];
+    walked?;
+
+    if fragmented || protocol == IPV6_NO_NEXT {
+        return Ok(());
+    }
+    decode_transport(protocol, &data[offset..end], packet)
+}
+
+fn decode_transport<'a>(protocol: u8, data: &'a [u8], packet: &mut Packet<'a>) -> Result<(), String> {
+    match protocol {
+        IP_PROTO_TCP => decode_tcp(data, packet),
+        IP_PROTO_UDP => decode_udp(data, packet),
+        IP_PROTO_ICMP | IP_PROTO_ICMPV6 => {
+            need(data, 8, "ICMP header")?;
+            let icmp = IcmpHeader {
+                icmp_type: data[0],
+                code: data[1],
+                checksum: be16(data, 2),
+                rest: [data[4], data[5], data[6], data[7]],
+            };
+            packet.transport = Some(if protocol == IP_PROTO_ICMP {
+                TransportLayer::Icmp(icmp)
+            } else {
+                TransportLayer::Icmpv6(icmp)
+            });
+            packet.payload = &data[8..];
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn decode_tcp<'a>(data: &'a [u8], packet: &mut Packet<'a>) -> Result<(), String> {
+    need(data, 20, "TCP header")?;
+    let header_len = (data[12] >> 4) as usize * 4;
+    if header_len < 20 {
+        return Err(format!("TCP data offset of {} bytes is too small", header_len));
+    }
+    need(data, header_len, "TCP options")?;
+    packet.transport = Some(TransportLayer::Tcp(TcpHeader {
+        source_port: be16(data, 0),
+        destination_port: be16(data, 2),
+        sequence: be32(data, 4),
+        acknowledgement: be32(data, 8),
+        header_len,
+        flags: be16(data, 12) & 0x01ff,
+        window: be16(data, 14),
+        checksum: be16(data, 16),
+        urgent_pointer: be16(data, 18),
+        options: &data[20..header_len],
+    }));
+    packet.payload = &data[header_len..];
+    Ok(())
+}
+
+fn decode_udp<'a>(data: &'a [u8], packet: &mut Packet<'a>) -> Result<(), String> {
+    need(data, 8, "UDP header")?;
+    let length = be16(data, 4);
+    packet.transport = Some(TransportLayer::Udp(UdpHeader {
+        source_port: be16(data, 0),
+        destination_port: be16(data, 2),
+        length,
+        checksum: be16(data, 6),
+    }));
+    let end = (length as usize).clamp(8, data.len());
+    packet.payload = &data[8..end];
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAC_A: MacAddr = [0x00, 0x11, 0x22, 0x33, 0x44, 0x55];
+    const MAC_B: MacAddr = [0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb];
+
+    fn ethernet(ethertype: u16, payload: &[u8]) -> Vec<u8> {
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&MAC_B);
+        frame.extend_from_slice(&MAC_A);
+        frame.extend_from_slice(&ethertype.to_be_bytes());
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    fn ipv4(protocol: u8, options: &[u8], payload: &[u8]) -> Vec<u8> {
+        let header_len = 20 + options.len();
+        let mut ip = vec![0x40 | (header_len / 4) as u8, 0];
+        ip.extend_from_slice(&((header_len + payload.len()) as u16).to_be_bytes());
+        ip.extend_from_slice(&[0x12, 0x34, 0x40, 0x00, 64, protocol, 0, 0]);
+        ip.extend_from_slice(&[192, 168, 1, 10]);
+        ip.extend_from_slice(&[93, 184, 216, 34]);
+        ip.extend_from_slice(options);
+        ip.extend_from_slice(payload);
+        ip
+    }
+
+    fn ipv6(next_header: u8, payload: &[u8]) -> Vec<u8> {
+        let mut ip = vec![0x60, 0x00, 0x00, 0x00];
+        ip.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+        ip.extend_from_slice(&[next_header, 64]);
+        ip.extend_from_slice(&"fe80::1".parse::<Ipv6Addr>().unwrap().octets());
+        ip.extend_from_slice(&"2001:db8::2".parse::<Ipv6Addr>().unwrap().octets());
+        ip.extend_from_slice(payload);
+        ip
+    }
+
+    fn tcp(flags: u8, options: &[u8], payload: &[u8]) -> Vec<u8> {
+        let header_len = 20 + options.len();
+        let mut seg = Vec::new();
+        seg.extend_from_slice(&51000u16.to_be_bytes());
+        seg.extend_from_slice(&443u16.to_be_bytes());
+        seg.extend_from_slice(&1000u32.to_be_bytes());
+        seg.extend_from_slice(&0u32.to_be_bytes());
+        seg.push(((header_len / 4) as u8) << 4);
+        seg.push(flags);
+        seg.extend_from_slice(&[0xff, 0xff, 0, 0, 0, 0]);
+        seg.extend_from_slice(options);
+        seg.extend_from_slice(payload);
+        seg
+    }
+
+    fn udp(payload: &[u8]) -> Vec<u8> {
+        let mut dgram = Vec::new();
+        dgram.extend_from_slice(&5353u16.to_be_bytes());
+        dgram.extend_from_slice(&53u16.to_be_bytes());
+        dgram.extend_from_slice(&((8 + payload.len()) as u16).to_be_bytes());
+        dgram.extend_from_slice(&[0, 0]);
+        dgram.extend_from_slice(payload);
+        dgram
+    }
+
+    #[test]
+    fn decodes_ethernet_ipv4_tcp_with_options() {
+        // MSS 1460, NOP, window scale 7, NOP, NOP, SACK permitted... padded to 12 bytes
+        let options = [2, 4, 0x05, 0xb4, 1, 3, 3, 7, 1, 1, 4, 2];
+        let frame = ethernet(ETHERTYPE_IPV4, &ipv4(IP_PROTO_TCP, &[], &tcp(0x02, &options, b"")));
+        let packet = decode(&frame, LinkType::Ethernet);
+
+        assert_eq!(packet.error, None);
+        let eth = packet.ethernet.as_ref().unwrap();
+        assert_eq!(eth.source, MAC_A);
+        assert_eq!(eth.destination, MAC_B);
+        assert_eq!(packet.source_ip(), Some("192.168.1.10".parse().unwrap()));
+        assert_eq!(packet.destination_ip(), Some("93.184.216.34".parse().unwrap()));
+        assert_eq!(packet.ports(), Some((51000, 443)));
+
+        let tcp = packet.tcp().unwrap();
+        assert!(tcp.has_flag(TCP_SYN));
+        assert!(!tcp.has_flag(TCP_ACK));
+        assert_eq!(
+            tcp.parsed_options(),
+            vec![TcpOption::MaxSegmentSize(1460), TcpOption::WindowScale(7), TcpOption::SackPermitted]
+        );
+        assert!(packet.payload.is_empty());
+    }
+
+    #[test]
+    fn honours_ipv4_ihl_and_strips_ethernet_padding() {
+        // 4 bytes of options (NOPs + EOL) push the transport header to offset 24
+        let mut frame = ethernet(ETHERTYPE_IPV4, &ipv4(IP_PROTO_UDP, &[1, 1, 1, 0], &udp(b"hi")));
+        frame.extend_from_slice(&[0u8; 12]); // padding up to the Ethernet minimum
+        let packet = decode(&frame, LinkType::Ethernet);
+
+        match &packet.network {
+            Some(NetworkLayer::Ipv4(ip)) => {
+                assert_eq!(ip.header_len, 24);
+                assert_eq!(ip.options, &[1, 1, 1, 0]);
+                assert!(ip.dont_fragment);
+            }
+            other => panic!("expected IPv4, got {:?}", other),
+        }
+        assert_eq!(packet.ports(), Some((5353, 53)));
+        assert_eq!(packet.payload, b"hi");
+    }
+
+    #[test]
+    fn decodes_linux_cooked_frames() {
+        // Outgoing (4), ARPHRD_ETHER, 6 byte address padded to 8, IPv4
+        let mut frame = vec![0, 4, 0, 1, 0, 6];
+        frame.extend_from_slice(&MAC_A);
+        frame.extend_from_slice(&[0, 0]);
+        frame.extend_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+        frame.extend_from_slice(&ipv4(IP_PROTO_UDP, &[], &udp(b"hi")));
+        let packet = decode(&frame, LinkType::LinuxSll);
+
+        assert_eq!(packet.error, None);
+        assert!(packet.ethernet.is_none());
+        assert_eq!(packet.source_ip(), Some("192.168.1.10".parse().unwrap()));
+        assert_eq!(packet.ports(), Some((5353, 53)));
+        assert_eq!(packet.payload, b"hi");
+        assert!(decode(&frame[..10], LinkType::LinuxSll).error.is_some());
+    }
+
+    #[test]
+    fn skips_transport_for_later_ipv4_fragments() {
+        let mut ip = ipv4(IP_PROTO_TCP, &[], &[0u8; 16]);
+        ip[6] = 0x00;
+        ip[7] = 0x10; // fragment offset 16
+        let packet = decode(&ip, LinkType::RawIp);
+        assert!(packet.transport.is_none());
+        assert_eq!(packet.payload.len(), 16);
+        assert_eq!(packet.error, None);
+    }
+
+    #[test]
+    fn walks_ipv6_extension_headers() {
+        // Hop-by-hop (8 bytes, PadN) then destination options, then UDP
+        let mut payload = vec![IPV6_DEST_OPTS, 0, 1, 4, 0, 0, 0, 0];
+        payload.extend_from_slice(&[IP_PROTO_UDP, 0, 1, 4, 0, 0, 0, 0]);
+        payload.extend_from_slice(&udp(b"v6"));
+        let frame = ethernet(ETHERTYPE_IPV6, &ipv6(IPV6_HOP_BY_HOP, &payload));
+        let packet = decode(&frame, LinkType::Ethernet);
+
+        assert_eq!(packet.error, None);
+        match &packet.network {
+            Some(NetworkLayer::Ipv6(ip)) => {
+                assert_eq!(ip.extensions.len(), 2);
+                assert_eq!(ip.extensions[0].kind, IPV6_HOP_BY_HOP);
+                assert_eq!(ip.extensions[1].kind, IPV6_DEST_OPTS);
+                assert_eq!(ip.upper_protocol, IP_PROTO_UDP);
+            }
+            other => panic!("expected IPv6, got {:?}", other),
+        }
+        assert_eq!(packet.source_ip(), Some("fe80::1".parse().unwrap()));
+        assert_eq!(packet.payload, b"v6");
+    }
+
+    #[test]
+    fn decodes_icmp_and_icmpv6() {
+        let echo = [8, 0, 0, 0, 0, 1, 0, 1, b'p', b'i', b'n', b'g'];
+        let ip = ipv4(IP_PROTO_ICMP, &[], &echo);
+        let packet = decode(&ip, LinkType::RawIp);
+        assert_eq!(
+            packet.transport,
+            Some(TransportLayer::Icmp(IcmpHeader { icmp_type: 8, code: 0, checksum: 0, rest: [0, 1, 0, 1] }))
+        );
+        assert_eq!(packet.payload, b"ping");
+
+        let neighbour_solicit = [135, 0, 0, 0, 0, 0, 0, 0];
+        let ip = ipv6(IP_PROTO_ICMPV6, &neighbour_solicit);
+        let packet = decode(&ip, LinkType::RawIp);
+        match packet.transport {
+            Some(TransportLayer::Icmpv6(icmp)) => assert_eq!(icmp.icmp_type, 135),
+            other => panic!("expected ICMPv6, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn decodes_arp_request() {
+        let mut arp = vec![0, 1, 8, 0, 6, 4, 0, 1];
+        arp.extend_from_slice(&MAC_A);
+        arp.extend_from_slice(&[10, 0, 0, 1]);
+        arp.extend_from_slice(&[0u8; 6]);
+        arp.extend_from_slice(&[10, 0, 0, 2]);
+        let frame = ethernet(ETHERTYPE_ARP, &arp);
+        let packet = decode(&frame, LinkType::Ethernet);
+
+        match &packet.network {
+            Some(NetworkLayer::Arp(arp)) => {
+                assert_eq!(arp.operation, 1);
+                assert_eq!(arp.sender_mac, MAC_A);
+                assert_eq!(arp.sender_ip, Ipv4Addr::new(10, 0, 0, 1));
+                assert_eq!(arp.target_ip, Ipv4Addr::new(10, 0, 0, 2));
+            }
+            other => panic!("expected ARP, got {:?}", other),
+        }
+        assert!(packet.summary().starts_with("ARP request 10.0.0.1 -> 10.0.0.2"));
+    }
+
+    #[test]
+    fn decodes_stacked_vlan_tags() {
+        let mut tagged = Vec::new();
+        tagged.extend_from_slice(&[0x20, 0x64]); // priority 1, VLAN 100
+        tagged.extend_from_slice(&ETHERTYPE_VLAN.to_be_bytes());
+        tagged.extend_from_slice(&[0x00, 0xc8]); // VLAN 200
+        tagged.extend_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+        tagged.extend_from_slice(&ipv4(IP_PROTO_UDP, &[], &udp(b"")));
+        let frame = ethernet(ETHERTYPE_QINQ, &tagged);
+        let packet = decode(&frame, LinkType::Ethernet);
+
+        assert_eq!(packet.vlans.len(), 2);
+        assert_eq!(packet.vlans[0], VlanTag { tpid: ETHERTYPE_QINQ, priority: 1, drop_eligible: false, vlan_id: 100 });
+        assert_eq!(packet.vlans[1].vlan_id, 200);
+        assert_eq!(packet.ethernet.as_ref().unwrap().ethertype, ETHERTYPE_IPV4);
+        assert_eq!(packet.ports(), Some((5353, 53)));
+    }
+
+    #[test]
+    fn keeps_decoded_layers_when_truncated() {
+        let frame = ethernet(ETHERTYPE_IPV4, &ipv4(IP_PROTO_TCP, &[], &tcp(0x12, &[], b"")));
+        let packet = decode(&frame[..14 + 20 + 10], LinkType::Ethernet);
+
+        assert!(packet.network.is_some());
+        assert!(packet.transport.is_none());
+        assert!(packet.error.as_deref().unwrap().starts_with("TCP header truncated"));
+    }
+
+    #[test]
+    fn rejects_bad_ihl() {
+        let mut ip = ipv4(IP_PROTO_UDP, &[], &udp(b""));
+        ip[0] = 0x44;
+        let packet = decode(&ip, LinkType::RawIp);
+        assert!(packet.network.is_none());
+        assert!(packet.error.is_some());
+    }
+}
@@ -0,0 +1,351 @@
+use crate::logging;
+use crate::capture_backend::CapturedPacket;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, RwLock};
+
+pub fn init_module() -> Result<(), String> {
+    // Placeholder for actual initialization logic
+    let initialization_passed = true;
+
+    if initialization_passed {
+        logging::debug_info("packet_store module is online");
+        Ok(())
+    } else {
+        Err("packet_store module initialization failed".to_string())
+    }
+}
+
+// Producers spread packets over this many independently locked shards so a
+// reader copying one shard never blocks the capture thread on the others
+const SHARD_COUNT: usize = 8;
+
+pub const DEFAULT_MAX_PACKETS: usize = 100_000;
+pub const DEFAULT_MAX_BYTES: usize = 256 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FullPolicy {
+    DropOldest, // Ring buffer: evict the oldest packets to make room
+    StopOnFull, // Keep what we have and refuse new packets
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StoreLimits {
+    pub max_packets: usize,
+    pub max_bytes: usize,
+    pub policy: FullPolicy,
+}
+
+impl Default for StoreLimits {
+    fn default() -> Self {
+        StoreLimits {
+            max_packets: DEFAULT_MAX_PACKETS,
+            max_bytes: DEFAULT_MAX_BYTES,
+            policy: FullPolicy::DropOldest,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushOutcome {
+    Stored,
+    StoredAfterEvicting(usize),
+    Rejected,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StoreStats {
+    pub stored_packets: u64,
+    pub stored_bytes: u64,
+    pub evicted_packets: u64,
+    pub rejected_packets: u64,
+    // Every packet ever stored, including those since evicted or cleared
+    pub accepted_packets: u64,
+}
+
+#[derive(Default)]
+struct Shard {
+    // (arrival sequence, packet)
+    packets: VecDeque<(u64, CapturedPacket)>,
+    bytes: usize,
+}
+
+pub struct PacketStore {
+    shards: Vec<Mutex<Shard>>,
+    limits: RwLock<StoreLimits>,
+    next_sequence: AtomicU64,
+    stored_packets: AtomicU64,
+    stored_bytes: AtomicU64,
+    evicted_packets: AtomicU64,
+    rejected_packets: AtomicU64,
+    accepted_packets: AtomicU64,
+}
+
+fn packet_bytes(packet: &CapturedPacket) -> usize {
+    packet.data.len()
+}
+
+impl PacketStore {
+    pub fn new(limits: StoreLimits) -> Self {
+        PacketStore {
+            shards: (0..SHARD_COUNT).map(|_| Mutex::new(Shard::default())).collect(),
+            limits: RwLock::new(limits),
+            next_sequence: AtomicU64::new(0),
+            stored_packets: AtomicU64::new(0),
+            stored_bytes: AtomicU64::new(0),
+            evicted_packets: AtomicU64::new(0),
+            rejected_packets: AtomicU64::new(0),
+            accepted_packets: AtomicU64::new(0),
+        }
+    }
+
+    pub fn limits(&self) -> StoreLimits {
+        *self.limits.read().unwrap()
+    }
+
+    // New limits apply from the next push; shrinking does not evict right away
+    pub fn set_limits(&self, limits: StoreLimits) {
+        *self.limits.write().unwrap() = limits;
+    }
+
+    // Claims room for one packet of `size` bytes in the store wide totals
+    fn try_reserve(&self, size: usize, limits: &StoreLimits) -> bool {
+        let packets = self.stored_packets.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |stored| {
+            (stored < limits.max_packets as u64).then_some(stored + 1)
+        });
+        if packets.is_err() {
+            return false;
+        }
+        let bytes = self.stored_bytes.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |stored| {
+            (stored + size as u64 <= limits.max_bytes as u64).then_some(stored + size as u64)
+        });
+        if bytes.is_err() {
+            self.stored_packets.fetch_sub(1, Ordering::Relaxed);
+            return false;
+        }
+        true
+    }
+
+    // Removes the packet with the lowest sequence number across all shards.
+    // False when nothing is stored yet.
+    fn evict_oldest(&self) -> bool {
+        loop {
+            let oldest = self
+                .shards
+                .iter()
+                .enumerate()
+                .filter_map(|(index, shard)| shard.lock().unwrap().packets.front().map(|(sequence, _)| (*sequence, index)))
+                .min();
+            let (sequence, index) = match oldest {
+                Some(oldest) => oldest,
+                None => return false,
+            };
+            let mut shard = self.shards[index].lock().unwrap();
+            // Another producer may have evicted it while no lock was held
+            if shard.packets.front().map(|(front, _)| *front) != Some(sequence) {
+                continue;
+            }
+            let (_, old) = shard.packets.pop_front().unwrap();
+            let old_size = packet_bytes(&old);
+            shard.bytes -= old_size;
+            self.stored_packets.fetch_sub(1, Ordering::Relaxed);
+            self.stored_bytes.fetch_sub(old_size as u64, Ordering::Relaxed);
+            return true;
+        }
+    }
+
+    // The limits hold for the store as a whole; shards only spread the locking
+    pub fn push(&self, packet: CapturedPacket) -> PushOutcome {
+        let limits = self.limits();
+        let size = packet_bytes(&packet);
+        if size > limits.max_bytes || limits.max_packets == 0 {
+            self.rejected_packets.fetch_add(1, Ordering::Relaxed);
+            return PushOutcome::Rejected;
+        }
+
+        let sequence = self.next_sequence.fetch_add(1, Ordering::Relaxed);
+        let mut evicted = 0;
+        while !self.try_reserve(size, &limits) {
+            if limits.policy == FullPolicy::StopOnFull {
+                self.rejected_packets.fetch_add(1, Ordering::Relaxed);
+                return PushOutcome::Rejected;
+            }
+            if !self.evict_oldest() {
+                // The room is held by producers that have not stored their
+                // packets yet; overshoot by this packet rather than wait on them
+                self.stored_packets.fetch_add(1, Ordering::Relaxed);
+                self.stored_bytes.fetch_add(size as u64, Ordering::Relaxed);
+                break;
+            }
+            evicted += 1;
+        }
+        if evicted > 0 {
+            self.evicted_packets.fetch_add(evicted as u64, Ordering::Relaxed);
+        }
+
+        let mut shard = self.shards[(sequence % SHARD_COUNT as u64) as usize].lock().unwrap();
+        shard.bytes += size;
+        shard.packets.push_back((sequence, packet));
+        self.accepted_packets.fetch_add(1, Ordering::Relaxed);
+
+        if evicted > 0 {
+            PushOutcome::StoredAfterEvicting(evicted)
+        } else {
+            PushOutcome::Stored
+        }
+    }
+
+    // Lock free; counts a push in progress before its packet is visible
+    pub fn len(&self) -> usize {
+        self.stored_packets.load(Ordering::Relaxed) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn stats(&self) -> StoreStats {
+        StoreStats {
+            stored_packets: self.stored_packets.load(Ordering::Relaxed),
+            stored_bytes: self.stored_bytes.load(Ordering::Relaxed),
+            evicted_packets: self.evicted_packets.load(Ordering::Relaxed),
+            rejected_packets: self.rejected_packets.load(Ordering::Relaxed),
+            accepted_packets: self.accepted_packets.load(Ordering::Relaxed),
+        }
+    }
+
+    // Copies every stored packet out in arrival order. Shards are locked one
+    // at a time, so producers only ever wait for a single shard copy.
+    pub fn snapshot(&self) -> Vec<CapturedPacket> {
+        let mut entries: Vec<(u64, CapturedPacket)> = Vec::with_capacity(self.len());
+        for shard in &self.shards {
+            entries.extend(shard.lock().unwrap().packets.iter().cloned());
+        }
+        entries.sort_by_key(|(sequence, _)| *sequence);
+        entries.into_iter().map(|(_, packet)| packet).collect()
+    }
+
+    pub fn clear(&self) {
+        for shard in &self.shards {
+            let mut shard = shard.lock().unwrap();
+            self.stored_packets.fetch_sub(shard.packets.len() as u64, Ordering::Relaxed);
+            self.stored_bytes.fetch_sub(shard.bytes as u64, Ordering::Relaxed);
+            shard.packets.clear();
+            shard.bytes = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture_backend::{LinkType, PacketMeta};
+    use std::sync::Arc;
+    use std::time::UNIX_EPOCH;
+
+    fn packet(marker: u8, len: usize) -> CapturedPacket {
+        let meta = PacketMeta {
+            timestamp: UNIX_EPOCH,
+            captured_len: len,
+            original_len: len,
+            interface_index: 0,
+            outbound: false,
+            link_type: LinkType::RawIp,
+        };
+        CapturedPacket::new(meta, vec![marker; len])
+    }
+
+    fn limits(max_packets: usize, max_bytes: usize, policy: FullPolicy) -> StoreLimits {
+        StoreLimits { max_packets, max_bytes, policy }
+    }
+
+    #[test]
+    fn drop_oldest_keeps_the_newest_packets() {
+        let store = PacketStore::new(limits(SHARD_COUNT * 2, usize::MAX, FullPolicy::DropOldest));
+        for i in 0..(SHARD_COUNT * 5) {
+            store.push(packet(i as u8, 10));
+        }
+        let kept: Vec<u8> = store.snapshot().iter().map(|p| p.data[0]).collect();
+        let expected: Vec<u8> = ((SHARD_COUNT * 3)..(SHARD_COUNT * 5)).map(|i| i as u8).collect();
+        assert_eq!(kept, expected);
+        assert_eq!(store.stats().evicted_packets, (SHARD_COUNT * 3) as u64);
+        assert_eq!(store.stats().stored_bytes, (SHARD_COUNT * 2 * 10) as u64);
+    }
+
+    #[test]
+    fn stop_on_full_rejects_new_packets() {
+        let store = PacketStore::new(limits(usize::MAX, 100, FullPolicy::StopOnFull));
+        let outcomes: Vec<PushOutcome> = (0..4).map(|i| store.push(packet(i, 30))).collect();
+        assert_eq!(outcomes, [PushOutcome::Stored, PushOutcome::Stored, PushOutcome::Stored, PushOutcome::Rejected]);
+        // Room for the bytes left over is still used
+        assert_eq!(store.push(packet(4, 10)), PushOutcome::Stored);
+        assert_eq!(store.stats().stored_bytes, 100);
+
+        let store = PacketStore::new(limits(1, usize::MAX, FullPolicy::StopOnFull));
+        assert_eq!(store.push(packet(0, 10)), PushOutcome::Stored);
+        assert_eq!(store.push(packet(1, 10)), PushOutcome::Rejected);
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn accepted_count_is_not_capped_by_the_limits() {
+        // capture --count relies on this to finish past the store's capacity
+        let store = PacketStore::new(limits(2, usize::MAX, FullPolicy::DropOldest));
+        for i in 0..5 {
+            store.push(packet(i, 10));
+        }
+        assert_eq!((store.len(), store.stats().accepted_packets), (2, 5));
+        store.clear();
+        assert_eq!(store.stats().accepted_packets, 5);
+
+        let store = PacketStore::new(limits(1, usize::MAX, FullPolicy::StopOnFull));
+        store.push(packet(0, 10));
+        store.push(packet(1, 10));
+        assert_eq!(store.stats().accepted_packets, 1);
+    }
+
+    #[test]
+    fn limits_apply_to_the_whole_store() {
+        let store = PacketStore::new(limits(1, usize::MAX, FullPolicy::DropOldest));
+        for i in 0..5 {
+            store.push(packet(i, 10));
+        }
+        assert_eq!(store.snapshot().iter().map(|p| p.data[0]).collect::<Vec<_>>(), [4]);
+        assert_eq!(store.stats().evicted_packets, 4);
+
+        // A packet may use more than a shard's share of the byte limit
+        let store = PacketStore::new(limits(usize::MAX, 100, FullPolicy::DropOldest));
+        assert_eq!(store.push(packet(0, 60)), PushOutcome::Stored);
+        assert_eq!(store.push(packet(1, 90)), PushOutcome::StoredAfterEvicting(1));
+        assert_eq!(store.push(packet(2, 10)), PushOutcome::Stored);
+        assert_eq!(store.push(packet(3, 20)), PushOutcome::StoredAfterEvicting(1));
+        assert_eq!(store.snapshot().iter().map(|p| p.data[0]).collect::<Vec<_>>(), [2, 3]);
+        assert_eq!(store.push(packet(4, 101)), PushOutcome::Rejected);
+        assert_eq!(store.stats().stored_bytes, 30);
+    }
+
+    #[test]
+    fn concurrent_producers_respect_limits() {
+        let store = Arc::new(PacketStore::new(limits(1000, usize::MAX, FullPolicy::DropOldest)));
+        let producers: Vec<_> = (0..4)
+            .map(|_| {
+                let store = store.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..2000 {
+                        store.push(packet(1, 20));
+                    }
+                })
+            })
+            .collect();
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        assert_eq!(store.len(), 1000);
+        assert_eq!(store.snapshot().len(), 1000);
+        assert_eq!(store.stats().evicted_packets, 7000);
+
+        store.clear();
+        assert!(store.is_empty());
+        assert_eq!(store.stats().stored_bytes, 0);
+    }
+}
@@ -1,6 +1,20 @@
-use crate::logging;
-//use crate::gui_engine_menu::MenuItem;
+use s2o_net_lib::logging;
+use crate::app_state::AppState;
+use crate::gui_engine_menu::MenuState;
+use crate::gui_engine_screens::{MenuEntry, Screen};
+use crate::gui_engine_style::MenuSettings;
+use s2o_net_lib::capture_filter::CaptureFilter;
+use s2o_net_lib::flow_table;
+use s2o_net_lib::packet_capture;
+use s2o_net_lib::inline_verdict;
+use s2o_net_lib::pcap_writer;
+use s2o_net_lib::process_attribution;
+use eframe::egui::{self, Align2, Area, Context, Id, RichText};
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
 
+// Filter expression typed into the PC menu, used by Start Capture and Replay
+static FILTER_INPUT: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new(String::new()));
 
 pub fn init_module() -> Result<(), String> {
     // Placeholder for actual initialization logic
@@ -14,33 +28,86 @@ pub fn init_module() -> Result<(), String> {
     }
 }
 
-// Placeholder menu items for pc_menu
-#[allow(dead_code)]
-pub fn menu_items() -> Vec<crate::gui_engine_menu::MenuItem> {
+// Logs the outcome of a capture action instead of dropping it
+fn report(action: &str, result: Result<(), String>) {
+    match result {
+        Ok(()) => logging::debug_info(&format!("{} succeeded", action)),
+        Err(e) => logging::debug_error(&format!("{} failed: {}", action, e)),
+    }
+}
+
+fn current_filter() -> String {
+    FILTER_INPUT.lock().unwrap().clone()
+}
+
+// Text box under the PC menu for the capture filter, checked as it is typed
+fn render_filter_input(ctx: &Context, settings: &MenuSettings) {
+    Area::new(Id::new("filter_area"))
+        .anchor(Align2::CENTER_BOTTOM, (0.0, -60.0))
+        .show(ctx, |ui| {
+            let mut filter = FILTER_INPUT.lock().unwrap();
+            ui.horizontal(|ui| {
+                ui.label(settings.apply_label("Filter:", false));
+                ui.add(
+                    egui::TextEdit::singleline(&mut *filter)
+                        .hint_text("e.g. tcp port 443 and not host 10.0.0.1")
+                        .desired_width(400.0),
+                );
+            });
+            if let Err(e) = CaptureFilter::parse(&filter) {
+                ui.label(RichText::new(e).color(egui::Color32::from_rgb(255, 80, 80)));
+            }
+        });
+}
+
+pub fn screen() -> Screen {
+    Screen {
+        state: AppState::PCMenu,
+        title: "PC Menu",
+        items,
+        on_enter: None,
+        render: Some(render),
+    }
+}
+
+fn render(ctx: &Context, menu_state: &MenuState) {
+    render_filter_input(ctx, menu_state.get_settings());
+}
+
+fn idle() -> bool {
+    !packet_capture::is_capturing()
+}
+
+fn items() -> Vec<MenuEntry> {
     vec![
-        crate::gui_engine_menu::MenuItem {
-            label: "Load Dll".to_string(),
-            action: None,
-        },
-        crate::gui_engine_menu::MenuItem {
-            label: "Unload DLL".to_string(),
-            action: None,
-        },
-		crate::gui_engine_menu::MenuItem {
-            label: "Start Capture".to_string(),
-            action: None,
-        },
-		crate::gui_engine_menu::MenuItem {
-            label: "Stop Capture".to_string(),
-            action: None,
-        },
-		crate::gui_engine_menu::MenuItem {
-            label: "Print Packet Data".to_string(),
-            action: None,
-        },
-		crate::gui_engine_menu::MenuItem {
-			label: "Exit".to_string(),
-			action: None,
-		},
+        MenuEntry::run("Check Backend", || report("Backend check", packet_capture::run_preliminary_tests())),
+        MenuEntry::run("Start Capture", || report("Start capture", packet_capture::start_capture(&current_filter())))
+            .enabled_if(idle),
+        MenuEntry::run("Start Inline Capture", || {
+            report("Start inline capture", packet_capture::start_inline_capture(&current_filter()))
+        })
+        .enabled_if(idle),
+        MenuEntry::run("Stop Capture", || report("Stop capture", packet_capture::stop_capture()))
+            .enabled_if(packet_capture::is_capturing),
+        MenuEntry::run("Replay Capture File", || {
+            report("Replay capture file", packet_capture::start_replay(packet_capture::DEFAULT_CAPTURE_FILE, &current_filter()))
+        })
+        .enabled_if(idle),
+        MenuEntry::run("Replay Firewall Verdicts", || {
+            inline_verdict::start_replay_inline(
+                packet_capture::DEFAULT_CAPTURE_FILE.into(),
+                packet_capture::INLINE_REPLAY_FILE.into(),
+                inline_verdict::firewall_verdict(),
+            )
+        })
+        .enabled_if(|| !inline_verdict::is_replaying()),
+        MenuEntry::run("Export Capture", || pcap_writer::start_export(packet_capture::DEFAULT_CAPTURE_FILE.into()))
+            .enabled_if(|| !pcap_writer::is_exporting()),
+        MenuEntry::run("Print Packet Data", packet_capture::print_packet_data),
+        MenuEntry::run("Print Domains", packet_capture::print_domain_contacts),
+        MenuEntry::run("Print Flows", flow_table::print_flows),
+        MenuEntry::run("Print Processes", process_attribution::print_process_traffic),
+        MenuEntry::run("Toggle Store Policy", packet_capture::toggle_full_policy),
+        MenuEntry::run("Clear Packets", packet_capture::clear_packets),
     ]
-}
\ No newline at end of file
+}
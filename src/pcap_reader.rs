@@ -0,0 +1,704 @@
+use crate::logging;
+use crate::capture_backend::{CaptureBackend, CaptureStats, CapturedPacket, LinkType, PacketMeta};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub fn init_module() -> Result<(), String> {
+    // Placeholder for actual initialization logic
+    let initialization_passed = true;
+
+    if initialization_passed {
+        logging::debug_info("pcap_reader module is online");
+        Ok(())
+    } else {
+        Err("pcap_reader module initialization failed".to_string())
+    }
+}
+
+// Classic libpcap magics as read little endian
+const PCAP_MAGIC_MICROS: u32 = 0xa1b2_c3d4;
+const PCAP_MAGIC_NANOS: u32 = 0xa1b2_3c4d;
+
+// pcapng block types
+const PCAPNG_SECTION_HEADER: u32 = 0x0a0d_0d0a;
+const PCAPNG_INTERFACE_DESCRIPTION: u32 = 0x0000_0001;
+const PCAPNG_SIMPLE_PACKET: u32 = 0x0000_0003;
+const PCAPNG_ENHANCED_PACKET: u32 = 0x0000_0006;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+const PCAPNG_OPTION_IF_TSRESOL: u16 = 9;
+const PCAPNG_OPTION_EPB_FLAGS: u16 = 2;
+
+// Refuse records larger than this instead of allocating whatever the file claims
+const MAX_RECORD_LEN: usize = 256 * 1024;
+
+// LINKTYPE_* values shared by both formats
+pub const LINKTYPE_ETHERNET: u32 = 1;
+pub const LINKTYPE_RAW: u32 = 101;
+pub const LINKTYPE_LINUX_SLL: u32 = 113;
+pub const LINKTYPE_IPV4: u32 = 228;
+pub const LINKTYPE_IPV6: u32 = 229;
+
+pub(crate) fn link_type_from_pcap(value: u32) -> Result<LinkType, String> {
+    match value {
+        LINKTYPE_ETHERNET => Ok(LinkType::Ethernet),
+        LINKTYPE_RAW | LINKTYPE_IPV4 | LINKTYPE_IPV6 => Ok(LinkType::RawIp),
+        LINKTYPE_LINUX_SLL => Ok(LinkType::LinuxSll),
+        other => Err(format!("Unsupported link type {}", other)),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PcapFormat {
+    Pcap,
+    PcapNg,
+}
+
+// Per interface state from a pcapng Interface Description Block
+#[derive(Debug, Clone, Copy)]
+struct NgInterface {
+    link_type: Option<LinkType>,
+    snap_len: u32,
+    // Timestamp units per second (if_tsresol), 1_000_000 by default
+    ts_units_per_sec: u64,
+}
+
+enum Header {
+    Pcap {
+        link_type: LinkType,
+        nanos: bool,
+    },
+    PcapNg {
+        interfaces: Vec<NgInterface>,
+    },
+}
+
+// Streaming reader for classic pcap and pcapng files
+pub struct PcapReader<R: Read> {
+    input: R,
+    big_endian: bool,
+    header: Header,
+}
+
+impl PcapReader<BufReader<File>> {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        PcapReader::new(BufReader::new(file))
+    }
+}
+
+impl<R: Read> PcapReader<R> {
+    // Reads the file header and detects the format from its magic
+    pub fn new(mut input: R) -> Result<Self, String> {
+        let mut magic = [0u8; 4];
+        input.read_exact(&mut magic).map_err(|e| format!("Failed to read capture header: {}", e))?;
+        let magic_le = u32::from_le_bytes(magic);
+
+        if magic_le == PCAPNG_SECTION_HEADER {
+            let mut reader = PcapReader {
+                input,
+                big_endian: false,
+                header: Header::PcapNg { interfaces: Vec::new() },
+            };
+            reader.read_section_header()?;
+            return Ok(reader);
+        }
+
+        let (big_endian, nanos) = match (magic_le, u32::from_be_bytes(magic)) {
+            (PCAP_MAGIC_MICROS, _) => (false, false),
+            (PCAP_MAGIC_NANOS, _) => (false, true),
+            (_, PCAP_MAGIC_MICROS) => (true, false),
+            (_, PCAP_MAGIC_NANOS) => (true, true),
+            _ => return Err(format!("Not a pcap or pcapng file (magic {:02x?})", magic)),
+        };
+
+        // version (2+2), thiszone, sigfigs, snaplen, network
+        let mut rest = [0u8; 20];
+        input.read_exact(&mut rest).map_err(|e| format!("Truncated pcap header: {}", e))?;
+        let network = read_u32(&rest[16..20], big_endian);
+        Ok(PcapReader {
+            input,
+            big_endian,
+            header: Header::Pcap {
+                link_type: link_type_from_pcap(network & 0x0fff_ffff)?,
+                nanos,
+            },
+        })
+    }
+
+    pub fn format(&self) -> PcapFormat {
+        match self.header {
+            Header::Pcap { .. } => PcapFormat::Pcap,
+            Header::PcapNg { .. } => PcapFormat::PcapNg,
+        }
+    }
+
+    // Returns the next packet, or None at the end of the file
+    pub fn next_packet(&mut self) -> Result<Option<CapturedPacket>, String> {
+        match self.header {
+            Header::Pcap { link_type, nanos } => self.next_pcap_record(link_type, nanos),
+            Header::PcapNg { .. } => self.next_pcapng_packet(),
+        }
+    }
+
+    fn u32_at(&self, bytes: &[u8]) -> u32 {
+        read_u32(bytes, self.big_endian)
+    }
+
+    fn u16_at(&self, bytes: &[u8]) -> u16 {
+        if self.big_endian {
+            u16::from_be_bytes([bytes[0], bytes[1]])
+        } else {
+            u16::from_le_bytes([bytes[0], bytes[1]])
+        }
+    }
+
+    // Fills `buf`; Ok(false) on a clean end of file before the first byte
+    fn read_or_eof(&mut self, buf: &mut [u8]) -> Result<bool, String> {
+        let mut filled = 0;
+        while filled < buf.len() {
+            match self.input.read(&mut buf[filled..]) {
+                Ok(0) if filled == 0 => return Ok(false),
+                Ok(0) => return Err("Capture file is truncated".to_string()),
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(format!("Failed to read capture file: {}", e)),
+            }
+        }
+        Ok(true)
+    }
+
+    fn read_vec(&mut self, len: usize) -> Result<Vec<u8>, String> {
+        if len > MAX_RECORD_LEN {
+            return Err(format!("Record of {} bytes exceeds the {} byte limit", len, MAX_RECORD_LEN));
+        }
+        let mut data = vec![0u8; len];
+        self.input.read_exact(&mut data).map_err(|_| "Capture file is truncated".to_string())?;
+        Ok(data)
+    }
+
+    fn next_pcap_record(&mut self, link_type: LinkType, nanos: bool) -> Result<Option<CapturedPacket>, String> {
+        let mut record = [0u8; 16];
+        if !self.read_or_eof(&mut record)? {
+            return Ok(None);
+        }
+        let ts_sec = self.u32_at(&record[0..4]) as u64;
+        let ts_frac = self.u32_at(&record[4..8]) as u64;
+        let captured_len = self.u32_at(&record[8..12]) as usize;
+        let original_len = self.u32_at(&record[12..16]) as usize;
+        let data = self.read_vec(captured_len)?;
+
+        let frac = if nanos { Duration::from_nanos(ts_frac) } else { Duration::from_micros(ts_frac) };
+        Ok(Some(CapturedPacket::new(
+            PacketMeta {
+                timestamp: UNIX_EPOCH + Duration::from_secs(ts_sec) + frac,
+                captured_len,
+                original_len,
+                interface_index: 0,
+                outbound: false,
+                link_type,
+            },
+            data,
+        )))
+    }
+
+    // Called with the block type already consumed
+    fn read_section_header(&mut self) -> Result<(), String> {
+        let mut head = [0u8; 8];
+        self.input.read_exact(&mut head).map_err(|_| "Truncated pcapng section header".to_string())?;
+        let bom = [head[4], head[5], head[6], head[7]];
+        self.big_endian = if u32::from_le_bytes(bom) == PCAPNG_BYTE_ORDER_MAGIC {
+            false
+        } else if u32::from_be_bytes(bom) == PCAPNG_BYTE_ORDER_MAGIC {
+            true
+        } else {
+            return Err("Invalid pcapng byte order magic".to_string());
+        };
+
+        let total_len = self.u32_at(&head[0..4]) as usize;
+        if total_len < 28 || !total_len.is_multiple_of(4) {
+            return Err(format!("Invalid pcapng section header length {}", total_len));
+        }
+        // Skip version, section length, options and trailing length
+        self.read_vec(total_len - 12)?;
+
+        // Interface ids restart in every section
+        self.header = Header::PcapNg { interfaces: Vec::new() };
+        Ok(())
+    }
+
+    fn next_pcapng_packet(&mut self) -> Result<Option<CapturedPacket>, String> {
+        loop {
+            let mut head = [0u8; 8];
+            if !self.read_or_eof(&mut head[..4])? {
+                return Ok(None);
+            }
+            if u32::from_le_bytes([head[0], head[1], head[2], head[3]]) == PCAPNG_SECTION_HEADER {
+                self.read_section_header()?;
+                continue;
+            }
+            self.input.read_exact(&mut head[4..]).map_err(|_| "Capture file is truncated".to_string())?;
+
+            let block_type = self.u32_at(&head[0..4]);
+            let total_len = self.u32_at(&head[4..8]) as usize;
+            if total_len < 12 || !total_len.is_multiple_of(4) {
+                return Err(format!("Invalid pcapng block length {}", total_len));
+            }
+            // Body without the two length fields and the type
+            let body = self.read_vec(total_len - 12)?;
+            let mut trailer = [0u8; 4];
+            self.input.read_exact(&mut trailer).map_err(|_| "Capture file is truncated".to_string())?;
+
+            match block_type {
+                PCAPNG_INTERFACE_DESCRIPTION => self.add_interface(&body)?,
+                PCAPNG_ENHANCED_PACKET => return self.enhanced_packet(&body).map(Some),
+                PCAPNG_SIMPLE_PACKET => return self.simple_packet(&body).map(Some),
+                // Name resolution, statistics, custom blocks etc. are skipped
+                _ => {}
+            }
+        }
+    }
+
+    fn interfaces(&self) -> &[NgInterface] {
+        match &self.header {
+            Header::PcapNg { interfaces } => interfaces,
+            Header::Pcap { .. } => &[],
+        }
+    }
+
+    fn add_interface(&mut self, body: &[u8]) -> Result<(), String> {
+        if body.len() < 8 {
+            return Err("Truncated pcapng interface description".to_string());
+        }
+        let link_type = link_type_from_pcap(self.u16_at(&body[0..2]) as u32).ok();
+        let snap_len = self.u32_at(&body[4..8]);
+
+        let mut ts_units_per_sec = 1_000_000u64;
+        let mut options = &body[8..];
+        while options.len() >= 4 {
+            let code = self.u16_at(&options[0..2]);
+            let len = self.u16_at(&options[2..4]) as usize;
+            let padded = (len + 3) & !3;
+            if code == 0 || options.len() < 4 + padded {
+                break;
+            }
+            if code == PCAPNG_OPTION_IF_TSRESOL && len >= 1 {
+                let resol = options[4];
+                let exponent = (resol & 0x7f) as u32;
+                ts_units_per_sec = if resol & 0x80 != 0 {
+                    2u64.checked_pow(exponent).unwrap_or(u64::MAX)
+                } else {
+                    10u64.checked_pow(exponent).unwrap_or(u64::MAX)
+                };
+            }
+            options = &options[4 + padded..];
+        }
+
+        if let Header::PcapNg { interfaces } = &mut self.header {
+            interfaces.push(NgInterface { link_type, snap_len, ts_units_per_sec });
+        }
+        Ok(())
+    }
+
+    fn interface(&self, index: usize) -> Result<(NgInterface, LinkType), String> {
+        let interface = *self
+            .interfaces()
+            .get(index)
+            .ok_or_else(|| format!("Packet references unknown interface {}", index))?;
+        let link_type = interface
+            .link_type
+            .ok_or_else(|| format!("Interface {} has an unsupported link type", index))?;
+        Ok((interface, link_type))
+    }
+
+    fn enhanced_packet(&self, body: &[u8]) -> Result<CapturedPacket, String> {
+        if body.len() < 20 {
+            return Err("Truncated pcapng enhanced packet block".to_string());
+        }
+        let interface_index = self.u32_at(&body[0..4]);
+        let (interface, link_type) = self.interface(interface_index as usize)?;
+        let ts = ((self.u32_at(&body[4..8]) as u64) << 32) | self.u32_at(&body[8..12]) as u64;
+        let captured_len = self.u32_at(&body[12..16]) as usize;
+        let original_len = self.u32_at(&body[16..20]) as usize;
+        let data = body
+            .get(20..20 + captured_len)
+            .ok_or("pcapng packet data is truncated")?
+            .to_vec();
+
+        // epb_flags bits 0-1 hold the direction, 2 meaning outbound
+        let mut outbound = false;
+        let mut options = &body[(20 + captured_len + 3) & !3..];
+        while options.len() >= 4 {
+            let code = self.u16_at(&options[0..2]);
+            let len = self.u16_at(&options[2..4]) as usize;
+            let padded = (len + 3) & !3;
+            if code == 0 || options.len() < 4 + padded {
+                break;
+            }
+            if code == PCAPNG_OPTION_EPB_FLAGS && len == 4 {
+                outbound = self.u32_at(&options[4..8]) & 0x3 == 2;
+            }
+            options = &options[4 + padded..];
+        }
+
+        Ok(CapturedPacket::new(
+            PacketMeta {
+                timestamp: ng_timestamp(ts, interface.ts_units_per_sec),
+                captured_len,
+                original_len,
+                interface_index,
+                outbound,
+                link_type,
+            },
+            data,
+        ))
+    }
+
+    fn simple_packet(&self, body: &[u8]) -> Result<CapturedPacket, String> {
+        if body.len() < 4 {
+            return Err("Truncated pcapng simple packet block".to_string());
+        }
+        let (interface, link_type) = self.interface(0)?;
+        let original_len = self.u32_at(&body[0..4]) as usize;
+        let mut captured_len = original_len.min(body.len() - 4);
+        if interface.snap_len != 0 {
+            captured_len = captured_len.min(interface.snap_len as usize);
+        }
+
+        Ok(CapturedPacket::new(
+            PacketMeta {
+                // Simple packet blocks carry no timestamp
+                timestamp: UNIX_EPOCH,
+                captured_len,
+                original_len,
+                interface_index: 0,
+                outbound: false,
+                link_type,
+            },
+            body[4..4 + captured_len].to_vec(),
+        ))
+    }
+}
+
+fn read_u32(bytes: &[u8], big_endian: bool) -> u32 {
+    let raw = [bytes[0], bytes[1], bytes[2], bytes[3]];
+    if big_endian {
+        u32::from_be_bytes(raw)
+    } else {
+        u32::from_le_bytes(raw)
+    }
+}
+
+fn ng_timestamp(ts: u64, units_per_sec: u64) -> SystemTime {
+    let units_per_sec = units_per_sec.max(1);
+    let secs = ts / units_per_sec;
+    let nanos = ((ts % units_per_sec) as u128 * 1_000_000_000 / units_per_sec as u128) as u64;
+    UNIX_EPOCH + Duration::from_secs(secs) + Duration::from_nanos(nanos)
+}
+
+// Capture source that plays a pcap/pcapng file back through the capture pipeline
+pub struct PcapReplayBackend {
+    path: PathBuf,
+    reader: Option<PcapReader<BufReader<File>>>,
+    finished: bool,
+    stats: CaptureStats,
+}
+
+impl PcapReplayBackend {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        PcapReplayBackend {
+            path: path.into(),
+            reader: None,
+            finished: false,
+            stats: CaptureStats::default(),
+        }
+    }
+}
+
+impl CaptureBackend for PcapReplayBackend {
+    fn name(&self) -> &str {
+        "pcap replay"
+    }
+
+    fn open(&mut self, filter: &str) -> Result<(), String> {
+        if !filter.trim().is_empty() && filter.trim() != "true" {
+            return Err(format!("pcap replay does not support filter \"{}\"", filter));
+        }
+        let reader = PcapReader::open(&self.path)?;
+        logging::debug_info(&format!("Replaying {:?} file {}", reader.format(), self.path.display()));
+        self.reader = Some(reader);
+        self.finished = false;
+        Ok(())
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> Result<Option<PacketMeta>, String> {
+        let reader = self.reader.as_mut().ok_or("Replay file is not open.")?;
+        let packet = match reader.next_packet() {
+            Ok(Some(packet)) => packet,
+            Ok(None) => {
+                self.finished = true;
+                return Ok(None);
+            }
+            Err(e) => {
+                // A corrupt record ends the replay rather than looping on it
+                self.finished = true;
+                self.stats.recv_errors += 1;
+                return Err(e);
+            }
+        };
+
+        let len = packet.data.len().min(buf.len());
+        buf[..len].copy_from_slice(&packet.data[..len]);
+        let mut meta = packet.meta;
+        meta.captured_len = len;
+        self.stats.record_packet(len);
+        Ok(Some(meta))
+    }
+
+    fn close(&mut self) -> Result<(), String> {
+        self.reader = None;
+        Ok(())
+    }
+
+    fn stats(&self) -> CaptureStats {
+        self.stats
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    // UDP 10.0.0.1:5353 -> 10.0.0.2:53 carrying "hi"
+    const IPV4_UDP: [u8; 30] = [
+        0x45, 0, 0, 30, 0, 0, 0, 0, 64, 17, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2,
+        0x14, 0xe9, 0, 53, 0, 10, 0, 0, b'h', b'i',
+    ];
+
+    fn put32(out: &mut Vec<u8>, value: u32, big_endian: bool) {
+        out.extend_from_slice(&if big_endian { value.to_be_bytes() } else { value.to_le_bytes() });
+    }
+
+    fn put16(out: &mut Vec<u8>, value: u16, big_endian: bool) {
+        out.extend_from_slice(&if big_endian { value.to_be_bytes() } else { value.to_le_bytes() });
+    }
+
+    fn pcap_header(big_endian: bool, nanos: bool, snap_len: u32, network: u32) -> Vec<u8> {
+        let mut out = Vec::new();
+        put32(&mut out, if nanos { PCAP_MAGIC_NANOS } else { PCAP_MAGIC_MICROS }, big_endian);
+        put16(&mut out, 2, big_endian);
+        put16(&mut out, 4, big_endian);
+        out.extend_from_slice(&[0; 8]);
+        put32(&mut out, snap_len, big_endian);
+        put32(&mut out, network, big_endian);
+        out
+    }
+
+    fn pcap_record(out: &mut Vec<u8>, big_endian: bool, ts: (u32, u32), original_len: u32, data: &[u8]) {
+        put32(out, ts.0, big_endian);
+        put32(out, ts.1, big_endian);
+        put32(out, data.len() as u32, big_endian);
+        put32(out, original_len, big_endian);
+        out.extend_from_slice(data);
+    }
+
+    fn ng_block(out: &mut Vec<u8>, big_endian: bool, block_type: u32, body: &[u8]) {
+        let padded = (body.len() + 3) & !3;
+        let total = 12 + padded as u32;
+        put32(out, block_type, big_endian);
+        put32(out, total, big_endian);
+        out.extend_from_slice(body);
+        out.resize(out.len() + padded - body.len(), 0);
+        put32(out, total, big_endian);
+    }
+
+    fn ng_section(out: &mut Vec<u8>, big_endian: bool) {
+        let mut body = Vec::new();
+        put32(&mut body, PCAPNG_BYTE_ORDER_MAGIC, big_endian);
+        put16(&mut body, 1, big_endian);
+        put16(&mut body, 0, big_endian);
+        body.extend_from_slice(&[0xff; 8]); // section length unknown
+        ng_block(out, big_endian, PCAPNG_SECTION_HEADER, &body);
+    }
+
+    fn ng_interface(out: &mut Vec<u8>, big_endian: bool, link_type: u16, snap_len: u32, tsresol: Option<u8>) {
+        let mut body = Vec::new();
+        put16(&mut body, link_type, big_endian);
+        put16(&mut body, 0, big_endian);
+        put32(&mut body, snap_len, big_endian);
+        if let Some(resol) = tsresol {
+            put16(&mut body, PCAPNG_OPTION_IF_TSRESOL, big_endian);
+            put16(&mut body, 1, big_endian);
+            body.extend_from_slice(&[resol, 0, 0, 0]);
+            body.extend_from_slice(&[0; 4]); // opt_endofopt
+        }
+        ng_block(out, big_endian, PCAPNG_INTERFACE_DESCRIPTION, &body);
+    }
+
+    fn ng_enhanced(out: &mut Vec<u8>, big_endian: bool, interface: u32, ts: u64, original_len: u32, data: &[u8], flags: Option<u32>) {
+        let mut body = Vec::new();
+        put32(&mut body, interface, big_endian);
+        put32(&mut body, (ts >> 32) as u32, big_endian);
+        put32(&mut body, ts as u32, big_endian);
+        put32(&mut body, data.len() as u32, big_endian);
+        put32(&mut body, original_len, big_endian);
+        body.extend_from_slice(data);
+        body.resize((body.len() + 3) & !3, 0);
+        if let Some(flags) = flags {
+            put16(&mut body, PCAPNG_OPTION_EPB_FLAGS, big_endian);
+            put16(&mut body, 4, big_endian);
+            put32(&mut body, flags, big_endian);
+        }
+        ng_block(out, big_endian, PCAPNG_ENHANCED_PACKET, &body);
+    }
+
+    fn ng_simple(out: &mut Vec<u8>, big_endian: bool, original_len: u32, data: &[u8]) {
+        let mut body = Vec::new();
+        put32(&mut body, original_len, big_endian);
+        body.extend_from_slice(data);
+        ng_block(out, big_endian, PCAPNG_SIMPLE_PACKET, &body);
+    }
+
+    fn read_all(bytes: Vec<u8>) -> Result<Vec<CapturedPacket>, String> {
+        let mut reader = PcapReader::new(Cursor::new(bytes))?;
+        let mut packets = Vec::new();
+        while let Some(packet) = reader.next_packet()? {
+            packets.push(packet);
+        }
+        Ok(packets)
+    }
+
+    fn at(secs: u64, nanos: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs) + Duration::from_nanos(nanos)
+    }
+
+    #[test]
+    fn reads_pcap_in_both_byte_orders_and_resolutions() {
+        for big_endian in [false, true] {
+            for nanos in [false, true] {
+                let mut file = pcap_header(big_endian, nanos, 65535, LINKTYPE_RAW);
+                pcap_record(&mut file, big_endian, (1_700_000_000, 250), 60, &IPV4_UDP);
+                let packets = read_all(file).unwrap();
+
+                assert_eq!(packets.len(), 1, "big_endian {big_endian} nanos {nanos}");
+                let meta = &packets[0].meta;
+                let frac = if nanos { 250 } else { 250_000 };
+                assert_eq!(meta.timestamp, at(1_700_000_000, frac));
+                assert_eq!(meta.link_type, LinkType::RawIp);
+                assert_eq!((meta.captured_len, meta.original_len), (30, 60));
+                assert_eq!(packets[0].data, IPV4_UDP);
+            }
+        }
+    }
+
+    #[test]
+    fn reads_pcapng_sections_interfaces_and_block_types() {
+        let mut file = Vec::new();
+        ng_section(&mut file, false);
+        ng_interface(&mut file, false, LINKTYPE_ETHERNET as u16, 0, None);
+        ng_interface(&mut file, false, LINKTYPE_RAW as u16, 0, Some(9));
+        ng_enhanced(&mut file, false, 1, 1_500_000_000_123, 30, &IPV4_UDP, Some(2));
+        ng_enhanced(&mut file, false, 0, 2_000_001, 14, &[0xaa; 14], None);
+        // Interface ids restart, and the byte order may change, per section
+        ng_section(&mut file, true);
+        ng_interface(&mut file, true, LINKTYPE_RAW as u16, 20, Some(0x80 | 10));
+        ng_enhanced(&mut file, true, 0, 3 * 1024 + 512, 30, &IPV4_UDP, None);
+        ng_simple(&mut file, true, 30, &IPV4_UDP);
+
+        assert_eq!(PcapReader::new(Cursor::new(file.clone())).unwrap().format(), PcapFormat::PcapNg);
+        let packets = read_all(file).unwrap();
+        assert_eq!(packets.len(), 4);
+
+        let first = &packets[0].meta;
+        assert_eq!(first.timestamp, at(1500, 123));
+        assert_eq!((first.interface_index, first.link_type, first.outbound), (1, LinkType::RawIp, true));
+        assert_eq!(packets[0].data, IPV4_UDP);
+
+        let second = &packets[1].meta;
+        assert_eq!(second.timestamp, at(2, 1_000));
+        assert_eq!((second.interface_index, second.link_type, second.outbound), (0, LinkType::Ethernet, false));
+
+        // 2^-10 second units
+        assert_eq!(packets[2].meta.timestamp, at(3, 500_000_000));
+        assert_eq!(packets[2].meta.link_type, LinkType::RawIp);
+
+        // Simple packets have no timestamp and are cut to the snap length
+        let simple = &packets[3];
+        assert_eq!(simple.meta.timestamp, UNIX_EPOCH);
+        assert_eq!((simple.meta.captured_len, simple.meta.original_len), (20, 30));
+        assert_eq!(simple.data, &IPV4_UDP[..20]);
+    }
+
+    #[test]
+    fn pcapng_packet_for_an_unknown_interface_is_an_error() {
+        let mut file = Vec::new();
+        ng_section(&mut file, false);
+        ng_interface(&mut file, false, LINKTYPE_RAW as u16, 0, None);
+        ng_section(&mut file, false);
+        ng_enhanced(&mut file, false, 0, 0, 30, &IPV4_UDP, None);
+        assert!(read_all(file).unwrap_err().contains("unknown interface 0"));
+    }
+
+    #[test]
+    fn rejects_truncated_records() {
+        let mut file = pcap_header(false, false, 65535, LINKTYPE_RAW);
+        pcap_record(&mut file, false, (1, 0), 30, &IPV4_UDP);
+        // Clean end of file after a whole record
+        assert_eq!(read_all(file.clone()).unwrap().len(), 1);
+
+        let mut cut_data = file.clone();
+        cut_data.truncate(cut_data.len() - 5);
+        assert!(read_all(cut_data).unwrap_err().contains("truncated"));
+
+        let mut cut_header = file;
+        cut_header.extend_from_slice(&[0; 8]);
+        assert!(read_all(cut_header).unwrap_err().contains("truncated"));
+
+        let mut ng = Vec::new();
+        ng_section(&mut ng, false);
+        ng_interface(&mut ng, false, LINKTYPE_RAW as u16, 0, None);
+        ng_enhanced(&mut ng, false, 0, 0, 30, &IPV4_UDP, None);
+        ng.truncate(ng.len() - 6);
+        assert!(read_all(ng).unwrap_err().contains("truncated"));
+
+        assert!(PcapReader::new(Cursor::new(pcap_header(false, false, 65535, LINKTYPE_RAW)[..12].to_vec())).is_err());
+    }
+
+    #[test]
+    fn reads_records_larger_than_the_snap_length() {
+        // Some writers exceed their own snaplen; the record length is what
+        // keeps the stream aligned, so trust it up to MAX_RECORD_LEN
+        let mut file = pcap_header(true, false, 16, LINKTYPE_RAW);
+        pcap_record(&mut file, true, (1, 0), 30, &IPV4_UDP);
+        pcap_record(&mut file, true, (2, 0), 30, &IPV4_UDP);
+        let packets = read_all(file.clone()).unwrap();
+        assert_eq!(packets.len(), 2);
+        assert_eq!(packets[1].meta.captured_len, 30);
+        assert_eq!(packets[1].data, IPV4_UDP);
+
+        let mut huge = pcap_header(false, false, 16, LINKTYPE_RAW);
+        for field in [1, 0, MAX_RECORD_LEN as u32 + 1, MAX_RECORD_LEN as u32 + 1] {
+            put32(&mut huge, field, false);
+        }
+        assert!(read_all(huge).unwrap_err().contains("limit"));
+    }
+
+    #[test]
+    fn reads_linux_cooked_captures() {
+        let mut frame = vec![0, 0, 0, 1, 0, 6, 1, 2, 3, 4, 5, 6, 0, 0, 0x08, 0x00];
+        frame.extend_from_slice(&IPV4_UDP);
+        let mut file = pcap_header(false, false, 65535, LINKTYPE_LINUX_SLL);
+        pcap_record(&mut file, false, (1, 0), frame.len() as u32, &frame);
+        let packets = read_all(file).unwrap();
+
+        assert_eq!(packets[0].meta.link_type, LinkType::LinuxSll);
+        let packet = crate::packet_decoder::decode(&packets[0].data, LinkType::LinuxSll);
+        assert_eq!(packet.ports(), Some((5353, 53)));
+    }
+}
@@ -0,0 +1,291 @@
+use crate::logging;
+use crate::capture_backend::{CapturedPacket, LinkType};
+use crate::packet_capture;
+use crate::pcap_reader::{LINKTYPE_ETHERNET, LINKTYPE_LINUX_SLL, LINKTYPE_RAW};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::UNIX_EPOCH;
+
+pub fn init_module() -> Result<(), String> {
+    // Placeholder for actual initialization logic
+    let initialization_passed = true;
+
+    if initialization_passed {
+        logging::debug_info("pcap_writer module is online");
+        Ok(())
+    } else {
+        Err("pcap_writer module initialization failed".to_string())
+    }
+}
+
+const PCAP_SNAPLEN: u32 = 65535;
+
+static EXPORT_RUNNING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Pcap,
+    PcapNg,
+}
+
+impl ExportFormat {
+    // Picks the format from the file extension, pcapng unless it says .pcap
+    pub fn from_path(path: &Path) -> ExportFormat {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("pcap") => ExportFormat::Pcap,
+            _ => ExportFormat::PcapNg,
+        }
+    }
+}
+
+pub(crate) fn link_type_to_pcap(link_type: LinkType) -> u32 {
+    match link_type {
+        LinkType::Ethernet => LINKTYPE_ETHERNET,
+        LinkType::RawIp => LINKTYPE_RAW,
+        LinkType::LinuxSll => LINKTYPE_LINUX_SLL,
+    }
+}
+
+// Seconds and nanoseconds since the epoch; pre-epoch timestamps clamp to 0
+fn timestamp_parts(packet: &CapturedPacket) -> (u64, u32) {
+    let since_epoch = packet.meta.timestamp.duration_since(UNIX_EPOCH).unwrap_or_default();
+    (since_epoch.as_secs(), since_epoch.subsec_nanos())
+}
+
+fn io_error(e: std::io::Error) -> String {
+    format!("Failed to write capture: {}", e)
+}
+
+// Classic libpcap with microsecond timestamps. The format has a single link
+// type per file, so every packet must share the first packet's link type.
+pub fn write_pcap<W: Write>(mut out: W, packets: &[CapturedPacket]) -> Result<(), String> {
+    let link_type = packets.first().map(|p| p.meta.link_type).unwrap_or(LinkType::Ethernet);
+    if packets.iter().any(|p| p.meta.link_type != link_type) {
+        return Err("Packets use different link types; export as pcapng instead".to_string());
+    }
+
+    let mut header = Vec::with_capacity(24);
+    header.extend_from_slice(&0xa1b2_c3d4u32.to_le_bytes());
+    header.extend_from_slice(&2u16.to_le_bytes());
+    header.extend_from_slice(&4u16.to_le_bytes());
+    header.extend_from_slice(&0i32.to_le_bytes()); // thiszone
+    header.extend_from_slice(&0u32.to_le_bytes()); // sigfigs
+    header.extend_from_slice(&PCAP_SNAPLEN.to_le_bytes());
+    header.extend_from_slice(&link_type_to_pcap(link_type).to_le_bytes());
+    out.write_all(&header).map_err(io_error)?;
+
+    for packet in packets {
+        let (secs, nanos) = timestamp_parts(packet);
+        let mut record = Vec::with_capacity(16);
+        record.extend_from_slice(&(secs as u32).to_le_bytes());
+        record.extend_from_slice(&(nanos / 1000).to_le_bytes());
+        record.extend_from_slice(&(packet.data.len() as u32).to_le_bytes());
+        record.extend_from_slice(&(packet.meta.original_len.max(packet.data.len()) as u32).to_le_bytes());
+        out.write_all(&record).map_err(io_error)?;
+        out.write_all(&packet.data).map_err(io_error)?;
+    }
+    out.flush().map_err(io_error)
+}
+
+// Appends a pcapng option, padded to 32 bits
+fn push_option(body: &mut Vec<u8>, code: u16, value: &[u8]) {
+    body.extend_from_slice(&code.to_le_bytes());
+    body.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    body.extend_from_slice(value);
+    body.resize((body.len() + 3) & !3, 0);
+}
+
+fn write_block<W: Write>(out: &mut W, block_type: u32, body: &[u8]) -> Result<(), String> {
+    let total_len = (12 + body.len()) as u32;
+    out.write_all(&block_type.to_le_bytes()).map_err(io_error)?;
+    out.write_all(&total_len.to_le_bytes()).map_err(io_error)?;
+    out.write_all(body).map_err(io_error)?;
+    out.write_all(&total_len.to_le_bytes()).map_err(io_error)
+}
+
+// Name recorded in the pcapng if_name option
+pub(crate) fn interface_name(index: u32) -> String {
+    #[cfg(target_os = "linux")]
+    {
+        let mut buf = [0 as libc::c_char; libc::IF_NAMESIZE];
+        let name = unsafe { libc::if_indextoname(index, buf.as_mut_ptr()) };
+        if !name.is_null() {
+            let name = unsafe { std::ffi::CStr::from_ptr(name) };
+            return name.to_string_lossy().into_owned();
+        }
+    }
+    format!("if{}", index)
+}
+
+// pcapng with one Interface Description Block per (interface, link type)
+// seen, nanosecond timestamps and the capture direction in epb_flags.
+pub fn write_pcapng<W: Write>(mut out: W, packets: &[CapturedPacket]) -> Result<(), String> {
+    // Section Header Block: byte order magic, version 1.0, unknown section length
+    let mut shb = Vec::new();
+    shb.extend_from_slice(&0x1a2b_3c4du32.to_le_bytes());
+    shb.extend_from_slice(&1u16.to_le_bytes());
+    shb.extend_from_slice(&0u16.to_le_bytes());
+    shb.extend_from_slice(&(-1i64).to_le_bytes());
+    push_option(&mut shb, 4, b"s2o_net_lib"); // shb_userappl
+    push_option(&mut shb, 0, &[]);
+    write_block(&mut out, 0x0a0d_0d0a, &shb)?;
+
+    let mut interfaces: Vec<(u32, LinkType)> = Vec::new();
+    for packet in packets {
+        let key = (packet.meta.interface_index, packet.meta.link_type);
+        let id = match interfaces.iter().position(|existing| *existing == key) {
+            Some(id) => id,
+            None => {
+                let mut idb = Vec::new();
+                idb.extend_from_slice(&(link_type_to_pcap(key.1) as u16).to_le_bytes());
+                idb.extend_from_slice(&0u16.to_le_bytes());
+                idb.extend_from_slice(&0u32.to_le_bytes()); // no snaplen limit
+                push_option(&mut idb, 2, interface_name(key.0).as_bytes()); // if_name
+                push_option(&mut idb, 9, &[9]); // if_tsresol: nanoseconds
+                push_option(&mut idb, 0, &[]);
+                write_block(&mut out, 0x0000_0001, &idb)?;
+                interfaces.push(key);
+                interfaces.len() - 1
+            }
+        };
+
+        let (secs, nanos) = timestamp_parts(packet);
+        let ts = secs * 1_000_000_000 + nanos as u64;
+        let mut epb = Vec::with_capacity(32 + packet.data.len());
+        epb.extend_from_slice(&(id as u32).to_le_bytes());
+        epb.extend_from_slice(&((ts >> 32) as u32).to_le_bytes());
+        epb.extend_from_slice(&(ts as u32).to_le_bytes());
+        epb.extend_from_slice(&(packet.data.len() as u32).to_le_bytes());
+        epb.extend_from_slice(&(packet.meta.original_len.max(packet.data.len()) as u32).to_le_bytes());
+        epb.extend_from_slice(&packet.data);
+        epb.resize((epb.len() + 3) & !3, 0);
+        let direction: u32 = if packet.meta.outbound { 2 } else { 1 };
+        push_option(&mut epb, 2, &direction.to_le_bytes()); // epb_flags
+        push_option(&mut epb, 0, &[]);
+        write_block(&mut out, 0x0000_0006, &epb)?;
+    }
+    out.flush().map_err(io_error)
+}
+
+pub fn write_capture<W: Write>(out: W, packets: &[CapturedPacket], format: ExportFormat) -> Result<(), String> {
+    match format {
+        ExportFormat::Pcap => write_pcap(out, packets),
+        ExportFormat::PcapNg => write_pcapng(out, packets),
+    }
+}
+
+// Writes everything in CAPTURED_PACKETS to `path`, returning the packet count
+pub fn export_capture(path: &Path) -> Result<usize, String> {
+    let packets = packet_capture::CAPTURED_PACKETS.snapshot();
+    let format = ExportFormat::from_path(path);
+    let file = File::create(path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    write_capture(BufWriter::new(file), &packets, format)?;
+    logging::debug_info(&format!("Exported {} packets to {} as {:?}", packets.len(), path.display(), format));
+    Ok(packets.len())
+}
+
+// Exports on a background thread so a large capture does not stall the GUI
+pub fn start_export(path: PathBuf) {
+    if EXPORT_RUNNING.swap(true, Ordering::SeqCst) {
+        return logging::debug_error("Export already running.");
+    }
+    std::thread::spawn(move || {
+        if let Err(e) = export_capture(&path) {
+            logging::debug_error(&format!("Export capture failed: {}", e));
+        }
+        EXPORT_RUNNING.store(false, Ordering::SeqCst);
+    });
+}
+
+pub fn is_exporting() -> bool {
+    EXPORT_RUNNING.load(Ordering::SeqCst)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture_backend::PacketMeta;
+    use crate::pcap_reader::{PcapFormat, PcapReader};
+    use std::io::Cursor;
+    use std::time::{Duration, SystemTime};
+
+    fn packet(interface_index: u32, link_type: LinkType, nanos: u64, original_len: usize, data: &[u8], outbound: bool) -> CapturedPacket {
+        CapturedPacket {
+            meta: PacketMeta {
+                timestamp: UNIX_EPOCH + Duration::from_secs(1_700_000_000) + Duration::from_nanos(nanos),
+                captured_len: data.len(),
+                original_len,
+                interface_index,
+                outbound,
+                link_type,
+            },
+            data: data.to_vec(),
+            app_layer: None,
+        }
+    }
+
+    fn read_back(bytes: Vec<u8>, format: PcapFormat) -> Vec<CapturedPacket> {
+        let mut reader = PcapReader::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(reader.format(), format);
+        let mut packets = Vec::new();
+        while let Some(packet) = reader.next_packet().unwrap() {
+            packets.push(packet);
+        }
+        packets
+    }
+
+    fn at(nanos: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(1_700_000_000) + Duration::from_nanos(nanos)
+    }
+
+    #[test]
+    fn pcap_round_trips_through_the_reader() {
+        let packets = [
+            packet(2, LinkType::RawIp, 123_456_789, 1500, &[0x45; 40], false),
+            packet(2, LinkType::RawIp, 999_999_999, 10, &[0x45; 20], true),
+        ];
+        let mut bytes = Vec::new();
+        write_capture(&mut bytes, &packets, ExportFormat::Pcap).unwrap();
+        let read = read_back(bytes, PcapFormat::Pcap);
+
+        assert_eq!(read.len(), 2);
+        // Classic pcap keeps microseconds and has no interface or direction
+        assert_eq!(read[0].meta.timestamp, at(123_456_000));
+        assert_eq!(read[1].meta.timestamp, at(999_999_000));
+        assert_eq!(read[0].meta.link_type, LinkType::RawIp);
+        assert_eq!((read[0].meta.captured_len, read[0].meta.original_len), (40, 1500));
+        // An original length below the captured one is raised to match
+        assert_eq!((read[1].meta.captured_len, read[1].meta.original_len), (20, 20));
+        assert_eq!(read[1].data, packets[1].data);
+
+        let mixed = [packets[0].clone(), packet(2, LinkType::Ethernet, 0, 14, &[0; 14], false)];
+        assert!(write_capture(Vec::new(), &mixed, ExportFormat::Pcap).is_err());
+    }
+
+    #[test]
+    fn pcapng_round_trips_through_the_reader() {
+        let packets = [
+            packet(3, LinkType::Ethernet, 1, 60, &[0xaa; 14], false),
+            packet(5, LinkType::RawIp, 2_000_000_001, 1500, &[0x45; 21], true),
+            packet(3, LinkType::Ethernet, 3, 14, &[0xbb; 14], true),
+            packet(3, LinkType::LinuxSll, 4, 16, &[0; 16], false),
+        ];
+        let mut bytes = Vec::new();
+        write_capture(&mut bytes, &packets, ExportFormat::PcapNg).unwrap();
+        let read = read_back(bytes, PcapFormat::PcapNg);
+
+        assert_eq!(read.len(), packets.len());
+        for (written, read) in packets.iter().zip(&read) {
+            assert_eq!(read.meta.timestamp, written.meta.timestamp);
+            assert_eq!(read.meta.link_type, written.meta.link_type);
+            assert_eq!(read.meta.outbound, written.meta.outbound);
+            assert_eq!((read.meta.captured_len, read.meta.original_len), (written.data.len(), written.meta.original_len));
+            assert_eq!(read.data, written.data);
+        }
+        // One interface block per (interface, link type), numbered in order of appearance
+        let ids: Vec<u32> = read.iter().map(|packet| packet.meta.interface_index).collect();
+        assert_eq!(ids, [0, 1, 0, 2]);
+    }
+}
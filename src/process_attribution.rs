@@ -0,0 +1,520 @@
+use crate::logging;
+use crate::capture_backend::Interrupter;
+use crate::flow_table::{self, Endpoint};
+use crate::packet_decoder::{Packet, IP_PROTO_TCP, IP_PROTO_UDP};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+#[cfg(target_os = "linux")]
+use std::time::Duration;
+
+pub fn init_module() -> Result<(), String> {
+    // Placeholder for actual initialization logic
+    let initialization_passed = true;
+
+    if initialization_passed {
+        logging::debug_info("process_attribution module is online");
+        Ok(())
+    } else {
+        Err("process_attribution module initialization failed".to_string())
+    }
+}
+
+// Socket owners known so far, filled from the WinDivert flow layer on
+// Windows and from /proc on Linux
+static PROCESS_TABLE: Lazy<Mutex<ProcessTable>> = Lazy::new(|| Mutex::new(ProcessTable::new()));
+// Lookups only run during a live capture; replayed files belong to no local process
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+// Background thread keeping PROCESS_TABLE current while attribution is on
+static MONITOR: Lazy<Mutex<Option<Monitor>>> = Lazy::new(|| Mutex::new(None));
+static MONITOR_STOP: AtomicBool = AtomicBool::new(false);
+// Rescanning /proc walks every process's fds, so misses refresh at most this often
+#[cfg(target_os = "linux")]
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+// How often the /proc refresher looks for a lookup miss
+#[cfg(target_os = "linux")]
+const REFRESH_POLL: Duration = Duration::from_millis(50);
+// Set by a lookup that missed; the refresher rescans /proc off the capture thread
+#[cfg(target_os = "linux")]
+static REFRESH_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+#[cfg_attr(not(any(windows, target_os = "linux")), allow(dead_code))]
+struct Monitor {
+    thread: JoinHandle<()>,
+    // Makes the thread notice MONITOR_STOP promptly
+    interrupt: Interrupter,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub name: String,
+    pub path: Option<String>,
+}
+
+impl fmt::Display for ProcessInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({})", self.name, self.pid)
+    }
+}
+
+// A socket as the OS reports it. Listening and unconnected sockets have no
+// remote endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SocketKey {
+    pub protocol: u8,
+    pub local: Endpoint,
+    pub remote: Option<Endpoint>,
+}
+
+#[derive(Debug, Default)]
+pub struct ProcessTable {
+    sockets: HashMap<SocketKey, ProcessInfo>,
+}
+
+fn unspecified_like(addr: IpAddr) -> [IpAddr; 2] {
+    // A dual stack socket bound to :: also receives IPv4 traffic
+    [
+        match addr {
+            IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        },
+        IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    ]
+}
+
+impl ProcessTable {
+    pub fn new() -> Self {
+        ProcessTable { sockets: HashMap::new() }
+    }
+
+    pub fn insert(&mut self, key: SocketKey, process: ProcessInfo) {
+        self.sockets.insert(key, process);
+    }
+
+    pub fn remove(&mut self, key: &SocketKey) {
+        self.sockets.remove(key);
+    }
+
+    pub fn len(&self) -> usize {
+        self.sockets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sockets.is_empty()
+    }
+
+    // Tries the connected socket first, then a listener or unconnected
+    // socket on the local port, bound either to the address or to any
+    pub fn lookup(&self, protocol: u8, local: Endpoint, remote: Endpoint) -> Option<&ProcessInfo> {
+        let exact = SocketKey { protocol, local, remote: Some(remote) };
+        if let Some(process) = self.sockets.get(&exact) {
+            return Some(process);
+        }
+        let mut addresses = vec![local.0];
+        addresses.extend(unspecified_like(local.0));
+        addresses
+            .into_iter()
+            .find_map(|addr| self.sockets.get(&SocketKey { protocol, local: (addr, local.1), remote: None }))
+    }
+}
+
+// Splits a packet into its local and remote endpoints
+fn packet_endpoints(packet: &Packet, outbound: bool) -> Option<(u8, Endpoint, Endpoint)> {
+    let protocol = packet.ip_protocol().filter(|p| *p == IP_PROTO_TCP || *p == IP_PROTO_UDP)?;
+    let (sport, dport) = packet.ports()?;
+    let source = (packet.source_ip()?, sport);
+    let destination = (packet.destination_ip()?, dport);
+    Some(if outbound { (protocol, source, destination) } else { (protocol, destination, source) })
+}
+
+// Parses one address from /proc/net/*: the kernel prints each 32-bit word
+// of the network order address as a host order hex number
+fn parse_proc_addr(hex: &str) -> Option<IpAddr> {
+    let mut bytes = Vec::with_capacity(16);
+    for chunk in hex.as_bytes().chunks(8) {
+        let word = u32::from_str_radix(std::str::from_utf8(chunk).ok()?, 16).ok()?;
+        bytes.extend_from_slice(&word.to_ne_bytes());
+    }
+    match bytes.len() {
+        4 => Some(IpAddr::from(<[u8; 4]>::try_from(bytes).ok()?)),
+        16 => {
+            let addr = Ipv6Addr::from(<[u8; 16]>::try_from(bytes).ok()?);
+            // IPv4 connections on a dual stack socket show up as ::ffff:a.b.c.d
+            Some(addr.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(IpAddr::V6(addr)))
+        }
+        _ => None,
+    }
+}
+
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) fn parse_proc_endpoint(value: &str) -> Option<Endpoint> {
+    let (addr, port) = value.split_once(':')?;
+    Some((parse_proc_addr(addr)?, u16::from_str_radix(port, 16).ok()?))
+}
+
+// Reads /proc/net/{tcp,tcp6,udp,udp6} text into sockets and their inodes
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) fn parse_proc_net(text: &str, protocol: u8) -> Vec<(SocketKey, u64)> {
+    text.lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let local = parse_proc_endpoint(fields.get(1)?)?;
+            let remote = parse_proc_endpoint(fields.get(2)?)?;
+            let inode: u64 = fields.get(9)?.parse().ok()?;
+            // TIME_WAIT sockets no longer belong to anyone
+            if inode == 0 {
+                return None;
+            }
+            let remote = (remote.1 != 0).then_some(remote);
+            Some((SocketKey { protocol, local, remote }, inode))
+        })
+        .collect()
+}
+
+// "socket:[12345]" as read from /proc/<pid>/fd/<n>
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) fn socket_inode(link: &str) -> Option<u64> {
+    link.strip_prefix("socket:[")?.strip_suffix(']')?.parse().ok()
+}
+
+// Socket inodes mapped to the processes holding them. Without root only our
+// own processes' fds are readable, so other users' sockets stay unattributed.
+#[cfg(target_os = "linux")]
+pub(crate) fn socket_owners() -> HashMap<u64, ProcessInfo> {
+    let mut owners: HashMap<u64, ProcessInfo> = HashMap::new();
+    let entries = std::fs::read_dir("/proc").into_iter().flatten().flatten();
+    for entry in entries {
+        let pid: u32 = match entry.file_name().to_str().and_then(|name| name.parse().ok()) {
+            Some(pid) => pid,
+            None => continue,
+        };
+        let fds = match std::fs::read_dir(entry.path().join("fd")) {
+            Ok(fds) => fds,
+            Err(_) => continue,
+        };
+        let mut process = None;
+        for fd in fds.flatten() {
+            let inode = std::fs::read_link(fd.path()).ok().and_then(|link| socket_inode(&link.to_string_lossy()));
+            if let Some(inode) = inode {
+                let process = process.get_or_insert_with(|| ProcessInfo {
+                    pid,
+                    name: std::fs::read_to_string(entry.path().join("comm"))
+                        .map(|comm| comm.trim().to_string())
+                        .unwrap_or_else(|_| format!("pid {}", pid)),
+                    path: std::fs::read_link(entry.path().join("exe")).ok().map(|exe| exe.to_string_lossy().into_owned()),
+                });
+                owners.insert(inode, process.clone());
+            }
+        }
+    }
+    owners
+}
+
+// Builds the table from /proc
+#[cfg(target_os = "linux")]
+fn scan_proc() -> ProcessTable {
+    let mut sockets = Vec::new();
+    for (file, protocol) in [("tcp", IP_PROTO_TCP), ("tcp6", IP_PROTO_TCP), ("udp", IP_PROTO_UDP), ("udp6", IP_PROTO_UDP)] {
+        if let Ok(text) = std::fs::read_to_string(format!("/proc/net/{}", file)) {
+            sockets.extend(parse_proc_net(&text, protocol));
+        }
+    }
+
+    let owners = socket_owners();
+    let mut table = ProcessTable::new();
+    for (key, inode) in sockets {
+        if let Some(process) = owners.get(&inode) {
+            table.insert(key, process.clone());
+        }
+    }
+    table
+}
+
+// Rescans /proc whenever a lookup missed, at most once per REFRESH_INTERVAL
+#[cfg(target_os = "linux")]
+fn start_monitor() -> Result<Monitor, String> {
+    REFRESH_REQUESTED.store(true, Ordering::SeqCst);
+    let thread = std::thread::Builder::new()
+        .name("proc-refresh".to_string())
+        .spawn(|| {
+            while !MONITOR_STOP.load(Ordering::SeqCst) {
+                if REFRESH_REQUESTED.swap(false, Ordering::SeqCst) {
+                    let table = scan_proc();
+                    *PROCESS_TABLE.lock().unwrap() = table;
+                    std::thread::park_timeout(REFRESH_INTERVAL);
+                } else {
+                    std::thread::park_timeout(REFRESH_POLL);
+                }
+            }
+        })
+        .map_err(|e| format!("Failed to start /proc refresher: {}", e))?;
+    let waker = thread.thread().clone();
+    Ok(Monitor { thread, interrupt: Box::new(move || waker.unpark()) })
+}
+
+// WINDIVERT_DATA_FLOW: EndpointId and ParentEndpointId (8 bytes each),
+// ProcessId, LocalAddr[4], RemoteAddr[4], LocalPort, RemotePort, Protocol.
+// Addresses are four host order words, least significant first, with IPv4
+// stored as ::ffff:a.b.c.d.
+#[cfg_attr(not(windows), allow(dead_code))]
+pub(crate) fn parse_windivert_flow(data: &[u8]) -> Option<(u32, SocketKey)> {
+    let u32_at = |offset: usize| Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?));
+    let u16_at = |offset: usize| Some(u16::from_le_bytes(data.get(offset..offset + 2)?.try_into().ok()?));
+    let addr_at = |offset: usize| -> Option<IpAddr> {
+        let mut value: u128 = 0;
+        for word in (0..4).rev() {
+            value = (value << 32) | u32_at(offset + word * 4)? as u128;
+        }
+        let addr = Ipv6Addr::from(value);
+        Some(addr.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(IpAddr::V6(addr)))
+    };
+    let pid = u32_at(16)?;
+    let local = (addr_at(20)?, u16_at(52)?);
+    let remote = (addr_at(36)?, u16_at(54)?);
+    let protocol = *data.get(56)?;
+    Some((pid, SocketKey { protocol, local, remote: Some(remote) }))
+}
+
+#[cfg(windows)]
+mod win32 {
+    use std::ffi::c_void;
+
+    pub const PROCESS_QUERY_LIMITED_INFORMATION: u32 = 0x1000;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        pub fn OpenProcess(access: u32, inherit: i32, pid: u32) -> *mut c_void;
+        pub fn QueryFullProcessImageNameW(process: *mut c_void, flags: u32, name: *mut u16, size: *mut u32) -> i32;
+        pub fn CloseHandle(handle: *mut c_void) -> i32;
+    }
+}
+
+// Looks up the executable behind a process ID
+#[cfg(windows)]
+pub(crate) fn windows_process_info(pid: u32) -> ProcessInfo {
+    let path = unsafe {
+        let handle = win32::OpenProcess(win32::PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if handle.is_null() {
+            None
+        } else {
+            let mut buf = [0u16; 1024];
+            let mut size = buf.len() as u32;
+            let ok = win32::QueryFullProcessImageNameW(handle, 0, buf.as_mut_ptr(), &mut size);
+            win32::CloseHandle(handle);
+            (ok != 0).then(|| String::from_utf16_lossy(&buf[..size as usize]))
+        }
+    };
+    let name = match (&path, pid) {
+        (Some(path), _) => path.rsplit('\\').next().unwrap_or(path).to_string(),
+        (None, 4) => "System".to_string(),
+        (None, _) => format!("pid {}", pid),
+    };
+    ProcessInfo { pid, name, path }
+}
+
+// Follows flow layer events until stopped, which shuts the handle down
+#[cfg(windows)]
+fn start_monitor() -> Result<Monitor, String> {
+    use crate::nc::{FlowEvent, WinDivertFlowMonitor};
+
+    let mut monitor = WinDivertFlowMonitor::open()?;
+    let interrupt = monitor.interrupter();
+    let thread = std::thread::spawn(move || {
+        let mut names: HashMap<u32, ProcessInfo> = HashMap::new();
+        loop {
+            let (event, data) = match monitor.recv() {
+                Ok(event) => event,
+                Err(e) => {
+                    if !MONITOR_STOP.load(Ordering::SeqCst) {
+                        logging::debug_error(&e);
+                    }
+                    break;
+                }
+            };
+            let (pid, key) = match parse_windivert_flow(&data) {
+                Some(flow) => flow,
+                None => continue,
+            };
+            match event {
+                FlowEvent::Established => {
+                    let process = names.entry(pid).or_insert_with(|| windows_process_info(pid)).clone();
+                    PROCESS_TABLE.lock().unwrap().insert(key, process);
+                }
+                FlowEvent::Deleted => PROCESS_TABLE.lock().unwrap().remove(&key),
+            }
+        }
+    });
+    Ok(Monitor { thread, interrupt })
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+fn start_monitor() -> Result<Monitor, String> {
+    Err("Process attribution is not supported on this platform".to_string())
+}
+
+// Turns attribution on for a live capture
+pub fn start() -> Result<(), String> {
+    let mut monitor = MONITOR.lock().unwrap();
+    // A monitor that ended on its own (e.g. a driver error) is replaced
+    if let Some(finished) = monitor.take_if(|running| running.thread.is_finished()) {
+        let _ = finished.thread.join();
+    }
+    if monitor.is_none() {
+        MONITOR_STOP.store(false, Ordering::SeqCst);
+        *monitor = Some(start_monitor()?);
+    }
+    ACTIVE.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+// Turns attribution off and stops the monitor thread, so start can run again
+pub fn stop() {
+    ACTIVE.store(false, Ordering::SeqCst);
+    let monitor = MONITOR.lock().unwrap().take();
+    if let Some(monitor) = monitor {
+        MONITOR_STOP.store(true, Ordering::SeqCst);
+        (monitor.interrupt)();
+        if monitor.thread.join().is_err() {
+            logging::debug_error("Process attribution monitor panicked.");
+        }
+    }
+}
+
+// Process that owns the local end of a TCP or UDP packet, if known
+pub fn lookup(packet: &Packet, outbound: bool) -> Option<ProcessInfo> {
+    if !ACTIVE.load(Ordering::SeqCst) {
+        return None;
+    }
+    let (protocol, local, remote) = packet_endpoints(packet, outbound)?;
+    let found = PROCESS_TABLE.lock().unwrap().lookup(protocol, local, remote).cloned();
+    // Later packets of the flow pick up the owner once the rescan is in
+    #[cfg(target_os = "linux")]
+    if found.is_none() {
+        REFRESH_REQUESTED.store(true, Ordering::SeqCst);
+    }
+    found
+}
+
+// Packets and bytes per process across the tracked flows
+pub fn print_process_traffic() {
+    let mut totals: Vec<(String, u64, u64, usize)> = Vec::new();
+    for flow in flow_table::snapshot() {
+        let label = flow.process.as_ref().map(|p| p.to_string()).unwrap_or_else(|| "unknown".to_string());
+        let packets = flow.packets[0] + flow.packets[1];
+        let bytes = flow.bytes[0] + flow.bytes[1];
+        match totals.iter_mut().find(|(name, ..)| *name == label) {
+            Some(total) => {
+                total.1 += packets;
+                total.2 += bytes;
+                total.3 += 1;
+            }
+            None => totals.push((label, packets, bytes, 1)),
+        }
+    }
+    totals.sort_by_key(|total| std::cmp::Reverse(total.2));
+    logging::debug_info(&format!(
+        "Processes with traffic: {} ({} sockets known)",
+        totals.len(),
+        PROCESS_TABLE.lock().unwrap().len()
+    ));
+    for (label, packets, bytes, flows) in totals {
+        logging::debug_info(&format!("{}: {} flows, {} packets, {} bytes", label, flows, packets, bytes));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROC_NET_TCP: &str = include_str!("../testdata/proc/net_tcp");
+    const PROC_NET_TCP6: &str = include_str!("../testdata/proc/net_tcp6");
+
+    fn process(pid: u32, name: &str) -> ProcessInfo {
+        ProcessInfo { pid, name: name.to_string(), path: None }
+    }
+
+    fn endpoint(addr: &str, port: u16) -> Endpoint {
+        (addr.parse().unwrap(), port)
+    }
+
+    #[test]
+    fn parses_proc_net_listings() {
+        let sockets = parse_proc_net(PROC_NET_TCP, IP_PROTO_TCP);
+        let keys: Vec<(Endpoint, Option<Endpoint>, u64)> = sockets.iter().map(|(k, inode)| (k.local, k.remote, *inode)).collect();
+        assert_eq!(
+            keys,
+            [
+                (endpoint("127.0.0.53", 53), None, 23456),
+                (endpoint("0.0.0.0", 22), None, 31337),
+                (endpoint("192.168.1.20", 22), Some(endpoint("192.168.1.5", 51234)), 41000),
+            ]
+        );
+
+        let sockets = parse_proc_net(PROC_NET_TCP6, IP_PROTO_TCP);
+        assert_eq!(sockets[0].0.local, endpoint("::", 8080));
+        assert_eq!(sockets[1].0.local, endpoint("2001:db8::1", 443));
+        assert_eq!(sockets[2].0.remote, Some(endpoint("10.0.0.9", 40000)));
+        assert_eq!(socket_inode("socket:[41000]"), Some(41000));
+        assert_eq!(socket_inode("pipe:[41000]"), None);
+    }
+
+    #[test]
+    fn looks_up_connected_then_listening_sockets() {
+        let mut table = ProcessTable::new();
+        let ssh = SocketKey { protocol: IP_PROTO_TCP, local: endpoint("0.0.0.0", 22), remote: None };
+        let session = SocketKey { protocol: IP_PROTO_TCP, local: endpoint("10.0.0.2", 22), remote: Some(endpoint("10.0.0.9", 5000)) };
+        let web = SocketKey { protocol: IP_PROTO_TCP, local: endpoint("::", 8080), remote: None };
+        table.insert(ssh, process(100, "sshd"));
+        table.insert(session, process(200, "sshd-session"));
+        table.insert(web, process(300, "web"));
+
+        let lookup = |local: &str, port: u16, remote: &str| {
+            table.lookup(IP_PROTO_TCP, endpoint(local, port), endpoint(remote, 5000)).map(|p| p.pid)
+        };
+        assert_eq!(lookup("10.0.0.2", 22, "10.0.0.9"), Some(200));
+        assert_eq!(lookup("10.0.0.2", 22, "10.0.0.8"), Some(100));
+        assert_eq!(lookup("10.0.0.2", 8080, "10.0.0.8"), Some(300));
+        assert_eq!(lookup("10.0.0.2", 23, "10.0.0.8"), None);
+        assert_eq!(table.lookup(IP_PROTO_UDP, endpoint("10.0.0.2", 22), endpoint("10.0.0.9", 5000)), None);
+    }
+
+    #[test]
+    fn parses_windivert_flow_data() {
+        let mut data = [0u8; 64];
+        data[16..20].copy_from_slice(&1234u32.to_le_bytes());
+        // 192.168.1.20 as ::ffff:c0a8:0114, least significant word first
+        data[20..24].copy_from_slice(&0xc0a8_0114u32.to_le_bytes());
+        data[24..28].copy_from_slice(&0x0000_ffffu32.to_le_bytes());
+        data[36..40].copy_from_slice(&1u32.to_le_bytes());
+        data[48..52].copy_from_slice(&0x2001_0db8u32.to_le_bytes());
+        data[52..54].copy_from_slice(&50000u16.to_le_bytes());
+        data[54..56].copy_from_slice(&443u16.to_le_bytes());
+        data[56] = IP_PROTO_TCP;
+
+        let (pid, key) = parse_windivert_flow(&data).unwrap();
+        assert_eq!(pid, 1234);
+        assert_eq!(key.local, endpoint("192.168.1.20", 50000));
+        assert_eq!(key.remote, Some(endpoint("2001:db8::1", 443)));
+        assert_eq!(key.protocol, IP_PROTO_TCP);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn monitor_stops_and_restarts() {
+        start().unwrap();
+        start().unwrap();
+        stop();
+        assert!(MONITOR.lock().unwrap().is_none());
+        assert!(!ACTIVE.load(Ordering::SeqCst));
+
+        start().unwrap();
+        assert!(MONITOR.lock().unwrap().as_ref().is_some_and(|monitor| !monitor.thread.is_finished()));
+        stop();
+    }
+}
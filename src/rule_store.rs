@@ -0,0 +1,821 @@
+use crate::logging;
+use crate::firewall::{self, Decision, FirewallRule, IpNetwork, PortRange, RuleAction, RuleDirection, RuleSet};
+use crate::packet_decoder::{IP_PROTO_ICMP, IP_PROTO_ICMPV6, IP_PROTO_TCP, IP_PROTO_UDP};
+use crate::windows_firewall::{self, FwAction, FwAddress, FwDirection, FwPort, FwProtocol, WindowsFirewallRule};
+use crate::linux_firewall;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::net::IpAddr;
+use std::path::Path;
+
+pub fn init_module() -> Result<(), String> {
+    // Placeholder for actual initialization logic
+    let initialization_passed = true;
+
+    if initialization_passed {
+        logging::debug_info("rule_store module is online");
+        Ok(())
+    } else {
+        Err("rule_store module initialization failed".to_string())
+    }
+}
+
+// Rule file format, documented in docs/firewall_rules.md. Bump the version
+// and add a step to `migrate` whenever the layout changes.
+pub const FORMAT_NAME: &str = "s2o-firewall-rules";
+pub const FORMAT_VERSION: u64 = 2;
+
+// Where the S menu saves and loads the rule set, and reads rules to import
+pub const DEFAULT_RULES_FILE: &str = "firewall_rules.json";
+pub const DEFAULT_IMPORT_FILE: &str = "firewall_import.txt";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleFile {
+    format: String,
+    version: u64,
+    default_inbound: String,
+    default_outbound: String,
+    rules: Vec<RuleEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleEntry {
+    name: String,
+    #[serde(default = "enabled_default")]
+    enabled: bool,
+    #[serde(default)]
+    priority: i32,
+    direction: String,
+    action: String,
+    #[serde(default)]
+    protocol: Option<String>,
+    #[serde(default)]
+    local_addresses: Vec<String>,
+    #[serde(default)]
+    remote_addresses: Vec<String>,
+    #[serde(default)]
+    local_ports: Vec<String>,
+    #[serde(default)]
+    remote_ports: Vec<String>,
+    #[serde(default)]
+    process: Option<String>,
+}
+
+fn enabled_default() -> bool {
+    true
+}
+
+fn decision_name(decision: Decision) -> String {
+    match decision {
+        Decision::Allow => "allow".to_string(),
+        Decision::Deny => "deny".to_string(),
+    }
+}
+
+fn parse_decision(value: &str) -> Result<Decision, String> {
+    match value {
+        "allow" => Ok(Decision::Allow),
+        "deny" => Ok(Decision::Deny),
+        _ => Err(format!("Unknown default policy \"{}\"", value)),
+    }
+}
+
+pub(crate) fn protocol_name(protocol: u8) -> String {
+    match protocol {
+        IP_PROTO_TCP => "tcp".to_string(),
+        IP_PROTO_UDP => "udp".to_string(),
+        IP_PROTO_ICMP => "icmp".to_string(),
+        IP_PROTO_ICMPV6 => "icmpv6".to_string(),
+        other => other.to_string(),
+    }
+}
+
+fn parse_protocol(value: &str) -> Result<u8, String> {
+    match value {
+        "tcp" => Ok(IP_PROTO_TCP),
+        "udp" => Ok(IP_PROTO_UDP),
+        "icmp" => Ok(IP_PROTO_ICMP),
+        "icmpv6" => Ok(IP_PROTO_ICMPV6),
+        other => other.parse().map_err(|_| format!("Unknown protocol \"{}\"", value)),
+    }
+}
+
+fn to_entry(rule: &FirewallRule) -> RuleEntry {
+    RuleEntry {
+        name: rule.name.clone(),
+        enabled: rule.enabled,
+        priority: rule.priority,
+        direction: match rule.direction {
+            RuleDirection::Inbound => "inbound",
+            RuleDirection::Outbound => "outbound",
+            RuleDirection::Any => "any",
+        }
+        .to_string(),
+        action: match rule.action {
+            RuleAction::Allow => "allow",
+            RuleAction::Deny => "deny",
+            RuleAction::Log => "log",
+        }
+        .to_string(),
+        protocol: rule.protocol.map(protocol_name),
+        local_addresses: rule.local_addresses.iter().map(|n| n.to_string()).collect(),
+        remote_addresses: rule.remote_addresses.iter().map(|n| n.to_string()).collect(),
+        local_ports: rule.local_ports.iter().map(|p| p.to_string()).collect(),
+        remote_ports: rule.remote_ports.iter().map(|p| p.to_string()).collect(),
+        process: rule.process.clone(),
+    }
+}
+
+fn from_entry(entry: RuleEntry) -> Result<FirewallRule, String> {
+    let context = |e: String| format!("Rule \"{}\": {}", entry.name, e);
+    let networks = |list: &[String]| list.iter().map(|n| IpNetwork::parse(n)).collect::<Result<Vec<_>, _>>();
+    let ports = |list: &[String]| list.iter().map(|p| PortRange::parse(p)).collect::<Result<Vec<_>, _>>();
+    Ok(FirewallRule {
+        name: entry.name.clone(),
+        enabled: entry.enabled,
+        priority: entry.priority,
+        direction: match entry.direction.as_str() {
+            "inbound" => RuleDirection::Inbound,
+            "outbound" => RuleDirection::Outbound,
+            "any" => RuleDirection::Any,
+            other => return Err(context(format!("unknown direction \"{}\"", other))),
+        },
+        action: match entry.action.as_str() {
+            "allow" => RuleAction::Allow,
+            "deny" => RuleAction::Deny,
+            "log" => RuleAction::Log,
+            other => return Err(context(format!("unknown action \"{}\"", other))),
+        },
+        protocol: entry.protocol.as_deref().map(parse_protocol).transpose().map_err(context)?,
+        local_addresses: networks(&entry.local_addresses).map_err(context)?,
+        remote_addresses: networks(&entry.remote_addresses).map_err(context)?,
+        local_ports: ports(&entry.local_ports).map_err(context)?,
+        remote_ports: ports(&entry.remote_ports).map_err(context)?,
+        process: entry.process,
+    })
+}
+
+pub fn to_json(rules: &RuleSet) -> String {
+    let file = RuleFile {
+        format: FORMAT_NAME.to_string(),
+        version: FORMAT_VERSION,
+        default_inbound: decision_name(rules.default_inbound),
+        default_outbound: decision_name(rules.default_outbound),
+        rules: rules.rules().iter().map(to_entry).collect(),
+    };
+    // Pretty printed with a trailing newline so diffs stay readable
+    let mut json = serde_json::to_string_pretty(&file).expect("rule file always serializes");
+    json.push('\n');
+    json
+}
+
+// Splits a version 1 comma separated field into a list
+fn split_v1_list(value: &Value) -> Value {
+    match value.as_str() {
+        Some(list) if list.trim().is_empty() || list.trim() == "any" => Value::Array(Vec::new()),
+        Some(list) => Value::Array(list.split(',').map(|item| Value::String(item.trim().to_string())).collect()),
+        None => value.clone(),
+    }
+}
+
+// Version 1 kept addresses and ports as comma separated strings, called deny
+// "block", and ordered rules by position only
+fn migrate_v1_to_v2(mut file: Value) -> Result<Value, String> {
+    let rules = file.get_mut("rules").and_then(Value::as_array_mut).ok_or("Version 1 file has no rules list")?;
+    for (index, rule) in rules.iter_mut().enumerate() {
+        let rule = rule.as_object_mut().ok_or("Version 1 rule is not an object")?;
+        for key in ["local_addresses", "remote_addresses", "local_ports", "remote_ports"] {
+            if let Some(value) = rule.get(key) {
+                let list = split_v1_list(value);
+                rule.insert(key.to_string(), list);
+            }
+        }
+        if rule.get("action").and_then(Value::as_str) == Some("block") {
+            rule.insert("action".to_string(), Value::String("deny".to_string()));
+        }
+        rule.entry("priority").or_insert(Value::from(index as i64 * 10));
+    }
+    if file.get("default_inbound").and_then(Value::as_str) == Some("block") {
+        file["default_inbound"] = Value::String("deny".to_string());
+    }
+    if file.get("default_outbound").and_then(Value::as_str) == Some("block") {
+        file["default_outbound"] = Value::String("deny".to_string());
+    }
+    file["version"] = Value::from(2);
+    Ok(file)
+}
+
+// Upgrades a parsed file one version at a time to FORMAT_VERSION
+pub(crate) fn migrate(mut file: Value) -> Result<Value, String> {
+    if file.get("format").and_then(Value::as_str) != Some(FORMAT_NAME) {
+        return Err(format!("Not an {} file", FORMAT_NAME));
+    }
+    loop {
+        let version = file.get("version").and_then(Value::as_u64).ok_or("Rule file has no version")?;
+        file = match version {
+            1 => migrate_v1_to_v2(file)?,
+            FORMAT_VERSION => return Ok(file),
+            newer if newer > FORMAT_VERSION => {
+                return Err(format!("Rule file version {} is newer than this build supports ({})", newer, FORMAT_VERSION))
+            }
+            other => return Err(format!("Unknown rule file version {}", other)),
+        };
+    }
+}
+
+pub fn from_json(json: &str) -> Result<RuleSet, String> {
+    let value: Value = serde_json::from_str(json).map_err(|e| format!("Invalid rule file: {}", e))?;
+    let file: RuleFile = serde_json::from_value(migrate(value)?).map_err(|e| format!("Invalid rule file: {}", e))?;
+
+    let mut rules = RuleSet::new();
+    rules.default_inbound = parse_decision(&file.default_inbound)?;
+    rules.default_outbound = parse_decision(&file.default_outbound)?;
+    for entry in file.rules {
+        rules.add(from_entry(entry)?)?;
+    }
+    Ok(rules)
+}
+
+pub fn save_rules(rules: &RuleSet, path: &Path) -> Result<(), String> {
+    std::fs::write(path, to_json(rules)).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+pub fn load_rules(path: &Path) -> Result<RuleSet, String> {
+    let json = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    from_json(&json)
+}
+
+// Rules brought in from another firewall, plus the ones that had no
+// equivalent in the rule model and why
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    pub rules: Vec<FirewallRule>,
+    pub default_inbound: Option<Decision>,
+    pub default_outbound: Option<Decision>,
+    pub skipped: Vec<(String, String)>,
+}
+
+impl ImportReport {
+    // Names must be unique in a RuleSet; repeats get a numeric suffix
+    fn push(&mut self, mut rule: FirewallRule) {
+        let base = rule.name.clone();
+        let mut n = 2;
+        while self.rules.iter().any(|existing| existing.name == rule.name) {
+            rule.name = format!("{} ({})", base, n);
+            n += 1;
+        }
+        self.rules.push(rule);
+    }
+
+    // Adds the imported rules after the existing ones in `rules`
+    pub fn apply_to(self, rules: &mut RuleSet) -> Result<(), String> {
+        if let Some(decision) = self.default_inbound {
+            rules.default_inbound = decision;
+        }
+        if let Some(decision) = self.default_outbound {
+            rules.default_outbound = decision;
+        }
+        let base = rules.rules().iter().map(|r| r.priority).max().unwrap_or(0);
+        for (index, rule) in self.rules.into_iter().enumerate() {
+            rules.add(FirewallRule { priority: base + 10 * (index as i32 + 1), ..rule })?;
+        }
+        Ok(())
+    }
+}
+
+// Smallest list of networks that exactly covers start..=end
+fn range_to_networks(start: IpAddr, end: IpAddr) -> Option<Vec<IpNetwork>> {
+    let (mut low, high, width) = match (start, end) {
+        (IpAddr::V4(s), IpAddr::V4(e)) => (u32::from(s) as u128, u32::from(e) as u128, 32),
+        (IpAddr::V6(s), IpAddr::V6(e)) => (u128::from(s), u128::from(e), 128),
+        _ => return None,
+    };
+    if low > high {
+        return None;
+    }
+    let to_addr = |bits: u128| -> IpAddr {
+        if width == 32 {
+            IpAddr::V4((bits as u32).into())
+        } else {
+            IpAddr::V6(bits.into())
+        }
+    };
+    let mut networks = Vec::new();
+    loop {
+        // Largest aligned block starting at `low` that stays inside the range
+        let mut size_bits = if low == 0 { width } else { low.trailing_zeros().min(width) };
+        while size_bits > 0 && 1u128.checked_shl(size_bits).is_none_or(|size| low + (size - 1) > high) {
+            size_bits -= 1;
+        }
+        networks.push(IpNetwork { addr: to_addr(low), prefix: (width - size_bits) as u8 });
+        match 1u128.checked_shl(size_bits).and_then(|size| low.checked_add(size)) {
+            Some(next) if next <= high => low = next,
+            _ => return Some(networks),
+        }
+    }
+}
+
+fn convert_windows_rule(rule: &WindowsFirewallRule) -> Result<FirewallRule, String> {
+    let addresses = |list: &[FwAddress]| -> Result<Vec<IpNetwork>, String> {
+        let mut networks = Vec::new();
+        for address in list {
+            match address {
+                FwAddress::Network(network) => networks.push(*network),
+                FwAddress::Range(start, end) => {
+                    networks.extend(range_to_networks(*start, *end).ok_or(format!("bad address range {}", address))?)
+                }
+                FwAddress::Keyword(keyword) => return Err(format!("address keyword {} has no fixed value", keyword)),
+            }
+        }
+        Ok(networks)
+    };
+    let ports = |list: &[FwPort]| -> Result<Vec<PortRange>, String> {
+        list.iter()
+            .map(|port| match port {
+                FwPort::Range(range) => Ok(*range),
+                FwPort::Keyword(keyword) => Err(format!("port keyword {} has no fixed value", keyword)),
+            })
+            .collect()
+    };
+    if rule.service.is_some() {
+        return Err("service-scoped rules are not supported".to_string());
+    }
+    Ok(FirewallRule {
+        name: rule.name.clone(),
+        enabled: rule.enabled,
+        priority: 0,
+        direction: match rule.direction {
+            FwDirection::In => RuleDirection::Inbound,
+            FwDirection::Out => RuleDirection::Outbound,
+        },
+        action: match rule.action {
+            FwAction::Allow | FwAction::Bypass => RuleAction::Allow,
+            FwAction::Block => RuleAction::Deny,
+        },
+        protocol: match &rule.protocol {
+            FwProtocol::Any => None,
+            FwProtocol::Tcp => Some(IP_PROTO_TCP),
+            FwProtocol::Udp => Some(IP_PROTO_UDP),
+            FwProtocol::Icmpv4(_) => Some(IP_PROTO_ICMP),
+            FwProtocol::Icmpv6(_) => Some(IP_PROTO_ICMPV6),
+            FwProtocol::Number(number) => Some(*number),
+        },
+        local_addresses: addresses(&rule.local_addresses)?,
+        remote_addresses: addresses(&rule.remote_addresses)?,
+        local_ports: ports(&rule.local_ports)?,
+        remote_ports: ports(&rule.remote_ports)?,
+        process: rule.program.clone(),
+    })
+}
+
+// Splits a netsh command line into arguments, honouring double quotes
+fn split_command_line(line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut in_arg = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_arg = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            c => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if in_arg {
+        args.push(current);
+    }
+    args
+}
+
+// Reads a script of `netsh advfirewall firewall add rule ...` lines
+fn parse_netsh_script(text: &str) -> Result<Vec<WindowsFirewallRule>, String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with("::") && !line.starts_with("rem "))
+        .map(|line| {
+            let args = split_command_line(line);
+            match args.split_first() {
+                Some((netsh, rest)) if netsh.eq_ignore_ascii_case("netsh") => windows_firewall::parse_add_rule_args(rest),
+                _ => Err(format!("Not a netsh command: {}", line)),
+            }
+        })
+        .collect()
+}
+
+// Imports `netsh advfirewall firewall show rule name=all verbose` output or
+// a script of `netsh advfirewall firewall add rule` commands
+pub fn import_netsh(text: &str) -> Result<ImportReport, String> {
+    let is_script = text.lines().map(str::trim).any(|line| line.starts_with("netsh "));
+    let windows_rules = if is_script { parse_netsh_script(text)? } else { windows_firewall::parse_show_rule(text)? };
+    let mut report = ImportReport::default();
+    for rule in &windows_rules {
+        match convert_windows_rule(rule) {
+            Ok(converted) => report.push(converted),
+            Err(reason) => report.skipped.push((rule.name.clone(), reason)),
+        }
+    }
+    Ok(report)
+}
+
+// Splits an nft rule line into words, keeping { a, b } sets and quoted
+// strings together as single tokens
+fn nft_tokens(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let mut token = String::new();
+        let closing = match c {
+            '{' => Some('}'),
+            '"' => Some('"'),
+            _ => None,
+        };
+        if let Some(closing) = closing {
+            token.push(chars.next().unwrap());
+            for c in chars.by_ref() {
+                token.push(c);
+                if c == closing {
+                    break;
+                }
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                token.push(c);
+                chars.next();
+            }
+        }
+        tokens.push(token);
+    }
+    tokens
+}
+
+fn nft_set_items(token: &str) -> Vec<String> {
+    token
+        .trim_start_matches('{')
+        .trim_end_matches('}')
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+// Converts one nft rule in a chain hooked at `direction`
+fn convert_nft_rule(line: &str, direction: RuleDirection) -> Result<FirewallRule, String> {
+    let tokens = nft_tokens(line);
+    let mut rule = FirewallRule::new("", RuleAction::Log);
+    let mut verdict = None;
+    let mut logs = false;
+    let mut i = 0;
+    let value = |i: usize| tokens.get(i).cloned().ok_or(format!("missing value after \"{}\"", tokens[i - 1]));
+    // Source and destination turn into local or remote depending on the chain
+    let inbound = direction == RuleDirection::Inbound;
+
+    while i < tokens.len() {
+        let token = tokens[i].as_str();
+        match token {
+            "ip" | "ip6" => {
+                let field = value(i + 1)?;
+                let networks = nft_set_items(&value(i + 2)?)
+                    .iter()
+                    .map(|n| IpNetwork::parse(n))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match (field.as_str(), inbound) {
+                    ("saddr", true) | ("daddr", false) => rule.remote_addresses = networks,
+                    ("saddr", false) | ("daddr", true) => rule.local_addresses = networks,
+                    ("protocol" | "nexthdr", _) => {
+                        let protocol = nft_set_items(&value(i + 2)?);
+                        rule.protocol = Some(parse_protocol(protocol.first().map(String::as_str).unwrap_or(""))?);
+                    }
+                    (other, _) => return Err(format!("unsupported match \"{} {}\"", token, other)),
+                }
+                i += 3;
+            }
+            "tcp" | "udp" | "th" => {
+                let field = value(i + 1)?;
+                let ports = nft_set_items(&value(i + 2)?)
+                    .iter()
+                    .map(|p| PortRange::parse(p))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if token != "th" {
+                    rule.protocol = Some(parse_protocol(token)?);
+                }
+                match (field.as_str(), inbound) {
+                    ("sport", true) | ("dport", false) => rule.remote_ports = ports,
+                    ("sport", false) | ("dport", true) => rule.local_ports = ports,
+                    (other, _) => return Err(format!("unsupported match \"{} {}\"", token, other)),
+                }
+                i += 3;
+            }
+            "meta" => {
+                if value(i + 1)? != "l4proto" {
+                    return Err(format!("unsupported match \"meta {}\"", tokens[i + 1]));
+                }
+                let protocols = nft_set_items(&value(i + 2)?);
+                // "{ tcp, udp }" only guards a "th" port match, which covers both
+                if let [protocol] = protocols.as_slice() {
+                    let protocol = if protocol == "ipv6-icmp" { "icmpv6" } else { protocol };
+                    rule.protocol = Some(parse_protocol(protocol)?);
+                } else if protocols != ["tcp", "udp"] {
+                    return Err(format!("unsupported protocol set {}", tokens[i + 2]));
+                }
+                i += 3;
+            }
+            "counter" => {
+                // "counter packets N bytes M" in listings
+                i += 1;
+                while matches!(tokens.get(i).map(String::as_str), Some("packets" | "bytes")) {
+                    i += 2;
+                }
+            }
+            "log" => {
+                logs = true;
+                i += 1;
+                while matches!(tokens.get(i).map(String::as_str), Some("prefix" | "level" | "group")) {
+                    i += 2;
+                }
+            }
+            "comment" => {
+                rule.name = value(i + 1)?.trim_matches('"').to_string();
+                i += 2;
+            }
+            "accept" => {
+                verdict = Some(RuleAction::Allow);
+                i += 1;
+            }
+            "drop" | "reject" => {
+                verdict = Some(RuleAction::Deny);
+                i += 1;
+                // "reject with icmp type ..." carries extra words
+                if token == "reject" && tokens.get(i).map(String::as_str) == Some("with") {
+                    i = tokens.iter().position(|t| t == "comment").unwrap_or(tokens.len());
+                }
+            }
+            other => return Err(format!("unsupported expression \"{}\"", other)),
+        }
+    }
+
+    rule.action = match (verdict, logs) {
+        (Some(action), _) => action,
+        (None, true) => RuleAction::Log,
+        (None, false) => return Err("rule has no verdict".to_string()),
+    };
+    rule.direction = direction;
+    Ok(rule)
+}
+
+// Imports the input and output chains of an `nft list ruleset` listing (or a
+// script like the one linux_firewall generates). Rules without a comment are
+// named after their chain and position.
+pub fn import_nftables(text: &str) -> Result<ImportReport, String> {
+    let mut report = ImportReport::default();
+    let mut direction: Option<Option<RuleDirection>> = None;
+    let mut chain_name = String::new();
+    let mut position = 0;
+
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix("chain ") {
+            chain_name = rest.trim_end_matches('{').trim().to_string();
+            direction = Some(None);
+            position = 0;
+            continue;
+        }
+        let Some(chain_direction) = direction else {
+            continue;
+        };
+        if line == "}" {
+            direction = None;
+            continue;
+        }
+        if line.starts_with("type ") {
+            let hook = line.split_whitespace().skip_while(|w| *w != "hook").nth(1).unwrap_or("");
+            let chain_direction = match hook {
+                "input" => Some(RuleDirection::Inbound),
+                "output" => Some(RuleDirection::Outbound),
+                _ => None,
+            };
+            direction = Some(chain_direction);
+            if let (Some(dir), Some(policy)) = (chain_direction, line.split("policy ").nth(1)) {
+                let decision = match policy.trim_end_matches(';').trim() {
+                    "drop" => Decision::Deny,
+                    _ => Decision::Allow,
+                };
+                if dir == RuleDirection::Inbound {
+                    report.default_inbound = Some(decision);
+                } else {
+                    report.default_outbound = Some(decision);
+                }
+            }
+            continue;
+        }
+        // Regular chains and forward hooks have no place in the rule model
+        let Some(chain_direction) = chain_direction else {
+            continue;
+        };
+        // The accepts nftables_ruleset puts first are not user rules
+        if chain_direction == RuleDirection::Inbound && linux_firewall::NFT_INPUT_PREAMBLE.contains(&line) {
+            continue;
+        }
+        position += 1;
+        let fallback_name = format!("{} rule {}", chain_name, position);
+        match convert_nft_rule(line, chain_direction) {
+            Ok(mut rule) => {
+                if rule.name.is_empty() {
+                    rule.name = fallback_name;
+                }
+                report.push(rule);
+            }
+            Err(reason) => report.skipped.push((fallback_name, reason)),
+        }
+    }
+    Ok(report)
+}
+
+// Picks the importer from the text: nft listings are made of tables and chains
+pub fn import_text(text: &str) -> Result<ImportReport, String> {
+    let is_nft = text.lines().map(str::trim).any(|line| line.starts_with("table ") || line.starts_with("chain "));
+    if is_nft {
+        import_nftables(text)
+    } else {
+        import_netsh(text)
+    }
+}
+
+pub fn import_into_current_rules() {
+    let result = std::fs::read_to_string(DEFAULT_IMPORT_FILE)
+        .map_err(|e| format!("Failed to read {}: {}", DEFAULT_IMPORT_FILE, e))
+        .and_then(|text| import_text(&text));
+    let report = match result {
+        Ok(report) => report,
+        Err(e) => return logging::debug_error(&e),
+    };
+    for (name, reason) in &report.skipped {
+        logging::debug_info(&format!("Skipped \"{}\": {}", name, reason));
+    }
+    let imported = report.rules.len();
+    let mut rules = firewall::FIREWALL.lock().unwrap();
+    // Apply to a copy so a name clash leaves the live rule set untouched
+    let mut updated = rules.clone();
+    match report.apply_to(&mut updated) {
+        Ok(()) => {
+            *rules = updated;
+            logging::debug_info(&format!("Imported {} firewall rules from {}", imported, DEFAULT_IMPORT_FILE));
+        }
+        Err(e) => logging::debug_error(&format!("Import from {} failed: {}", DEFAULT_IMPORT_FILE, e)),
+    }
+}
+
+pub fn save_current_rules() {
+    let rules = firewall::FIREWALL.lock().unwrap().clone();
+    match save_rules(&rules, Path::new(DEFAULT_RULES_FILE)) {
+        Ok(()) => logging::debug_info(&format!("Saved {} firewall rules to {}", rules.rules().len(), DEFAULT_RULES_FILE)),
+        Err(e) => logging::debug_error(&e),
+    }
+}
+
+pub fn load_current_rules() {
+    match load_rules(Path::new(DEFAULT_RULES_FILE)) {
+        Ok(rules) => {
+            logging::debug_info(&format!("Loaded {} firewall rules from {}", rules.rules().len(), DEFAULT_RULES_FILE));
+            *firewall::FIREWALL.lock().unwrap() = rules;
+        }
+        Err(e) => logging::debug_error(&e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHOW_RULE_VERBOSE: &str = include_str!("../testdata/netsh/show_rule_verbose.txt");
+    const NFT_RULESET: &str = include_str!("../testdata/firewall/ruleset.nft");
+
+    fn sample_rules() -> RuleSet {
+        let mut rules = RuleSet::new();
+        rules.default_inbound = Decision::Deny;
+        rules
+            .add(FirewallRule {
+                priority: 5,
+                direction: RuleDirection::Inbound,
+                protocol: Some(IP_PROTO_TCP),
+                remote_addresses: vec![IpNetwork::parse("10.0.0.0/8").unwrap()],
+                local_ports: vec![PortRange::single(22), PortRange { start: 8000, end: 8080 }],
+                ..FirewallRule::new("ssh", RuleAction::Allow)
+            })
+            .unwrap();
+        rules
+            .add(FirewallRule {
+                enabled: false,
+                process: Some("C:\\Apps\\agent.exe".to_string()),
+                ..FirewallRule::new("agent", RuleAction::Deny)
+            })
+            .unwrap();
+        rules
+    }
+
+    #[test]
+    fn json_round_trip_keeps_every_field() {
+        let rules = sample_rules();
+        let json = to_json(&rules);
+        assert!(json.contains("\"version\": 2"));
+        assert_eq!(from_json(&json).unwrap(), rules);
+        assert!(from_json(&json.replace("\"version\": 2", "\"version\": 9")).unwrap_err().contains("newer"));
+        assert!(from_json(&json.replace("\"priority\"", "\"prio\"")).is_err());
+    }
+
+    #[test]
+    fn migrates_version_1_files() {
+        let v1 = r#"{
+            "format": "s2o-firewall-rules",
+            "version": 1,
+            "default_inbound": "block",
+            "default_outbound": "allow",
+            "rules": [
+                { "name": "web", "direction": "inbound", "action": "allow", "protocol": "tcp", "local_ports": "80,443" },
+                { "name": "lan", "direction": "any", "action": "block", "remote_addresses": "192.168.0.0/16, fd00::/8" }
+            ]
+        }"#;
+        let rules = from_json(v1).unwrap();
+        assert_eq!(rules.default_inbound, Decision::Deny);
+        let web = &rules.rules()[0];
+        assert_eq!((web.priority, web.local_ports.len()), (0, 2));
+        let lan = &rules.rules()[1];
+        assert_eq!((lan.priority, lan.action, lan.remote_addresses.len()), (10, RuleAction::Deny, 2));
+    }
+
+    #[test]
+    fn imports_netsh_output_and_scripts() {
+        let report = import_netsh(SHOW_RULE_VERBOSE).unwrap();
+        let names: Vec<&str> = report.rules.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["Block Telemetry"]);
+        let telemetry = &report.rules[0];
+        assert_eq!(telemetry.action, RuleAction::Deny);
+        // 20.190.128.0-20.190.191.255 is exactly one /18
+        assert_eq!(telemetry.remote_addresses[1], IpNetwork::parse("20.190.128.0/18").unwrap());
+        assert_eq!(report.skipped.len(), 2);
+
+        let script = "netsh advfirewall firewall add rule \"name=Web Server\" dir=in action=allow protocol=tcp localport=80,443 remoteip=10.0.0.1-10.0.0.6\n";
+        let report = import_netsh(script).unwrap();
+        let web = &report.rules[0];
+        assert_eq!(web.name, "Web Server");
+        assert_eq!(web.local_ports, vec![PortRange::single(80), PortRange::single(443)]);
+        let networks: Vec<String> = web.remote_addresses.iter().map(|n| n.to_string()).collect();
+        assert_eq!(networks, ["10.0.0.1/32", "10.0.0.2/31", "10.0.0.4/31", "10.0.0.6/32"]);
+    }
+
+    #[test]
+    fn imports_generated_nftables_ruleset() {
+        let report = import_nftables(NFT_RULESET).unwrap();
+        assert_eq!(report.default_inbound, Some(Decision::Deny));
+        assert!(report.skipped.is_empty(), "{:?}", report.skipped);
+        let summary: Vec<String> =
+            report.rules.iter().map(|r| format!("{} {:?} {:?}", r.name, r.direction, r.action)).collect();
+        assert_eq!(
+            summary,
+            [
+                "log_dns Inbound Log",
+                "ssh-from-lan Inbound Allow",
+                "ssh-from-lan (2) Inbound Allow",
+                "ntp Inbound Allow",
+                "icmpv6 Inbound Allow",
+                "log_dns (2) Outbound Log",
+                "block-doc-net Outbound Deny",
+            ]
+        );
+        let ssh = &report.rules[1];
+        assert_eq!(ssh.remote_addresses, vec![IpNetwork::parse("10.0.0.0/8").unwrap()]);
+        assert_eq!(ssh.local_ports, vec![PortRange::single(22), PortRange { start: 8000, end: 8080 }]);
+        assert_eq!(report.rules[3].remote_ports, vec![PortRange::single(123)]);
+        assert_eq!(report.rules[3].protocol, None);
+
+        let mut rules = RuleSet::new();
+        report.apply_to(&mut rules).unwrap();
+        assert_eq!(rules.rules().len(), 7);
+        assert!(import_nftables("chain input {\n type filter hook input priority 0;\n ip saddr 10.0.0.1 ct state established accept\n}")
+            .unwrap()
+            .skipped[0]
+            .1
+            .contains("ct"));
+    }
+}
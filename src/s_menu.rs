@@ -1,7 +1,10 @@
-use crate::logging;
+use s2o_net_lib::logging;
 use crate::app_state::AppState;
-use crate::gui_engine_menu::MenuItem;
-use crate::gui_engine_style::MenuSettings;  // Import MenuSettings
+use crate::gui_engine_screens::{MenuEntry, Screen};
+use s2o_net_lib::firewall;
+use s2o_net_lib::linux_firewall;
+use s2o_net_lib::rule_store;
+use s2o_net_lib::windows_firewall;
 
 pub fn init_module() -> Result<(), String> {
     // Placeholder for actual initialization logic
@@ -15,22 +18,30 @@ pub fn init_module() -> Result<(), String> {
     }
 }
 
-// Placeholder menu items for s_menu
-pub fn menu_items<S: Fn(AppState) + Clone + 'static>(
-    set_app_state: S,
-    menu_settings: &MenuSettings,  // Add menu_settings parameter
-) -> Vec<MenuItem> {
+pub fn screen() -> Screen {
+    Screen {
+        state: AppState::SMenu,
+        title: "Security Menu",
+        items,
+        on_enter: None,
+        render: None,
+    }
+}
+
+fn items() -> Vec<MenuEntry> {
+    let mut items = vec![MenuEntry::open("Admin Menu", AppState::PMenu)];
+    items.extend(firewall_entries());
+    items
+}
+
+// Rule file and ruleset items, shared with the NS menu's Firewall Menu
+pub fn firewall_entries() -> Vec<MenuEntry> {
     vec![
-        MenuItem {
-            label: menu_settings.apply_label("Admin Menu", false).text().to_string(),  // Use menu_settings and convert to String
-            action: Some(Box::new({
-                let set_app_state = set_app_state.clone();
-                move || set_app_state(AppState::PMenu)
-            })),
-        },
-        MenuItem {
-            label: "Exit".to_string(),  // No need to use menu_settings for a simple label
-            action: Some(Box::new(|| std::process::exit(0))),
-        },
+        MenuEntry::run("Print Firewall Rules", firewall::print_rules),
+        MenuEntry::run("Save Firewall Rules", rule_store::save_current_rules),
+        MenuEntry::run("Load Firewall Rules", rule_store::load_current_rules),
+        MenuEntry::run("Import Firewall Rules", rule_store::import_into_current_rules),
+        MenuEntry::run("Print Linux Ruleset", linux_firewall::print_rulesets),
+        MenuEntry::run("Print Windows Firewall Rules", windows_firewall::print_windows_rules),
     ]
 }
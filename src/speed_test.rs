@@ -0,0 +1,466 @@
+use crate::logging;
+use crate::throughput_monitor::format_rate;
+use once_cell::sync::Lazy;
+use std::fmt;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+pub fn init_module() -> Result<(), String> {
+    // Placeholder for actual initialization logic
+    let initialization_passed = true;
+
+    if initialization_passed {
+        logging::debug_info("speed_test module is online");
+        Ok(())
+    } else {
+        Err("speed_test module initialization failed".to_string())
+    }
+}
+
+// The server listens on this port for both the TCP transfers and the UDP echo
+pub const DEFAULT_PORT: u16 = 47800;
+const CHUNK_SIZE: usize = 64 * 1024;
+// Largest transfer a client may ask for, so a stray client cannot tie the server up
+const MAX_TRANSFER: u64 = 4 * 1024 * 1024 * 1024;
+// Each UDP probe carries its sequence number and send time
+const PROBE_SIZE: usize = 16;
+
+// Server started from the DS menu, and the last client result
+static MENU_SERVER: Lazy<Mutex<Option<SpeedTestServer>>> = Lazy::new(|| Mutex::new(None));
+static LAST_RESULT: Lazy<Mutex<Option<Result<SpeedTestResult, String>>>> = Lazy::new(|| Mutex::new(None));
+static CLIENT_RUNNING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone)]
+pub struct SpeedTestConfig {
+    pub server: SocketAddr,
+    // Bytes sent in each direction
+    pub transfer_bytes: u64,
+    pub probes: usize,
+    pub probe_interval: Duration,
+    // How long to wait for a reply before a probe counts as lost
+    pub timeout: Duration,
+}
+
+impl SpeedTestConfig {
+    pub fn new(server: SocketAddr) -> Self {
+        SpeedTestConfig {
+            server,
+            transfer_bytes: 64 * 1024 * 1024,
+            probes: 50,
+            probe_interval: Duration::from_millis(20),
+            timeout: Duration::from_secs(2),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpeedTestResult {
+    // Bytes per second
+    pub upload: f64,
+    pub download: f64,
+    pub rtt_min: Duration,
+    pub rtt_avg: Duration,
+    pub rtt_max: Duration,
+    // Mean difference between consecutive round trip times
+    pub jitter: Duration,
+    pub probes_sent: usize,
+    pub probes_received: usize,
+}
+
+impl SpeedTestResult {
+    pub fn loss(&self) -> f64 {
+        if self.probes_sent == 0 {
+            return 0.0;
+        }
+        1.0 - self.probes_received as f64 / self.probes_sent as f64
+    }
+}
+
+impl fmt::Display for SpeedTestResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        write!(
+            f,
+            "upload {}, download {}, rtt {:.2}/{:.2}/{:.2} ms, jitter {:.2} ms, loss {:.1}%",
+            format_rate(self.upload),
+            format_rate(self.download),
+            ms(self.rtt_min),
+            ms(self.rtt_avg),
+            ms(self.rtt_max),
+            ms(self.jitter),
+            self.loss() * 100.0
+        )
+    }
+}
+
+// One client connection: a command line, then the transfer it asked for.
+// "UPLOAD n" reads n bytes and answers "DONE"; "DOWNLOAD n" sends n bytes.
+fn serve_connection(stream: TcpStream) -> Result<(), String> {
+    stream.set_read_timeout(Some(Duration::from_secs(10))).map_err(|e| e.to_string())?;
+    let mut reader = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
+    let mut writer = stream;
+    let mut command = String::new();
+    reader.read_line(&mut command).map_err(|e| format!("Failed to read command: {}", e))?;
+    let (verb, bytes) = command.trim().split_once(' ').ok_or(format!("Bad command \"{}\"", command.trim()))?;
+    let bytes: u64 = bytes.parse().map_err(|_| format!("Bad byte count \"{}\"", bytes))?;
+    if bytes > MAX_TRANSFER {
+        return Err(format!("Refusing {} byte transfer", bytes));
+    }
+
+    match verb {
+        "UPLOAD" => {
+            let received = std::io::copy(&mut reader.by_ref().take(bytes), &mut std::io::sink())
+                .map_err(|e| format!("Upload failed: {}", e))?;
+            if received != bytes {
+                return Err(format!("Upload ended after {} of {} bytes", received, bytes));
+            }
+            writer.write_all(b"DONE\n").map_err(|e| e.to_string())
+        }
+        "DOWNLOAD" => send_bytes(&mut writer, bytes),
+        _ => Err(format!("Unknown command \"{}\"", verb)),
+    }
+}
+
+fn send_bytes(writer: &mut impl Write, bytes: u64) -> Result<(), String> {
+    let chunk = vec![0x5a; CHUNK_SIZE];
+    let mut remaining = bytes;
+    while remaining > 0 {
+        let len = remaining.min(CHUNK_SIZE as u64) as usize;
+        writer.write_all(&chunk[..len]).map_err(|e| format!("Send failed: {}", e))?;
+        remaining -= len as u64;
+    }
+    writer.flush().map_err(|e| e.to_string())
+}
+
+// TCP transfer and UDP echo server, each on its own thread until stopped
+pub struct SpeedTestServer {
+    addr: SocketAddr,
+    running: Arc<AtomicBool>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl SpeedTestServer {
+    // Port 0 picks a free port; local_addr() reports which
+    pub fn start(addr: SocketAddr) -> Result<Self, String> {
+        let listener = TcpListener::bind(addr).map_err(|e| format!("Failed to bind TCP {}: {}", addr, e))?;
+        let addr = listener.local_addr().map_err(|e| e.to_string())?;
+        let udp = UdpSocket::bind(addr).map_err(|e| format!("Failed to bind UDP {}: {}", addr, e))?;
+        // Both loops poll so stop() is noticed promptly
+        listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+        udp.set_read_timeout(Some(Duration::from_millis(100))).map_err(|e| e.to_string())?;
+
+        let running = Arc::new(AtomicBool::new(true));
+        let tcp_running = running.clone();
+        let tcp_thread = std::thread::spawn(move || {
+            while tcp_running.load(Ordering::SeqCst) {
+                match listener.accept() {
+                    Ok((stream, peer)) => {
+                        let _ = stream.set_nonblocking(false);
+                        std::thread::spawn(move || {
+                            if let Err(e) = serve_connection(stream) {
+                                logging::debug_error(&format!("Speed test client {}: {}", peer, e));
+                            }
+                        });
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => std::thread::sleep(Duration::from_millis(20)),
+                    Err(e) => logging::debug_error(&format!("Speed test accept failed: {}", e)),
+                }
+            }
+        });
+
+        let udp_running = running.clone();
+        let udp_thread = std::thread::spawn(move || {
+            let mut buf = [0u8; 1500];
+            while udp_running.load(Ordering::SeqCst) {
+                if let Ok((len, peer)) = udp.recv_from(&mut buf) {
+                    let _ = udp.send_to(&buf[..len], peer);
+                }
+            }
+        });
+
+        logging::debug_info(&format!("Speed test server listening on {}", addr));
+        Ok(SpeedTestServer { addr, running, threads: vec![tcp_thread, udp_thread] })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for SpeedTestServer {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+fn connect(config: &SpeedTestConfig, command: &str) -> Result<TcpStream, String> {
+    let mut stream = TcpStream::connect_timeout(&config.server, config.timeout)
+        .map_err(|e| format!("Failed to connect to {}: {}", config.server, e))?;
+    stream.set_read_timeout(Some(config.timeout.max(Duration::from_secs(10)))).map_err(|e| e.to_string())?;
+    stream.set_nodelay(true).map_err(|e| e.to_string())?;
+    stream.write_all(command.as_bytes()).map_err(|e| e.to_string())?;
+    Ok(stream)
+}
+
+fn rate(bytes: u64, elapsed: Duration) -> f64 {
+    bytes as f64 / elapsed.as_secs_f64().max(1e-9)
+}
+
+fn measure_upload(config: &SpeedTestConfig) -> Result<f64, String> {
+    let mut stream = connect(config, &format!("UPLOAD {}\n", config.transfer_bytes))?;
+    let start = Instant::now();
+    send_bytes(&mut stream, config.transfer_bytes)?;
+    // Stop the clock once the server confirms it has everything
+    let mut reply = String::new();
+    BufReader::new(&stream).read_line(&mut reply).map_err(|e| format!("No upload confirmation: {}", e))?;
+    if reply.trim() != "DONE" {
+        return Err(format!("Unexpected upload reply \"{}\"", reply.trim()));
+    }
+    Ok(rate(config.transfer_bytes, start.elapsed()))
+}
+
+fn measure_download(config: &SpeedTestConfig) -> Result<f64, String> {
+    let stream = connect(config, &format!("DOWNLOAD {}\n", config.transfer_bytes))?;
+    let start = Instant::now();
+    let received = std::io::copy(&mut stream.take(config.transfer_bytes), &mut std::io::sink())
+        .map_err(|e| format!("Download failed: {}", e))?;
+    if received != config.transfer_bytes {
+        return Err(format!("Download ended after {} of {} bytes", received, config.transfer_bytes));
+    }
+    Ok(rate(received, start.elapsed()))
+}
+
+// Round trip times of the probes that came back, in send order
+fn measure_latency(config: &SpeedTestConfig) -> Result<Vec<Duration>, String> {
+    let bind = if config.server.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+    let socket = UdpSocket::bind(bind).map_err(|e| e.to_string())?;
+    socket.connect(config.server).map_err(|e| e.to_string())?;
+    socket.set_read_timeout(Some(Duration::from_millis(5))).map_err(|e| e.to_string())?;
+
+    let start = Instant::now();
+    let mut rtts: Vec<Option<Duration>> = vec![None; config.probes];
+    let mut buf = [0u8; PROBE_SIZE];
+    let mut collect = |rtts: &mut Vec<Option<Duration>>| {
+        while let Ok(len) = socket.recv(&mut buf) {
+            if len != PROBE_SIZE {
+                continue;
+            }
+            let seq = u64::from_be_bytes(buf[..8].try_into().unwrap()) as usize;
+            let sent = Duration::from_nanos(u64::from_be_bytes(buf[8..].try_into().unwrap()));
+            if let Some(slot @ None) = rtts.get_mut(seq) {
+                *slot = Some(start.elapsed().saturating_sub(sent));
+            }
+        }
+    };
+
+    for seq in 0..config.probes {
+        let mut probe = [0u8; PROBE_SIZE];
+        probe[..8].copy_from_slice(&(seq as u64).to_be_bytes());
+        probe[8..].copy_from_slice(&(start.elapsed().as_nanos() as u64).to_be_bytes());
+        socket.send(&probe).map_err(|e| format!("Failed to send probe: {}", e))?;
+        let next = Instant::now() + config.probe_interval;
+        while Instant::now() < next {
+            collect(&mut rtts);
+        }
+    }
+    // Give the last probes their full timeout
+    let deadline = Instant::now() + config.timeout;
+    while Instant::now() < deadline && rtts.iter().any(Option::is_none) {
+        collect(&mut rtts);
+    }
+    Ok(rtts.into_iter().flatten().collect())
+}
+
+// Summarises round trip times; jitter is the mean change between neighbours
+pub(crate) fn latency_stats(rtts: &[Duration]) -> (Duration, Duration, Duration, Duration) {
+    if rtts.is_empty() {
+        return (Duration::ZERO, Duration::ZERO, Duration::ZERO, Duration::ZERO);
+    }
+    let min = *rtts.iter().min().unwrap();
+    let max = *rtts.iter().max().unwrap();
+    let avg = rtts.iter().sum::<Duration>() / rtts.len() as u32;
+    let jitter = if rtts.len() < 2 {
+        Duration::ZERO
+    } else {
+        let changes: Duration = rtts.windows(2).map(|pair| pair[0].abs_diff(pair[1])).sum();
+        changes / (rtts.len() - 1) as u32
+    };
+    (min, avg, max, jitter)
+}
+
+pub fn run_client(config: &SpeedTestConfig) -> Result<SpeedTestResult, String> {
+    logging::debug_info(&format!("Running speed test against {}", config.server));
+    let rtts = measure_latency(config)?;
+    let upload = measure_upload(config)?;
+    let download = measure_download(config)?;
+    let (rtt_min, rtt_avg, rtt_max, jitter) = latency_stats(&rtts);
+    Ok(SpeedTestResult {
+        upload,
+        download,
+        rtt_min,
+        rtt_avg,
+        rtt_max,
+        jitter,
+        probes_sent: config.probes,
+        probes_received: rtts.len(),
+    })
+}
+
+// Resolves "host", "host:port" or "[v6]:port", defaulting the port
+pub(crate) fn resolve_target(target: &str) -> Result<SocketAddr, String> {
+    let target = target.trim();
+    let has_port = target.parse::<SocketAddr>().is_ok()
+        || matches!(target.rsplit_once(':'), Some((host, port)) if !host.contains(':') && port.parse::<u16>().is_ok());
+    let with_port = if has_port {
+        target.to_string()
+    } else if target.contains(':') {
+        format!("[{}]:{}", target.trim_matches(|c| c == '[' || c == ']'), DEFAULT_PORT)
+    } else {
+        format!("{}:{}", target, DEFAULT_PORT)
+    };
+    with_port
+        .to_socket_addrs()
+        .map_err(|e| format!("Cannot resolve \"{}\": {}", target, e))?
+        .next()
+        .ok_or(format!("\"{}\" has no addresses", target))
+}
+
+// Tests against `target`, or against a temporary loopback server when it
+// is empty
+pub fn run_target(target: &str) -> Result<SpeedTestResult, String> {
+    let target = target.trim();
+    if target.is_empty() {
+        SpeedTestServer::start(SocketAddr::from(([127, 0, 0, 1], 0))).and_then(|server| {
+            let result = run_client(&SpeedTestConfig::new(server.local_addr()));
+            server.stop();
+            result
+        })
+    } else {
+        resolve_target(target).and_then(|addr| run_client(&SpeedTestConfig::new(addr)))
+    }
+}
+
+// Runs the client on a background thread so the GUI keeps drawing
+pub fn start_client(target: &str) {
+    if CLIENT_RUNNING.swap(true, Ordering::SeqCst) {
+        logging::debug_error("Speed test already running.");
+        return;
+    }
+    let target = target.to_string();
+    std::thread::spawn(move || {
+        let result = run_target(&target);
+        match &result {
+            Ok(result) => logging::debug_info(&format!("Speed test: {}", result)),
+            Err(e) => logging::debug_error(&format!("Speed test failed: {}", e)),
+        }
+        *LAST_RESULT.lock().unwrap() = Some(result);
+        CLIENT_RUNNING.store(false, Ordering::SeqCst);
+    });
+}
+
+pub fn is_client_running() -> bool {
+    CLIENT_RUNNING.load(Ordering::SeqCst)
+}
+
+pub fn last_result() -> Option<Result<SpeedTestResult, String>> {
+    LAST_RESULT.lock().unwrap().clone()
+}
+
+pub fn is_server_running() -> bool {
+    MENU_SERVER.lock().unwrap().is_some()
+}
+
+pub fn stop_server() {
+    if let Some(running) = MENU_SERVER.lock().unwrap().take() {
+        running.stop();
+        logging::debug_info("Speed test server stopped.");
+    }
+}
+
+// Starts or stops the server other machines test against
+pub fn toggle_server() {
+    let mut server = MENU_SERVER.lock().unwrap();
+    match server.take() {
+        Some(running) => {
+            running.stop();
+            logging::debug_info("Speed test server stopped.");
+        }
+        None => match SpeedTestServer::start(SocketAddr::from(([0, 0, 0, 0], DEFAULT_PORT))) {
+            Ok(started) => *server = Some(started),
+            Err(e) => logging::debug_error(&e),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measures_against_loopback_server() {
+        let server = SpeedTestServer::start(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+        let config = SpeedTestConfig {
+            transfer_bytes: 256 * 1024,
+            probes: 10,
+            probe_interval: Duration::from_millis(1),
+            ..SpeedTestConfig::new(server.local_addr())
+        };
+        let result = run_client(&config).unwrap();
+        assert!(result.upload > 0.0 && result.download > 0.0);
+        assert_eq!((result.probes_sent, result.probes_received), (10, 10));
+        assert_eq!(result.loss(), 0.0);
+        assert!(result.rtt_min <= result.rtt_avg && result.rtt_avg <= result.rtt_max);
+
+        // A bad command gets the connection closed rather than an answer
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        stream.write_all(b"FETCH 10\n").unwrap();
+        let mut reply = Vec::new();
+        stream.read_to_end(&mut reply).unwrap();
+        assert!(reply.is_empty());
+        server.stop();
+    }
+
+    #[test]
+    fn summarises_latency() {
+        let ms = Duration::from_millis;
+        assert_eq!(latency_stats(&[ms(10), ms(14), ms(12)]), (ms(10), ms(12), ms(14), ms(3)));
+        assert_eq!(latency_stats(&[]).3, Duration::ZERO);
+        let result = SpeedTestResult {
+            upload: 1.25e6,
+            download: 2.5e6,
+            rtt_min: ms(1),
+            rtt_avg: ms(2),
+            rtt_max: ms(3),
+            jitter: ms(1),
+            probes_sent: 4,
+            probes_received: 3,
+        };
+        assert_eq!(
+            result.to_string(),
+            "upload 10.00 Mbit/s, download 20.00 Mbit/s, rtt 1.00/2.00/3.00 ms, jitter 1.00 ms, loss 25.0%"
+        );
+    }
+
+    #[test]
+    fn resolves_targets_with_default_port() {
+        assert_eq!(resolve_target("127.0.0.1").unwrap(), SocketAddr::from(([127, 0, 0, 1], DEFAULT_PORT)));
+        assert_eq!(resolve_target("127.0.0.1:9000").unwrap().port(), 9000);
+        assert_eq!(resolve_target("::1").unwrap().port(), DEFAULT_PORT);
+        assert_eq!(resolve_target("[::1]:9000").unwrap().port(), 9000);
+    }
+}
@@ -0,0 +1,363 @@
+use crate::logging;
+use once_cell::sync::Lazy;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+pub fn init_module() -> Result<(), String> {
+    // Placeholder for actual initialization logic
+    let initialization_passed = true;
+
+    if initialization_passed {
+        logging::debug_info("throughput_monitor module is online");
+        Ok(())
+    } else {
+        Err("throughput_monitor module initialization failed".to_string())
+    }
+}
+
+// How often the counters are read, and how many samples the graphs keep
+pub const SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+pub const HISTORY_LEN: usize = 120;
+// Weight of the newest sample in the smoothed rate
+const SMOOTHING: f64 = 0.3;
+
+pub static MONITOR: Lazy<Mutex<ThroughputMonitor>> = Lazy::new(|| Mutex::new(ThroughputMonitor::new(HISTORY_LEN)));
+static RUNNING: AtomicBool = AtomicBool::new(false);
+static SAMPLER_THREAD: Lazy<Mutex<Option<JoinHandle<()>>>> = Lazy::new(|| Mutex::new(None));
+
+// Cumulative byte and packet counters for one interface
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterfaceCounters {
+    pub name: String,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    pub rx_packets: u64,
+    pub tx_packets: u64,
+}
+
+// Smoothed receive and transmit rates in bytes per second, plus the recent
+// history the DS menu draws
+#[derive(Debug, Clone, PartialEq)]
+pub struct InterfaceRates {
+    pub name: String,
+    pub rx: f64,
+    pub tx: f64,
+    pub rx_total: u64,
+    pub tx_total: u64,
+    pub history: VecDeque<(f64, f64)>,
+}
+
+#[derive(Debug)]
+struct InterfaceState {
+    last: InterfaceCounters,
+    rates: InterfaceRates,
+}
+
+pub struct ThroughputMonitor {
+    interfaces: BTreeMap<String, InterfaceState>,
+    history_len: usize,
+}
+
+impl ThroughputMonitor {
+    pub fn new(history_len: usize) -> Self {
+        ThroughputMonitor { interfaces: BTreeMap::new(), history_len }
+    }
+
+    // Turns a new reading into rates. A counter that went backwards means
+    // the interface was reset, so that interval counts as zero.
+    pub fn update(&mut self, counters: Vec<InterfaceCounters>, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64();
+        let seen: Vec<String> = counters.iter().map(|c| c.name.clone()).collect();
+        for current in counters {
+            let state = match self.interfaces.get_mut(&current.name) {
+                Some(state) => state,
+                None => {
+                    let rates = InterfaceRates {
+                        name: current.name.clone(),
+                        rx: 0.0,
+                        tx: 0.0,
+                        rx_total: current.rx_bytes,
+                        tx_total: current.tx_bytes,
+                        history: VecDeque::new(),
+                    };
+                    self.interfaces.insert(current.name.clone(), InterfaceState { last: current, rates });
+                    continue;
+                }
+            };
+            let rate = |now: u64, before: u64| {
+                if seconds > 0.0 {
+                    now.saturating_sub(before) as f64 / seconds
+                } else {
+                    0.0
+                }
+            };
+            let rx = rate(current.rx_bytes, state.last.rx_bytes);
+            let tx = rate(current.tx_bytes, state.last.tx_bytes);
+            let rates = &mut state.rates;
+            rates.rx = SMOOTHING * rx + (1.0 - SMOOTHING) * rates.rx;
+            rates.tx = SMOOTHING * tx + (1.0 - SMOOTHING) * rates.tx;
+            rates.rx_total = current.rx_bytes;
+            rates.tx_total = current.tx_bytes;
+            rates.history.push_back((rates.rx, rates.tx));
+            while rates.history.len() > self.history_len {
+                rates.history.pop_front();
+            }
+            state.last = current;
+        }
+        // Interfaces that went away (unplugged adapters, closed VPNs)
+        self.interfaces.retain(|name, _| seen.contains(name));
+    }
+
+    // Busiest interfaces first
+    pub fn rates(&self) -> Vec<InterfaceRates> {
+        let mut rates: Vec<InterfaceRates> = self.interfaces.values().map(|state| state.rates.clone()).collect();
+        rates.sort_by(|a, b| (b.rx + b.tx).total_cmp(&(a.rx + a.tx)).then_with(|| a.name.cmp(&b.name)));
+        rates
+    }
+
+    pub fn clear(&mut self) {
+        self.interfaces.clear();
+    }
+}
+
+// Parses /proc/net/dev: two header lines, then "name: rx fields | tx fields"
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) fn parse_proc_net_dev(text: &str) -> Result<Vec<InterfaceCounters>, String> {
+    let mut interfaces = Vec::new();
+    for line in text.lines().skip(2) {
+        let (name, fields) = line.split_once(':').ok_or(format!("Malformed /proc/net/dev line: {}", line))?;
+        let fields: Vec<u64> = fields
+            .split_whitespace()
+            .map(|field| field.parse().map_err(|_| format!("Bad counter \"{}\" for {}", field, name.trim())))
+            .collect::<Result<_, _>>()?;
+        if fields.len() < 16 {
+            return Err(format!("Expected 16 counters for {}, found {}", name.trim(), fields.len()));
+        }
+        interfaces.push(InterfaceCounters {
+            name: name.trim().to_string(),
+            rx_bytes: fields[0],
+            rx_packets: fields[1],
+            tx_bytes: fields[8],
+            tx_packets: fields[9],
+        });
+    }
+    Ok(interfaces)
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn read_counters() -> Result<Vec<InterfaceCounters>, String> {
+    let text = std::fs::read_to_string("/proc/net/dev").map_err(|e| format!("Failed to read /proc/net/dev: {}", e))?;
+    parse_proc_net_dev(&text)
+}
+
+#[cfg(windows)]
+mod win32 {
+    use std::ffi::c_void;
+
+    // Byte offsets into MIB_IF_ROW2 (netioapi.h); rows are 1352 bytes and the
+    // table starts 8 bytes into MIB_IF_TABLE2
+    pub const ROW_SIZE: usize = 1352;
+    pub const TABLE_OFFSET: usize = 8;
+    pub const ALIAS: usize = 28;
+    pub const ALIAS_CHARS: usize = 257;
+    pub const STATUS_FLAGS: usize = 1152;
+    pub const OPER_STATUS: usize = 1156;
+    pub const IN_OCTETS: usize = 1208;
+    pub const IN_UCAST_PKTS: usize = 1216;
+    pub const IN_NUCAST_PKTS: usize = 1224;
+    pub const OUT_OCTETS: usize = 1280;
+    pub const OUT_UCAST_PKTS: usize = 1288;
+    pub const OUT_NUCAST_PKTS: usize = 1296;
+    pub const IF_OPER_STATUS_UP: u32 = 1;
+    // InterfaceAndOperStatusFlags.FilterInterface
+    pub const FILTER_INTERFACE: u8 = 0x02;
+
+    #[link(name = "iphlpapi")]
+    extern "system" {
+        pub fn GetIfTable2(table: *mut *mut c_void) -> u32;
+        pub fn FreeMibTable(memory: *mut c_void);
+    }
+}
+
+// Reads every interface that is up, skipping the NDIS filter duplicates
+// Windows lists alongside each adapter
+#[cfg(windows)]
+pub(crate) fn read_counters() -> Result<Vec<InterfaceCounters>, String> {
+    use win32::*;
+
+    let mut table: *mut std::ffi::c_void = std::ptr::null_mut();
+    let status = unsafe { GetIfTable2(&mut table) };
+    if status != 0 || table.is_null() {
+        return Err(format!("GetIfTable2 failed with status {}", status));
+    }
+    let base = table as *const u8;
+    let mut interfaces = Vec::new();
+    unsafe {
+        let count = (base as *const u32).read_unaligned() as usize;
+        for index in 0..count {
+            let row = base.add(TABLE_OFFSET + index * ROW_SIZE);
+            let u64_at = |offset: usize| (row.add(offset) as *const u64).read_unaligned();
+            let oper_status = (row.add(OPER_STATUS) as *const u32).read_unaligned();
+            if oper_status != IF_OPER_STATUS_UP || *row.add(STATUS_FLAGS) & FILTER_INTERFACE != 0 {
+                continue;
+            }
+            let alias: Vec<u16> = (0..ALIAS_CHARS)
+                .map(|i| (row.add(ALIAS + i * 2) as *const u16).read_unaligned())
+                .take_while(|c| *c != 0)
+                .collect();
+            interfaces.push(InterfaceCounters {
+                name: String::from_utf16_lossy(&alias),
+                rx_bytes: u64_at(IN_OCTETS),
+                tx_bytes: u64_at(OUT_OCTETS),
+                rx_packets: u64_at(IN_UCAST_PKTS) + u64_at(IN_NUCAST_PKTS),
+                tx_packets: u64_at(OUT_UCAST_PKTS) + u64_at(OUT_NUCAST_PKTS),
+            });
+        }
+        FreeMibTable(table);
+    }
+    Ok(interfaces)
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+pub(crate) fn read_counters() -> Result<Vec<InterfaceCounters>, String> {
+    Err("Interface counters are not supported on this platform".to_string())
+}
+
+// Rates are shown in bits per second, the way link speeds are quoted
+pub fn format_rate(bytes_per_second: f64) -> String {
+    let bits = bytes_per_second * 8.0;
+    if bits >= 1e9 {
+        format!("{:.2} Gbit/s", bits / 1e9)
+    } else if bits >= 1e6 {
+        format!("{:.2} Mbit/s", bits / 1e6)
+    } else if bits >= 1e3 {
+        format!("{:.1} kbit/s", bits / 1e3)
+    } else {
+        format!("{:.0} bit/s", bits)
+    }
+}
+
+// Starts sampling on a background thread; does nothing if already running
+pub fn start() -> Result<(), String> {
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        return Ok(());
+    }
+    // Fail early rather than leave a thread logging the same error
+    let first = match read_counters() {
+        Ok(counters) => counters,
+        Err(e) => {
+            RUNNING.store(false, Ordering::SeqCst);
+            return Err(e);
+        }
+    };
+    {
+        let mut monitor = MONITOR.lock().unwrap();
+        monitor.clear();
+        monitor.update(first, Duration::ZERO);
+    }
+
+    let handle = std::thread::spawn(|| {
+        let mut last = Instant::now();
+        while RUNNING.load(Ordering::SeqCst) {
+            std::thread::sleep(SAMPLE_INTERVAL);
+            match read_counters() {
+                Ok(counters) => {
+                    let now = Instant::now();
+                    MONITOR.lock().unwrap().update(counters, now - last);
+                    last = now;
+                }
+                Err(e) => logging::debug_error(&format!("Throughput sample failed: {}", e)),
+            }
+        }
+    });
+    *SAMPLER_THREAD.lock().unwrap() = Some(handle);
+    logging::debug_info("Throughput monitor started.");
+    Ok(())
+}
+
+pub fn stop() {
+    RUNNING.store(false, Ordering::SeqCst);
+    if let Some(handle) = SAMPLER_THREAD.lock().unwrap().take() {
+        let _ = handle.join();
+        logging::debug_info("Throughput monitor stopped.");
+    }
+}
+
+pub fn is_running() -> bool {
+    RUNNING.load(Ordering::SeqCst)
+}
+
+pub fn toggle() {
+    if is_running() {
+        stop();
+    } else if let Err(e) = start() {
+        logging::debug_error(&format!("Failed to start throughput monitor: {}", e));
+    }
+}
+
+pub fn snapshot() -> Vec<InterfaceRates> {
+    MONITOR.lock().unwrap().rates()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROC_NET_DEV: &str = include_str!("../testdata/proc/net_dev");
+
+    fn counters(name: &str, rx_bytes: u64, tx_bytes: u64) -> InterfaceCounters {
+        InterfaceCounters { name: name.to_string(), rx_bytes, tx_bytes, rx_packets: 0, tx_packets: 0 }
+    }
+
+    #[test]
+    fn parses_proc_net_dev() {
+        let interfaces = parse_proc_net_dev(PROC_NET_DEV).unwrap();
+        let names: Vec<&str> = interfaces.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, ["lo", "eth0", "wlp3s0"]);
+        assert_eq!(
+            interfaces[1],
+            InterfaceCounters {
+                name: "eth0".to_string(),
+                rx_bytes: 1234567890,
+                tx_bytes: 987654321,
+                rx_packets: 9876543,
+                tx_packets: 6543210,
+            }
+        );
+        assert!(parse_proc_net_dev("header\nheader\n  eth0: 1 2 3\n").is_err());
+    }
+
+    #[test]
+    fn smooths_rates_and_survives_counter_resets() {
+        let mut monitor = ThroughputMonitor::new(3);
+        let second = Duration::from_secs(1);
+        monitor.update(vec![counters("eth0", 0, 0), counters("lo", 0, 0)], Duration::ZERO);
+        monitor.update(vec![counters("eth0", 1000, 100), counters("lo", 10, 10)], second);
+        let eth0 = &monitor.rates()[0];
+        assert_eq!(eth0.name, "eth0");
+        assert!((eth0.rx - 300.0).abs() < 1e-9 && (eth0.tx - 30.0).abs() < 1e-9);
+
+        monitor.update(vec![counters("eth0", 2000, 200)], second);
+        let eth0 = &monitor.rates()[0];
+        assert!((eth0.rx - 510.0).abs() < 1e-9);
+        assert_eq!(monitor.rates().len(), 1, "lo disappeared from the reading");
+
+        // Counters reset by a driver reload must not produce a huge spike
+        monitor.update(vec![counters("eth0", 50, 20)], second);
+        monitor.update(vec![counters("eth0", 60, 30)], second);
+        let eth0 = &monitor.rates()[0];
+        assert!(eth0.rx < 510.0);
+        assert_eq!(eth0.history.len(), 3);
+    }
+
+    #[test]
+    fn formats_rates_in_bits() {
+        assert_eq!(format_rate(0.0), "0 bit/s");
+        assert_eq!(format_rate(1500.0), "12.0 kbit/s");
+        assert_eq!(format_rate(12_500_000.0), "100.00 Mbit/s");
+        assert_eq!(format_rate(250_000_000.0), "2.00 Gbit/s");
+    }
+}
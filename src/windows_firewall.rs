@@ -0,0 +1,631 @@
+use crate::logging;
+use crate::firewall::{IpNetwork, PortRange};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr};
+
+pub fn init_module() -> Result<(), String> {
+    // Placeholder for actual initialization logic
+    let initialization_passed = true;
+
+    if initialization_passed {
+        logging::debug_info("windows_firewall module is online");
+        Ok(())
+    } else {
+        Err("windows_firewall module initialization failed".to_string())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FwDirection {
+    In,
+    Out,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FwAction {
+    Allow,
+    Block,
+    // Allow even if IPsec would otherwise be required
+    Bypass,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FwProfile {
+    Domain,
+    Private,
+    Public,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FwProtocol {
+    Any,
+    Tcp,
+    Udp,
+    // ICMP type/code pairs as netsh lists them, e.g. "8:Any"
+    Icmpv4(Vec<String>),
+    Icmpv6(Vec<String>),
+    Number(u8),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FwAddress {
+    Network(IpNetwork),
+    Range(IpAddr, IpAddr),
+    // LocalSubnet, DNS, DHCP, DefaultGateway, ...
+    Keyword(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FwPort {
+    Range(PortRange),
+    // RPC, RPC-EPMap, Teredo, IPHTTPS, ...
+    Keyword(String),
+}
+
+// A Windows Firewall rule as netsh describes it. Empty address and port
+// lists mean "Any", and an empty profile list means all profiles.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowsFirewallRule {
+    pub name: String,
+    pub enabled: bool,
+    pub direction: FwDirection,
+    pub action: FwAction,
+    pub profiles: Vec<FwProfile>,
+    pub group: Option<String>,
+    pub protocol: FwProtocol,
+    pub local_addresses: Vec<FwAddress>,
+    pub remote_addresses: Vec<FwAddress>,
+    pub local_ports: Vec<FwPort>,
+    pub remote_ports: Vec<FwPort>,
+    pub program: Option<String>,
+    pub service: Option<String>,
+}
+
+impl WindowsFirewallRule {
+    // An enabled rule for every profile, protocol, address and port
+    pub fn new(name: &str, direction: FwDirection, action: FwAction) -> WindowsFirewallRule {
+        WindowsFirewallRule {
+            name: name.to_string(),
+            enabled: true,
+            direction,
+            action,
+            profiles: Vec::new(),
+            group: None,
+            protocol: FwProtocol::Any,
+            local_addresses: Vec::new(),
+            remote_addresses: Vec::new(),
+            local_ports: Vec::new(),
+            remote_ports: Vec::new(),
+            program: None,
+            service: None,
+        }
+    }
+}
+
+impl fmt::Display for FwAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FwAddress::Network(network) => write!(f, "{}", network),
+            FwAddress::Range(start, end) => write!(f, "{}-{}", start, end),
+            FwAddress::Keyword(keyword) => write!(f, "{}", keyword),
+        }
+    }
+}
+
+impl fmt::Display for FwPort {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FwPort::Range(range) => write!(f, "{}", range),
+            FwPort::Keyword(keyword) => write!(f, "{}", keyword),
+        }
+    }
+}
+
+fn parse_yes_no(value: &str) -> Result<bool, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "yes" => Ok(true),
+        "no" => Ok(false),
+        _ => Err(format!("Expected Yes or No, found \"{}\"", value)),
+    }
+}
+
+// Converts a dotted IPv4 mask such as 255.192.0.0 to a prefix length
+fn mask_prefix(mask: &str) -> Option<u8> {
+    let bits = u32::from(mask.parse::<Ipv4Addr>().ok()?);
+    let prefix = bits.leading_ones();
+    (bits.checked_shl(prefix).unwrap_or(0) == 0).then_some(prefix as u8)
+}
+
+fn parse_address(value: &str) -> Result<FwAddress, String> {
+    if let Some((start, end)) = value.split_once('-') {
+        if let (Ok(start), Ok(end)) = (start.parse(), end.parse()) {
+            return Ok(FwAddress::Range(start, end));
+        }
+    }
+    if let Some((addr, mask)) = value.split_once('/') {
+        if let Some(prefix) = mask_prefix(mask) {
+            return IpNetwork::parse(&format!("{}/{}", addr, prefix)).map(FwAddress::Network);
+        }
+    }
+    if value.parse::<IpAddr>().is_ok() || value.contains('/') {
+        return IpNetwork::parse(value).map(FwAddress::Network);
+    }
+    Ok(FwAddress::Keyword(value.to_string()))
+}
+
+fn parse_port(value: &str) -> FwPort {
+    match PortRange::parse(value) {
+        Ok(range) => FwPort::Range(range),
+        Err(_) => FwPort::Keyword(value.to_string()),
+    }
+}
+
+// Splits a comma list, with "Any" (or nothing) meaning an empty list
+fn parse_list<T>(value: &str, parse: impl Fn(&str) -> Result<T, String>) -> Result<Vec<T>, String> {
+    if value.is_empty() || value.eq_ignore_ascii_case("any") {
+        return Ok(Vec::new());
+    }
+    value.split(',').map(|item| parse(item.trim())).collect()
+}
+
+fn parse_profiles(value: &str) -> Result<Vec<FwProfile>, String> {
+    parse_list(value, |profile| match profile.to_ascii_lowercase().as_str() {
+        "domain" => Ok(FwProfile::Domain),
+        "private" => Ok(FwProfile::Private),
+        "public" => Ok(FwProfile::Public),
+        _ => Err(format!("Unknown profile \"{}\"", profile)),
+    })
+}
+
+fn parse_protocol(value: &str, icmp_types: Vec<String>) -> Result<FwProtocol, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "any" => Ok(FwProtocol::Any),
+        "tcp" => Ok(FwProtocol::Tcp),
+        "udp" => Ok(FwProtocol::Udp),
+        "icmpv4" => Ok(FwProtocol::Icmpv4(icmp_types)),
+        "icmpv6" => Ok(FwProtocol::Icmpv6(icmp_types)),
+        other => other.parse().map(FwProtocol::Number).map_err(|_| format!("Unknown protocol \"{}\"", value)),
+    }
+}
+
+fn optional(value: &str) -> Option<String> {
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+// Builds one rule from the "Key: value" lines that follow its dashed line
+fn parse_rule(name: &str, lines: &[&str]) -> Result<WindowsFirewallRule, String> {
+    let mut fields: Vec<(String, String)> = Vec::new();
+    let mut icmp_types = Vec::new();
+    for line in lines {
+        match line.split_once(':') {
+            Some((key, value)) if !line.starts_with(char::is_whitespace) => {
+                fields.push((key.trim().to_ascii_lowercase(), value.trim().to_string()));
+            }
+            // Indented "Type  Code" table under an ICMP protocol
+            _ => {
+                let columns: Vec<&str> = line.split_whitespace().collect();
+                if let [icmp_type, code] = columns[..] {
+                    if icmp_type != "Type" {
+                        icmp_types.push(format!("{}:{}", icmp_type, code));
+                    }
+                }
+            }
+        }
+    }
+    let field = |key: &str| fields.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+    let required = |key: &str| field(key).ok_or(format!("Rule \"{}\" has no {} field", name, key));
+
+    let direction = match required("direction")?.to_ascii_lowercase().as_str() {
+        "in" => FwDirection::In,
+        "out" => FwDirection::Out,
+        other => return Err(format!("Unknown direction \"{}\"", other)),
+    };
+    let action = match required("action")?.to_ascii_lowercase().as_str() {
+        "allow" => FwAction::Allow,
+        "block" => FwAction::Block,
+        "bypass" => FwAction::Bypass,
+        other => return Err(format!("Unknown action \"{}\"", other)),
+    };
+
+    Ok(WindowsFirewallRule {
+        name: name.to_string(),
+        enabled: parse_yes_no(required("enabled")?)?,
+        direction,
+        action,
+        profiles: parse_profiles(field("profiles").unwrap_or("Any"))?,
+        group: field("grouping").and_then(optional),
+        protocol: parse_protocol(field("protocol").unwrap_or("Any"), icmp_types)?,
+        local_addresses: parse_list(field("localip").unwrap_or("Any"), parse_address)?,
+        remote_addresses: parse_list(field("remoteip").unwrap_or("Any"), parse_address)?,
+        local_ports: parse_list(field("localport").unwrap_or("Any"), |p| Ok(parse_port(p)))?,
+        remote_ports: parse_list(field("remoteport").unwrap_or("Any"), |p| Ok(parse_port(p)))?,
+        program: field("program").and_then(optional),
+        service: field("service").and_then(optional),
+    })
+}
+
+// Parses `netsh advfirewall firewall show rule name=... verbose`. Each rule
+// starts with its name line followed by a row of dashes, so names are found
+// by position and survive localised "Rule Name" labels; the remaining field
+// labels are the English ones.
+pub(crate) fn parse_show_rule(output: &str) -> Result<Vec<WindowsFirewallRule>, String> {
+    let lines: Vec<&str> = output.lines().map(|line| line.trim_end()).collect();
+    let starts: Vec<usize> = (1..lines.len())
+        .filter(|&i| lines[i].len() >= 10 && lines[i].chars().all(|c| c == '-'))
+        .collect();
+    if starts.is_empty() {
+        let message = lines.iter().find(|line| !line.trim().is_empty()).copied().unwrap_or("");
+        if message.trim() == "Ok." || message.contains("No rules match") {
+            return Ok(Vec::new());
+        }
+        return Err(format!("Unexpected netsh output: {}", message.trim()));
+    }
+
+    let mut rules = Vec::new();
+    for (index, &dashes) in starts.iter().enumerate() {
+        let name = lines[dashes - 1]
+            .split_once(':')
+            .map(|(_, name)| name.trim())
+            .ok_or("Rule without a name line")?;
+        // Fields run until the next rule's name line, a blank line or "Ok."
+        let end = starts.get(index + 1).map(|next| next - 1).unwrap_or(lines.len());
+        let body: Vec<&str> = lines[dashes + 1..end]
+            .iter()
+            .copied()
+            .take_while(|line| !line.trim().is_empty() && line.trim() != "Ok.")
+            .collect();
+        rules.push(parse_rule(name, &body)?);
+    }
+    Ok(rules)
+}
+
+fn join<T: ToString>(items: &[T]) -> String {
+    if items.is_empty() {
+        "any".to_string()
+    } else {
+        items.iter().map(|item| item.to_string()).collect::<Vec<_>>().join(",")
+    }
+}
+
+fn enable_arg(enabled: bool) -> String {
+    format!("enable={}", if enabled { "yes" } else { "no" })
+}
+
+// Arguments for `netsh advfirewall firewall add rule`. Each value is its own
+// argument, so names and paths with spaces need no extra quoting. netsh takes
+// a single ICMP type per rule, so a rule with several types becomes one
+// command per type.
+pub(crate) fn add_rule_args(rule: &WindowsFirewallRule) -> Vec<Vec<String>> {
+    let icmp = |name: &str, types: &[String]| -> Vec<String> {
+        if types.is_empty() {
+            return vec![name.to_string()];
+        }
+        types.iter().map(|t| format!("{}:{}", name, t.replace("Any", "any").replacen(':', ",", 1))).collect()
+    };
+    let protocols = match &rule.protocol {
+        FwProtocol::Any => vec!["any".to_string()],
+        FwProtocol::Tcp => vec!["tcp".to_string()],
+        FwProtocol::Udp => vec!["udp".to_string()],
+        FwProtocol::Icmpv4(types) => icmp("icmpv4", types),
+        FwProtocol::Icmpv6(types) => icmp("icmpv6", types),
+        FwProtocol::Number(number) => vec![number.to_string()],
+    };
+    protocols.iter().map(|protocol| add_rule_command(rule, protocol)).collect()
+}
+
+fn add_rule_command(rule: &WindowsFirewallRule, protocol: &str) -> Vec<String> {
+    let mut args: Vec<String> = ["advfirewall", "firewall", "add", "rule"].iter().map(|s| s.to_string()).collect();
+    args.push(format!("name={}", rule.name));
+    args.push(format!("dir={}", if rule.direction == FwDirection::In { "in" } else { "out" }));
+    args.push(format!(
+        "action={}",
+        match rule.action {
+            FwAction::Allow => "allow",
+            FwAction::Block => "block",
+            FwAction::Bypass => "bypass",
+        }
+    ));
+    args.push(enable_arg(rule.enabled));
+    if !rule.profiles.is_empty() {
+        let profiles: Vec<String> = rule.profiles.iter().map(|p| format!("{:?}", p).to_lowercase()).collect();
+        args.push(format!("profile={}", profiles.join(",")));
+    }
+    args.push(format!("protocol={}", protocol));
+    args.push(format!("localip={}", join(&rule.local_addresses)));
+    args.push(format!("remoteip={}", join(&rule.remote_addresses)));
+    // netsh refuses port arguments unless the protocol is TCP or UDP
+    if matches!(rule.protocol, FwProtocol::Tcp | FwProtocol::Udp) {
+        args.push(format!("localport={}", join(&rule.local_ports)));
+        args.push(format!("remoteport={}", join(&rule.remote_ports)));
+    }
+    if let Some(program) = &rule.program {
+        args.push(format!("program={}", program));
+    }
+    if let Some(service) = &rule.service {
+        args.push(format!("service={}", service));
+    }
+    if let Some(group) = &rule.group {
+        args.push(format!("group={}", group));
+    }
+    args
+}
+
+// Reverse of add_rule_args, for rules kept as netsh scripts
+pub(crate) fn parse_add_rule_args(args: &[String]) -> Result<WindowsFirewallRule, String> {
+    let command = ["advfirewall", "firewall", "add", "rule"];
+    if args.len() < command.len() || !args.iter().zip(command).all(|(arg, word)| arg.eq_ignore_ascii_case(word)) {
+        return Err(format!("Not an add rule command: {}", args.join(" ")));
+    }
+    let params = &args[command.len()..];
+    let mut fields: Vec<(String, &str)> = Vec::new();
+    for param in params {
+        let (key, value) = param.split_once('=').ok_or(format!("Expected key=value, found \"{}\"", param))?;
+        fields.push((key.to_ascii_lowercase(), value.trim()));
+    }
+    let field = |key: &str| fields.iter().find(|(k, _)| k == key).map(|(_, v)| *v);
+    let name = field("name").ok_or("add rule command has no name")?;
+
+    let direction = match field("dir").ok_or(format!("Rule \"{}\" has no dir", name))?.to_ascii_lowercase().as_str() {
+        "in" => FwDirection::In,
+        "out" => FwDirection::Out,
+        other => return Err(format!("Unknown direction \"{}\"", other)),
+    };
+    let action = match field("action").ok_or(format!("Rule \"{}\" has no action", name))?.to_ascii_lowercase().as_str() {
+        "allow" => FwAction::Allow,
+        "block" => FwAction::Block,
+        "bypass" => FwAction::Bypass,
+        other => return Err(format!("Unknown action \"{}\"", other)),
+    };
+    // "icmpv4:8,any" carries the ICMP type and code after the protocol
+    let protocol = field("protocol").unwrap_or("any");
+    let (protocol, icmp) = protocol.split_once(':').unwrap_or((protocol, ""));
+    let icmp_types = if icmp.is_empty() { Vec::new() } else { vec![icmp.replacen(',', ":", 1)] };
+
+    Ok(WindowsFirewallRule {
+        name: name.to_string(),
+        enabled: parse_yes_no(field("enable").unwrap_or("yes"))?,
+        direction,
+        action,
+        profiles: parse_profiles(field("profile").unwrap_or("any"))?,
+        group: field("group").and_then(optional),
+        protocol: parse_protocol(protocol, icmp_types)?,
+        local_addresses: parse_list(field("localip").unwrap_or("any"), parse_address)?,
+        remote_addresses: parse_list(field("remoteip").unwrap_or("any"), parse_address)?,
+        local_ports: parse_list(field("localport").unwrap_or("any"), |p| Ok(parse_port(p)))?,
+        remote_ports: parse_list(field("remoteport").unwrap_or("any"), |p| Ok(parse_port(p)))?,
+        program: field("program").and_then(optional),
+        service: field("service").and_then(optional),
+    })
+}
+
+// Runs netsh; split out so the firewall logic can be tested with canned output
+pub trait CommandRunner {
+    fn run(&self, args: &[String]) -> Result<String, String>;
+}
+
+pub struct NetshRunner;
+
+impl CommandRunner for NetshRunner {
+    #[cfg(windows)]
+    fn run(&self, args: &[String]) -> Result<String, String> {
+        let output = std::process::Command::new("netsh")
+            .args(args)
+            .output()
+            .map_err(|e| format!("Failed to run netsh: {}", e))?;
+        let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+        if output.status.success() {
+            Ok(stdout)
+        } else {
+            Err(format!("netsh failed: {}", stdout.trim()))
+        }
+    }
+
+    #[cfg(not(windows))]
+    fn run(&self, _args: &[String]) -> Result<String, String> {
+        Err("netsh is only available on Windows".to_string())
+    }
+}
+
+pub struct WindowsFirewall<R: CommandRunner = NetshRunner> {
+    runner: R,
+}
+
+impl WindowsFirewall<NetshRunner> {
+    pub fn new() -> Self {
+        WindowsFirewall { runner: NetshRunner }
+    }
+}
+
+impl Default for WindowsFirewall<NetshRunner> {
+    fn default() -> Self {
+        WindowsFirewall::new()
+    }
+}
+
+impl<R: CommandRunner> WindowsFirewall<R> {
+    pub fn with_runner(runner: R) -> Self {
+        WindowsFirewall { runner }
+    }
+
+    fn netsh(&self, args: &[&str]) -> Result<String, String> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        self.runner.run(&args)
+    }
+
+    pub fn list_rules(&self) -> Result<Vec<WindowsFirewallRule>, String> {
+        parse_show_rule(&self.netsh(&["advfirewall", "firewall", "show", "rule", "name=all", "verbose"])?)
+    }
+
+    // All rules with this name; netsh allows duplicates
+    pub fn find_rules(&self, name: &str) -> Result<Vec<WindowsFirewallRule>, String> {
+        let name_arg = format!("name={}", name);
+        match self.netsh(&["advfirewall", "firewall", "show", "rule", &name_arg, "verbose"]) {
+            Ok(output) => parse_show_rule(&output),
+            // netsh exits with an error when nothing matches
+            Err(e) if e.contains("No rules match") => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn add_rule(&self, rule: &WindowsFirewallRule) -> Result<(), String> {
+        if rule.name.is_empty() || rule.name.eq_ignore_ascii_case("all") {
+            return Err(format!("\"{}\" cannot be used as a rule name", rule.name));
+        }
+        for args in add_rule_args(rule) {
+            self.runner.run(&args)?;
+        }
+        Ok(())
+    }
+
+    pub fn set_enabled(&self, name: &str, enabled: bool) -> Result<(), String> {
+        let name_arg = format!("name={}", name);
+        let enable = enable_arg(enabled);
+        self.netsh(&["advfirewall", "firewall", "set", "rule", &name_arg, "new", &enable]).map(|_| ())
+    }
+
+    pub fn delete_rule(&self, name: &str) -> Result<(), String> {
+        let name_arg = format!("name={}", name);
+        self.netsh(&["advfirewall", "firewall", "delete", "rule", &name_arg]).map(|_| ())
+    }
+}
+
+pub fn print_windows_rules() {
+    match WindowsFirewall::new().list_rules() {
+        Ok(rules) => {
+            logging::debug_info(&format!("Windows Firewall rules: {}", rules.len()));
+            for rule in rules {
+                logging::debug_info(&format!(
+                    "{} {:?} {:?} {:?} local={} remote={} ports={}/{} program={}{}",
+                    rule.name,
+                    rule.direction,
+                    rule.action,
+                    rule.protocol,
+                    join(&rule.local_addresses),
+                    join(&rule.remote_addresses),
+                    join(&rule.local_ports),
+                    join(&rule.remote_ports),
+                    rule.program.as_deref().unwrap_or("any"),
+                    if rule.enabled { "" } else { " (disabled)" }
+                ));
+            }
+        }
+        Err(e) => logging::debug_error(&format!("Failed to list Windows Firewall rules: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    const SHOW_RULE_VERBOSE: &str = include_str!("../testdata/netsh/show_rule_verbose.txt");
+    const SHOW_RULE_NO_MATCH: &str = include_str!("../testdata/netsh/show_rule_no_match.txt");
+
+    // Records every command and replies with canned output
+    struct FakeRunner {
+        calls: RefCell<Vec<Vec<String>>>,
+        reply: Result<String, String>,
+    }
+
+    impl CommandRunner for FakeRunner {
+        fn run(&self, args: &[String]) -> Result<String, String> {
+            self.calls.borrow_mut().push(args.to_vec());
+            self.reply.clone()
+        }
+    }
+
+    fn fake(reply: Result<&str, &str>) -> WindowsFirewall<FakeRunner> {
+        let reply = reply.map(str::to_string).map_err(str::to_string);
+        WindowsFirewall::with_runner(FakeRunner { calls: RefCell::new(Vec::new()), reply })
+    }
+
+    #[test]
+    fn parses_verbose_show_rule_sample() {
+        let rules = parse_show_rule(SHOW_RULE_VERBOSE).unwrap();
+        assert_eq!(rules.len(), 3);
+
+        let teredo = &rules[0];
+        assert_eq!(teredo.name, "Core Networking - Teredo (UDP-In)");
+        assert_eq!((teredo.enabled, teredo.direction, teredo.action), (true, FwDirection::In, FwAction::Allow));
+        assert_eq!(teredo.profiles, vec![FwProfile::Domain, FwProfile::Private, FwProfile::Public]);
+        assert_eq!(teredo.protocol, FwProtocol::Udp);
+        assert_eq!(teredo.local_ports, vec![FwPort::Keyword("Teredo".to_string())]);
+        assert_eq!(teredo.service.as_deref(), Some("iphlpsvc"));
+        assert_eq!(teredo.group.as_deref(), Some("Core Networking"));
+
+        let telemetry = &rules[1];
+        assert!(!telemetry.enabled);
+        assert_eq!(telemetry.group, None);
+        assert_eq!(
+            telemetry.remote_addresses,
+            vec![
+                FwAddress::Network(IpNetwork::parse("13.64.0.0/10").unwrap()),
+                FwAddress::Range("20.190.128.0".parse().unwrap(), "20.190.191.255".parse().unwrap()),
+                FwAddress::Network(IpNetwork::parse("2603:1000::/25").unwrap()),
+            ]
+        );
+        assert_eq!(
+            telemetry.remote_ports,
+            vec![FwPort::Range(PortRange::single(80)), FwPort::Range(PortRange::single(443)), FwPort::Range(PortRange { start: 8000, end: 8080 })]
+        );
+        assert_eq!(telemetry.program.as_deref(), Some("C:\\Program Files\\Example\\agent.exe"));
+
+        let echo = &rules[2];
+        assert_eq!(echo.protocol, FwProtocol::Icmpv6(vec!["128:Any".to_string()]));
+        assert_eq!(echo.remote_addresses, vec![FwAddress::Keyword("LocalSubnet".to_string())]);
+        assert!(echo.local_ports.is_empty());
+
+        assert_eq!(parse_show_rule(SHOW_RULE_NO_MATCH).unwrap(), Vec::new());
+        assert!(parse_show_rule("The requested operation requires elevation (Run as administrator).").is_err());
+    }
+
+    #[test]
+    fn builds_netsh_add_arguments() {
+        let rule = WindowsFirewallRule {
+            profiles: vec![FwProfile::Private],
+            protocol: FwProtocol::Tcp,
+            remote_addresses: vec![FwAddress::Network(IpNetwork::parse("10.0.0.0/8").unwrap())],
+            local_ports: vec![FwPort::Range(PortRange { start: 8000, end: 8080 })],
+            program: Some("C:\\Program Files\\App\\app.exe".to_string()),
+            ..WindowsFirewallRule::new("App Server", FwDirection::In, FwAction::Allow)
+        };
+        assert_eq!(
+            add_rule_args(&rule),
+            [[
+                "advfirewall", "firewall", "add", "rule", "name=App Server", "dir=in", "action=allow", "enable=yes",
+                "profile=private", "protocol=tcp", "localip=any", "remoteip=10.0.0.0/8", "localport=8000-8080",
+                "remoteport=any", "program=C:\\Program Files\\App\\app.exe",
+            ]]
+        );
+        assert_eq!(parse_add_rule_args(&add_rule_args(&rule)[0]).unwrap(), rule);
+
+        // One command per ICMP type, each of which parses back to that type
+        let ping = WindowsFirewallRule {
+            protocol: FwProtocol::Icmpv4(vec!["8:Any".to_string(), "0:Any".to_string()]),
+            ..WindowsFirewallRule::new("Ping", FwDirection::In, FwAction::Allow)
+        };
+        let commands = add_rule_args(&ping);
+        assert_eq!(commands.len(), 2);
+        assert!(commands[0].contains(&"protocol=icmpv4:8,any".to_string()));
+        assert!(commands[1].contains(&"protocol=icmpv4:0,any".to_string()));
+        assert_eq!(parse_add_rule_args(&commands[1]).unwrap().protocol, FwProtocol::Icmpv4(vec!["0:any".to_string()]));
+    }
+
+    #[test]
+    fn manages_rules_through_the_runner() {
+        let firewall = fake(Ok(SHOW_RULE_VERBOSE));
+        assert_eq!(firewall.list_rules().unwrap().len(), 3);
+        firewall.set_enabled("Block Telemetry", true).unwrap();
+        firewall.delete_rule("Block Telemetry").unwrap();
+        assert!(firewall.add_rule(&WindowsFirewallRule::new("all", FwDirection::Out, FwAction::Block)).is_err());
+
+        let calls = firewall.runner.calls.borrow();
+        assert_eq!(calls[0].join(" "), "advfirewall firewall show rule name=all verbose");
+        assert_eq!(calls[1].join(" "), "advfirewall firewall set rule name=Block Telemetry new enable=yes");
+        assert_eq!(calls[2].join(" "), "advfirewall firewall delete rule name=Block Telemetry");
+        assert_eq!(calls.len(), 3);
+
+        let missing = fake(Err("netsh failed: No rules match the specified criteria."));
+        assert_eq!(missing.find_rules("nothing").unwrap(), Vec::new());
+    }
+}